@@ -0,0 +1,539 @@
+/*
+ * name: build.rs
+ * desc: generates the instruction decoder, encoder and mnemonic lookup from the
+ *       opcode tables in opcodes/, the output is included by idecoder.rs
+ *
+ * Note: the tables use the riscv-opcodes format (github.com/riscv/riscv-opcodes),
+ *       one instruction per line:
+ *
+ *           add  rd rs1 rs2 31..25=0 14..12=0 6..2=0x0C 1..0=3
+ *
+ *       the file name says which XLEN an entry is valid for, rv_* for both, rv32_*
+ *       and rv64_* for one of them, and the extension after the '_', "$pseudo_op
+ *       ext::name" lines are taken as regular entries of the file they are in,
+ *       that's how the RV32 shifts with the 5 bit shamt are written upstream
+ *
+ *       opcodes/variants says which Instruction variant every entry becomes and
+ *       which fields it has, adding an instruction is a table entry plus its
+ *       variant and execute arm, a whole extension can go to one variant that
+ *       keeps its own fields (F/D, Zb*, V)
+ *
+ *       arguments the variant has no field for are reserved fields, decode
+ *       ignores them and encode leaves them zero (FENCE rd/rs1)
+ *
+ *       the entries of a variant that keeps the whole word also get a MATCH_<NAME>
+ *       constant with their fixed bits, encoder.rs builds F/D and V from those
+ *
+ *       the build fails on an entry that doesn't account for all 32 bits, an
+ *       argument or variant field it doesn't know, two entries of the same
+ *       XLEN that can match the same word, and an RV32 and an RV64 entry that
+ *       match the same word under different names (mnemonic() has no XLEN)
+ *
+ */
+
+/* same conventions as the crate itself, see main.rs */
+#![allow(clippy::needless_return)]
+#![allow(clippy::redundant_field_names)]
+#![allow(clippy::upper_case_acronyms)]
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const TABLE_DIR: &str = "opcodes";
+const VARIANTS: &str = "opcodes/variants";
+
+/* operand fields, where they sit and how they turn into Instruction fields and back */
+struct ArgInfo {
+    name: &'static str,
+    msb: u32,
+    lsb: u32,
+    field: &'static str,  /* field of the Instruction variant */
+    decode: &'static str, /* expression on inst */
+    encode: &'static str, /* expression on the field, bits in place */
+}
+
+const fn arg(name: &'static str, msb: u32, lsb: u32, field: &'static str, decode: &'static str, encode: &'static str) -> ArgInfo {
+    return ArgInfo { name: name, msb: msb, lsb: lsb, field: field, decode: decode, encode: encode };
+}
+
+/* split immediates (imm12hi/imm12lo) decode as one field, the helpers live in idecoder.rs */
+const ARGS: [ArgInfo; 30] = [
+    arg("rd",       11,  7, "rd",     "((inst >> 7) & 0x1F) as u8",    "((rd as u32) << 7)"),
+    arg("rs1",      19, 15, "rs1",    "((inst >> 15) & 0x1F) as u8",   "((rs1 as u32) << 15)"),
+    arg("rs2",      24, 20, "rs2",    "((inst >> 20) & 0x1F) as u8",   "((rs2 as u32) << 20)"),
+    arg("rs3",      31, 27, "rs3",    "((inst >> 27) & 0x1F) as u8",   "((rs3 as u32) << 27)"),
+    arg("imm12",    31, 20, "imm",    "imm_i(inst)",                   "place_i(imm)"),
+    arg("imm12hi",  31, 25, "imm",    "imm_s(inst)",                   "place_s(imm)"),
+    arg("imm12lo",  11,  7, "imm",    "imm_s(inst)",                   "place_s(imm)"),
+    arg("bimm12hi", 31, 25, "imm",    "imm_b(inst)",                   "place_b(imm)"),
+    arg("bimm12lo", 11,  7, "imm",    "imm_b(inst)",                   "place_b(imm)"),
+    arg("jimm20",   31, 12, "imm",    "imm_j(inst)",                   "place_j(imm)"),
+    arg("imm20",    31, 12, "imm",    "imm_u(inst)",                   "place_u(imm)"),
+    arg("shamtw",   24, 20, "shamt",  "((inst >> 20) & 0x1F) as u8",   "((shamt as u32) << 20)"),
+    arg("shamtd",   25, 20, "shamt",  "((inst >> 20) & 0x3F) as u8",   "((shamt as u32) << 20)"),
+    arg("csr",      31, 20, "csr",    "((inst >> 20) & 0xFFF) as u16", "((csr as u32) << 20)"),
+    arg("zimm",     19, 15, "uimm",   "((inst >> 15) & 0x1F) as u8",   "((uimm as u32) << 15)"),
+    arg("fm",       31, 28, "fm",     "((inst >> 28) & 0xF) as u8",    "((fm as u32) << 28)"),
+    arg("pred",     27, 24, "pred",   "((inst >> 24) & 0xF) as u8",    "((pred as u32) << 24)"),
+    arg("succ",     23, 20, "succ",   "((inst >> 20) & 0xF) as u8",    "((succ as u32) << 20)"),
+    arg("aq",       26, 26, "aq",     "((inst >> 26) & 0x1) != 0",     "((aq as u32) << 26)"),
+    arg("rl",       25, 25, "rl",     "((inst >> 25) & 0x1) != 0",     "((rl as u32) << 25)"),
+    arg("rm",       14, 12, "rm",     "((inst >> 12) & 0x7) as u8",    "((rm as u32) << 12)"),
+    /* vector operands, vs3 is the data register of a store */
+    arg("vd",       11,  7, "vd",     "((inst >> 7) & 0x1F) as u8",    "((vd as u32) << 7)"),
+    arg("vs3",      11,  7, "vs3",    "((inst >> 7) & 0x1F) as u8",    "((vs3 as u32) << 7)"),
+    arg("vs1",      19, 15, "vs1",    "((inst >> 15) & 0x1F) as u8",   "((vs1 as u32) << 15)"),
+    arg("vs2",      24, 20, "vs2",    "((inst >> 20) & 0x1F) as u8",   "((vs2 as u32) << 20)"),
+    arg("vm",       25, 25, "vm",     "((inst >> 25) & 0x1) as u8",    "((vm as u32) << 25)"),
+    arg("simm5",    19, 15, "simm5",  "((((inst >> 15) & 0x1F) << 27) as i32) >> 27", "(((simm5 as u32) & 0x1F) << 15)"),
+    arg("zimm5",    19, 15, "zimm5",  "((inst >> 15) & 0x1F) as u8",   "((zimm5 as u32) << 15)"),
+    arg("zimm10",   29, 20, "zimm10", "((inst >> 20) & 0x3FF) as u16", "((zimm10 as u32) << 20)"),
+    arg("zimm11",   30, 20, "zimm11", "((inst >> 20) & 0x7FF) as u16", "((zimm11 as u32) << 20)"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum XlenSel {
+    ANY,
+    RV32,
+    RV64,
+}
+
+struct Entry {
+    name: String,
+    file: String,
+    ext: String, /* rv64_zbb -> zbb */
+    xlen: XlenSel,
+    mask: u32,
+    matches: u32,
+    args: Vec<&'static ArgInfo>,
+}
+
+/* where the value of a variant field comes from, see opcodes/variants */
+#[derive(Clone, PartialEq)]
+enum FieldKind {
+    ARG,             /* the argument filling the field */
+    DEFAULT(String), /* same, the value when the entry has no such argument */
+    OP(String),      /* enum type, the variant is named by the entry name */
+    WIDE,            /* .w/.d */
+    XLEN,            /* the XLEN passed to decode */
+    WORD,            /* the whole encoding */
+}
+
+struct Field {
+    name: String,
+    kind: FieldKind,
+}
+
+struct Variant {
+    name: String,
+    wraps: Option<String>,      /* type of a tuple variant */
+    fields: Option<Vec<Field>>, /* None for unit variants */
+    exts: Vec<String>,          /* extensions all of whose entries go here */
+}
+
+impl Variant {
+    fn field(&self, kind: &FieldKind) -> Option<&Field> {
+        return self.fields.iter().flatten().find(|f| f.kind == *kind);
+    }
+
+    fn op_field(&self) -> Option<(&Field, &str)> {
+        return self.fields.iter().flatten().find_map(|f| match &f.kind {
+            FieldKind::OP(ty) => Some((f, ty.as_str())),
+            _ => None,
+        });
+    }
+
+    fn is_word(&self) -> bool {
+        return self.field(&FieldKind::WORD).is_some();
+    }
+}
+
+fn bit_range(msb: u32, lsb: u32) -> u32 {
+    return ((((1u64 << (msb + 1)) - 1) >> lsb) << lsb) as u32;
+}
+
+fn parse_num(text: &str) -> Option<u32> {
+    return match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse::<u32>().ok(),
+    };
+}
+
+/* one table line, file is only for the error messages */
+fn parse_entry(line: &str, file: &str, xlen: XlenSel) -> Entry {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens[0] == "$pseudo_op" {
+        tokens.remove(0); /* ext::name of the real instruction */
+        tokens.remove(0);
+    } else if tokens[0].starts_with('$') {
+        panic!("{}: unsupported directive {}", file, tokens[0]);
+    }
+
+    let name: String = tokens[0].to_string();
+    let mut mask: u32 = 0;
+    let mut matches: u32 = 0;
+    let mut args: Vec<&'static ArgInfo> = Vec::new();
+    let mut claim = |bits: u32, what: &str| {
+        if (mask & bits) != 0 {
+            panic!("{}: {} sets bits of {} twice", file, name, what);
+        }
+        mask |= bits;
+    };
+
+    for token in tokens[1..].iter() {
+        if let Some((range, value)) = token.split_once('=') {
+            let (msb, lsb): (u32, u32) = match range.split_once("..") {
+                Some((msb, lsb)) => (parse_num(msb).unwrap(), parse_num(lsb).unwrap()),
+                None => (parse_num(range).unwrap(), parse_num(range).unwrap()),
+            };
+            let value: u32 = parse_num(value).unwrap_or_else(|| panic!("{}: bad value in {}", file, token));
+            if value > (bit_range(msb, lsb) >> lsb) {
+                panic!("{}: {} doesn't fit in {}", file, value, range);
+            }
+            claim(bit_range(msb, lsb), token);
+            matches |= value << lsb;
+        } else {
+            let info: &'static ArgInfo = ARGS.iter().find(|a| a.name == *token)
+                .unwrap_or_else(|| panic!("{}: unknown argument {} of {}", file, token, tokens[0]));
+            claim(bit_range(info.msb, info.lsb), token);
+            args.push(info);
+        }
+    }
+
+    if mask != 0xFFFF_FFFF {
+        panic!("{}: {} leaves bits 0x{:08x} undefined", file, name, !mask);
+    }
+    let args_bits: u32 = args.iter().fold(0, |acc, a| acc | bit_range(a.msb, a.lsb));
+    /* the fixed bits without the argument bits */
+    let mask: u32 = mask & !args_bits;
+    if (mask & 0x7F) != 0x7F {
+        panic!("{}: {} has no fixed opcode", file, name);
+    }
+
+    let ext: String = file.split_once('_').map(|(_, ext)| ext.to_string()).unwrap_or_default();
+    return Entry { name: name, file: file.to_string(), ext: ext, xlen: xlen, mask: mask, matches: matches, args: args };
+}
+
+/* one line of opcodes/variants */
+fn parse_variant(line: &str) -> Variant {
+    let (decl, exts): (&str, &str) = line.split_once('<').unwrap_or((line, ""));
+    let mut tokens = decl.split_whitespace();
+    let head: &str = tokens.next().unwrap();
+    let (name, wraps): (String, Option<String>) = match head.split_once('(') {
+        Some((name, ty)) => (name.to_string(), Some(ty.trim_end_matches(')').to_string())),
+        None => (head.to_string(), None),
+    };
+
+    let fields: Vec<Field> = tokens.map(|token| {
+        let (field, kind): (&str, FieldKind) = if let Some((field, value)) = token.split_once('=') {
+            (field, FieldKind::DEFAULT(value.to_string()))
+        } else if let Some((field, ty)) = token.split_once(':') {
+            (field, FieldKind::OP(ty.to_string()))
+        } else {
+            (token, match token {
+                "wide" => FieldKind::WIDE,
+                "xlen" => FieldKind::XLEN,
+                "word" => FieldKind::WORD,
+                _ => FieldKind::ARG,
+            })
+        };
+        return Field { name: field.to_string(), kind: kind };
+    }).collect();
+
+    if wraps.is_none() && fields.iter().any(|f| f.kind == FieldKind::WORD) {
+        panic!("{}: only a tuple variant can be built from the whole word", name);
+    }
+    let fields: Option<Vec<Field>> = if fields.is_empty() && wraps.is_none() { None } else { Some(fields) };
+    let exts: Vec<String> = exts.split_whitespace().map(|ext| ext.to_string()).collect();
+    return Variant { name: name, wraps: wraps, fields: fields, exts: exts };
+}
+
+/* lr.w -> Lr, sfence.vma -> SfenceVma */
+fn camel(name: &str) -> String {
+    return name.split('.').map(|part| {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+            None => String::new(),
+        }
+    }).collect();
+}
+
+/* variant named by an entry name, plus what's left of the name for the op field (amoadd -> Amo, add) */
+fn lookup<'a>(name: &str, variants: &'a [Variant]) -> Option<(&'a Variant, Option<String>)> {
+    let by_ext = |v: &&Variant| -> bool { return v.exts.is_empty(); };
+    if let Some(variant) = variants.iter().filter(by_ext).find(|v| v.name == camel(name)) {
+        return Some((variant, None));
+    }
+    return variants.iter().filter(by_ext).filter(|v| v.op_field().is_some()).find_map(|v| {
+        let op: &str = name.strip_prefix(v.name.to_lowercase().as_str())?;
+        return if op.is_empty() { None } else { Some((v, Some(op.to_string()))) };
+    });
+}
+
+/*
+ * name: variant_of
+ * desc: Instruction variant an entry decodes to, plus the fields that are fixed
+ *       by the name (wide, op) with their values
+ */
+fn variant_of<'a>(entry: &Entry, variants: &'a [Variant]) -> (&'a Variant, Vec<(String, String)>) {
+    let mut consts: Vec<(String, String)> = Vec::new();
+
+    let (variant, op): (&Variant, Option<String>) = match variants.iter().find(|v| v.exts.contains(&entry.ext)) {
+        Some(variant) => (variant, Some(entry.name.replace('.', "_"))),
+        None => {
+            let mut base: &str = entry.name.as_str();
+            if let Some((stem, width)) = entry.name.rsplit_once('.') {
+                let wide: bool = width == "d";
+                let has_wide: bool = matches!(lookup(stem, variants), Some((v, _)) if v.field(&FieldKind::WIDE).is_some());
+                if (width == "w" || wide) && has_wide {
+                    consts.push(("wide".to_string(), wide.to_string()));
+                    base = stem;
+                }
+            }
+            lookup(base, variants)
+                .unwrap_or_else(|| panic!("{}: {} has no variant in {}", entry.file, entry.name, VARIANTS))
+        }
+    };
+    if let (Some((field, ty)), Some(op)) = (variant.op_field(), op) {
+        consts.push((field.name.clone(), format!("{}::{}", ty, op.to_uppercase())));
+    }
+
+    for field in variant.fields.iter().flatten() {
+        let filled: bool = match field.kind {
+            FieldKind::ARG => entry.args.iter().any(|a| a.field == field.name),
+            FieldKind::OP(_) | FieldKind::WIDE => consts.iter().any(|(f, _)| *f == field.name),
+            _ => true,
+        };
+        if !filled {
+            panic!("{}: nothing in {} fills Instruction::{}::{}", entry.file, entry.name, variant.name, field.name);
+        }
+    }
+    return (variant, consts);
+}
+
+/* the argument of an entry that fills a field */
+fn arg_of<'a>(entry: &'a Entry, field: &str) -> Option<&'a ArgInfo> {
+    return entry.args.iter().find(|a| a.field == field).copied();
+}
+
+/* Instruction an entry decodes to, an expression on inst and xlen */
+fn decode_value(entry: &Entry, variant: &Variant, consts: &[(String, String)]) -> String {
+    let fields: &Vec<Field> = match &variant.fields {
+        None => return format!("Instruction::{}", variant.name),
+        Some(fields) => fields,
+    };
+    if variant.is_word() {
+        return format!("Instruction::{}({}::new(inst))", variant.name, variant.wraps.as_ref().unwrap());
+    }
+
+    let inits: Vec<String> = fields.iter().map(|field| {
+        let value: String = match &field.kind {
+            FieldKind::OP(_) | FieldKind::WIDE => consts.iter().find(|(f, _)| *f == field.name).unwrap().1.clone(),
+            FieldKind::XLEN => "xlen".to_string(),
+            FieldKind::DEFAULT(value) => arg_of(entry, &field.name).map(|a| a.decode.to_string()).unwrap_or(value.clone()),
+            _ => arg_of(entry, &field.name).unwrap().decode.to_string(),
+        };
+        return format!("{}: {}", field.name, value);
+    }).collect();
+    return match &variant.wraps {
+        Some(ty) => format!("Instruction::{}({} {{ {} }})", variant.name, ty, inits.join(", ")),
+        None => format!("Instruction::{} {{ {} }}", variant.name, inits.join(", ")),
+    };
+}
+
+/* match pattern for the Instruction an entry decodes to and the bits it puts in the word */
+fn encode_pattern(entry: &Entry, variant: &Variant, consts: &[(String, String)]) -> (String, Vec<String>) {
+    let fields: &Vec<Field> = match &variant.fields {
+        None => return (format!("Instruction::{}", variant.name), Vec::new()),
+        Some(fields) => fields,
+    };
+    if variant.is_word() {
+        return (format!("Instruction::{}(ext)", variant.name), Vec::new());
+    }
+
+    let mut binds: Vec<String> = Vec::new();
+    let mut parts: Vec<String> = Vec::new();
+    for field in fields.iter() {
+        match &field.kind {
+            FieldKind::OP(_) | FieldKind::WIDE => {
+                binds.push(format!("{}: {}", field.name, consts.iter().find(|(f, _)| *f == field.name).unwrap().1));
+            }
+            /* only the RV32 and RV64 forms of an entry have to tell the widths apart */
+            FieldKind::XLEN => match entry.xlen {
+                XlenSel::ANY => (),
+                XlenSel::RV32 => binds.push("xlen: Xlen::RV32".to_string()),
+                XlenSel::RV64 => binds.push("xlen: Xlen::RV64".to_string()),
+            },
+            _ => {
+                if let Some(info) = arg_of(entry, &field.name) {
+                    binds.push(field.name.clone());
+                    if !parts.contains(&info.encode.to_string()) {
+                        parts.push(info.encode.to_string());
+                    }
+                }
+            }
+        }
+    }
+    if binds.len() < fields.len() {
+        binds.push("..".to_string());
+    }
+    let pattern: String = match &variant.wraps {
+        Some(ty) => format!("Instruction::{}({} {{ {} }})", variant.name, ty, binds.join(", ")),
+        None => format!("Instruction::{} {{ {} }}", variant.name, binds.join(", ")),
+    };
+    return (pattern, parts);
+}
+
+/* the match guard of an entry, the fixed bits other than the opcode */
+fn fixed_bits(entry: &Entry, word: &str) -> Option<String> {
+    if entry.mask == 0xFFFF_FFFF {
+        return Some(format!("{} == 0x{:08x}", word, entry.matches));
+    }
+    if entry.mask != 0x7F {
+        return Some(format!("({} & 0x{:08x}) == 0x{:08x}", word, entry.mask, entry.matches));
+    }
+    return None;
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", TABLE_DIR);
+
+    let variants: Vec<Variant> = fs::read_to_string(VARIANTS).expect("can't read the variant list").lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_variant)
+        .collect();
+
+    /* sorted so the output doesn't depend on the directory order */
+    let mut files: Vec<String> = fs::read_dir(TABLE_DIR).expect("no opcode tables")
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("rv"))
+        .collect();
+    files.sort();
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for file in files.iter() {
+        let xlen: XlenSel = if file.starts_with("rv32_") {
+            XlenSel::RV32
+        } else if file.starts_with("rv64_") {
+            XlenSel::RV64
+        } else {
+            XlenSel::ANY
+        };
+        let text: String = fs::read_to_string(Path::new(TABLE_DIR).join(file)).unwrap();
+        for line in text.lines() {
+            let line: &str = line.split('#').next().unwrap().trim();
+            if !line.is_empty() {
+                entries.push(parse_entry(line, file, xlen));
+            }
+        }
+    }
+
+    /* mnemonic() doesn't know the XLEN, an RV32 and an RV64 entry sharing a word have to share the name */
+    for (i, a) in entries.iter().enumerate() {
+        for b in entries[i + 1..].iter() {
+            if ((a.matches ^ b.matches) & a.mask & b.mask) != 0 {
+                continue;
+            }
+            let same_xlen: bool = a.xlen == XlenSel::ANY || b.xlen == XlenSel::ANY || a.xlen == b.xlen;
+            if same_xlen || a.name != b.name {
+                panic!("{} ({}) and {} ({}) overlap", a.name, a.file, b.name, b.file);
+            }
+        }
+    }
+
+    let mut by_opcode: BTreeMap<u32, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries.iter() {
+        by_opcode.entry(entry.matches & 0x7F).or_default().push(entry);
+    }
+
+    let mut out: String = String::new();
+    out += "/* generated by build.rs from the tables in opcodes/, edit those instead */\n\n";
+
+    /* decode, a match on the opcode, guarded by the rest of the fixed bits */
+    out += "/* every instruction in the tables, None for anything else */\n";
+    out += "pub fn decode_table(inst: u32, xlen: Xlen) -> Option<Instruction> {\n";
+    out += "    let rv32: bool = xlen == Xlen::RV32;\n";
+    out += "    let rv64: bool = xlen == Xlen::RV64;\n\n";
+    out += "    return match inst & 0x7F {\n";
+    for (opcode, group) in by_opcode.iter() {
+        for entry in group.iter() {
+            let (variant, consts): (&Variant, Vec<(String, String)>) = variant_of(entry, &variants);
+            let mut guards: Vec<String> = Vec::new();
+            match entry.xlen {
+                XlenSel::ANY => (),
+                XlenSel::RV32 => guards.push("rv32".to_string()),
+                XlenSel::RV64 => guards.push("rv64".to_string()),
+            }
+            guards.extend(fixed_bits(entry, "inst"));
+            let guard: String = if guards.is_empty() { String::new() } else { format!(" if {}", guards.join(" && ")) };
+            out += &format!("        /* {} */\n", entry.name);
+            out += &format!("        0x{:02x}{} => Some({}),\n", opcode, guard, decode_value(entry, variant, &consts));
+        }
+    }
+    out += "        _ => None,\n";
+    out += "    };\n";
+    out += "}\n\n";
+
+    /* encode, one arm per variant, the RV32 and RV64 forms of a shift are the same arm */
+    out += "/* the word decode_table() turns into inst */\n";
+    out += "pub fn encode_table(inst: &Instruction) -> Option<u32> {\n";
+    out += "    let word: u32 = match *inst {\n";
+    let mut patterns: Vec<(String, u32)> = Vec::new();
+    for entry in entries.iter() {
+        let (variant, consts): (&Variant, Vec<(String, String)>) = variant_of(entry, &variants);
+        let (pattern, parts): (String, Vec<String>) = encode_pattern(entry, variant, &consts);
+        if let Some((_, matches)) = patterns.iter().find(|(p, _)| *p == pattern) {
+            if *matches != entry.matches && !variant.is_word() {
+                panic!("{} ({}) needs a field that tells its encodings apart", entry.name, entry.file);
+            }
+            continue;
+        }
+        let value: String = if variant.is_word() {
+            "ext.encode()".to_string()
+        } else {
+            parts.iter().fold(format!("0x{:08x}", entry.matches), |acc, part| acc + " | " + part)
+        };
+        out += &format!("        {} => {},\n", pattern, value);
+        patterns.push((pattern, entry.matches));
+    }
+    /* a Bitmanip op carrying an XLEN it has no entry for */
+    out += "        _ => return None,\n";
+    out += "    };\n";
+    out += "    return Some(word);\n";
+    out += "}\n\n";
+
+    /* names for the disassembler, the table entry the encoding matches */
+    out += "/* assembler name of inst, the name of its table entry */\n";
+    out += "pub fn mnemonic(inst: &Instruction) -> Option<&'static str> {\n";
+    out += "    let word: u32 = encode_table(inst)?;\n";
+    out += "    return match word & 0x7F {\n";
+    for (opcode, group) in by_opcode.iter() {
+        for entry in group.iter() {
+            let guard: String = fixed_bits(entry, "word").map(|g| format!(" if {}", g)).unwrap_or_default();
+            out += &format!("        0x{:02x}{} => Some(\"{}\"),\n", opcode, guard, entry.name);
+        }
+    }
+    out += "        _ => None,\n";
+    out += "    };\n";
+    out += "}\n";
+
+    /* fixed bits of the entries whose variant keeps the whole word, what their constructors start from */
+    out += "\n/* fixed bits of the F/D and V entries, riscv-opcodes' encoding.h names */\n";
+    out += "/* one per entry like encoding.h, outside the tests only the F/D loads/stores use them */\n";
+    out += "#[allow(dead_code)]\n";
+    out += "pub mod encoding {\n";
+    for entry in entries.iter() {
+        let (variant, _): (&Variant, Vec<(String, String)>) = variant_of(entry, &variants);
+        if variant.is_word() {
+            out += &format!("    pub const MATCH_{}: u32 = 0x{:08x};\n", entry.name.replace('.', "_").to_uppercase(), entry.matches);
+        }
+    }
+    out += "}\n";
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("opcodes.rs");
+    fs::write(dest, out).unwrap();
+}
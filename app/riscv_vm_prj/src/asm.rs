@@ -0,0 +1,1017 @@
+/*
+ * name: asm.rs
+ * desc: two pass assembler for GNU style RV32I/M/A/Zicsr assembly, turns source text
+ *       into a flat image that runs from address 0 like a .bin does
+ *
+ * Note: pass one only lays things out (label addresses and sizes), pass two encodes,
+ *       the only instruction whose size depends on a value is li, when its value isn't
+ *       known yet in pass one (a forward label) it gets the lui+addi pair
+ *
+ *       there is no linker, .text/.data/.section just keep appending to the same image
+ *       in source order, memory is only as big as the image so a program that needs a
+ *       stack has to reserve one (.zero/.space)
+ *
+ *       numeric labels can be defined more than once, 1b/1f refer to the closest one
+ *       before/after the reference like in GNU as
+ *
+ */
+
+use std::collections::HashMap;
+use crate::idecoder::*;
+use crate::csr::*;
+use crate::encoder::*;
+use crate::disasm::*;
+use crate::memory::*;
+
+/* line is 1 based */
+#[derive(Debug)]
+pub struct AsmError {
+    pub line: usize,
+    pub msg: String,
+}
+
+enum Item {
+    Label(String),
+    Stmt(String, Vec<String>), /* mnemonic or directive and its operands */
+}
+
+/* the instructions are built by their encoder.rs constructors, one table per operand shape */
+type RegOp = fn(Reg, Reg, Reg) -> Instruction;
+type ImmOp = fn(Reg, Reg, i32) -> Instruction;   /* also loads, stores and branches */
+type ShiftOp = fn(Reg, Reg, u32) -> Instruction;
+type CsrOp = fn(Reg, u16, Reg) -> Instruction;
+type CsrImmOp = fn(Reg, u16, u32) -> Instruction;
+type PrivOp = fn() -> Instruction;
+
+const R_OPS: [(&str, RegOp); 18] = [
+    ("add", Inst::add), ("sub", Inst::sub), ("sll", Inst::sll), ("slt", Inst::slt),
+    ("sltu", Inst::sltu), ("xor", Inst::xor), ("srl", Inst::srl), ("sra", Inst::sra),
+    ("or", Inst::or), ("and", Inst::and),
+    ("mul", Inst::mul), ("mulh", Inst::mulh), ("mulhsu", Inst::mulhsu), ("mulhu", Inst::mulhu),
+    ("div", Inst::div), ("divu", Inst::divu), ("rem", Inst::rem), ("remu", Inst::remu),
+];
+
+const I_OPS: [(&str, ImmOp); 6] = [
+    ("addi", Inst::addi), ("slti", Inst::slti), ("sltiu", Inst::sltiu),
+    ("xori", Inst::xori), ("ori", Inst::ori), ("andi", Inst::andi),
+];
+
+const SHIFT_OPS: [(&str, ShiftOp); 3] = [
+    ("slli", Inst::slli), ("srli", Inst::srli), ("srai", Inst::srai),
+];
+
+const LOAD_OPS: [(&str, ImmOp); 5] = [
+    ("lb", Inst::lb), ("lh", Inst::lh), ("lw", Inst::lw), ("lbu", Inst::lbu), ("lhu", Inst::lhu),
+];
+
+/* rs2 (the value) first like the constructors */
+const STORE_OPS: [(&str, ImmOp); 3] = [
+    ("sb", Inst::sb), ("sh", Inst::sh), ("sw", Inst::sw),
+];
+
+const BRANCH_OPS: [(&str, ImmOp); 6] = [
+    ("beq", Inst::beq), ("bne", Inst::bne), ("blt", Inst::blt),
+    ("bge", Inst::bge), ("bltu", Inst::bltu), ("bgeu", Inst::bgeu),
+];
+
+/* without the .w, the ordering suffixes can follow it, lr has no rs2 and is handled on its own */
+const AMO_OPS: [(&str, RegOp); 10] = [
+    ("sc", Inst::sc_w), ("amoswap", Inst::amoswap_w), ("amoadd", Inst::amoadd_w),
+    ("amoxor", Inst::amoxor_w), ("amoand", Inst::amoand_w), ("amoor", Inst::amoor_w),
+    ("amomin", Inst::amomin_w), ("amomax", Inst::amomax_w), ("amominu", Inst::amominu_w),
+    ("amomaxu", Inst::amomaxu_w),
+];
+
+const CSR_OPS: [(&str, CsrOp); 3] = [
+    ("csrrw", Inst::csrrw), ("csrrs", Inst::csrrs), ("csrrc", Inst::csrrc),
+];
+
+const CSR_IMM_OPS: [(&str, CsrImmOp); 3] = [
+    ("csrrwi", Inst::csrrwi), ("csrrsi", Inst::csrrsi), ("csrrci", Inst::csrrci),
+];
+
+const PRIV_OPS: [(&str, PrivOp); 5] = [
+    ("ecall", Inst::ecall), ("ebreak", Inst::ebreak), ("sret", Inst::sret), ("mret", Inst::mret), ("wfi", Inst::wfi),
+];
+
+/* directives that don't change the image */
+const IGNORED_DIRECTIVES: [&str; 10] = [
+    ".globl", ".global", ".local", ".type", ".size", ".file", ".ident", ".option", ".attribute", ".weak",
+];
+
+fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    return table.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+}
+
+/* x0..x31, the ABI names and fp (s0) */
+fn parse_reg(s: &str) -> Result<Reg,String> {
+    let s: &str = s.trim();
+    if s == "fp" {
+        return Ok(Reg::S0);
+    }
+    if let Some(reg) = (0..32).find(|r| xreg(*r) == s) {
+        return Ok(Reg::x(reg));
+    }
+    return match s.strip_prefix('x').map(|n| n.parse::<u8>()) {
+        Some(Ok(reg)) if reg < 32 => Ok(Reg::x(reg)),
+        _ => Err(format!("invalid register '{}'", s)),
+    };
+}
+
+/* low 12 bits sign extended, what addi/jalr/loads add on top of lui/auipc */
+fn lo12(val: i64) -> i64 {
+    return ((val & 0xFFF) ^ 0x800) - 0x800;
+}
+
+/* upper 20 bits that go with lo12 */
+fn hi20(val: i64) -> i64 {
+    return ((val - lo12(val)) >> 12) & 0xFFFFF;
+}
+
+fn check_range(val: i64, lo: i64, hi: i64, what: &str) -> Result<i64,String> {
+    if val < lo || val > hi {
+        return Err(format!("{} {} out of range [{}, {}]", what, val, lo, hi));
+    }
+    return Ok(val);
+}
+
+fn check_args(args: &[String], n: usize) -> Result<(),String> {
+    if args.len() != n {
+        return Err(format!("expected {} operands, got {}", n, args.len()));
+    }
+    return Ok(());
+}
+
+/* split an operand list on the commas outside of quotes and parentheses */
+fn split_operands(s: &str) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    let mut cur: String = String::new();
+    let mut depth: u32 = 0;
+    let mut quoted: bool = false;
+    let mut escaped: bool = false;
+
+    for c in s.chars() {
+        if quoted {
+            cur.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                quoted = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => { quoted = true; cur.push(c); }
+            '(' => { depth += 1; cur.push(c); }
+            ')' => { depth = depth.saturating_sub(1); cur.push(c); }
+            ',' if depth == 0 => { args.push(cur.trim().to_string()); cur.clear(); }
+            _ => cur.push(c),
+        }
+    }
+    if !cur.trim().is_empty() || !args.is_empty() {
+        args.push(cur.trim().to_string());
+    }
+    return args;
+}
+
+/* drop a # comment, unless the # is inside a string or character literal */
+fn strip_comment(line: &str) -> &str {
+    let mut quoted: bool = false;
+    let mut escaped: bool = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == '#' && !quoted && !line[..i].ends_with('\'') {
+            return &line[..i];
+        }
+    }
+    return line;
+}
+
+/* contents of a "..." literal with the usual C escapes */
+fn parse_string(s: &str) -> Result<Vec<u8>,String> {
+    let s: &str = s.trim();
+    let inner: &str = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).ok_or(format!("expected a string, got '{}'", s))?;
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf: [u8; 4] = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        bytes.push(match chars.next() {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            Some('\'') => b'\'',
+            _ => return Err(format!("bad escape in {}", s)),
+        });
+    }
+    return Ok(bytes);
+}
+
+/* labels first, then the statement, a line can be just a label */
+fn parse(src: &str) -> Vec<(usize, Item)> {
+    let mut items: Vec<(usize, Item)> = Vec::new();
+
+    for (i, raw) in src.lines().enumerate() {
+        let line: usize = i + 1;
+        let mut text: &str = strip_comment(raw).trim();
+
+        while let Some(colon) = text.find(':') {
+            let name: &str = text[..colon].trim();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$') {
+                break;
+            }
+            items.push((line, Item::Label(name.to_string())));
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (op, rest): (&str, &str) = match text.find(char::is_whitespace) {
+            Some(n) => (&text[..n], text[n..].trim()),
+            None => (text, ""),
+        };
+        items.push((line, Item::Stmt(op.to_lowercase(), split_operands(rest))));
+    }
+    return items;
+}
+
+struct Assembler {
+    symbols: HashMap<String, i64>,
+    locals: Vec<(String, usize, i64)>, /* numeric labels: name, item index, address */
+    sizes: Vec<u64>, /* bytes each item takes, worked out in pass one */
+    item: usize,     /* item being assembled */
+    pc: i64,
+    text: bool,      /* inside a code section, alignment pads with nops */
+    out: Vec<u8>,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        return Assembler {
+            symbols: HashMap::new(),
+            locals: Vec::new(),
+            sizes: Vec::new(),
+            item: 0,
+            pc: 0,
+            text: true,
+            out: Vec::new(),
+        };
+    }
+
+    /*
+     * name: eval
+     * desc: value of an expression, terms added and subtracted left to right
+     *
+     * NOTE: a term is a number (decimal, 0x, 0b or 'c'), a symbol, . (the current
+     *       address), a numeric label reference, a parenthesized expression or
+     *       %hi()/%lo() of an expression
+     */
+    fn eval(&self, expr: &str) -> Result<i64,String> {
+        let s: &str = expr.trim();
+        if s.is_empty() {
+            return Err("missing expression".to_string());
+        }
+
+        let mut val: i64 = 0;
+        let mut rest: &str = s;
+        loop {
+            let mut sign: i64 = 1;
+            rest = rest.trim_start();
+            while let Some(r) = rest.strip_prefix('-').or(rest.strip_prefix('+')) {
+                if rest.starts_with('-') {
+                    sign = -sign;
+                }
+                rest = r.trim_start();
+            }
+
+            let (term, tail): (i64, &str) = self.term(rest).map_err(|e| if e.is_empty() { format!("bad expression '{}'", s) } else { e })?;
+            val = val.wrapping_add(sign * term);
+
+            rest = tail.trim_start();
+            if rest.is_empty() {
+                return Ok(val);
+            }
+            if !rest.starts_with('+') && !rest.starts_with('-') {
+                return Err(format!("bad expression '{}'", s));
+            }
+        }
+    }
+
+    /* one term at the start of s and what follows it, an empty error means a syntax error */
+    fn term<'a>(&self, s: &'a str) -> Result<(i64, &'a str),String> {
+        for (func, hi) in [("%hi", true), ("%lo", false)] {
+            if let Some(inner) = s.strip_prefix(func).filter(|inner| inner.starts_with('(')) {
+                let (val, tail): (i64, &str) = self.term(inner)?;
+                return Ok((if hi { hi20(val) } else { lo12(val) }, tail));
+            }
+        }
+
+        if s.starts_with('(') {
+            let mut depth: u32 = 0;
+            for (i, c) in s.char_indices() {
+                match c {
+                    '(' => depth += 1,
+                    ')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok((self.eval(&s[1..i])?, &s[i + 1..]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            return Err(String::new());
+        }
+
+        if let Some(lit) = s.strip_prefix('\'') {
+            let end: usize = lit.find('\'').ok_or(String::new())?;
+            let bytes: Vec<u8> = parse_string(&format!("\"{}\"", &lit[..end]))?;
+            if bytes.len() != 1 {
+                return Err(String::new());
+            }
+            return Ok((bytes[0] as i64, &lit[end + 1..]));
+        }
+
+        let end: usize = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')).unwrap_or(s.len());
+        if end == 0 {
+            return Err(String::new());
+        }
+        return Ok((self.value(&s[..end])?, &s[end..]));
+    }
+
+    /* a number, . or a symbol */
+    fn value(&self, tok: &str) -> Result<i64,String> {
+        if tok == "." {
+            return Ok(self.pc);
+        }
+
+        if tok.starts_with(|c: char| c.is_ascii_digit()) {
+            let digits: &str = &tok[..tok.len() - 1];
+            if tok.len() > 1 && digits.chars().all(|c| c.is_ascii_digit()) && (tok.ends_with('b') || tok.ends_with('f')) {
+                return self.local(digits, tok.ends_with('f')).ok_or(format!("undefined label {}", tok));
+            }
+            let lower: String = tok.to_lowercase();
+            let res = if let Some(hex) = lower.strip_prefix("0x") {
+                i64::from_str_radix(hex, 16)
+            } else if let Some(bin) = lower.strip_prefix("0b") {
+                i64::from_str_radix(bin, 2)
+            } else {
+                lower.parse::<i64>()
+            };
+            return res.map_err(|_| format!("bad number '{}'", tok));
+        }
+
+        return self.symbols.get(tok).copied().ok_or(format!("undefined symbol '{}'", tok));
+    }
+
+    /* closest definition of a numeric label before (or after) the current item */
+    fn local(&self, name: &str, forward: bool) -> Option<i64> {
+        let mut defs = self.locals.iter().filter(|(n, _, _)| n == name);
+        let found = if forward {
+            defs.find(|(_, item, _)| *item > self.item)
+        } else {
+            defs.rfind(|(_, item, _)| *item < self.item)
+        };
+        return found.map(|(_, _, addr)| *addr);
+    }
+
+    fn define(&mut self, name: &str, val: i64) -> Result<(),String> {
+        if name.chars().all(|c| c.is_ascii_digit()) {
+            self.locals.push((name.to_string(), self.item, val));
+            return Ok(());
+        }
+        if self.symbols.insert(name.to_string(), val).is_some() {
+            return Err(format!("symbol '{}' is already defined", name));
+        }
+        return Ok(());
+    }
+
+    /* "imm(reg)" or "(reg)" */
+    fn mem_operand(&self, s: &str) -> Result<(i64, Reg),String> {
+        let s: &str = s.trim();
+        let open: usize = s.rfind('(').ok_or(format!("expected offset(register), got '{}'", s))?;
+        let reg: &str = s[open + 1..].strip_suffix(')').ok_or(format!("expected offset(register), got '{}'", s))?;
+        let offset: i64 = if s[..open].trim().is_empty() { 0 } else { self.eval(&s[..open])? };
+        return Ok((offset, parse_reg(reg)?));
+    }
+
+    /* a CSR by name or number */
+    fn csr_operand(&self, s: &str) -> Result<u16,String> {
+        return match csr_addr(s) {
+            Some(addr) => Ok(addr),
+            None => Ok(check_range(self.eval(s)?, 0, 0xFFF, "csr")? as u16),
+        };
+    }
+
+    /* pc relative offset to a branch/jump target, range is in bytes either way */
+    fn target(&self, s: &str, range: i64) -> Result<i64,String> {
+        let offset: i64 = self.eval(s)? - self.pc;
+        if offset % 2 != 0 {
+            return Err(format!("branch target {} is misaligned", s));
+        }
+        return check_range(offset, -range, range - 2, "branch offset");
+    }
+
+    /* li: one addi when the value fits in 12 bits, one lui when its low bits are zero */
+    fn li_size(&self, args: &[String]) -> u64 {
+        return match args.get(1).map(|v| self.eval(v)) {
+            Some(Ok(val)) if lo12(val as i32 as i64) == val as i32 as i64 || val & 0xFFF == 0 => 4,
+            _ => 8,
+        };
+    }
+
+    /* bytes a statement takes, pass one */
+    fn size(&self, op: &str, args: &[String]) -> Result<u64,String> {
+        return match op {
+            ".byte" => Ok(args.len() as u64),
+            ".half" | ".short" | ".2byte" => Ok(2 * args.len() as u64),
+            ".word" | ".long" | ".4byte" => Ok(4 * args.len() as u64),
+            ".ascii" | ".asciz" | ".string" => {
+                let nul: u64 = if op == ".ascii" { 0 } else { 1 };
+                let mut n: u64 = 0;
+                for a in args {
+                    n += parse_string(a)?.len() as u64 + nul;
+                }
+                Ok(n)
+            }
+            ".zero" | ".space" | ".skip" => {
+                let n: i64 = self.eval(args.first().ok_or("missing size")?)?;
+                Ok(check_range(n, 0, 1 << 24, "size")? as u64)
+            }
+            ".align" | ".p2align" | ".balign" => Ok(self.padding(op, args)?),
+            "li" => Ok(self.li_size(args)),
+            "la" | "lla" | "call" | "tail" => Ok(8),
+            _ if op.starts_with('.') => Ok(0),
+            _ => Ok(4),
+        };
+    }
+
+    /* .align/.p2align take a power of two, .balign the alignment itself, both top out at 64KiB */
+    fn padding(&self, op: &str, args: &[String]) -> Result<u64,String> {
+        let n: i64 = self.eval(args.first().ok_or("missing alignment")?)?;
+        let align: i64 = if op == ".balign" {
+            let n: i64 = check_range(n, 1, 1 << 16, "alignment")?;
+            if (n & (n - 1)) != 0 {
+                return Err(format!("alignment {} is not a power of two", n));
+            }
+            n
+        } else {
+            1 << check_range(n, 0, 16, "alignment")?
+        };
+        return Ok(((align - self.pc % align) % align) as u64);
+    }
+
+    fn emit32(&mut self, inst: Instruction) {
+        self.out.extend_from_slice(&inst.encode().to_le_bytes());
+    }
+
+    /* the integer data directives, values have to fit either signed or unsigned */
+    fn data(&mut self, args: &[String], width: u32) -> Result<(),String> {
+        for a in args {
+            let val: i64 = self.eval(a)?;
+            let bits: u32 = width * 8;
+            check_range(val, -(1 << (bits - 1)), (1 << bits) - 1, "value")?;
+            self.out.extend_from_slice(&val.to_le_bytes()[..width as usize]);
+        }
+        return Ok(());
+    }
+
+    /* pass two, everything but instructions */
+    fn directive(&mut self, op: &str, args: &[String]) -> Result<(),String> {
+        match op {
+            ".text" => self.text = true,
+            ".data" | ".rodata" | ".bss" => self.text = false,
+            ".section" => self.text = args.first().is_some_and(|name| name.starts_with(".text")),
+            ".equ" | ".set" => {}
+            ".byte" => self.data(args, 1)?,
+            ".half" | ".short" | ".2byte" => self.data(args, 2)?,
+            ".word" | ".long" | ".4byte" => self.data(args, 4)?,
+            ".ascii" | ".asciz" | ".string" => {
+                for a in args {
+                    let bytes: Vec<u8> = parse_string(a)?;
+                    self.out.extend_from_slice(&bytes);
+                    if op != ".ascii" {
+                        self.out.push(0);
+                    }
+                }
+            }
+            ".zero" | ".space" | ".skip" => {
+                let n: u64 = self.sizes[self.item];
+                let fill: i64 = match args.get(1) {
+                    Some(f) => check_range(self.eval(f)?, -128, 255, "fill value")?,
+                    None => 0,
+                };
+                self.out.resize(self.out.len() + n as usize, fill as u8);
+            }
+            ".align" | ".p2align" | ".balign" => {
+                let n: u64 = self.sizes[self.item];
+                /* code falling through an alignment keeps running */
+                if self.text && n.is_multiple_of(4) && self.pc % 4 == 0 {
+                    for _ in 0..n / 4 {
+                        self.emit32(Inst::addi(Reg::Zero, Reg::Zero, 0));
+                    }
+                } else {
+                    self.out.resize(self.out.len() + n as usize, 0);
+                }
+            }
+            _ if IGNORED_DIRECTIVES.contains(&op) => {}
+            _ => return Err(format!("unknown directive {}", op)),
+        }
+        return Ok(());
+    }
+
+    /*
+     * name: instruction
+     * desc: encode one instruction or pseudo-instruction, pass two
+     *
+     * NOTE: pseudo-instructions that are a single base instruction are rewritten
+     *       and encoded through this same function
+     */
+    fn instruction(&mut self, op: &str, args: &[String]) -> Result<(),String> {
+        if self.pc % 4 != 0 {
+            return Err("instruction is not 4 byte aligned".to_string());
+        }
+        let a = |i: usize| -> &str { return args[i].as_str() };
+
+        if let Some(make) = lookup(&R_OPS, op) {
+            check_args(args, 3)?;
+            self.emit32(make(parse_reg(a(0))?, parse_reg(a(1))?, parse_reg(a(2))?));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&I_OPS, op) {
+            check_args(args, 3)?;
+            let imm: i64 = check_range(self.eval(a(2))?, -2048, 2047, "immediate")?;
+            self.emit32(make(parse_reg(a(0))?, parse_reg(a(1))?, imm as i32));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&SHIFT_OPS, op) {
+            check_args(args, 3)?;
+            let shamt: i64 = check_range(self.eval(a(2))?, 0, 31, "shift amount")?;
+            self.emit32(make(parse_reg(a(0))?, parse_reg(a(1))?, shamt as u32));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&LOAD_OPS, op) {
+            check_args(args, 2)?;
+            let (offset, rs1): (i64, Reg) = self.mem_operand(a(1))?;
+            let offset: i64 = check_range(offset, -2048, 2047, "offset")?;
+            self.emit32(make(parse_reg(a(0))?, rs1, offset as i32));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&STORE_OPS, op) {
+            check_args(args, 2)?;
+            let (offset, rs1): (i64, Reg) = self.mem_operand(a(1))?;
+            let offset: i64 = check_range(offset, -2048, 2047, "offset")?;
+            self.emit32(make(parse_reg(a(0))?, rs1, offset as i32));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&BRANCH_OPS, op) {
+            check_args(args, 3)?;
+            let offset: i64 = self.target(a(2), 4096)?;
+            self.emit32(make(parse_reg(a(0))?, parse_reg(a(1))?, offset as i32));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&CSR_OPS, op) {
+            check_args(args, 3)?;
+            self.emit32(make(parse_reg(a(0))?, self.csr_operand(a(1))?, parse_reg(a(2))?));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&CSR_IMM_OPS, op) {
+            check_args(args, 3)?;
+            let uimm: i64 = check_range(self.eval(a(2))?, 0, 31, "immediate")?;
+            self.emit32(make(parse_reg(a(0))?, self.csr_operand(a(1))?, uimm as u32));
+            return Ok(());
+        }
+        if let Some(make) = lookup(&PRIV_OPS, op) {
+            check_args(args, 0)?;
+            self.emit32(make());
+            return Ok(());
+        }
+        if let Some(inst) = self.amo(op, args)? {
+            self.emit32(inst);
+            return Ok(());
+        }
+
+        match op {
+            "lui" | "auipc" => {
+                check_args(args, 2)?;
+                let imm: i64 = check_range(self.eval(a(1))?, 0, 0xFFFFF, "immediate")?;
+                let make: fn(Reg, u32) -> Instruction = if op == "lui" { Inst::lui } else { Inst::auipc };
+                self.emit32(make(parse_reg(a(0))?, imm as u32));
+            }
+            /* jal target is jal ra,target */
+            "jal" => {
+                let (rd, target): (Reg, &str) = match args.len() {
+                    1 => (Reg::Ra, a(0)),
+                    2 => (parse_reg(a(0))?, a(1)),
+                    _ => return Err("expected 1 or 2 operands".to_string()),
+                };
+                let offset: i64 = self.target(target, 1 << 20)?;
+                self.emit32(Inst::jal(rd, offset as i32));
+            }
+            /* jalr rs, jalr rd,rs, jalr rd,rs,imm and jalr rd,imm(rs) */
+            "jalr" => {
+                let (rd, rs1, imm): (Reg, Reg, i64) = match args.len() {
+                    1 => (Reg::Ra, parse_reg(a(0))?, 0),
+                    2 if a(1).contains('(') => {
+                        let (offset, rs1): (i64, Reg) = self.mem_operand(a(1))?;
+                        (parse_reg(a(0))?, rs1, offset)
+                    }
+                    2 => (parse_reg(a(0))?, parse_reg(a(1))?, 0),
+                    3 => (parse_reg(a(0))?, parse_reg(a(1))?, self.eval(a(2))?),
+                    _ => return Err("expected 1 to 3 operands".to_string()),
+                };
+                let imm: i64 = check_range(imm, -2048, 2047, "offset")?;
+                self.emit32(Inst::jalr(rd, rs1, imm as i32));
+            }
+            "fence" => {
+                let (pred, succ): (u8, u8) = match args.len() {
+                    0 => (0xF, 0xF),
+                    2 => (fence_bits(a(0))?, fence_bits(a(1))?),
+                    _ => return Err("expected 0 or 2 operands".to_string()),
+                };
+                self.emit32(Inst::fence(pred, succ));
+            }
+            "fence.tso" => {
+                check_args(args, 0)?;
+                self.emit32(Inst::fence_tso());
+            }
+            "fence.i" => {
+                check_args(args, 0)?;
+                self.emit32(Inst::fence_i());
+            }
+            "sfence.vma" => {
+                let rs1: Reg = if !args.is_empty() { parse_reg(a(0))? } else { Reg::Zero };
+                let rs2: Reg = if args.len() > 1 { parse_reg(a(1))? } else { Reg::Zero };
+                self.emit32(Inst::sfence_vma(rs1, rs2));
+            }
+            "li" => {
+                check_args(args, 2)?;
+                let rd: Reg = parse_reg(a(0))?;
+                let val: i64 = check_range(self.eval(a(1))?, i32::MIN as i64, u32::MAX as i64, "immediate")?;
+                let val: i64 = val as i32 as i64;
+                let lo: i64 = lo12(val);
+                if self.sizes[self.item] == 8 || (lo != val && lo != 0) {
+                    self.emit32(Inst::lui(rd, hi20(val) as u32));
+                    self.emit32(Inst::addi(rd, rd, lo as i32));
+                } else if lo == val {
+                    self.emit32(Inst::addi(rd, Reg::Zero, lo as i32));
+                } else {
+                    self.emit32(Inst::lui(rd, hi20(val) as u32));
+                }
+            }
+            /* auipc pairs, rd (or the scratch register) holds the upper part */
+            "la" | "lla" | "call" | "tail" => {
+                let (rd, scratch, target): (Reg, Reg, &str) = match op {
+                    "call" if args.len() == 1 => (Reg::Ra, Reg::Ra, a(0)),
+                    "tail" if args.len() == 1 => (Reg::Zero, Reg::T1, a(0)),
+                    "la" | "lla" if args.len() == 2 => (parse_reg(a(0))?, parse_reg(a(0))?, a(1)),
+                    _ => return Err(format!("wrong number of operands for {}", op)),
+                };
+                let offset: i64 = check_range(self.eval(target)? - self.pc, i32::MIN as i64, i32::MAX as i64, "offset")?;
+                self.emit32(Inst::auipc(scratch, hi20(offset) as u32));
+                let inst: Instruction = if op.starts_with('l') {
+                    Inst::addi(rd, scratch, lo12(offset) as i32)
+                } else {
+                    Inst::jalr(rd, scratch, lo12(offset) as i32)
+                };
+                self.emit32(inst);
+            }
+            _ => {
+                let (base, base_args): (&str, Vec<String>) = pseudo(op, args)?;
+                return self.instruction(base, &base_args);
+            }
+        }
+        return Ok(());
+    }
+
+    /* lr.w, sc.w and amo*.w with an optional .aq/.rl/.aqrl, None if op isn't one */
+    fn amo(&self, op: &str, args: &[String]) -> Result<Option<Instruction>,String> {
+        let (name, ordering): (&str, &str) = match op.find(".w") {
+            Some(n) => (&op[..n], &op[n + 2..]),
+            None => return Ok(None),
+        };
+        let make: Option<RegOp> = lookup(&AMO_OPS, name);
+        if make.is_none() && name != "lr" {
+            return Ok(None);
+        }
+        let (aq, rl): (bool, bool) = match ordering {
+            "" => (false, false),
+            ".aq" => (true, false),
+            ".rl" => (false, true),
+            ".aqrl" => (true, true),
+            _ => return Ok(None),
+        };
+
+        check_args(args, if make.is_none() { 2 } else { 3 })?;
+        let (offset, rs1): (i64, Reg) = self.mem_operand(&args[args.len() - 1])?;
+        if offset != 0 {
+            return Err("atomics take (register) without an offset".to_string());
+        }
+        let rd: Reg = parse_reg(&args[0])?;
+        let mut inst: Instruction = match make {
+            Some(make) => make(rd, parse_reg(&args[1])?, rs1),
+            None => Inst::lr_w(rd, rs1),
+        };
+        if aq {
+            inst = inst.aq();
+        }
+        if rl {
+            inst = inst.rl();
+        }
+        return Ok(Some(inst));
+    }
+
+    /* pass one: label addresses and statement sizes */
+    fn layout(&mut self, items: &[(usize, Item)]) -> Result<(),AsmError> {
+        self.pc = 0;
+        for (i, (line, item)) in items.iter().enumerate() {
+            self.item = i;
+            let err = |msg: String| -> AsmError { return AsmError { line: *line, msg: msg } };
+            let size: u64 = match item {
+                Item::Label(name) => {
+                    self.define(name, self.pc).map_err(err)?;
+                    0
+                }
+                Item::Stmt(op, args) if op == ".equ" || op == ".set" => {
+                    check_args(args, 2).map_err(err)?;
+                    let val: i64 = self.eval(&args[1]).map_err(err)?;
+                    self.define(&args[0], val).map_err(err)?;
+                    0
+                }
+                Item::Stmt(op, args) => self.size(op, args).map_err(err)?,
+            };
+            self.sizes.push(size);
+            self.pc += size as i64;
+        }
+        return Ok(());
+    }
+
+    /* pass two: encode into out */
+    fn emit(&mut self, items: &[(usize, Item)]) -> Result<(),AsmError> {
+        self.pc = 0;
+        self.text = true;
+        for (i, (line, item)) in items.iter().enumerate() {
+            self.item = i;
+            if let Item::Stmt(op, args) = item {
+                let res: Result<(),String> = if op.starts_with('.') {
+                    self.directive(op, args)
+                } else {
+                    self.instruction(op, args)
+                };
+                res.map_err(|msg| AsmError { line: *line, msg: format!("{}: {}", op, msg) })?;
+            }
+            self.pc += self.sizes[i] as i64;
+        }
+        return Ok(());
+    }
+}
+
+/* fence operands, a combination of i, o, r, w */
+fn fence_bits(s: &str) -> Result<u8,String> {
+    let mut bits: u8 = 0;
+    for c in s.trim().chars() {
+        bits |= match c {
+            'i' => 0x8,
+            'o' => 0x4,
+            'r' => 0x2,
+            'w' => 0x1,
+            _ => return Err(format!("bad fence operand '{}'", s)),
+        };
+    }
+    return Ok(bits);
+}
+
+/* pseudo-instructions that are one base instruction with different operands */
+fn pseudo(op: &str, args: &[String]) -> Result<(&'static str, Vec<String>),String> {
+    let zero: String = "zero".to_string();
+    let v = |list: &[&String]| -> Vec<String> { return list.iter().map(|s| s.to_string()).collect() };
+    let arity = |n: usize| -> Result<(),String> { return check_args(args, n) };
+
+    return match op {
+        "nop" => { arity(0)?; Ok(("addi", v(&[&zero, &zero, &"0".to_string()]))) }
+        "mv" => { arity(2)?; Ok(("addi", v(&[&args[0], &args[1], &"0".to_string()]))) }
+        "not" => { arity(2)?; Ok(("xori", v(&[&args[0], &args[1], &"-1".to_string()]))) }
+        "neg" => { arity(2)?; Ok(("sub", v(&[&args[0], &zero, &args[1]]))) }
+        "seqz" => { arity(2)?; Ok(("sltiu", v(&[&args[0], &args[1], &"1".to_string()]))) }
+        "snez" => { arity(2)?; Ok(("sltu", v(&[&args[0], &zero, &args[1]]))) }
+        "sltz" => { arity(2)?; Ok(("slt", v(&[&args[0], &args[1], &zero]))) }
+        "sgtz" => { arity(2)?; Ok(("slt", v(&[&args[0], &zero, &args[1]]))) }
+        "beqz" => { arity(2)?; Ok(("beq", v(&[&args[0], &zero, &args[1]]))) }
+        "bnez" => { arity(2)?; Ok(("bne", v(&[&args[0], &zero, &args[1]]))) }
+        "blez" => { arity(2)?; Ok(("bge", v(&[&zero, &args[0], &args[1]]))) }
+        "bgez" => { arity(2)?; Ok(("bge", v(&[&args[0], &zero, &args[1]]))) }
+        "bltz" => { arity(2)?; Ok(("blt", v(&[&args[0], &zero, &args[1]]))) }
+        "bgtz" => { arity(2)?; Ok(("blt", v(&[&zero, &args[0], &args[1]]))) }
+        "bgt" => { arity(3)?; Ok(("blt", v(&[&args[1], &args[0], &args[2]]))) }
+        "ble" => { arity(3)?; Ok(("bge", v(&[&args[1], &args[0], &args[2]]))) }
+        "bgtu" => { arity(3)?; Ok(("bltu", v(&[&args[1], &args[0], &args[2]]))) }
+        "bleu" => { arity(3)?; Ok(("bgeu", v(&[&args[1], &args[0], &args[2]]))) }
+        "j" => { arity(1)?; Ok(("jal", v(&[&zero, &args[0]]))) }
+        "jr" => { arity(1)?; Ok(("jalr", v(&[&zero, &args[0], &"0".to_string()]))) }
+        "ret" => { arity(0)?; Ok(("jalr", v(&[&zero, &"ra".to_string(), &"0".to_string()]))) }
+        "csrr" => { arity(2)?; Ok(("csrrs", v(&[&args[0], &args[1], &zero]))) }
+        "csrw" => { arity(2)?; Ok(("csrrw", v(&[&zero, &args[0], &args[1]]))) }
+        "csrs" => { arity(2)?; Ok(("csrrs", v(&[&zero, &args[0], &args[1]]))) }
+        "csrc" => { arity(2)?; Ok(("csrrc", v(&[&zero, &args[0], &args[1]]))) }
+        "csrwi" => { arity(2)?; Ok(("csrrwi", v(&[&zero, &args[0], &args[1]]))) }
+        "csrsi" => { arity(2)?; Ok(("csrrsi", v(&[&zero, &args[0], &args[1]]))) }
+        "csrci" => { arity(2)?; Ok(("csrrci", v(&[&zero, &args[0], &args[1]]))) }
+        "rdcycle" | "rdcycleh" | "rdtime" | "rdtimeh" | "rdinstret" | "rdinstreth" => {
+            arity(1)?;
+            Ok(("csrrs", v(&[&args[0], &op[2..].to_string(), &zero])))
+        }
+        /* csrrw zero,cycle,zero, cycle is read only so it always traps */
+        "unimp" => { arity(0)?; Ok(("csrrw", v(&[&zero, &"cycle".to_string(), &zero]))) }
+        _ => Err("unknown instruction".to_string()),
+    };
+}
+
+/*
+ * name: assemble
+ * desc: assemble a whole source file into an image that starts at address 0
+ */
+pub fn assemble(src: &str) -> Result<Vec<u8>,AsmError> {
+    let items: Vec<(usize, Item)> = parse(src);
+    let mut asm: Assembler = Assembler::new();
+    asm.layout(&items)?;
+    asm.emit(&items)?;
+    return Ok(asm.out);
+}
+
+/* assemble src and make it the contents of mem, ready to run */
+pub fn assemble_into(mem: &mut Memory, src: &str) -> Result<(),AsmError> {
+    let image: Vec<u8> = assemble(src)?;
+    mem.load_from_bytes(&image);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::*;
+    use crate::isa::*;
+    use crate::xlen::*;
+
+    fn image(src: &str) -> Vec<u8> {
+        return assemble(src).unwrap_or_else(|e| panic!("line {}: {}", e.line, e.msg));
+    }
+
+    /* the image as 32 bit words, src has to assemble to a multiple of 4 bytes */
+    fn words(src: &str) -> Vec<u32> {
+        let image: Vec<u8> = image(src);
+        assert!(image.len().is_multiple_of(4), "{} bytes", image.len());
+        return image.chunks(4).map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])).collect();
+    }
+
+    fn enc(insts: &[Instruction]) -> Vec<u32> {
+        return insts.iter().map(|inst| inst.encode()).collect();
+    }
+
+    /* words the GNU/LLVM assemblers produce for the same source */
+    #[test]
+    fn assemble_words() {
+        let src: &str = "
+            addi a0, zero, 5
+            add a2, a0, a1
+            lw a0, -4(sp)
+            sw t5, 0(t6)
+            csrr a1, fflags
+            amoadd.w.aqrl a0, a2, (a1)
+            ret
+            fence
+            srai a0, a1, 7   # comment
+        ";
+        assert_eq!(words(src), vec![
+            0x00500513, 0x00b50633, 0xffc12503, 0x01efa023, 0x001025f3,
+            0x06c5a52f, 0x00008067, 0x0ff0000f, 0x4075d513,
+        ]);
+    }
+
+    /* one addi or lui when that's enough, lui+addi otherwise and always for a forward label */
+    #[test]
+    fn li_sizes() {
+        let src: &str = "
+            li a0, 5
+            li a1, 0x12345678
+            li a2, -1
+            li a3, 0x7ffff000
+            li a4, 0x12345fff
+            li a5, fwd
+        fwd:
+        ";
+        assert_eq!(words(src), enc(&[
+            Inst::addi(Reg::A0, Reg::Zero, 5),
+            Inst::lui(Reg::A1, 0x12345), Inst::addi(Reg::A1, Reg::A1, 0x678),
+            Inst::addi(Reg::A2, Reg::Zero, -1),
+            Inst::lui(Reg::A3, 0x7ffff),
+            Inst::lui(Reg::A4, 0x12346), Inst::addi(Reg::A4, Reg::A4, -1),
+            Inst::lui(Reg::A5, 0), Inst::addi(Reg::A5, Reg::A5, 36),
+        ]));
+    }
+
+    /* auipc pairs and the numeric label references */
+    #[test]
+    fn pc_relative() {
+        let src: &str = "
+            nop
+            la a0, target
+            call target
+            j 2f
+        1:  nop
+        2:  j 1b
+        target:
+            ret
+        ";
+        assert_eq!(words(src), enc(&[
+            Inst::addi(Reg::Zero, Reg::Zero, 0),
+            Inst::auipc(Reg::A0, 0), Inst::addi(Reg::A0, Reg::A0, 28),
+            Inst::auipc(Reg::Ra, 0), Inst::jalr(Reg::Ra, Reg::Ra, 20),
+            Inst::jal(Reg::Zero, 8),
+            Inst::addi(Reg::Zero, Reg::Zero, 0),
+            Inst::jal(Reg::Zero, -4),
+            Inst::jalr(Reg::Zero, Reg::Ra, 0),
+        ]));
+
+        /* a low part of -2048 carries into the auipc */
+        let far: Vec<u32> = words("call far\n.zero 0x7f8\nfar: ret");
+        assert_eq!(far[..2], enc(&[Inst::auipc(Reg::Ra, 1), Inst::jalr(Reg::Ra, Reg::Ra, -2048)]));
+    }
+
+    #[test]
+    fn data_directives() {
+        let src: &str = "
+            .word 1, -1, 0x12345678
+            .ascii \"ab\"
+            .align 2
+            .byte 7
+        ";
+        assert_eq!(image(src), vec![1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0x78, 0x56, 0x34, 0x12, b'a', b'b', 0, 0, 7]);
+
+        /* code keeps running through an alignment, data gets zeros */
+        let nop: u32 = Inst::addi(Reg::Zero, Reg::Zero, 0).encode();
+        assert_eq!(words("nop\n.align 3\nret"), vec![nop, nop, 0x00008067]);
+        assert_eq!(image(".data\n.byte 1\n.align 2\n.byte 2"), vec![1, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn errors_carry_the_line() {
+        let err: AsmError = assemble("nop\naddi a0, a0, 4096").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.msg.contains("out of range"), "{}", err.msg);
+
+        assert_eq!(assemble("nop\nnop\nj nowhere").unwrap_err().line, 3);
+        assert_eq!(assemble("add a0, a1").unwrap_err().line, 1);
+
+        let err: AsmError = assemble("nop\n.balign 0x10000000000").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.msg.contains("out of range"), "{}", err.msg);
+        assert!(assemble("nop\n.balign 0x10000").is_ok());
+    }
+
+    /* a loop, a call and memory, run until the final jump to itself */
+    #[test]
+    fn runs_on_a_machine() {
+        let src: &str = "
+                li a0, 0
+                li t0, 10
+            1:  add a0, a0, t0
+                addi t0, t0, -1
+                bnez t0, 1b
+                la a1, value
+                lw a2, 0(a1)
+                call double
+                sw a2, 4(a1)
+                lw a3, 4(a1)
+                j .
+            double:
+                add a2, a2, a2
+                ret
+                .align 2
+            value:
+                .word 21, 0
+        ";
+        let mut mem: Memory = Memory::new();
+        assemble_into(&mut mem, src).unwrap_or_else(|e| panic!("line {}: {}", e.line, e.msg));
+
+        let mut machine: Machine = Machine::new(mem, &IsaConfig::new(Xlen::RV32), 1);
+        machine.run();
+        let cpu: &crate::cpu::Cpu = &machine.get_harts()[0];
+        assert_eq!(cpu.get_reg(Reg::A0 as u8), 55);
+        assert_eq!(cpu.get_reg(Reg::A2 as u8), 42);
+        assert_eq!(cpu.get_reg(Reg::A3 as u8), 42);
+    }
+}
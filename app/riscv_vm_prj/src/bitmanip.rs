@@ -0,0 +1,187 @@
+/*
+ * name: bitmanip.rs
+ * desc: Zba, Zbb, Zbc and Zbs bit manipulation extensions
+ *
+ * Note: these share the OP/OP-IMM/OP-32/OP-IMM-32 opcodes with the base ISA, the
+ *       encodings are in the rv*_zba/zbb/zbc/zbs tables in opcodes/ and build.rs
+ *       generates the decoder that fills in BitmanipInst, the op is named after
+ *       the table entry (sh1add.uw -> SH1ADD_UW)
+ *
+ *       each extension can be left out of the ISA string (isa.rs), a disabled extension
+ *       decodes as an illegal instruction the same as on hardware that doesn't have it
+ *
+ */
+
+use crate::trap::*;
+use crate::xlen::*;
+use crate::disasm::*;
+
+/* the four extensions, see IsaExt::from_bitmanip */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitmanipExt {
+    Zba = 0, /* address generation */
+    Zbb = 1, /* basic bit manipulation */
+    Zbc = 2, /* carry-less multiply */
+    Zbs = 3, /* single bit instructions */
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum BitmanipOp {
+    /* Zba */
+    SH1ADD, SH2ADD, SH3ADD,
+    ADD_UW, SH1ADD_UW, SH2ADD_UW, SH3ADD_UW, SLLI_UW, /* RV64 only */
+
+    /* Zbb */
+    ANDN, ORN, XNOR,
+    CLZ, CTZ, CPOP,
+    CLZW, CTZW, CPOPW, /* RV64 only */
+    MAX, MAXU, MIN, MINU,
+    SEXT_B, SEXT_H, ZEXT_H,
+    ROL, ROR, RORI,
+    ROLW, RORW, RORIW, /* RV64 only */
+    ORC_B, REV8,
+
+    /* Zbc */
+    CLMUL, CLMULH, CLMULR,
+
+    /* Zbs */
+    BCLR, BCLRI, BEXT, BEXTI, BINV, BINVI, BSET, BSETI,
+}
+
+impl BitmanipOp {
+    pub fn extension(&self) -> BitmanipExt {
+        use BitmanipOp::*;
+
+        return match self {
+            SH1ADD | SH2ADD | SH3ADD | ADD_UW | SH1ADD_UW | SH2ADD_UW | SH3ADD_UW | SLLI_UW => BitmanipExt::Zba,
+            CLMUL | CLMULH | CLMULR => BitmanipExt::Zbc,
+            BCLR | BCLRI | BEXT | BEXTI | BINV | BINVI | BSET | BSETI => BitmanipExt::Zbs,
+            _ => BitmanipExt::Zbb,
+        };
+    }
+}
+
+/* one of the rv*_zb* table entries, operands the entry doesn't have are 0 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmanipInst {
+    pub op: BitmanipOp,
+    pub rd: u8,     /* 5 bits */
+    pub rs1: u8,    /* 5 bits */
+    pub rs2: u8,    /* 5 bits */
+    pub shamt: u8,  /* 6 bits, immediate shifts and bit indexes */
+    pub xlen: Xlen, /* width it was decoded for, ZEXT.H and REV8 encode differently per XLEN */
+}
+
+impl BitmanipInst {
+
+    pub fn extension(&self) -> BitmanipExt {
+        return self.op.extension();
+    }
+
+    /* objdump text of the instruction, name is the mnemonic of its table entry */
+    pub fn disassemble(&self, name: &str) -> String {
+        use BitmanipOp::*;
+
+        let (rd, rs1, rs2): (&str, &str, &str) = (xreg(self.rd), xreg(self.rs1), xreg(self.rs2));
+        return match self.op {
+            CLZ | CTZ | CPOP | CLZW | CTZW | CPOPW | SEXT_B | SEXT_H | ZEXT_H | ORC_B | REV8 => {
+                format!("{}\t{},{}", name, rd, rs1)
+            }
+            RORI | RORIW | BCLRI | BEXTI | BINVI | BSETI | SLLI_UW => {
+                format!("{}\t{},{},0x{:x}", name, rd, rs1, self.shamt)
+            }
+            _ => format!("{}\t{},{},{}", name, rd, rs1, rs2),
+        };
+    }
+
+    pub fn execute(&mut self, regs: &mut RegFile, pc: &mut u64) -> Result<(),Exception> {
+        use BitmanipOp::*;
+
+        let op: BitmanipOp = self.op;
+        let xlen: Xlen = regs.xlen();
+        let bits: u32 = xlen.bits();
+        let rs1: u64 = regs.read(self.rs1);
+        let rs2: u64 = regs.read(self.rs2);
+        let srs1: i64 = regs.read_signed(self.rs1);
+        let srs2: i64 = regs.read_signed(self.rs2);
+        let idx: u32 = (rs2 as u32) & xlen.shamt_mask(); /* bit index/shift amount from a register */
+        let uw: u64 = rs1 & 0xFFFF_FFFF;                  /* the *.uw ops zero extend rs1 first */
+
+        let shamt: u32 = self.shamt as u32;
+
+        /* rotate within XLEN bits */
+        let rotl = |val: u64, amt: u32| -> u64 {
+            return if xlen == Xlen::RV32 { (val as u32).rotate_left(amt) as u64 } else { val.rotate_left(amt) };
+        };
+
+        let res: u64 = match op {
+            SH1ADD => (rs1 << 1).wrapping_add(rs2),
+            SH2ADD => (rs1 << 2).wrapping_add(rs2),
+            SH3ADD => (rs1 << 3).wrapping_add(rs2),
+            ADD_UW => uw.wrapping_add(rs2),
+            SH1ADD_UW => (uw << 1).wrapping_add(rs2),
+            SH2ADD_UW => (uw << 2).wrapping_add(rs2),
+            SH3ADD_UW => (uw << 3).wrapping_add(rs2),
+            SLLI_UW => uw << shamt,
+
+            ANDN => rs1 & !rs2,
+            ORN => rs1 | !rs2,
+            XNOR => !(rs1 ^ rs2),
+            /* the upper half is zero on RV32, don't count it */
+            CLZ => (rs1.leading_zeros() - (64 - bits)) as u64,
+            CTZ => rs1.trailing_zeros().min(bits) as u64,
+            CPOP => rs1.count_ones() as u64,
+            CLZW => (rs1 as u32).leading_zeros() as u64,
+            CTZW => (rs1 as u32).trailing_zeros() as u64,
+            CPOPW => (rs1 as u32).count_ones() as u64,
+            MAX => srs1.max(srs2) as u64,
+            MAXU => rs1.max(rs2),
+            MIN => srs1.min(srs2) as u64,
+            MINU => rs1.min(rs2),
+            SEXT_B => rs1 as i8 as i64 as u64,
+            SEXT_H => rs1 as i16 as i64 as u64,
+            ZEXT_H => rs1 & 0xFFFF,
+            ROL => rotl(rs1, idx),
+            ROR => rotl(rs1, bits - idx),
+            RORI => rotl(rs1, bits - shamt),
+            ROLW => sext32((rs1 as u32).rotate_left((rs2 & 0x1F) as u32) as u64),
+            RORW => sext32((rs1 as u32).rotate_right((rs2 & 0x1F) as u32) as u64),
+            RORIW => sext32((rs1 as u32).rotate_right(shamt & 0x1F) as u64),
+            /* every non zero byte becomes 0xFF */
+            ORC_B => (0..8).fold(0, |acc, byte| {
+                return if (rs1 >> (byte * 8)) & 0xFF != 0 { acc | (0xFF << (byte * 8)) } else { acc };
+            }),
+            REV8 => if xlen == Xlen::RV32 { (rs1 as u32).swap_bytes() as u64 } else { rs1.swap_bytes() },
+
+            /* low, high and bit reversed (bits 2*XLEN-2 down to XLEN-1) halves of the product */
+            CLMUL => clmul(rs1, rs2, bits) as u64,
+            CLMULH => (clmul(rs1, rs2, bits) >> bits) as u64,
+            CLMULR => (clmul(rs1, rs2, bits) >> (bits - 1)) as u64,
+
+            BCLR => rs1 & !(1 << idx),
+            BCLRI => rs1 & !(1 << shamt),
+            BEXT => (rs1 >> idx) & 0x1,
+            BEXTI => (rs1 >> shamt) & 0x1,
+            BINV => rs1 ^ (1 << idx),
+            BINVI => rs1 ^ (1 << shamt),
+            BSET => rs1 | (1 << idx),
+            BSETI => rs1 | (1 << shamt),
+        };
+
+        regs.write(self.rd, res);
+        *pc = xlen.trunc(*pc + 4);
+        return Ok(());
+    }
+}
+
+/* full 2*XLEN bit carry-less product of two XLEN bit values */
+fn clmul(a: u64, b: u64, bits: u32) -> u128 {
+    let mut prod: u128 = 0;
+    for i in 0..bits {
+        if (b >> i) & 0x1 != 0 {
+            prod ^= (a as u128) << i;
+        }
+    }
+    return prod;
+}
@@ -0,0 +1,131 @@
+/*
+ * name: clint.rs
+ * desc: core local interruptor, the msip, mtimecmp and mtime registers that drive
+ *       each hart's machine software (MSIP) and timer (MTIP) interrupts
+ *
+ * Note: SiFive layout, one 32 bit msip word per hart at 0x0000, one 64 bit mtimecmp
+ *       per hart at 0x4000 and the shared mtime at 0xBFF8, accessed a byte at a time
+ *       like the uart registers
+ *
+ *       there is no real time base, mtime goes up by one for every step any hart
+ *       takes, once every hart is parked by WFI the machine skips ahead to the next
+ *       mtimecmp instead of ticking up to it
+ *
+ *       mtimecmp starts out at the largest value so no timer fires before software
+ *       programs one
+ *
+ */
+
+pub const CLINT_BASE: u64 = 0x0200_0000;
+pub const CLINT_SIZE: u64 = 0x1_0000;
+
+/* register offsets from CLINT_BASE */
+pub const CLINT_MSIP: u64 = 0x0000;     /* 4 bytes per hart, only bit 0 exists */
+pub const CLINT_MTIMECMP: u64 = 0x4000; /* 8 bytes per hart */
+pub const CLINT_MTIME: u64 = 0xBFF8;
+
+/* at most 4095 harts fit the msip block */
+const CLINT_MAX_HARTS: usize = 4095;
+
+#[derive(Debug)]
+pub struct Clint {
+    msip: Vec<bool>,
+    mtimecmp: Vec<u64>,
+    mtime: u64,
+}
+
+impl Clint {
+    /* constructor: nothing pending, no timer armed, grows as harts show up */
+    pub fn new() -> Clint {
+        return Clint {
+            msip: Vec::new(),
+            mtimecmp: Vec::new(),
+            mtime: 0,
+        };
+    }
+
+    pub fn contains(addr: u64) -> bool {
+        return (CLINT_BASE..CLINT_BASE + CLINT_SIZE).contains(&addr);
+    }
+
+    /* make room for hart, registers of harts nobody asked about yet read as reset values */
+    fn grow(&mut self, hart: usize) {
+        if hart >= self.msip.len() {
+            self.msip.resize(hart + 1, false);
+            self.mtimecmp.resize(hart + 1, u64::MAX);
+        }
+    }
+
+    /* byte of a register at offset from CLINT_BASE, holes read as zero */
+    pub fn read(&self, offset: u64) -> u8 {
+        let (reg, byte): (u64, u64) = match offset {
+            o if o < CLINT_MSIP + 4 * CLINT_MAX_HARTS as u64 => {
+                let hart: usize = ((o - CLINT_MSIP) / 4) as usize;
+                (self.msip.get(hart).map_or(0, |msip| *msip as u64), (o - CLINT_MSIP) % 4)
+            }
+            o if (CLINT_MTIMECMP..CLINT_MTIMECMP + 8 * CLINT_MAX_HARTS as u64).contains(&o) => {
+                let hart: usize = ((o - CLINT_MTIMECMP) / 8) as usize;
+                (self.mtimecmp.get(hart).map_or(u64::MAX, |cmp| *cmp), (o - CLINT_MTIMECMP) % 8)
+            }
+            o if (CLINT_MTIME..CLINT_MTIME + 8).contains(&o) => (self.mtime, o - CLINT_MTIME),
+            _ => return 0,
+        };
+        return (reg >> (8 * byte)) as u8;
+    }
+
+    pub fn write(&mut self, offset: u64, data: u8) {
+        match offset {
+            o if o < CLINT_MSIP + 4 * CLINT_MAX_HARTS as u64 => {
+                let hart: usize = ((o - CLINT_MSIP) / 4) as usize;
+                /* bit 0 of the lowest byte, the rest of the word is hardwired to zero */
+                if (o - CLINT_MSIP).is_multiple_of(4) {
+                    self.grow(hart);
+                    self.msip[hart] = (data & 1) != 0;
+                }
+            }
+            o if (CLINT_MTIMECMP..CLINT_MTIMECMP + 8 * CLINT_MAX_HARTS as u64).contains(&o) => {
+                let hart: usize = ((o - CLINT_MTIMECMP) / 8) as usize;
+                self.grow(hart);
+                self.mtimecmp[hart] = set_byte(self.mtimecmp[hart], (o - CLINT_MTIMECMP) % 8, data);
+            }
+            o if (CLINT_MTIME..CLINT_MTIME + 8).contains(&o) => {
+                self.mtime = set_byte(self.mtime, o - CLINT_MTIME, data);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn get_mtime(&self) -> u64 {
+        return self.mtime;
+    }
+
+    /* one step of one hart */
+    pub fn tick(&mut self) {
+        self.mtime = self.mtime.wrapping_add(1);
+    }
+
+    pub fn software_interrupt_pending(&self, hart: usize) -> bool {
+        return self.msip.get(hart).copied().unwrap_or(false);
+    }
+
+    pub fn timer_interrupt_pending(&self, hart: usize) -> bool {
+        return self.mtimecmp.get(hart).is_some_and(|cmp| self.mtime >= *cmp);
+    }
+
+    /* move mtime up to the closest armed mtimecmp still ahead of it, false if there is none */
+    pub fn skip_to_next_timer(&mut self) -> bool {
+        let next: Option<u64> = self.mtimecmp.iter().copied().filter(|cmp| *cmp > self.mtime && *cmp != u64::MAX).min();
+        return match next {
+            Some(cmp) => {
+                self.mtime = cmp;
+                true
+            }
+            None => false,
+        };
+    }
+}
+
+fn set_byte(reg: u64, byte: u64, data: u8) -> u64 {
+    let shift: u64 = 8 * byte;
+    return (reg & !(0xFF << shift)) | ((data as u64) << shift);
+}
@@ -1,73 +1,357 @@
-/* 
+/*
 * name: cpu.rs
 * desc: implements CPU logic including registers
-*  
+*
 */
 use crate::memory::*;
 use crate::idecoder::*;
-use std::process;
+use crate::csr::*;
+use crate::trap::*;
+use crate::rvc::*;
+use crate::xlen::*;
+use crate::fpu::*;
+use crate::isa::*;
+use crate::mmu::*;
+use crate::vector::*;
+use crate::hpm::*;
+use std::sync::MutexGuard;
+use std::thread;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct Cpu {
-    regs: [u32; 32], /* registers implemented via array */
-    pc: u128, /* program counter */
-    mem: Memory, 
+    regs: RegFile, /* XLEN wide integer registers */
+    fregs: FpRegFile, /* F/D registers */
+    vregs: VRegFile, /* V registers, VLEN bits each */
+    pc: u64, /* program counter, kept within XLEN bits */
+    mem: SharedMemory, /* locked by every access, other harts may share it */
+    hartid: usize,
+    mmu: Mmu, /* Sv32 translation and the TLB */
+    csrs: CsrFile, /* machine mode control and status registers */
+    isa: IsaConfig, /* which extensions are implemented */
+    halted: bool, /* set once the program can no longer make progress */
+    waiting: bool, /* parked by WFI until an interrupt is pending */
+    inst_count: u64, /* number of retired instructions */
 }
 
 impl Cpu {
-    pub fn new() -> Cpu {
+    /*
+     * constructor: takes a handle on an already loaded memory image, execution starts at address 0
+     *              isa picks between an RV32 and an RV64 hart and its extensions, hartid ends up in mhartid
+     */
+    pub fn new(mem: SharedMemory, isa: &IsaConfig, hartid: usize) -> Cpu {
+        let xlen: Xlen = isa.xlen();
         return Cpu {
-            regs: [0;32],
+            regs: RegFile::new(xlen),
+            fregs: FpRegFile::new(),
+            vregs: VRegFile::new(VLEN_DEFAULT),
             pc: 0,
-            mem: Memory::new(),
+            mem: mem,
+            hartid: hartid,
+            mmu: Mmu::new(),
+            csrs: CsrFile::new(isa, hartid as u64),
+            isa: *isa,
+            halted: false,
+            waiting: false,
+            inst_count: 0,
         };
     }
 
-    fn decode(inst : u32) {
-        let inst_type: InstType = opcode_to_InstType(inst);
+    pub fn is_halted(&self) -> bool {
+        return self.halted;
+    }
 
-        match inst_type {
+    /* parked by WFI, stepping it does nothing until an interrupt is pending */
+    pub fn is_waiting(&self) -> bool {
+        return self.waiting;
+    }
 
-            // RType => , 
-            // IType => ,
-            // SType => ,
-            // BType => ,
-            // UType => ,
-            // JType => ,
+    pub fn get_pc(&self) -> u64 {
+        return self.pc;
+    }
 
-            /* just hard fault the cpu */
-            Invalid => {
-                println!("ERROR: Invalid Opcode, Hard Faulting");
-                println!("Received Instruction: {:08x}",inst);
-                process::exit(1);
-            },
+    #[cfg(test)]
+    pub fn get_reg(&self, reg: u8) -> u64 {
+        return self.regs.read(reg);
+    }
+
+    pub fn get_inst_count(&self) -> u64 {
+        return self.inst_count;
+    }
+
+    pub fn get_tlb_stats(&self) -> TlbStats {
+        return self.mmu.get_stats();
+    }
+
+    /* pick VLEN (see is_valid_vlen), clears the vector registers */
+    pub fn set_vlen(&mut self, vlen: usize) {
+        self.vregs = VRegFile::new(vlen);
+        self.csrs.set_vlenb((vlen / 8) as u64);
+    }
+
+    /*
+     * name: step
+     * desc: fetch, decode and execute a single instruction
+     *
+     * NOTE: every execute() is responsible for moving the pc, so an instruction
+     *       that leaves the pc where it was is a jump to itself (ie while(1);)
+     *       and nothing can ever change again, treat that as the halt condition
+     *
+     *       a faulting instruction does not retire, the trap handler takes over
+     *
+     *       a jump to itself with interrupts enabled is an idle loop, not a halt
+     */
+    pub fn step(&mut self) {
+        if self.halted {
+            return;
+        }
+
+        self.update_interrupt_lines();
+
+        /* WFI: stay parked until an enabled interrupt is pending (regardless of mstatus.MIE) */
+        if self.waiting {
+            if self.csrs.pending_interrupts() == 0 {
+                /* nothing enabled can ever wake us up */
+                if self.csrs.get_mie() == 0 {
+                    self.halted = true;
+                }
+                return;
+            }
+            self.waiting = false;
+        }
+
+        if self.take_interrupt() {
+            return;
+        }
+
+        /* translation follows the privilege level and satp the instruction runs with */
+        self.mmu.set_context(&self.csrs);
+
+        let old_pc: u64 = self.pc;
+        let tlb_misses: u64 = self.mmu.get_stats().misses;
+
+        let res: Result<(),Exception> = match self.fetch() {
+            Ok((inst, ilen)) => self.decode(inst, ilen).map_err(|e| match e {
+                /* decoders don't keep the raw bits, fill them in for mtval */
+                Exception::IllegalInstruction(_) => Exception::IllegalInstruction(inst as u64),
+                _ => e,
+            }),
+            Err(e) => Err(e),
+        };
+
+        match res {
+            Ok(()) => {
+                self.inst_count += 1;
+                self.csrs.tick(true);
+            }
+            Err(e) => {
+                self.csrs.tick(false);
+                self.take_trap(e);
+            }
+        }
+
+        /* fetch and data walks alike, a faulting instruction still did its walks */
+        let walks: u64 = self.mmu.get_stats().misses - tlb_misses;
+        if walks != 0 {
+            self.csrs.record_event(HpmEvent::TLB_MISS, walks);
         }
 
+        if self.pc == old_pc && !self.can_take_interrupt() {
+            self.halted = true;
+        }
     }
 
-    fn decode_r_type(inst : u32) {
+    /*
+     * name: update_interrupt_lines
+     * desc: drive mip from the peripherals, MSIP and MTIP come from the hart's CLINT
+     *       registers, there is no PLIC so the uart only reaches hart 0
+     *
+     * NOTE: this is also where mtime ticks, once per step of every hart, time reads it
+     */
+    fn update_interrupt_lines(&mut self) {
+        let mut mem: MutexGuard<Memory> = self.mem.lock().unwrap();
+        mem.tick_timer();
+        self.csrs.set_time(mem.get_mtime());
+        self.csrs.set_pending(MipBm::MSIP_bm as u64, mem.software_interrupt_pending(self.hartid));
+        self.csrs.set_pending(MipBm::MTIP_bm as u64, mem.timer_interrupt_pending(self.hartid));
 
+        if self.hartid == 0 {
+            self.csrs.set_pending(MipBm::MEIP_bm as u64, mem.external_interrupt_pending());
+        }
     }
 
-    fn decode_i_type(inst : u32) {
+    /* could an interrupt ever arrive and get taken */
+    fn can_take_interrupt(&self) -> bool {
+        return self.csrs.interrupts_possible();
+    }
 
+    /*
+     * name: take_interrupt
+     * desc: take the highest priority pending interrupt, if any are enabled
+     *       at the current privilege level
+     */
+    fn take_interrupt(&mut self) -> bool {
+        match self.csrs.next_interrupt() {
+            Some(cause) => {
+                /* xepc is the instruction that would have run next */
+                self.pc = self.csrs.enter_trap(self.pc, cause, 0, true);
+                self.csrs.record_event(HpmEvent::TRAP, 1);
+                return true;
+            }
+            None => {
+                return false;
+            }
+        }
     }
 
-    fn decode_s_type(inst : u32) {
+    /* run until the halt condition is reached, sleeping while parked by WFI */
+    pub fn run(&mut self) {
+        while !self.halted {
+            self.step();
+            if self.waiting {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
 
+        /* make sure everything the program printed actually made it out */
+        self.mem.lock().unwrap().flush_peripherals();
     }
 
-    fn decode_b_type(inst : u32) {
+    /*
+     * name: fetch
+     * desc: returns the raw instruction bits and the instruction length, 16 bit
+     *       instructions are returned as is and expanded in decode()
+     *
+     * NOTE: the upper half of a 32 bit instruction is only fetched once the low
+     *       half says it's needed, it may sit in a different memory region or page
+     */
+    fn fetch(&mut self) -> Result<(u32, u8),Exception> {
+        let pc: u64 = self.pc;
+        let fault: Exception = Exception::InstructionAccessFault(pc);
+        let mut mem: MutexGuard<Memory> = self.mem.lock().unwrap();
+
+        let paddr: u64 = self.mmu.translate(&mut mem, pc, 2, AccessType::Fetch)?;
+        let low: u16 = mem.read_16bit(paddr).map_err(|_| fault)?;
+        if is_compressed(low) {
+            return Ok((low as u32, 2));
+        }
 
+        let pc_hi: u64 = self.regs.xlen().trunc(pc + 2);
+        let paddr_hi: u64 = self.mmu.translate(&mut mem, pc_hi, 2, AccessType::Fetch)?;
+        let high: u16 = mem.read_16bit(paddr_hi).map_err(|_| Exception::InstructionAccessFault(pc_hi))?;
+        return Ok(((low as u32) | ((high as u32) << 16), 4));
     }
 
-    fn decode_u_type(inst : u32) {
+    /*
+     * name: take_trap
+     * desc: record the exception in the CSRs and jump to the handler
+     *
+     * NOTE: an exception with no handler installed would restart the program at
+     *       address 0 over and over (an illegal instruction is the usual result of an
+     *       --isa that leaves out an extension the program uses), report it and halt
+     */
+    fn take_trap(&mut self, e: Exception) {
+        if self.csrs.exception_handler(e.cause()) == 0 {
+            let what: String = match e {
+                Exception::EnvironmentCallFromUMode | Exception::EnvironmentCallFromSMode | Exception::EnvironmentCallFromMMode => e.name().to_string(),
+                _ => format!("{} {:08x}", e.name(), e.tval()),
+            };
+            println!("hart {}: {} at pc={:08x}, no trap handler installed",self.hartid,what,self.pc);
+            self.halted = true;
+            return;
+        }
+        self.pc = self.csrs.enter_trap(self.pc, e.cause(), e.tval(), false);
+        self.csrs.record_event(HpmEvent::TRAP, 1);
+    }
+
+    /* report what a retired instruction did to the hpm counters, inst is already expanded */
+    fn count_events(&mut self, inst: u32, ilen: u8, pc: u64) {
+        let opcode: u8 = (inst & 0x7F) as u8;
+        let (load, store): (bool, bool) = match opcode {
+            0x03 | 0x07 => (true, false), /* integer, FP and vector loads */
+            0x23 | 0x27 => (false, true), /* integer, FP and vector stores */
+            /* LR only reads, SC only writes, every AMO does both */
+            0x2F => match inst >> 27 {
+                0x02 => (true, false),
+                0x03 => (false, true),
+                _ => (true, true),
+            },
+            _ => (false, false),
+        };
 
+        if load {
+            self.csrs.record_event(HpmEvent::LOAD, 1);
+        }
+        if store {
+            self.csrs.record_event(HpmEvent::STORE, 1);
+        }
+        /* a conditional branch that didn't fall through */
+        if opcode == 0x63 && self.pc != self.regs.xlen().trunc(pc + ilen as u64) {
+            self.csrs.record_event(HpmEvent::BRANCH_TAKEN, 1);
+        }
     }
 
-    fn decode_j_type(inst : u32) {
+    /* instructions from extensions the hart doesn't have are illegal, RVC included */
+    fn decode(&mut self, inst : u32, ilen : u8) -> Result<(),Exception> {
+        if ilen == 2 && !self.isa.is_enabled(IsaExt::C) {
+            return Err(Exception::IllegalInstruction(inst as u64));
+        }
+        let inst: u32 = if ilen == 2 {
+            expand_compressed(inst as u16, self.regs.xlen()).map_err(|_| Exception::IllegalInstruction(inst as u64))?
+        } else {
+            inst
+        };
+        let pc: u64 = self.pc;
+
+        let decoded: Instruction = Instruction::decode(inst, self.regs.xlen())?;
+        if !self.isa.allows(&decoded) {
+            return Err(Exception::IllegalInstruction(inst as u64));
+        }
+        self.execute(decoded, ilen)?;
 
+        self.count_events(inst, ilen, pc);
+        return Ok(());
     }
 
+    /*
+     * name: execute
+     * desc: run a decoded instruction, the extensions with register files of their own
+     *       are handed them here
+     *
+     * NOTE: decode() already turned away anything from a disabled extension
+     */
+    fn execute(&mut self, inst: Instruction, ilen : u8) -> Result<(),Exception> {
+        let mut mem: MutexGuard<Memory> = self.mem.lock().unwrap();
+
+        match inst {
+            Instruction::Float(fp) => {
+                return fp.with_len(ilen).execute(&mut self.regs, &mut self.fregs, &mut self.pc, &mut mem, &mut self.mmu, &mut self.csrs);
+            }
+            /* no RVC forms */
+            Instruction::Vector(mut v) => {
+                return v.execute(&mut self.regs, &mut self.vregs, &mut self.pc, &mut mem, &mut self.mmu, &mut self.csrs);
+            }
+            Instruction::Bitmanip(mut bm) => {
+                return bm.execute(&mut self.regs, &mut self.pc);
+            }
+            _ => {}
+        }
+
+        inst.execute(ilen, &mut self.regs, &mut self.pc, &mut mem, &mut self.mmu, &mut self.csrs, self.hartid)?;
+
+        match inst {
+            Instruction::Wfi => {
+                self.waiting = true;
+            }
+            /* x0 as rs1/rs2 means every address/address space */
+            Instruction::SfenceVma { rs1, rs2 } => {
+                let vaddr: Option<u64> = if rs1 != 0 { Some(self.regs.read(rs1)) } else { None };
+                let asid: Option<u64> = if rs2 != 0 { Some(self.regs.read(rs2)) } else { None };
+                self.mmu.flush(vaddr, asid);
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
 
 }
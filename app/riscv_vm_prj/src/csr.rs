@@ -0,0 +1,1071 @@
+/*
+ * name: csr.rs
+ * desc: control and status register file (Zicsr) and the current privilege level
+ *
+ * Note: every register not listed in CsrFile::read()/write() is unimplemented and
+ *       accessing it is an illegal instruction, same goes for writing a read-only CSR
+ *
+ *       CSRs are XLEN bits wide, the *H halves of the counters only exist on RV32
+ *
+ *       address bits [9:8] give the lowest privilege level allowed to access a CSR,
+ *       sstatus/sie/sip are restricted views of mstatus/mie/mip rather than registers
+ *
+ */
+
+use crate::xlen::*;
+use crate::trap::*;
+use crate::pmp::*;
+use crate::vector::*;
+use crate::hpm::*;
+use crate::isa::*;
+use crate::mmu::*;
+
+/* CSR addresses, see privileged spec table 2.2-2.5 */
+pub const CSR_FFLAGS: u16    = 0x001;
+pub const CSR_FRM: u16       = 0x002;
+pub const CSR_FCSR: u16      = 0x003;
+
+pub const CSR_VSTART: u16    = 0x008;
+pub const CSR_VXSAT: u16     = 0x009;
+pub const CSR_VXRM: u16      = 0x00A;
+pub const CSR_VCSR: u16      = 0x00F;
+
+pub const CSR_SSTATUS: u16    = 0x100;
+pub const CSR_SIE: u16        = 0x104;
+pub const CSR_STVEC: u16      = 0x105;
+pub const CSR_SCOUNTEREN: u16 = 0x106;
+pub const CSR_SSCRATCH: u16   = 0x140;
+pub const CSR_SEPC: u16       = 0x141;
+pub const CSR_SCAUSE: u16     = 0x142;
+pub const CSR_STVAL: u16      = 0x143;
+pub const CSR_SIP: u16        = 0x144;
+pub const CSR_SATP: u16       = 0x180;
+
+pub const CSR_MSTATUS: u16    = 0x300;
+pub const CSR_MISA: u16       = 0x301;
+pub const CSR_MEDELEG: u16    = 0x302;
+pub const CSR_MIDELEG: u16    = 0x303;
+pub const CSR_MIE: u16        = 0x304;
+pub const CSR_MTVEC: u16      = 0x305;
+pub const CSR_MCOUNTEREN: u16 = 0x306;
+pub const CSR_MSCRATCH: u16  = 0x340;
+pub const CSR_MEPC: u16      = 0x341;
+pub const CSR_MCAUSE: u16    = 0x342;
+pub const CSR_MTVAL: u16     = 0x343;
+pub const CSR_MIP: u16       = 0x344;
+
+pub const CSR_MCOUNTINHIBIT: u16 = 0x320;
+pub const CSR_MHPMEVENT3: u16    = 0x323;
+pub const CSR_MHPMEVENT31: u16   = 0x33F;
+
+pub const CSR_PMPCFG0: u16   = 0x3A0;
+pub const CSR_PMPCFG1: u16   = 0x3A1;
+pub const CSR_PMPCFG3: u16   = 0x3A3;
+pub const CSR_PMPADDR0: u16  = 0x3B0;
+pub const CSR_PMPADDR15: u16 = 0x3BF;
+
+pub const CSR_MCYCLE: u16    = 0xB00;
+pub const CSR_MINSTRET: u16  = 0xB02;
+pub const CSR_MCYCLEH: u16   = 0xB80;
+pub const CSR_MINSTRETH: u16 = 0xB82;
+pub const CSR_MHPMCOUNTER3: u16   = 0xB03;
+pub const CSR_MHPMCOUNTER31: u16  = 0xB1F;
+pub const CSR_MHPMCOUNTER3H: u16  = 0xB83;
+pub const CSR_MHPMCOUNTER31H: u16 = 0xB9F;
+
+pub const CSR_CYCLE: u16     = 0xC00;
+pub const CSR_TIME: u16      = 0xC01;
+pub const CSR_INSTRET: u16   = 0xC02;
+pub const CSR_CYCLEH: u16    = 0xC80;
+pub const CSR_TIMEH: u16     = 0xC81;
+pub const CSR_INSTRETH: u16  = 0xC82;
+pub const CSR_HPMCOUNTER3: u16   = 0xC03;
+pub const CSR_HPMCOUNTER31: u16  = 0xC1F;
+pub const CSR_HPMCOUNTER3H: u16  = 0xC83;
+pub const CSR_HPMCOUNTER31H: u16 = 0xC9F;
+
+pub const CSR_VL: u16        = 0xC20;
+pub const CSR_VTYPE: u16     = 0xC21;
+pub const CSR_VLENB: u16     = 0xC22;
+
+pub const CSR_MVENDORID: u16 = 0xF11;
+pub const CSR_MARCHID: u16   = 0xF12;
+pub const CSR_MIMPID: u16    = 0xF13;
+pub const CSR_MHARTID: u16   = 0xF14;
+
+/* mstatus bit masks */
+pub enum MstatusBm {
+    SIE_bm  = 0x0000_0002,
+    MIE_bm  = 0x0000_0008,
+    SPIE_bm = 0x0000_0020,
+    MPIE_bm = 0x0000_0080,
+    SPP_bm  = 0x0000_0100,
+    VS_bm   = 0x0000_0600, /* vector unit state, same encoding as FS */
+    MPP_bm  = 0x0000_1800,
+    FS_bm   = 0x0000_6000,
+    MPRV_bm = 0x0002_0000, /* M-mode loads/stores use the privilege in MPP */
+    SUM_bm  = 0x0004_0000, /* permit supervisor user memory access */
+    MXR_bm  = 0x0008_0000, /* make executable readable */
+    TVM_bm  = 0x0010_0000, /* trap satp accesses and SFENCE.VMA in S-mode */
+    TW_bm   = 0x0020_0000, /* trap WFI below M-mode */
+    TSR_bm  = 0x0040_0000, /* trap SRET in S-mode */
+}
+
+pub const MSTATUS_MPP_SHIFT: u64 = 11;
+
+/* RV64 only, UXL and SXL are hardwired to 2 (64 bit) */
+pub const MSTATUS_UXL_64: u64 = 2 << 32;
+pub const MSTATUS_SXL_64: u64 = 2 << 34;
+
+/* mstatus bits visible through sstatus (SD and UXL are added on read) */
+pub const SSTATUS_MASK: u64 = MstatusBm::SIE_bm as u64 | MstatusBm::SPIE_bm as u64 | MstatusBm::SPP_bm as u64
+                            | MstatusBm::VS_bm as u64 | MstatusBm::FS_bm as u64 | MstatusBm::SUM_bm as u64 | MstatusBm::MXR_bm as u64;
+
+/* privilege levels, the encoding used by mstatus.MPP */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivMode {
+    U = 0,
+    S = 1,
+    M = 3,
+}
+
+impl PrivMode {
+    /* 2 (the old H-mode) is not a legal value for MPP */
+    pub fn from_mpp(bits: u64) -> Option<PrivMode> {
+        return match bits {
+            0 => Some(PrivMode::U),
+            1 => Some(PrivMode::S),
+            3 => Some(PrivMode::M),
+            _ => None,
+        };
+    }
+}
+
+/* mstatus.FS states */
+pub const MSTATUS_FS_OFF: u64     = 0x0000_0000;
+pub const MSTATUS_FS_INITIAL: u64 = 0x0000_2000;
+pub const MSTATUS_FS_DIRTY: u64   = 0x0000_6000;
+
+/* mstatus.VS states */
+pub const MSTATUS_VS_OFF: u64     = 0x0000_0000;
+pub const MSTATUS_VS_INITIAL: u64 = 0x0000_0200;
+pub const MSTATUS_VS_DIRTY: u64   = 0x0000_0600;
+
+/* fcsr fields */
+pub const FCSR_FFLAGS_MASK: u64 = 0x1F;
+pub const FCSR_FRM_SHIFT: u64   = 5;
+pub const FCSR_FRM_MASK: u64    = 0x7;
+
+/* vcsr fields, vxsat and vxrm are views into it */
+pub const VCSR_VXSAT_MASK: u64 = 0x1;
+pub const VCSR_VXRM_SHIFT: u64 = 1;
+pub const VCSR_VXRM_MASK: u64  = 0x3;
+
+/* mip/mie bit masks */
+pub enum MipBm {
+    SSIP_bm = 0x0000_0002,
+    MSIP_bm = 0x0000_0008,
+    STIP_bm = 0x0000_0020,
+    MTIP_bm = 0x0000_0080,
+    SEIP_bm = 0x0000_0200,
+    MEIP_bm = 0x0000_0800,
+}
+
+/* interrupts with an enable bit in mie */
+pub const MIE_MASK: u64 = MipBm::SSIP_bm as u64 | MipBm::MSIP_bm as u64 | MipBm::STIP_bm as u64
+                        | MipBm::MTIP_bm as u64 | MipBm::SEIP_bm as u64 | MipBm::MEIP_bm as u64;
+
+/* supervisor interrupts are the only ones that can be delegated, the guest may also raise them itself */
+pub const MIDELEG_MASK: u64 = MipBm::SSIP_bm as u64 | MipBm::STIP_bm as u64 | MipBm::SEIP_bm as u64;
+
+/* every exception except an ecall from M-mode can be delegated (bits 10 and 14 are reserved) */
+pub const MEDELEG_MASK: u64 = 0xB3FF;
+
+/* interrupt priority, highest first */
+const INTERRUPT_PRIORITY: [(u64, Interrupt); 6] = [
+    (MipBm::MEIP_bm as u64, Interrupt::MEI),
+    (MipBm::MSIP_bm as u64, Interrupt::MSI),
+    (MipBm::MTIP_bm as u64, Interrupt::MTI),
+    (MipBm::SEIP_bm as u64, Interrupt::SEI),
+    (MipBm::SSIP_bm as u64, Interrupt::SSI),
+    (MipBm::STIP_bm as u64, Interrupt::STI),
+];
+
+/* mcounteren/scounteren: CY, TM, IR and HPM3..31 */
+pub const COUNTEREN_MASK: u64 = 0xFFFF_FFFF;
+
+/* mcountinhibit: CY, IR and HPM3..31, time can't be stopped */
+pub const MCOUNTINHIBIT_MASK: u64 = 0xFFFF_FFFD;
+pub const MCOUNTINHIBIT_CY: u64 = 0x1;
+pub const MCOUNTINHIBIT_IR: u64 = 0x4;
+
+/* mcause interrupt flag, the top bit of the register */
+pub fn mcause_interrupt(xlen: Xlen) -> u64 {
+    return 1 << (xlen.bits() - 1);
+}
+
+/* mtvec mode field, 0 is direct */
+pub const MTVEC_MODE_VECTORED: u64 = 1;
+
+/* misa: MXL in the top two bits (1 = 32 bit, 2 = 64 bit) and one bit per extension letter */
+pub fn misa_mxl(xlen: Xlen) -> u64 {
+    return match xlen {
+        Xlen::RV32 => 1 << 30,
+        Xlen::RV64 => 2 << 62,
+    };
+}
+
+pub fn misa_ext(letter: char) -> u64 {
+    return 1 << (letter as u64 - 'A' as u64);
+}
+
+/* assembler names of the CSRs with a single address, the numbered families are in csr_name */
+const CSR_NAMES: [(u16, &str); 47] = [
+    (CSR_FFLAGS, "fflags"), (CSR_FRM, "frm"), (CSR_FCSR, "fcsr"),
+    (CSR_VSTART, "vstart"), (CSR_VXSAT, "vxsat"), (CSR_VXRM, "vxrm"), (CSR_VCSR, "vcsr"),
+    (CSR_SSTATUS, "sstatus"), (CSR_SIE, "sie"), (CSR_STVEC, "stvec"), (CSR_SCOUNTEREN, "scounteren"),
+    (CSR_SSCRATCH, "sscratch"), (CSR_SEPC, "sepc"), (CSR_SCAUSE, "scause"), (CSR_STVAL, "stval"),
+    (CSR_SIP, "sip"), (CSR_SATP, "satp"),
+    (CSR_MSTATUS, "mstatus"), (CSR_MISA, "misa"), (CSR_MEDELEG, "medeleg"), (CSR_MIDELEG, "mideleg"),
+    (CSR_MIE, "mie"), (CSR_MTVEC, "mtvec"), (CSR_MCOUNTEREN, "mcounteren"), (CSR_MCOUNTINHIBIT, "mcountinhibit"),
+    (CSR_MSCRATCH, "mscratch"), (CSR_MEPC, "mepc"), (CSR_MCAUSE, "mcause"), (CSR_MTVAL, "mtval"),
+    (CSR_MIP, "mip"),
+    (CSR_MCYCLE, "mcycle"), (CSR_MINSTRET, "minstret"), (CSR_MCYCLEH, "mcycleh"), (CSR_MINSTRETH, "minstreth"),
+    (CSR_CYCLE, "cycle"), (CSR_TIME, "time"), (CSR_INSTRET, "instret"),
+    (CSR_CYCLEH, "cycleh"), (CSR_TIMEH, "timeh"), (CSR_INSTRETH, "instreth"),
+    (CSR_VL, "vl"), (CSR_VTYPE, "vtype"), (CSR_VLENB, "vlenb"),
+    (CSR_MVENDORID, "mvendorid"), (CSR_MARCHID, "marchid"), (CSR_MIMPID, "mimpid"), (CSR_MHARTID, "mhartid"),
+];
+
+/* the name objdump prints for a CSR address, None if it doesn't have one */
+pub fn csr_name(addr: u16) -> Option<String> {
+    if let Some((_, name)) = CSR_NAMES.iter().find(|(a, _)| *a == addr) {
+        return Some(name.to_string());
+    }
+
+    let numbered = |prefix: &str, first: u16, base: u16| -> Option<String> {
+        return Some(format!("{}{}", prefix, addr - first + base));
+    };
+    return match addr {
+        CSR_PMPCFG0..=CSR_PMPCFG3 => numbered("pmpcfg", CSR_PMPCFG0, 0),
+        CSR_PMPADDR0..=CSR_PMPADDR15 => numbered("pmpaddr", CSR_PMPADDR0, 0),
+        CSR_MHPMEVENT3..=CSR_MHPMEVENT31 => numbered("mhpmevent", CSR_MHPMEVENT3, 3),
+        CSR_MHPMCOUNTER3..=CSR_MHPMCOUNTER31 => numbered("mhpmcounter", CSR_MHPMCOUNTER3, 3),
+        CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H => numbered("mhpmcounter", CSR_MHPMCOUNTER3H, 3).map(|n| n + "h"),
+        CSR_HPMCOUNTER3..=CSR_HPMCOUNTER31 => numbered("hpmcounter", CSR_HPMCOUNTER3, 3),
+        CSR_HPMCOUNTER3H..=CSR_HPMCOUNTER31H => numbered("hpmcounter", CSR_HPMCOUNTER3H, 3).map(|n| n + "h"),
+        _ => None,
+    };
+}
+
+/* reverse of csr_name, for the assembler */
+pub fn csr_addr(name: &str) -> Option<u16> {
+    return (0..0x1000).find(|addr| csr_name(*addr).as_deref() == Some(name));
+}
+
+#[derive(Debug)]
+pub struct CsrFile {
+    xlen: Xlen,
+    privilege: PrivMode, /* level the hart is currently running at */
+    mstatus: u64,
+    isa: IsaConfig, /* misa, IALIGN and whether the FP/vector state exists */
+    medeleg: u64,
+    mideleg: u64,
+    mie: u64,
+    mip: u64,
+    mtvec: u64,
+    mscratch: u64,
+    mepc: u64,
+    mcause: u64,
+    mtval: u64,
+    mcounteren: u64,
+    stvec: u64,
+    sscratch: u64,
+    sepc: u64,
+    scause: u64,
+    stval: u64,
+    scounteren: u64,
+    mcountinhibit: u64,
+    satp: u64,
+    pmp: Pmp,
+    hpm: Hpm,
+    mhartid: u64,
+    fcsr: u64, /* frm and fflags */
+    vcsr: u64, /* vxrm and vxsat */
+    vstart: u64,
+    vl: u64,
+    vtype: u64,
+    vlenb: u64, /* VLEN/8, fixed once the hart is built */
+    cycle: u64,
+    time: u64, /* copy of the CLINT's mtime, mcountinhibit and mcycle writes leave it alone */
+    instret: u64,
+    counters_written: u64, /* CY/IR in mcountinhibit positions, written by this step's instruction */
+}
+
+impl CsrFile {
+    /* constructor: machine mode reset state of the hart with the given mhartid, implementing isa */
+    pub fn new(isa: &IsaConfig, hartid: u64) -> CsrFile {
+        let xlen: Xlen = isa.xlen();
+        return CsrFile {
+            xlen: xlen,
+            privilege: PrivMode::M,
+            /* MPP starts out at M, the FPU and the vector unit start out usable so guests don't have to enable them */
+            mstatus: MstatusBm::MPP_bm as u64 | MSTATUS_FS_INITIAL | MSTATUS_VS_INITIAL,
+            isa: *isa,
+            medeleg: 0,
+            mideleg: 0,
+            mie: 0,
+            mip: 0,
+            mtvec: 0,
+            mscratch: 0,
+            mepc: 0,
+            mcause: 0,
+            mtval: 0,
+            mcounteren: 0,
+            stvec: 0,
+            sscratch: 0,
+            sepc: 0,
+            scause: 0,
+            stval: 0,
+            scounteren: 0,
+            mcountinhibit: 0,
+            satp: 0,
+            pmp: Pmp::new(),
+            hpm: Hpm::new(),
+            mhartid: hartid,
+            fcsr: 0,
+            vcsr: 0,
+            vstart: 0,
+            vl: 0,
+            vtype: vtype_vill(xlen),
+            vlenb: (VLEN_DEFAULT / 8) as u64,
+            cycle: 0,
+            time: 0,
+            instret: 0,
+            counters_written: 0,
+        };
+    }
+
+    pub fn get_privilege(&self) -> PrivMode {
+        return self.privilege;
+    }
+
+    pub fn get_satp(&self) -> u64 {
+        return self.satp;
+    }
+
+    /* satp selects Sv32, RV64 only implements Bare so whatever else its satp holds never turns translation on */
+    pub fn sv32_enabled(&self) -> bool {
+        return self.xlen == Xlen::RV32 && (self.satp & SATP32_MODE_SV32) != 0;
+    }
+
+    pub fn get_pmp(&self) -> &Pmp {
+        return &self.pmp;
+    }
+
+    /* raw mstatus, without the computed SD bit */
+    pub fn get_mstatus(&self) -> u64 {
+        return self.mstatus;
+    }
+
+    /* privilege loads and stores are checked and translated with, MPRV makes M-mode borrow MPP */
+    pub fn get_data_privilege(&self) -> PrivMode {
+        if self.privilege == PrivMode::M && (self.mstatus & MstatusBm::MPRV_bm as u64) != 0 {
+            let mpp: u64 = (self.mstatus & MstatusBm::MPP_bm as u64) >> MSTATUS_MPP_SHIFT;
+            return PrivMode::from_mpp(mpp).unwrap_or(PrivMode::M);
+        }
+        return self.privilege;
+    }
+
+    /*
+     * interrupts the current privilege level lets through: M-mode interrupts are always
+     * enabled below M and masked by MIE in M, delegated ones are always enabled in U,
+     * masked by SIE in S and never taken in M
+     */
+    fn enabled_interrupts(&self) -> u64 {
+        let m_enabled: bool = self.privilege != PrivMode::M || (self.mstatus & MstatusBm::MIE_bm as u64) != 0;
+        let s_enabled: bool = self.privilege == PrivMode::U
+                              || (self.privilege == PrivMode::S && (self.mstatus & MstatusBm::SIE_bm as u64) != 0);
+        let mut mask: u64 = 0;
+        if m_enabled {
+            mask |= !self.mideleg;
+        }
+        if s_enabled {
+            mask |= self.mideleg;
+        }
+        return mask & self.mie;
+    }
+
+    /* could an interrupt ever be taken without software changing the enables first */
+    pub fn interrupts_possible(&self) -> bool {
+        return self.enabled_interrupts() != 0;
+    }
+
+    /*
+     * name: next_interrupt
+     * desc: cause of the interrupt to take now, if any
+     *
+     * NOTE: interrupts destined for M-mode win over delegated ones, within each
+     *       group the order is MEI, MSI, MTI, SEI, SSI, STI
+     */
+    pub fn next_interrupt(&self) -> Option<u32> {
+        let takeable: u64 = self.mip & self.enabled_interrupts();
+        for group in [takeable & !self.mideleg, takeable & self.mideleg] {
+            for (bit, cause) in INTERRUPT_PRIORITY {
+                if (group & bit) != 0 {
+                    return Some(cause as u32);
+                }
+            }
+        }
+        return None;
+    }
+
+    /* the cpu copies mtime in before every step */
+    pub fn set_time(&mut self, mtime: u64) {
+        self.time = mtime;
+    }
+
+    /* interrupts that are both pending and individually enabled (ignores mstatus.MIE/SIE) */
+    pub fn pending_interrupts(&self) -> u64 {
+        return self.mip & self.mie;
+    }
+
+    /* interrupt enables, when zero nothing can ever wake a WFI */
+    pub fn get_mie(&self) -> u64 {
+        return self.mie;
+    }
+
+    /* hardware side of mip, the guest can't write these bits */
+    pub fn set_pending(&mut self, bit: u64, pending: bool) {
+        if pending {
+            self.mip |= bit;
+        }
+        else {
+            self.mip &= !bit;
+        }
+    }
+
+    /* FP instructions and CSRs are illegal while mstatus.FS is Off or without F */
+    pub fn fp_enabled(&self) -> bool {
+        return self.isa.is_enabled(IsaExt::F) && (self.mstatus & MstatusBm::FS_bm as u64) != MSTATUS_FS_OFF;
+    }
+
+    /* called whenever the FP registers or fflags may have changed */
+    pub fn set_fp_dirty(&mut self) {
+        self.mstatus |= MSTATUS_FS_DIRTY;
+    }
+
+    /* instruction alignment in bytes, 2 with C and 4 without, for jump targets and xepc */
+    pub fn ialign(&self) -> u64 {
+        return if self.isa.is_enabled(IsaExt::C) { 2 } else { 4 };
+    }
+
+    /* dynamic rounding mode */
+    pub fn get_frm(&self) -> u8 {
+        return ((self.fcsr >> FCSR_FRM_SHIFT) & FCSR_FRM_MASK) as u8;
+    }
+
+    /* exception flags are sticky, instructions only ever set them */
+    pub fn accrue_fflags(&mut self, flags: u8) {
+        self.fcsr |= flags as u64 & FCSR_FFLAGS_MASK;
+    }
+
+    /* vector instructions and CSRs are illegal while mstatus.VS is Off or without Zve64x */
+    pub fn vector_enabled(&self) -> bool {
+        return self.isa.is_enabled(IsaExt::Zve64x) && (self.mstatus & MstatusBm::VS_bm as u64) != MSTATUS_VS_OFF;
+    }
+
+    /* called whenever the vector registers or vector CSRs may have changed */
+    pub fn set_vector_dirty(&mut self) {
+        self.mstatus |= MSTATUS_VS_DIRTY;
+    }
+
+    pub fn get_vl(&self) -> u64 {
+        return self.vl;
+    }
+
+    pub fn get_vtype(&self) -> u64 {
+        return self.vtype;
+    }
+
+    pub fn get_vstart(&self) -> u64 {
+        return self.vstart;
+    }
+
+    /* vsetvl and friends, vl and vtype can't be written any other way */
+    pub fn set_vector_config(&mut self, vl: u64, vtype: u64) {
+        self.vl = vl;
+        self.vtype = vtype;
+    }
+
+    /* every vector instruction that completes leaves vstart at zero */
+    pub fn clear_vstart(&mut self) {
+        self.vstart = 0;
+    }
+
+    /* vxsat is sticky, saturating instructions only ever set it */
+    pub fn set_vxsat(&mut self) {
+        self.vcsr |= VCSR_VXSAT_MASK;
+    }
+
+    /* VLEN is picked when the hart is built, vlenb just reports it */
+    pub fn set_vlenb(&mut self, vlenb: u64) {
+        self.vlenb = vlenb;
+    }
+
+    fn is_vector_csr(addr: u16) -> bool {
+        return matches!(addr, CSR_VSTART | CSR_VXSAT | CSR_VXRM | CSR_VCSR | CSR_VL | CSR_VTYPE | CSR_VLENB);
+    }
+
+    /* mstatus.SD summarizes a dirty FS or VS in the top bit */
+    fn read_mstatus(&self) -> u64 {
+        let mut val: u64 = self.mstatus;
+        if self.xlen == Xlen::RV64 {
+            val |= MSTATUS_UXL_64 | MSTATUS_SXL_64;
+        }
+        if (self.mstatus & MstatusBm::FS_bm as u64) == MSTATUS_FS_DIRTY
+           || (self.mstatus & MstatusBm::VS_bm as u64) == MSTATUS_VS_DIRTY {
+            val |= 1 << (self.xlen.bits() - 1);
+        }
+        return val;
+    }
+
+    /* sstatus keeps SD and, on RV64, UXL */
+    fn read_sstatus(&self) -> u64 {
+        let mut mask: u64 = SSTATUS_MASK | (1 << (self.xlen.bits() - 1));
+        if self.xlen == Xlen::RV64 {
+            mask |= MSTATUS_UXL_64;
+        }
+        return self.read_mstatus() & mask;
+    }
+
+    /* CSRs with address bits [11:10] == 0b11 are read-only */
+    pub fn is_read_only(addr: u16) -> bool {
+        return (addr >> 10) & 0x3 == 0x3;
+    }
+
+    /* cycle, time, instret, hpmcounter3..31 and their upper halves */
+    fn is_user_counter(addr: u16) -> bool {
+        return (CSR_CYCLE..=CSR_HPMCOUNTER31).contains(&addr) || (CSR_CYCLEH..=CSR_HPMCOUNTER31H).contains(&addr);
+    }
+
+    /*
+     * name: accessible
+     * desc: privilege check done before every CSR access
+     *
+     * NOTE: besides address bits [9:8], satp is off limits to S-mode while TVM is set
+     *       and the user counters need their bit in mcounteren (and in scounteren for U-mode)
+     */
+    fn accessible(&self, addr: u16) -> bool {
+        if (self.privilege as u16) < ((addr >> 8) & 0x3) {
+            return false;
+        }
+        if addr == CSR_SATP && self.privilege == PrivMode::S && (self.mstatus & MstatusBm::TVM_bm as u64) != 0 {
+            return false;
+        }
+        if CsrFile::is_user_counter(addr) {
+            let bit: u64 = 1 << (addr & 0x1F);
+            if self.privilege != PrivMode::M && (self.mcounteren & bit) == 0 {
+                return false;
+            }
+            if self.privilege == PrivMode::U && (self.scounteren & bit) == 0 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /* upper counter halves and the odd pmpcfg registers, RV64 packs twice as much into the even ones */
+    pub fn is_rv32_only(addr: u16) -> bool {
+        return matches!(addr, CSR_MCYCLEH | CSR_MINSTRETH | CSR_CYCLEH | CSR_TIMEH | CSR_INSTRETH | CSR_PMPCFG1 | CSR_PMPCFG3)
+               || (CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H).contains(&addr)
+               || (CSR_HPMCOUNTER3H..=CSR_HPMCOUNTER31H).contains(&addr);
+    }
+
+    fn is_pmpcfg(addr: u16) -> bool {
+        return (CSR_PMPCFG0..=CSR_PMPCFG3).contains(&addr);
+    }
+
+    fn is_pmpaddr(addr: u16) -> bool {
+        return (CSR_PMPADDR0..CSR_PMPADDR0 + PMP_ENTRIES as u16).contains(&addr);
+    }
+
+    /*
+     * name: tick
+     * desc: called by the cpu once per step, instructions that trap take a cycle but don't retire
+     *
+     * NOTE: a counter written by the instruction holds the value written, the
+     *       increment of that step is dropped like the spec requires
+     */
+    pub fn tick(&mut self, retired: bool) {
+        let frozen: u64 = self.mcountinhibit | self.counters_written;
+        self.counters_written = 0;
+
+        if (frozen & MCOUNTINHIBIT_CY) == 0 {
+            self.cycle = self.cycle.wrapping_add(1);
+        }
+        if retired && (frozen & MCOUNTINHIBIT_IR) == 0 {
+            self.instret = self.instret.wrapping_add(1);
+        }
+    }
+
+    /* feed the hpm counters, the cpu reports every event it sees */
+    pub fn record_event(&mut self, event: HpmEvent, n: u64) {
+        self.hpm.record(event, n, self.mcountinhibit);
+    }
+
+    /* in vectored mode only interrupts jump to BASE + 4*cause, synchronous exceptions always go to BASE */
+    fn trap_vector(&self, tvec: u64, cause: u64, interrupt: bool) -> u64 {
+        let base: u64 = tvec & !0x3;
+        if interrupt && (tvec & 0x3) == MTVEC_MODE_VECTORED {
+            return self.xlen.trunc(base.wrapping_add(4 * cause));
+        }
+        return base;
+    }
+
+    /* handler address of a synchronous exception, 0 when software never set one up (mtvec/stvec reset to 0) */
+    pub fn exception_handler(&self, cause: u32) -> u64 {
+        let delegated: bool = self.privilege != PrivMode::M && ((self.medeleg >> cause) & 1) != 0;
+        return self.trap_vector(if delegated { self.stvec } else { self.mtvec }, cause as u64, false);
+    }
+
+    /*
+     * name: enter_trap
+     * desc: record a trap and return the handler address
+     *
+     * NOTE: traps taken in S or U mode whose bit is set in medeleg/mideleg go to
+     *       S-mode, everything else (and anything raised in M-mode) goes to M-mode,
+     *       xPP remembers where it came from
+     */
+    pub fn enter_trap(&mut self, epc: u64, cause: u32, tval: u64, interrupt: bool) -> u64 {
+        let cause: u64 = cause as u64;
+        let deleg: u64 = if interrupt { self.mideleg } else { self.medeleg };
+
+        if self.privilege != PrivMode::M && ((deleg >> cause) & 1) != 0 {
+            self.sepc = epc;
+            self.scause = if interrupt { cause | mcause_interrupt(self.xlen) } else { cause };
+            self.stval = tval;
+
+            /* SPIE <- SIE, SIE <- 0, SPP <- current privilege, switch to S */
+            let sie: bool = (self.mstatus & MstatusBm::SIE_bm as u64) != 0;
+            self.mstatus &= !(MstatusBm::SIE_bm as u64 | MstatusBm::SPIE_bm as u64 | MstatusBm::SPP_bm as u64);
+            if sie {
+                self.mstatus |= MstatusBm::SPIE_bm as u64;
+            }
+            if self.privilege == PrivMode::S {
+                self.mstatus |= MstatusBm::SPP_bm as u64;
+            }
+            self.privilege = PrivMode::S;
+            return self.trap_vector(self.stvec, cause, interrupt);
+        }
+
+        self.mepc = epc;
+        self.mcause = if interrupt { cause | mcause_interrupt(self.xlen) } else { cause };
+        self.mtval = tval;
+
+        /* MPIE <- MIE, MIE <- 0 */
+        let mie: bool = (self.mstatus & MstatusBm::MIE_bm as u64) != 0;
+        self.mstatus &= !(MstatusBm::MIE_bm as u64 | MstatusBm::MPIE_bm as u64);
+        if mie {
+            self.mstatus |= MstatusBm::MPIE_bm as u64;
+        }
+
+        /* MPP <- current privilege, switch to M */
+        self.mstatus = (self.mstatus & !(MstatusBm::MPP_bm as u64)) | ((self.privilege as u64) << MSTATUS_MPP_SHIFT);
+        self.privilege = PrivMode::M;
+        return self.trap_vector(self.mtvec, cause, interrupt);
+    }
+
+    /*
+     * MRET: MIE <- MPIE, MPIE <- 1, privilege <- MPP and MPP <- the least privileged
+     *       mode (U), returns the address to resume at
+     *
+     *       returning anywhere below M-mode clears MPRV
+     */
+    pub fn mret(&mut self) -> u64 {
+        let mpie: bool = (self.mstatus & MstatusBm::MPIE_bm as u64) != 0;
+        self.mstatus &= !(MstatusBm::MIE_bm as u64);
+        if mpie {
+            self.mstatus |= MstatusBm::MIE_bm as u64;
+        }
+        self.mstatus |= MstatusBm::MPIE_bm as u64;
+
+        let mpp: u64 = (self.mstatus & MstatusBm::MPP_bm as u64) >> MSTATUS_MPP_SHIFT;
+        self.privilege = PrivMode::from_mpp(mpp).unwrap_or(PrivMode::M);
+        self.mstatus = (self.mstatus & !(MstatusBm::MPP_bm as u64)) | ((PrivMode::U as u64) << MSTATUS_MPP_SHIFT);
+        if self.privilege != PrivMode::M {
+            self.mstatus &= !(MstatusBm::MPRV_bm as u64);
+        }
+        return self.mepc;
+    }
+
+    /* SRET: SIE <- SPIE, SPIE <- 1, privilege <- SPP and SPP <- U, always clears MPRV */
+    pub fn sret(&mut self) -> u64 {
+        let spie: bool = (self.mstatus & MstatusBm::SPIE_bm as u64) != 0;
+        self.mstatus &= !(MstatusBm::SIE_bm as u64);
+        if spie {
+            self.mstatus |= MstatusBm::SIE_bm as u64;
+        }
+        self.mstatus |= MstatusBm::SPIE_bm as u64;
+
+        self.privilege = if (self.mstatus & MstatusBm::SPP_bm as u64) != 0 { PrivMode::S } else { PrivMode::U };
+        self.mstatus &= !(MstatusBm::SPP_bm as u64 | MstatusBm::MPRV_bm as u64);
+        return self.sepc;
+    }
+
+    /* returns Err(()) for unimplemented CSRs and ones the current privilege level can't access */
+    pub fn read(&mut self, addr: u16) -> Result<u64,()> {
+        if !self.accessible(addr) {
+            return Err(());
+        }
+        if self.xlen == Xlen::RV64 && CsrFile::is_rv32_only(addr) {
+            return Err(());
+        }
+
+        let is_fp_csr: bool = matches!(addr, CSR_FFLAGS | CSR_FRM | CSR_FCSR);
+        if is_fp_csr && !self.fp_enabled() {
+            return Err(());
+        }
+        if CsrFile::is_vector_csr(addr) && !self.vector_enabled() {
+            return Err(());
+        }
+
+        let val: u64 = match addr {
+            CSR_FFLAGS => self.fcsr & FCSR_FFLAGS_MASK,
+            CSR_FRM => (self.fcsr >> FCSR_FRM_SHIFT) & FCSR_FRM_MASK,
+            CSR_FCSR => self.fcsr,
+
+            CSR_VSTART => self.vstart,
+            CSR_VXSAT => self.vcsr & VCSR_VXSAT_MASK,
+            CSR_VXRM => (self.vcsr >> VCSR_VXRM_SHIFT) & VCSR_VXRM_MASK,
+            CSR_VCSR => self.vcsr,
+            CSR_VL => self.vl,
+            CSR_VTYPE => self.vtype,
+            CSR_VLENB => self.vlenb,
+
+            CSR_SSTATUS => self.read_sstatus(),
+            CSR_SIE => self.mie & self.mideleg,
+            CSR_STVEC => self.stvec,
+            CSR_SCOUNTEREN => self.scounteren,
+            CSR_SSCRATCH => self.sscratch,
+            CSR_SEPC => self.sepc,
+            CSR_SCAUSE => self.scause,
+            CSR_STVAL => self.stval,
+            CSR_SIP => self.mip & self.mideleg,
+            CSR_SATP => self.satp,
+
+            CSR_MSTATUS => self.read_mstatus(),
+            CSR_MISA => self.isa.misa(),
+            CSR_MEDELEG => self.medeleg,
+            CSR_MIDELEG => self.mideleg,
+            CSR_MIE => self.mie,
+            CSR_MTVEC => self.mtvec,
+            CSR_MCOUNTEREN => self.mcounteren,
+            CSR_MSCRATCH => self.mscratch,
+            CSR_MEPC => self.mepc,
+            CSR_MCAUSE => self.mcause,
+            CSR_MTVAL => self.mtval,
+            CSR_MIP => self.mip,
+
+            addr if CsrFile::is_pmpcfg(addr) => self.pmp.read_cfg((addr - CSR_PMPCFG0) as usize, self.xlen),
+            addr if CsrFile::is_pmpaddr(addr) => self.pmp.read_addr((addr - CSR_PMPADDR0) as usize),
+
+            CSR_MCYCLE | CSR_CYCLE => self.xlen.trunc(self.cycle),
+            CSR_MCYCLEH | CSR_CYCLEH => self.cycle >> 32,
+            /* mtime as of the start of the step, it can't be inhibited or written */
+            CSR_TIME => self.xlen.trunc(self.time),
+            CSR_TIMEH => self.time >> 32,
+            CSR_MINSTRET | CSR_INSTRET => self.xlen.trunc(self.instret),
+            CSR_MINSTRETH | CSR_INSTRETH => self.instret >> 32,
+            CSR_MCOUNTINHIBIT => self.mcountinhibit,
+
+            addr if (CSR_MHPMCOUNTER3..=CSR_MHPMCOUNTER31).contains(&addr) => self.xlen.trunc(self.hpm.read_counter((addr & 0x1F) as usize)),
+            addr if (CSR_HPMCOUNTER3..=CSR_HPMCOUNTER31).contains(&addr) => self.xlen.trunc(self.hpm.read_counter((addr & 0x1F) as usize)),
+            addr if (CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H).contains(&addr) => self.hpm.read_counter((addr & 0x1F) as usize) >> 32,
+            addr if (CSR_HPMCOUNTER3H..=CSR_HPMCOUNTER31H).contains(&addr) => self.hpm.read_counter((addr & 0x1F) as usize) >> 32,
+            addr if (CSR_MHPMEVENT3..=CSR_MHPMEVENT31).contains(&addr) => self.hpm.read_event((addr & 0x1F) as usize),
+
+            CSR_MVENDORID | CSR_MARCHID | CSR_MIMPID => 0,
+            CSR_MHARTID => self.mhartid,
+
+            _ => return Err(()),
+        };
+        return Ok(val);
+    }
+
+    /* returns Err(()) for unimplemented, inaccessible or read-only CSRs, WARL fields keep their legal values */
+    pub fn write(&mut self, addr: u16, val: u64) -> Result<(),()> {
+        if !self.accessible(addr) || CsrFile::is_read_only(addr) {
+            return Err(());
+        }
+        if self.xlen == Xlen::RV64 && CsrFile::is_rv32_only(addr) {
+            return Err(());
+        }
+        let is_fp_csr: bool = matches!(addr, CSR_FFLAGS | CSR_FRM | CSR_FCSR);
+        if is_fp_csr && !self.fp_enabled() {
+            return Err(());
+        }
+        if CsrFile::is_vector_csr(addr) && !self.vector_enabled() {
+            return Err(());
+        }
+
+        match addr {
+            /* fflags and frm are views into fcsr, writing any of them dirties the FP state */
+            CSR_FFLAGS => {
+                self.fcsr = (self.fcsr & !FCSR_FFLAGS_MASK) | (val & FCSR_FFLAGS_MASK);
+                self.set_fp_dirty();
+            }
+            CSR_FRM => {
+                self.fcsr = (self.fcsr & FCSR_FFLAGS_MASK) | ((val & FCSR_FRM_MASK) << FCSR_FRM_SHIFT);
+                self.set_fp_dirty();
+            }
+            CSR_FCSR => {
+                self.fcsr = val & ((FCSR_FRM_MASK << FCSR_FRM_SHIFT) | FCSR_FFLAGS_MASK);
+                self.set_fp_dirty();
+            }
+
+            /* vstart only needs to hold an element index, VLEN-1 at most, the rest dirty the vector state */
+            CSR_VSTART => {
+                self.vstart = val & (self.vlenb * 8 - 1);
+                self.set_vector_dirty();
+            }
+            CSR_VXSAT => {
+                self.vcsr = (self.vcsr & !VCSR_VXSAT_MASK) | (val & VCSR_VXSAT_MASK);
+                self.set_vector_dirty();
+            }
+            CSR_VXRM => {
+                self.vcsr = (self.vcsr & VCSR_VXSAT_MASK) | ((val & VCSR_VXRM_MASK) << VCSR_VXRM_SHIFT);
+                self.set_vector_dirty();
+            }
+            CSR_VCSR => {
+                self.vcsr = val & ((VCSR_VXRM_MASK << VCSR_VXRM_SHIFT) | VCSR_VXSAT_MASK);
+                self.set_vector_dirty();
+            }
+
+            CSR_SSTATUS => self.mstatus = (self.mstatus & !SSTATUS_MASK) | (val & SSTATUS_MASK),
+            /* only the delegated bits are visible, and of the pending bits only SSIP is writable */
+            CSR_SIE => self.mie = (self.mie & !self.mideleg) | (val & self.mideleg),
+            CSR_STVEC => {
+                if (val & 0x3) <= MTVEC_MODE_VECTORED {
+                    self.stvec = val;
+                }
+            }
+            CSR_SCOUNTEREN => self.scounteren = val & COUNTEREN_MASK,
+            CSR_SSCRATCH => self.sscratch = val,
+            CSR_SEPC => self.sepc = val & !(self.ialign() - 1),
+            CSR_SCAUSE => self.scause = val,
+            CSR_STVAL => self.stval = val,
+            CSR_SIP => {
+                let mask: u64 = MipBm::SSIP_bm as u64 & self.mideleg;
+                self.mip = (self.mip & !mask) | (val & mask);
+            }
+
+            /* Bare and Sv32 on RV32, only Bare on RV64, writes selecting any other mode are ignored */
+            CSR_SATP => {
+                let legal: bool = match self.xlen {
+                    Xlen::RV32 => true,
+                    Xlen::RV64 => (val >> 60) == 0,
+                };
+                if legal {
+                    self.satp = val;
+                }
+            }
+
+            CSR_MSTATUS => {
+                let mut mask: u64 = SSTATUS_MASK | MstatusBm::MIE_bm as u64 | MstatusBm::MPIE_bm as u64
+                                  | MstatusBm::MPRV_bm as u64 | MstatusBm::TVM_bm as u64 | MstatusBm::TW_bm as u64
+                                  | MstatusBm::TSR_bm as u64;
+                /* MPP is WARL, an unsupported mode keeps the old value */
+                if PrivMode::from_mpp((val & MstatusBm::MPP_bm as u64) >> MSTATUS_MPP_SHIFT).is_some() {
+                    mask |= MstatusBm::MPP_bm as u64;
+                }
+                self.mstatus = (self.mstatus & !mask) | (val & mask);
+            }
+            /* the extensions are fixed by the ISA string the hart was built with */
+            CSR_MISA => {}
+            CSR_MEDELEG => self.medeleg = val & MEDELEG_MASK,
+            CSR_MIDELEG => self.mideleg = val & MIDELEG_MASK,
+            CSR_MIE => self.mie = val & MIE_MASK,
+            /* mode 0 = direct, 1 = vectored, the rest are reserved */
+            CSR_MTVEC => {
+                if (val & 0x3) <= MTVEC_MODE_VECTORED {
+                    self.mtvec = val;
+                }
+            }
+            CSR_MSCRATCH => self.mscratch = val,
+            CSR_MEPC => self.mepc = val & !(self.ialign() - 1),
+            CSR_MCAUSE => self.mcause = val,
+            CSR_MTVAL => self.mtval = val,
+            /* the machine level bits are driven by the hardware, the supervisor ones are up to M-mode software */
+            CSR_MIP => self.mip = (self.mip & !MIDELEG_MASK) | (val & MIDELEG_MASK),
+            CSR_MCOUNTEREN => self.mcounteren = val & COUNTEREN_MASK,
+
+            addr if CsrFile::is_pmpcfg(addr) => self.pmp.write_cfg((addr - CSR_PMPCFG0) as usize, val, self.xlen),
+            addr if CsrFile::is_pmpaddr(addr) => self.pmp.write_addr((addr - CSR_PMPADDR0) as usize, val, self.xlen),
+
+            CSR_MCYCLE => {
+                self.cycle = (self.cycle & !self.xlen.trunc(u64::MAX)) | val;
+                self.counters_written |= MCOUNTINHIBIT_CY;
+            }
+            CSR_MCYCLEH => {
+                self.cycle = (self.cycle & 0xFFFF_FFFF) | (val << 32);
+                self.counters_written |= MCOUNTINHIBIT_CY;
+            }
+            CSR_MINSTRET => {
+                self.instret = (self.instret & !self.xlen.trunc(u64::MAX)) | val;
+                self.counters_written |= MCOUNTINHIBIT_IR;
+            }
+            CSR_MINSTRETH => {
+                self.instret = (self.instret & 0xFFFF_FFFF) | (val << 32);
+                self.counters_written |= MCOUNTINHIBIT_IR;
+            }
+            CSR_MCOUNTINHIBIT => self.mcountinhibit = val & MCOUNTINHIBIT_MASK,
+
+            addr if (CSR_MHPMCOUNTER3..=CSR_MHPMCOUNTER31).contains(&addr) => self.hpm.write_counter((addr & 0x1F) as usize, val, self.xlen),
+            addr if (CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H).contains(&addr) => self.hpm.write_counter_high((addr & 0x1F) as usize, val),
+            addr if (CSR_MHPMEVENT3..=CSR_MHPMEVENT31).contains(&addr) => self.hpm.write_event((addr & 0x1F) as usize, val),
+
+            _ => return Err(()),
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::*;
+    use crate::isa::*;
+    use crate::xlen::*;
+    use crate::encoder::*;
+
+    fn run(src: &str) -> Machine {
+        return run_source(src, &IsaConfig::new(Xlen::RV32), 1);
+    }
+
+    fn reg(machine: &Machine, reg: Reg) -> u64 {
+        return machine.get_harts()[0].get_reg(reg as u8);
+    }
+
+    /* inhibiting and rewriting mcycle stops cycle, time keeps going */
+    #[test]
+    fn time_has_its_own_counter() {
+        let machine: Machine = run("
+            csrwi mcountinhibit, 1
+            csrw mcycle, zero
+            rdtime a0
+            nop
+            nop
+            rdtime a1
+            rdcycle a2
+            j .
+        ");
+        assert_eq!(reg(&machine, Reg::A1) - reg(&machine, Reg::A0), 3);
+        assert_eq!(reg(&machine, Reg::A2), 0);
+    }
+
+    /* the instruction that writes a counter doesn't also count itself */
+    #[test]
+    fn written_counter_skips_its_step() {
+        let machine: Machine = run("
+            csrw minstret, zero
+            csrr a0, minstret
+            csrw mcycle, zero
+            csrr a1, mcycle
+            j .
+        ");
+        assert_eq!(reg(&machine, Reg::A0), 0);
+        assert_eq!(reg(&machine, Reg::A1), 0);
+    }
+
+    /*
+     * a U-mode ecall delegated through medeleg lands in S-mode and SRET goes back to
+     * U, the illegal CSR access that follows and the M-mode ecall aren't delegated
+     */
+    #[test]
+    fn delegated_trap_and_sret() {
+        let machine: Machine = run("
+                la t0, mtrap
+                csrw mtvec, t0
+                la t0, strap
+                csrw stvec, t0
+                li t0, 0x900         # ecall from U and from M
+                csrw medeleg, t0
+                ecall
+                la t0, umode
+                csrw mepc, t0
+                li t1, 0x1800        # MPP = U
+                csrc mstatus, t1
+                mret
+            umode:
+                ecall
+                csrr a3, mstatus
+                li a4, 4
+                j .
+            strap:
+                csrr a1, scause
+                csrr a2, sstatus
+                csrr t0, sepc
+                addi t0, t0, 4
+                csrw sepc, t0
+                sret
+            mtrap:
+                csrr t5, mcause
+                slli a5, a5, 8
+                or a5, a5, t5
+                csrr a6, mstatus
+                csrr t6, mepc
+                addi t6, t6, 4
+                csrw mepc, t6
+                mret
+        ");
+        assert_eq!(reg(&machine, Reg::A1), 8);
+        /* SPP records U */
+        assert_eq!(reg(&machine, Reg::A2) & 0x100, 0);
+        /* M-mode traps stay in M whatever medeleg says, then the U-mode mstatus read */
+        assert_eq!(reg(&machine, Reg::A5), 0x0B02);
+        assert_eq!(reg(&machine, Reg::A6) & 0x1800, 0);
+        assert_eq!(reg(&machine, Reg::A3), 0);
+        assert_eq!(reg(&machine, Reg::A4), 4);
+    }
+
+    /* with MPRV M-mode loads are checked as MPP, here U against a PMP entry covering only the code */
+    #[test]
+    fn mprv_loads_use_mpp() {
+        let machine: Machine = run("
+                la t0, mtrap
+                csrw mtvec, t0
+                la s0, data
+                srli t0, s0, 2
+                csrw pmpaddr0, t0
+                li t0, 0x0f          # TOR, RWX
+                csrw pmpcfg0, t0
+                lw a0, 0(s0)
+                li t1, 0x1800        # MPP = U
+                csrc mstatus, t1
+                li t0, 0x20000       # MPRV
+                csrs mstatus, t0
+                lw a1, 0(s0)
+                la t1, mtrap
+                lw a2, 0(t1)
+                csrc mstatus, t0
+                lw a3, 0(s0)
+                j .
+            mtrap:
+                addi a7, a7, 1
+                csrr a5, mcause
+                csrr t6, mepc
+                addi t6, t6, 4
+                csrw mepc, t6
+                mret
+            data:
+                .word 0x12345678
+        ");
+        assert_eq!(reg(&machine, Reg::A0), 0x12345678);
+        assert_eq!(reg(&machine, Reg::A1), 0);
+        assert_ne!(reg(&machine, Reg::A2), 0);
+        assert_eq!(reg(&machine, Reg::A3), 0x12345678);
+        /* just the one load access fault */
+        assert_eq!(reg(&machine, Reg::A7), 1);
+        assert_eq!(reg(&machine, Reg::A5), 5);
+    }
+}
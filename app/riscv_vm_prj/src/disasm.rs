@@ -0,0 +1,409 @@
+/*
+ * name: disasm.rs
+ * desc: disassembler, renders decoded instructions in GNU objdump syntax
+ *
+ * Note: registers go by their ABI names like objdump prints them, immediates are
+ *       decimal except for LUI/AUIPC, shift amounts and unnamed CSRs which are hex,
+ *       branch and jump targets are printed as absolute addresses followed by
+ *       <symbol+offset> when a symbol table is loaded
+ *
+ *       instructions are printed as the canonical pseudo instructions (nop, li, mv,
+ *       j, ret, beqz, ...) unless aliases are off (objdump -M no-aliases), the
+ *       LUI+ADDI, AUIPC+ADDI and AUIPC+JALR pairs the assembler emits for li, la,
+ *       call and tail are printed as one line with both encodings, a pair is never
+ *       joined across a symbol since something may jump to the second half
+ *
+ *       an RVC instruction shows up as the 32 bit instruction it expands to while
+ *       the encoding column keeps the 16 bits that were fetched
+ *
+ *       anything that doesn't decode is printed as a .2byte/.4byte directive, so is
+ *       anything from an extension left out of the ISA given to with_isa()
+ *
+ */
+
+use crate::idecoder::*;
+use crate::csr::*;
+use crate::rvc::*;
+use crate::xlen::*;
+use crate::isa::*;
+
+const XREG_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+    "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
+const FREG_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7",
+    "fs0", "fs1", "fa0", "fa1", "fa2", "fa3", "fa4", "fa5",
+    "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7",
+    "fs8", "fs9", "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+/* ABI name of an integer register */
+pub fn xreg(reg: u8) -> &'static str {
+    return XREG_NAMES[(reg & 0x1F) as usize];
+}
+
+/* ABI name of a floating point register */
+pub fn freg(reg: u8) -> &'static str {
+    return FREG_NAMES[(reg & 0x1F) as usize];
+}
+
+/* CSR operand, the name if it has one */
+fn csr_operand(csr: u16) -> String {
+    return csr_name(csr).unwrap_or(format!("0x{:x}", csr));
+}
+
+/* pred/succ sets of FENCE, in the IORW order the assembler takes them */
+fn fence_set(set: u8) -> String {
+    let names: String = ['i', 'o', 'r', 'w'].iter().enumerate()
+        .filter(|(i, _)| (set >> (3 - i)) & 1 != 0)
+        .map(|(_, c)| *c)
+        .collect();
+    return if names.is_empty() { "0".to_string() } else { names };
+}
+
+/* ordering bits of an atomic, they go after the .w/.d of the name */
+fn ordering(aq: bool, rl: bool) -> &'static str {
+    return match (aq, rl) {
+        (true, true) => ".aqrl",
+        (true, false) => ".aq",
+        (false, true) => ".rl",
+        (false, false) => "",
+    };
+}
+
+/* address to name map for <symbol+offset> annotations */
+pub struct SymbolTable {
+    symbols: Vec<(u64, String)>, /* sorted by address, one name each */
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        return SymbolTable { symbols: Vec::new() };
+    }
+
+    /* the first name given to an address is the one that gets printed */
+    pub fn add(&mut self, addr: u64, name: &str) {
+        if let Err(idx) = self.symbols.binary_search_by_key(&addr, |(a, _)| *a) {
+            self.symbols.insert(idx, (addr, name.to_string()));
+        }
+    }
+
+    /* symbol that starts exactly at addr */
+    pub fn at(&self, addr: u64) -> Option<&str> {
+        return self.symbols.binary_search_by_key(&addr, |(a, _)| *a).ok()
+            .map(|idx| self.symbols[idx].1.as_str());
+    }
+
+    /* " <name>" or " <name+0x10>" for the closest symbol at or below addr, empty without one */
+    pub fn annotate(&self, addr: u64) -> String {
+        let idx: usize = self.symbols.partition_point(|(a, _)| *a <= addr);
+        if idx == 0 {
+            return String::new();
+        }
+        let (base, name): &(u64, String) = &self.symbols[idx - 1];
+        return if addr == *base { format!(" <{}>", name) } else { format!(" <{}+0x{:x}>", name, addr - base) };
+    }
+}
+
+pub struct Disassembler {
+    xlen: Xlen,
+    aliases: bool, /* print pseudo instructions, objdump's default */
+    symbols: SymbolTable,
+    isa: Option<IsaConfig>, /* instructions outside of it print as data, None for everything */
+}
+
+impl Disassembler {
+    pub fn new(xlen: Xlen, aliases: bool, symbols: SymbolTable) -> Disassembler {
+        return Disassembler {
+            xlen: xlen,
+            aliases: aliases,
+            symbols: symbols,
+            isa: None,
+        };
+    }
+
+    /* only decode what a hart built for isa would, the XLEN stays the one given to new() */
+    pub fn with_isa(mut self, isa: &IsaConfig) -> Self {
+        self.isa = Some(*isa);
+        return self;
+    }
+
+    /* absolute address of a branch/jump/la target plus the symbol it falls in */
+    fn target(&self, addr: u64) -> String {
+        let addr: u64 = self.xlen.trunc(addr);
+        return format!("{:x}{}", addr, self.symbols.annotate(addr));
+    }
+
+    /*
+     * name: disassemble
+     * desc: objdump text of one decoded instruction, mnemonic and operands are
+     *       separated by a tab, pc is the address it was fetched from
+     *
+     * NOTE: the mnemonic is the name of the opcodes/ table entry the instruction
+     *       encodes to (build.rs), None only for an instruction that has no encoding
+     */
+    pub fn disassemble(&self, inst: &Instruction, pc: u64) -> Option<String> {
+        use Instruction::*;
+
+        if self.aliases {
+            if let Some(text) = self.alias(inst, pc) {
+                return Some(text);
+            }
+        }
+
+        /* the name of the table entry, everything below only lays out the operands */
+        let name: &str = mnemonic(inst)?;
+        let target = |imm: i32| -> String { return self.target(pc.wrapping_add(imm as i64 as u64)); };
+        let csr = |rd: u8, csr: u16, src: String| -> String {
+            return format!("{}\t{},{},{}", name, xreg(rd), csr_operand(csr), src);
+        };
+
+        let text: String = match *inst {
+            Lui { rd, imm } | Auipc { rd, imm } => format!("{}\t{},0x{:x}", name, xreg(rd), (imm as u32) >> 12),
+            Jal { rd, imm } => format!("{}\t{},{}", name, xreg(rd), target(imm)),
+
+            Beq { rs1, rs2, imm } | Bne { rs1, rs2, imm } | Blt { rs1, rs2, imm }
+            | Bge { rs1, rs2, imm } | Bltu { rs1, rs2, imm } | Bgeu { rs1, rs2, imm } => {
+                format!("{}\t{},{},{}", name, xreg(rs1), xreg(rs2), target(imm))
+            }
+
+            Jalr { rd, rs1, imm } | Lb { rd, rs1, imm } | Lh { rd, rs1, imm } | Lw { rd, rs1, imm }
+            | Ld { rd, rs1, imm } | Lbu { rd, rs1, imm } | Lhu { rd, rs1, imm } | Lwu { rd, rs1, imm } => {
+                format!("{}\t{},{}({})", name, xreg(rd), imm, xreg(rs1))
+            }
+            Sb { rs1, rs2, imm } | Sh { rs1, rs2, imm } | Sw { rs1, rs2, imm } | Sd { rs1, rs2, imm } => {
+                format!("{}\t{},{}({})", name, xreg(rs2), imm, xreg(rs1))
+            }
+
+            Addi { rd, rs1, imm } | Slti { rd, rs1, imm } | Sltiu { rd, rs1, imm } | Xori { rd, rs1, imm }
+            | Ori { rd, rs1, imm } | Andi { rd, rs1, imm } | Addiw { rd, rs1, imm } => {
+                format!("{}\t{},{},{}", name, xreg(rd), xreg(rs1), imm)
+            }
+            Slli { rd, rs1, shamt } | Srli { rd, rs1, shamt } | Srai { rd, rs1, shamt }
+            | Slliw { rd, rs1, shamt } | Srliw { rd, rs1, shamt } | Sraiw { rd, rs1, shamt } => {
+                format!("{}\t{},{},0x{:x}", name, xreg(rd), xreg(rs1), shamt)
+            }
+
+            Add { rd, rs1, rs2 } | Sub { rd, rs1, rs2 } | Sll { rd, rs1, rs2 } | Slt { rd, rs1, rs2 }
+            | Sltu { rd, rs1, rs2 } | Xor { rd, rs1, rs2 } | Srl { rd, rs1, rs2 } | Sra { rd, rs1, rs2 }
+            | Or { rd, rs1, rs2 } | And { rd, rs1, rs2 }
+            | Addw { rd, rs1, rs2 } | Subw { rd, rs1, rs2 } | Sllw { rd, rs1, rs2 } | Srlw { rd, rs1, rs2 } | Sraw { rd, rs1, rs2 }
+            | Mul { rd, rs1, rs2 } | Mulh { rd, rs1, rs2 } | Mulhsu { rd, rs1, rs2 } | Mulhu { rd, rs1, rs2 }
+            | Div { rd, rs1, rs2 } | Divu { rd, rs1, rs2 } | Rem { rd, rs1, rs2 } | Remu { rd, rs1, rs2 }
+            | Mulw { rd, rs1, rs2 } | Divw { rd, rs1, rs2 } | Divuw { rd, rs1, rs2 } | Remw { rd, rs1, rs2 } | Remuw { rd, rs1, rs2 } => {
+                format!("{}\t{},{},{}", name, xreg(rd), xreg(rs1), xreg(rs2))
+            }
+
+            Lr { rd, rs1, aq, rl, .. } => format!("{}{}\t{},({})", name, ordering(aq, rl), xreg(rd), xreg(rs1)),
+            Sc { rd, rs1, rs2, aq, rl, .. } | Amo { rd, rs1, rs2, aq, rl, .. } => {
+                format!("{}{}\t{},{},({})", name, ordering(aq, rl), xreg(rd), xreg(rs2), xreg(rs1))
+            }
+
+            Csrrw { rd, rs1, csr: c } | Csrrs { rd, rs1, csr: c } | Csrrc { rd, rs1, csr: c } => csr(rd, c, xreg(rs1).to_string()),
+            Csrrwi { rd, uimm, csr: c } | Csrrsi { rd, uimm, csr: c } | Csrrci { rd, uimm, csr: c } => csr(rd, c, uimm.to_string()),
+
+            Ecall | Ebreak | Mret | Sret | Wfi | FenceI => name.to_string(),
+            SfenceVma { rs1, rs2 } => format!("{}\t{},{}", name, xreg(rs1), xreg(rs2)),
+            /* fm = 0b1000 with RW,RW is FENCE.TSO */
+            Fence { fm: 0x8, pred: 0x3, succ: 0x3 } => "fence.tso".to_string(),
+            Fence { pred, succ, .. } => format!("{}\t{},{}", name, fence_set(pred), fence_set(succ)),
+
+            Float(fp) => fp.disassemble(name)?,
+            Bitmanip(bm) => bm.disassemble(name),
+            Vector(v) => v.disassemble(name),
+        };
+        return Some(text);
+    }
+
+    /* the pseudo instruction a single instruction is the canonical form of */
+    fn alias(&self, inst: &Instruction, pc: u64) -> Option<String> {
+        use Instruction::*;
+
+        let target = |imm: i32| -> String { return self.target(pc.wrapping_add(imm as i64 as u64)); };
+        let op2 = |name: &str, rd: u8, rs: u8| -> String {
+            return format!("{}\t{},{}", name, xreg(rd), xreg(rs));
+        };
+        let branch = |name: &str, rs: u8, imm: i32| -> String {
+            return format!("{}\t{},{}", name, xreg(rs), target(imm));
+        };
+
+        let text: String = match *inst {
+            Addi { rd: 0, rs1: 0, imm: 0 } => "nop".to_string(),
+            Addi { rd, rs1: 0, imm } => format!("li\t{},{}", xreg(rd), imm),
+            Addi { rd, rs1, imm: 0 } => op2("mv", rd, rs1),
+            Addiw { rd, rs1, imm: 0 } => op2("sext.w", rd, rs1),
+            Xori { rd, rs1, imm: -1 } => op2("not", rd, rs1),
+            Sub { rd, rs1: 0, rs2 } => op2("neg", rd, rs2),
+            Subw { rd, rs1: 0, rs2 } => op2("negw", rd, rs2),
+            Sltiu { rd, rs1, imm: 1 } => op2("seqz", rd, rs1),
+            Sltu { rd, rs1: 0, rs2 } => op2("snez", rd, rs2),
+            Slt { rd, rs1, rs2: 0 } => op2("sltz", rd, rs1),
+            Slt { rd, rs1: 0, rs2 } => op2("sgtz", rd, rs2),
+
+            Jal { rd: 0, imm } => format!("j\t{}", target(imm)),
+            Jal { rd: 1, imm } => format!("jal\t{}", target(imm)),
+            Jalr { rd: 0, rs1: 1, imm: 0 } => "ret".to_string(),
+            Jalr { rd: 0, rs1, imm: 0 } => format!("jr\t{}", xreg(rs1)),
+            Jalr { rd: 1, rs1, imm: 0 } => format!("jalr\t{}", xreg(rs1)),
+
+            Beq { rs1, rs2: 0, imm } => branch("beqz", rs1, imm),
+            Bne { rs1, rs2: 0, imm } => branch("bnez", rs1, imm),
+            Bge { rs1: 0, rs2, imm } => branch("blez", rs2, imm),
+            Bge { rs1, rs2: 0, imm } => branch("bgez", rs1, imm),
+            Blt { rs1, rs2: 0, imm } => branch("bltz", rs1, imm),
+            Blt { rs1: 0, rs2, imm } => branch("bgtz", rs2, imm),
+
+            Csrrs { rd, rs1: 0, csr } => format!("csrr\t{},{}", xreg(rd), csr_operand(csr)),
+            Csrrw { rd: 0, rs1, csr } => format!("csrw\t{},{}", csr_operand(csr), xreg(rs1)),
+            Csrrs { rd: 0, rs1, csr } => format!("csrs\t{},{}", csr_operand(csr), xreg(rs1)),
+            Csrrc { rd: 0, rs1, csr } => format!("csrc\t{},{}", csr_operand(csr), xreg(rs1)),
+            Csrrwi { rd: 0, uimm, csr } => format!("csrwi\t{},{}", csr_operand(csr), uimm),
+            Csrrsi { rd: 0, uimm, csr } => format!("csrsi\t{},{}", csr_operand(csr), uimm),
+            Csrrci { rd: 0, uimm, csr } => format!("csrci\t{},{}", csr_operand(csr), uimm),
+
+            Fence { fm: 0, pred: 0xF, succ: 0xF } => "fence".to_string(),
+            _ => return None,
+        };
+        return Some(text);
+    }
+
+    /*
+     * name: alias_pair
+     * desc: the li/la/call/tail a two instruction sequence was assembled from,
+     *       pc is the address of the first one
+     */
+    fn alias_pair(&self, first: &Instruction, second: &Instruction, pc: u64) -> Option<String> {
+        use Instruction::*;
+
+        let text: String = match (*first, *second) {
+            (Lui { rd, imm: hi }, Addi { rd: rd2, rs1, imm: lo }) if rd != 0 && rd2 == rd && rs1 == rd => {
+                let value: i64 = if self.xlen == Xlen::RV32 { hi.wrapping_add(lo) as i64 } else { hi as i64 + lo as i64 };
+                format!("li\t{},{}", xreg(rd), value)
+            }
+            (Lui { rd, imm: hi }, Addiw { rd: rd2, rs1, imm: lo }) if rd != 0 && rd2 == rd && rs1 == rd => {
+                format!("li\t{},{}", xreg(rd), hi.wrapping_add(lo))
+            }
+            (Auipc { rd, imm: hi }, Addi { rd: rd2, rs1, imm: lo }) if rd != 0 && rd2 == rd && rs1 == rd => {
+                format!("la\t{},{}", xreg(rd), self.target(pc.wrapping_add(hi as i64 as u64).wrapping_add(lo as i64 as u64)))
+            }
+            /* call goes through ra, tail through t1 */
+            (Auipc { rd: 1, imm: hi }, Jalr { rd: 1, rs1: 1, imm: lo }) => {
+                format!("call\t{}", self.target(pc.wrapping_add(hi as i64 as u64).wrapping_add(lo as i64 as u64)))
+            }
+            (Auipc { rd: 6, imm: hi }, Jalr { rd: 0, rs1: 6, imm: lo }) => {
+                format!("tail\t{}", self.target(pc.wrapping_add(hi as i64 as u64).wrapping_add(lo as i64 as u64)))
+            }
+            _ => return None,
+        };
+        return Some(text);
+    }
+
+    /* expand and decode, the length is the one fetched even if it doesn't decode */
+    fn decode(&self, bits: u32) -> (Option<Instruction>, u8) {
+        let compressed: bool = is_compressed(bits as u16);
+        let (inst, ilen): (Result<u32,()>, u8) = if compressed {
+            (expand_compressed(bits as u16, self.xlen), 2)
+        } else {
+            (Ok(bits), 4)
+        };
+        let decoded: Option<Instruction> = inst.ok().and_then(|inst| Instruction::decode(inst, self.xlen).ok());
+        return match &self.isa {
+            Some(isa) if compressed && !isa.is_enabled(IsaExt::C) => (None, ilen),
+            Some(isa) => (decoded.filter(|decoded| isa.allows(decoded)), ilen),
+            None => (decoded, ilen),
+        };
+    }
+
+    /*
+     * name: disassemble_bits
+     * desc: decode and print the instruction at the start of bits, returns the text and
+     *       the length of the encoding in bytes
+     *
+     * NOTE: only the low 16 bits are looked at when they are an RVC instruction
+     */
+    pub fn disassemble_bits(&self, bits: u32, pc: u64) -> (String, u8) {
+        let (inst, ilen): (Option<Instruction>, u8) = self.decode(bits);
+        return match inst.and_then(|decoded| self.disassemble(&decoded, pc)) {
+            Some(text) => (text, ilen),
+            None if ilen == 2 => (format!(".2byte\t0x{:x}", bits as u16), 2),
+            None => (format!(".4byte\t0x{:x}", bits), 4),
+        };
+    }
+
+    /*
+     * name: disassemble_region
+     * desc: one objdump style line per instruction, address, raw encoding and text,
+     *       bytes holds the code that starts at addr, every symbol in it starts a
+     *       "<name>:" block
+     *
+     * NOTE: a trailing half instruction is printed as the bytes that are left
+     */
+    pub fn disassemble_region(&self, bytes: &[u8], addr: u64) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut off: usize = 0;
+
+        while off < bytes.len() {
+            let pc: u64 = addr + off as u64;
+            let left: &[u8] = &bytes[off..];
+
+            if let Some(name) = self.symbols.at(pc) {
+                let width: usize = if self.xlen == Xlen::RV32 { 8 } else { 16 };
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(format!("{:0width$x} <{}>:", pc, name, width = width));
+            }
+
+            let bits: u32 = match fetch(left) {
+                Some(bits) => bits,
+                None => {
+                    let raw: String = left.iter().rev().map(|b| format!("{:02x}", b)).collect();
+                    lines.push(format!("{:8x}:\t{:<18}\t.byte\t{}", pc, raw,
+                        left.iter().map(|b| format!("0x{:x}", b)).collect::<Vec<String>>().join(",")));
+                    break;
+                }
+            };
+            let (inst, ilen): (Option<Instruction>, u8) = self.decode(bits);
+
+            /* li/la/call/tail, both halves go in the encoding column */
+            let next: usize = off + ilen as usize;
+            if let (true, Some(first), Some(bits2)) = (self.aliases, inst, fetch(&bytes[next..])) {
+                let (second, ilen2): (Option<Instruction>, u8) = self.decode(bits2);
+                let joined: Option<String> = match second {
+                    Some(second) if self.symbols.at(addr + next as u64).is_none() => self.alias_pair(&first, &second, pc),
+                    _ => None,
+                };
+                if let Some(text) = joined {
+                    let raw: String = format!("{} {}", raw_bits(bits, ilen), raw_bits(bits2, ilen2));
+                    lines.push(format!("{:8x}:\t{:<18}\t{}", pc, raw, text));
+                    off = next + ilen2 as usize;
+                    continue;
+                }
+            }
+
+            let (text, ilen): (String, u8) = self.disassemble_bits(bits, pc);
+            lines.push(format!("{:8x}:\t{:<18}\t{}", pc, raw_bits(bits, ilen), text));
+            off += ilen as usize;
+        }
+        return lines;
+    }
+}
+
+/* the next 16 or 32 bit instruction, None if bytes ends in the middle of it */
+fn fetch(bytes: &[u8]) -> Option<u32> {
+    let low: u16 = u16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]);
+    if is_compressed(low) {
+        return Some(low as u32);
+    }
+    let high: u16 = u16::from_le_bytes([*bytes.get(2)?, *bytes.get(3)?]);
+    return Some(low as u32 | ((high as u32) << 16));
+}
+
+/* encoding column, 4 hex digits for RVC */
+fn raw_bits(bits: u32, ilen: u8) -> String {
+    return if ilen == 2 { format!("{:04x}", bits as u16) } else { format!("{:08x}", bits) };
+}
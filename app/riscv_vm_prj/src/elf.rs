@@ -0,0 +1,265 @@
+/*
+ * name: elf.rs
+ * desc: minimal ELF reader, just enough to find the code in a RISC-V object or executable
+ *
+ * Note: only little endian ELF32/ELF64 with e_machine == EM_RISCV is accepted, the
+ *       class decides XLEN
+ *
+ *       sections are kept when they hold program bits and are executable, addresses
+ *       are the section addresses (0 in a relocatable object, like objdump shows them)
+ *
+ *       symbols come from .symtab, only named functions, objects and labels that are
+ *       defined in a loaded section, in a relocatable object every section starts at 0
+ *       so only the ones in executable sections are kept there
+ *
+ */
+
+use crate::xlen::*;
+
+const EM_RISCV: u16 = 243;
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+const ET_REL: u16 = 1;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STB_GLOBAL: u8 = 1;
+
+#[derive(Debug)]
+pub struct ElfSection {
+    pub name: String,
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ElfSymbol {
+    pub name: String,
+    pub addr: u64,
+    pub global: bool,
+}
+
+#[derive(Debug)]
+pub struct ElfImage {
+    pub xlen: Xlen,
+    pub sections: Vec<ElfSection>, /* executable sections in file order */
+    pub symbols: Vec<ElfSymbol>,   /* by address, globals first where they share one */
+}
+
+/* little endian field readers, anything out of bounds is a malformed file */
+fn read_u16(bytes: &[u8], off: usize) -> Result<u16,()> {
+    let b: &[u8] = bytes.get(off..off + 2).ok_or(())?;
+    return Ok(u16::from_le_bytes([b[0], b[1]]));
+}
+
+fn read_u32(bytes: &[u8], off: usize) -> Result<u32,()> {
+    let b: &[u8] = bytes.get(off..off + 4).ok_or(())?;
+    return Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+}
+
+fn read_u64(bytes: &[u8], off: usize) -> Result<u64,()> {
+    let lo: u64 = read_u32(bytes, off)? as u64;
+    let hi: u64 = read_u32(bytes, off + 4)? as u64;
+    return Ok(lo | (hi << 32));
+}
+
+/* NUL terminated string at off */
+fn read_str(bytes: &[u8], off: usize) -> Result<String,()> {
+    let tail: &[u8] = bytes.get(off..).ok_or(())?;
+    let len: usize = tail.iter().position(|b| *b == 0).ok_or(())?;
+    return Ok(String::from_utf8_lossy(&tail[..len]).into_owned());
+}
+
+/* base + add as an offset into the file, a crafted header can't wrap it around */
+fn file_offset(bytes: &[u8], base: u64, add: u64) -> Result<usize,()> {
+    let off: u64 = base.checked_add(add).ok_or(())?;
+    if off > bytes.len() as u64 {
+        return Err(());
+    }
+    return Ok(off as usize);
+}
+
+/* raw section header fields, widened to 64 bits */
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,    /* string table of a symbol table */
+    entsize: u64,
+}
+
+impl ElfImage {
+    /* true if the bytes start with the ELF magic, the caller can fall back to a raw image otherwise */
+    pub fn is_elf(bytes: &[u8]) -> bool {
+        return bytes.starts_with(b"\x7fELF");
+    }
+
+    /*
+     * name: parse
+     * desc: read the header and section table of an ELF image
+     *
+     * NOTE: fails on anything that isn't a little endian RISC-V ELF or that points
+     *       outside the file, offsets are added with overflow checks since they
+     *       come straight from the file
+     */
+    pub fn parse(bytes: &[u8]) -> Result<ElfImage,()> {
+        if !ElfImage::is_elf(bytes) || bytes.len() < 0x34 {
+            return Err(());
+        }
+        let class: u8 = bytes[4];
+        if bytes[5] != ELFDATA2LSB || read_u16(bytes, 0x12)? != EM_RISCV {
+            return Err(());
+        }
+
+        let is64: bool = match class {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            _ => return Err(()),
+        };
+        let xlen: Xlen = if is64 { Xlen::RV64 } else { Xlen::RV32 };
+
+        let relocatable: bool = read_u16(bytes, 0x10)? == ET_REL;
+        let (shoff, fields): (u64, usize) = if is64 {
+            (read_u64(bytes, 0x28)?, 0x3A)
+        } else {
+            (read_u32(bytes, 0x20)? as u64, 0x2E)
+        };
+        let shentsize: usize = read_u16(bytes, fields)? as usize;
+        let shnum: usize = read_u16(bytes, fields + 2)? as usize;
+        let shstrndx: usize = read_u16(bytes, fields + 4)? as usize;
+
+        let mut headers: Vec<SectionHeader> = Vec::new();
+        for i in 0..shnum {
+            let sh: usize = file_offset(bytes, shoff, (i as u64).checked_mul(shentsize as u64).ok_or(())?)?;
+            headers.push(if is64 {
+                SectionHeader {
+                    name: read_u32(bytes, sh)?,
+                    kind: read_u32(bytes, sh + 0x4)?,
+                    flags: read_u64(bytes, sh + 0x8)?,
+                    addr: read_u64(bytes, sh + 0x10)?,
+                    offset: read_u64(bytes, sh + 0x18)?,
+                    size: read_u64(bytes, sh + 0x20)?,
+                    link: read_u32(bytes, sh + 0x28)?,
+                    entsize: read_u64(bytes, sh + 0x38)?,
+                }
+            } else {
+                SectionHeader {
+                    name: read_u32(bytes, sh)?,
+                    kind: read_u32(bytes, sh + 0x4)?,
+                    flags: read_u32(bytes, sh + 0x8)? as u64,
+                    addr: read_u32(bytes, sh + 0xC)? as u64,
+                    offset: read_u32(bytes, sh + 0x10)? as u64,
+                    size: read_u32(bytes, sh + 0x14)? as u64,
+                    link: read_u32(bytes, sh + 0x18)?,
+                    entsize: read_u32(bytes, sh + 0x24)? as u64,
+                }
+            });
+        }
+
+        let strtab: u64 = headers.get(shstrndx).ok_or(())?.offset;
+        let mut sections: Vec<ElfSection> = Vec::new();
+        for sh in headers.iter() {
+            if sh.kind != SHT_PROGBITS || (sh.flags & SHF_EXECINSTR) == 0 {
+                continue;
+            }
+            let start: usize = file_offset(bytes, sh.offset, 0)?;
+            let end: usize = file_offset(bytes, sh.offset, sh.size)?;
+            sections.push(ElfSection {
+                name: read_str(bytes, file_offset(bytes, strtab, sh.name as u64)?)?,
+                addr: sh.addr,
+                data: bytes[start..end].to_vec(),
+            });
+        }
+
+        let mut symbols: Vec<ElfSymbol> = Vec::new();
+        for symtab in headers.iter().filter(|sh| sh.kind == SHT_SYMTAB && sh.entsize != 0) {
+            let names: u64 = headers.get(symtab.link as usize).ok_or(())?.offset;
+            for i in 0..(symtab.size / symtab.entsize) {
+                let sym: usize = file_offset(bytes, symtab.offset, i.checked_mul(symtab.entsize).ok_or(())?)?;
+                let (name, info, shndx, addr): (u32, u8, u16, u64) = if is64 {
+                    (read_u32(bytes, sym)?, *bytes.get(sym + 0x4).ok_or(())?,
+                     read_u16(bytes, sym + 0x6)?, read_u64(bytes, sym + 0x8)?)
+                } else {
+                    (read_u32(bytes, sym)?, *bytes.get(sym + 0xC).ok_or(())?,
+                     read_u16(bytes, sym + 0xE)?, read_u32(bytes, sym + 0x4)? as u64)
+                };
+
+                /* undefined and the special indexes (absolute, common) have no section */
+                let section: &SectionHeader = match headers.get(shndx as usize) {
+                    Some(section) if shndx != 0 && shndx < 0xFF00 => section,
+                    _ => continue,
+                };
+                let wanted: u64 = if relocatable { SHF_EXECINSTR } else { SHF_ALLOC };
+                if (section.flags & wanted) == 0 || ![STT_NOTYPE, STT_OBJECT, STT_FUNC].contains(&(info & 0xF)) {
+                    continue;
+                }
+                /* $x/$d mapping symbols and the assembler's .L labels */
+                let name: String = read_str(bytes, file_offset(bytes, names, name as u64)?)?;
+                if name.is_empty() || name.starts_with('$') || name.starts_with(".L") {
+                    continue;
+                }
+                symbols.push(ElfSymbol {
+                    name: name,
+                    addr: addr,
+                    global: (info >> 4) == STB_GLOBAL,
+                });
+            }
+        }
+        symbols.sort_by_key(|sym| (sym.addr, !sym.global));
+
+        return Ok(ElfImage {
+            xlen: xlen,
+            sections: sections,
+            symbols: symbols,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* ELF64 header with one executable section header right after it */
+    fn elf64(shoff: u64, offset: u64, size: u64) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![0; 0x80];
+        bytes[..4].copy_from_slice(b"\x7fELF");
+        bytes[4] = ELFCLASS64;
+        bytes[5] = ELFDATA2LSB;
+        bytes[0x12..0x14].copy_from_slice(&EM_RISCV.to_le_bytes());
+        bytes[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        bytes[0x3A..0x3C].copy_from_slice(&0x40u16.to_le_bytes());
+        bytes[0x3C..0x3E].copy_from_slice(&1u16.to_le_bytes());
+        bytes[0x44..0x48].copy_from_slice(&SHT_PROGBITS.to_le_bytes());
+        bytes[0x48..0x50].copy_from_slice(&(SHF_ALLOC | SHF_EXECINSTR).to_le_bytes());
+        bytes[0x58..0x60].copy_from_slice(&offset.to_le_bytes());
+        bytes[0x60..0x68].copy_from_slice(&size.to_le_bytes());
+        return bytes;
+    }
+
+    #[test]
+    fn section_in_bounds() {
+        let image: ElfImage = ElfImage::parse(&elf64(0x40, 0x40, 0x10)).unwrap();
+        assert_eq!(image.xlen, Xlen::RV64);
+        assert_eq!(image.sections.len(), 1);
+        assert_eq!(image.sections[0].data.len(), 0x10);
+    }
+
+    /* offsets that wrap around are a malformed file, not a panic */
+    #[test]
+    fn wrapping_offsets_rejected() {
+        assert!(ElfImage::parse(&elf64(0x40, 0xFFFF_FFFF_FFFF_FFF0, 0x100)).is_err());
+        assert!(ElfImage::parse(&elf64(0x40, 0x40, u64::MAX)).is_err());
+        assert!(ElfImage::parse(&elf64(u64::MAX - 0x10, 0x40, 0x10)).is_err());
+    }
+}
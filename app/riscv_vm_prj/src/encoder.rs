@@ -0,0 +1,2515 @@
+/*
+ * name: encoder.rs
+ * desc: typed instruction builder, the inverse of Instruction::decode
+ *
+ *       Inst::addi(Reg::A0, Reg::Zero, 5).encode() == 0x00500513
+ *
+ * Note: there is a constructor for every entry of the opcode tables, the base
+ *       ISA, M, A, Zicsr, Zifencei and the privileged ones build the variant
+ *       directly, F/D and V start from the entry's MATCH_ bits (build.rs) and
+ *       put the operands at their place, Zb* fill in a BitmanipInst for RV64
+ *
+ *       the constructors take immediates as the instruction uses them (byte
+ *       offsets for branches/jumps, the 20 bit value for LUI/AUIPC) and panic
+ *       on anything the encoding can't hold, so a test can't silently build a
+ *       different instruction than the one it asked for
+ *
+ *       the atomics are built without ordering bits, .aq()/.rl() add them, the
+ *       same way F/D round by frm unless .rm() is given, V is unmasked unless
+ *       .masked() and Zb* use .xlen() for RV32
+ *
+ */
+
+use crate::idecoder::*;
+use crate::idecoder::encoding::*;
+use crate::fpu::*;
+#[cfg(test)]
+use crate::vector::*;
+#[cfg(test)]
+use crate::bitmanip::*;
+#[cfg(test)]
+use crate::softfloat::*;
+#[cfg(test)]
+use crate::xlen::*;
+
+/* the instructions are variants of the decoder's type so decode(encode(x)) compares directly */
+pub type Inst = Instruction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    Zero = 0, Ra, Sp, Gp, Tp, T0, T1, T2,
+    S0, S1, A0, A1, A2, A3, A4, A5,
+    A6, A7, S2, S3, S4, S5, S6, S7,
+    S8, S9, S10, S11, T3, T4, T5, T6,
+}
+
+const REGS: [Reg; 32] = [
+    Reg::Zero, Reg::Ra, Reg::Sp, Reg::Gp, Reg::Tp, Reg::T0, Reg::T1, Reg::T2,
+    Reg::S0, Reg::S1, Reg::A0, Reg::A1, Reg::A2, Reg::A3, Reg::A4, Reg::A5,
+    Reg::A6, Reg::A7, Reg::S2, Reg::S3, Reg::S4, Reg::S5, Reg::S6, Reg::S7,
+    Reg::S8, Reg::S9, Reg::S10, Reg::S11, Reg::T3, Reg::T4, Reg::T5, Reg::T6,
+];
+
+impl Reg {
+    /* x0..x31 by number, only the low 5 bits count */
+    pub fn x(num: u8) -> Reg {
+        return REGS[(num & 0x1F) as usize];
+    }
+}
+
+/* signed immediate of the given width, scale is the alignment the encoding drops */
+fn check_imm(imm: i32, bits: u32, scale: i32, what: &str) -> i32 {
+    let lo: i32 = -(1 << (bits - 1));
+    let hi: i32 = (1 << (bits - 1)) - 1;
+    assert!(imm >= lo && imm <= hi && imm % scale == 0, "{} {} doesn't fit the encoding", what, imm);
+    return imm;
+}
+
+fn check_uimm(imm: u32, bits: u32, what: &str) -> u32 {
+    assert!(imm < (1 << bits), "{} {} doesn't fit the encoding", what, imm);
+    return imm;
+}
+
+fn amo(op: AmoOp, rd: Reg, rs2: Reg, rs1: Reg, wide: bool) -> Instruction {
+    return Instruction::Amo { op: op, rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8, wide: wide, aq: false, rl: false };
+}
+
+impl Instruction {
+
+    /*
+     * name: encode
+     * desc: the 32 bit word this instruction decodes from
+     *
+     * NOTE: FENCE keeps only fm/pred/succ, the reserved fields come out as zero
+     *
+     *       every encoding is in the opcode tables (build.rs), the only thing that
+     *       can miss is a Bitmanip op built for an XLEN that doesn't have it
+     */
+    pub fn encode(&self) -> u32 {
+        return encode_table(self).unwrap_or_else(|| panic!("{:?} has no encoding for its XLEN", self));
+    }
+
+    /* acquire ordering on an LR/SC/AMO */
+    pub fn aq(self) -> Instruction {
+        use Instruction::*;
+        return match self {
+            Lr { rd, rs1, wide, rl, .. } => Lr { rd, rs1, wide, aq: true, rl },
+            Sc { rd, rs1, rs2, wide, rl, .. } => Sc { rd, rs1, rs2, wide, aq: true, rl },
+            Amo { op, rd, rs1, rs2, wide, rl, .. } => Amo { op, rd, rs1, rs2, wide, aq: true, rl },
+            _ => panic!("aq only applies to atomics, not {:?}", self),
+        };
+    }
+
+    /* release ordering on an LR/SC/AMO */
+    pub fn rl(self) -> Instruction {
+        use Instruction::*;
+        return match self {
+            Lr { rd, rs1, wide, aq, .. } => Lr { rd, rs1, wide, aq, rl: true },
+            Sc { rd, rs1, rs2, wide, aq, .. } => Sc { rd, rs1, rs2, wide, aq, rl: true },
+            Amo { op, rd, rs1, rs2, wide, aq, .. } => Amo { op, rd, rs1, rs2, wide, aq, rl: true },
+            _ => panic!("rl only applies to atomics, not {:?}", self),
+        };
+    }
+
+    /* upper immediates take the 20 bit value, like lui a0,0x12345 */
+    pub fn lui(rd: Reg, imm: u32) -> Instruction {
+        return Instruction::Lui { rd: rd as u8, imm: (check_uimm(imm, 20, "lui immediate") << 12) as i32 };
+    }
+
+    pub fn auipc(rd: Reg, imm: u32) -> Instruction {
+        return Instruction::Auipc { rd: rd as u8, imm: (check_uimm(imm, 20, "auipc immediate") << 12) as i32 };
+    }
+
+    /* jump and branch offsets are in bytes, relative to the instruction */
+    pub fn jal(rd: Reg, offset: i32) -> Instruction {
+        return Instruction::Jal { rd: rd as u8, imm: check_imm(offset, 21, 2, "jump offset") };
+    }
+
+    pub fn jalr(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Jalr { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    pub fn beq(rs1: Reg, rs2: Reg, offset: i32) -> Instruction {
+        return Instruction::Beq { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(offset, 13, 2, "branch offset") };
+    }
+
+    pub fn bne(rs1: Reg, rs2: Reg, offset: i32) -> Instruction {
+        return Instruction::Bne { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(offset, 13, 2, "branch offset") };
+    }
+
+    pub fn blt(rs1: Reg, rs2: Reg, offset: i32) -> Instruction {
+        return Instruction::Blt { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(offset, 13, 2, "branch offset") };
+    }
+
+    pub fn bge(rs1: Reg, rs2: Reg, offset: i32) -> Instruction {
+        return Instruction::Bge { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(offset, 13, 2, "branch offset") };
+    }
+
+    pub fn bltu(rs1: Reg, rs2: Reg, offset: i32) -> Instruction {
+        return Instruction::Bltu { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(offset, 13, 2, "branch offset") };
+    }
+
+    pub fn bgeu(rs1: Reg, rs2: Reg, offset: i32) -> Instruction {
+        return Instruction::Bgeu { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(offset, 13, 2, "branch offset") };
+    }
+
+    /* loads and stores, imm(rs1) */
+    pub fn lb(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Lb { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn lh(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Lh { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn lw(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Lw { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn ld(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Ld { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn lbu(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Lbu { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn lhu(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Lhu { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    #[cfg(test)]
+    pub fn lwu(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Lwu { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    /* stores read rs2 into imm(rs1), the operand order of sw rs2,imm(rs1) */
+    pub fn sb(rs2: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Sb { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn sh(rs2: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Sh { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn sw(rs2: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Sw { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn sd(rs2: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Sd { rs1: rs1 as u8, rs2: rs2 as u8, imm: check_imm(imm, 12, 1, "offset") };
+    }
+
+    pub fn addi(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Addi { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    pub fn slti(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Slti { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    pub fn sltiu(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Sltiu { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    pub fn xori(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Xori { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    pub fn ori(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Ori { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    pub fn andi(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Andi { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    /* 6 bit shift amounts only decode on RV64 */
+    pub fn slli(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return Instruction::Slli { rd: rd as u8, rs1: rs1 as u8, shamt: check_uimm(shamt, 6, "shift amount") as u8 };
+    }
+
+    pub fn srli(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return Instruction::Srli { rd: rd as u8, rs1: rs1 as u8, shamt: check_uimm(shamt, 6, "shift amount") as u8 };
+    }
+
+    pub fn srai(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return Instruction::Srai { rd: rd as u8, rs1: rs1 as u8, shamt: check_uimm(shamt, 6, "shift amount") as u8 };
+    }
+
+    pub fn add(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Add { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn sub(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Sub { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn sll(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Sll { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn slt(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Slt { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn sltu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Sltu { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn xor(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Xor { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn srl(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Srl { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn sra(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Sra { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn or(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Or { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn and(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::And { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    /* RV64 only */
+    pub fn addiw(rd: Reg, rs1: Reg, imm: i32) -> Instruction {
+        return Instruction::Addiw { rd: rd as u8, rs1: rs1 as u8, imm: check_imm(imm, 12, 1, "immediate") };
+    }
+
+    #[cfg(test)]
+    pub fn slliw(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return Instruction::Slliw { rd: rd as u8, rs1: rs1 as u8, shamt: check_uimm(shamt, 5, "shift amount") as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn srliw(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return Instruction::Srliw { rd: rd as u8, rs1: rs1 as u8, shamt: check_uimm(shamt, 5, "shift amount") as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn sraiw(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return Instruction::Sraiw { rd: rd as u8, rs1: rs1 as u8, shamt: check_uimm(shamt, 5, "shift amount") as u8 };
+    }
+
+    pub fn addw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Addw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn subw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Subw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn sllw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Sllw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn srlw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Srlw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn sraw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Sraw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    /* M extension */
+    pub fn mul(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Mul { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn mulh(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Mulh { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn mulhsu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Mulhsu { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn mulhu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Mulhu { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn div(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Div { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn divu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Divu { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn rem(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Rem { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    pub fn remu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Remu { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn mulw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Mulw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn divw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Divw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn divuw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Divuw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn remw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Remw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    #[cfg(test)]
+    pub fn remuw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::Remuw { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    /* A extension, operands in assembler order: lr.w rd,(rs1) and amoadd.w rd,rs2,(rs1) */
+    pub fn lr_w(rd: Reg, rs1: Reg) -> Instruction {
+        return Instruction::Lr { rd: rd as u8, rs1: rs1 as u8, wide: false, aq: false, rl: false };
+    }
+
+    #[cfg(test)]
+    pub fn lr_d(rd: Reg, rs1: Reg) -> Instruction {
+        return Instruction::Lr { rd: rd as u8, rs1: rs1 as u8, wide: true, aq: false, rl: false };
+    }
+
+    pub fn sc_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return Instruction::Sc { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8, wide: false, aq: false, rl: false };
+    }
+
+    #[cfg(test)]
+    pub fn sc_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return Instruction::Sc { rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8, wide: true, aq: false, rl: false };
+    }
+
+    pub fn amoswap_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::SWAP, rd, rs2, rs1, false);
+    }
+
+    pub fn amoadd_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::ADD, rd, rs2, rs1, false);
+    }
+
+    pub fn amoxor_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::XOR, rd, rs2, rs1, false);
+    }
+
+    pub fn amoand_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::AND, rd, rs2, rs1, false);
+    }
+
+    pub fn amoor_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::OR, rd, rs2, rs1, false);
+    }
+
+    pub fn amomin_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MIN, rd, rs2, rs1, false);
+    }
+
+    pub fn amomax_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MAX, rd, rs2, rs1, false);
+    }
+
+    pub fn amominu_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MINU, rd, rs2, rs1, false);
+    }
+
+    pub fn amomaxu_w(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MAXU, rd, rs2, rs1, false);
+    }
+
+    #[cfg(test)]
+    pub fn amoswap_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::SWAP, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amoadd_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::ADD, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amoxor_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::XOR, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amoand_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::AND, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amoor_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::OR, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amomin_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MIN, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amomax_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MAX, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amominu_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MINU, rd, rs2, rs1, true);
+    }
+
+    #[cfg(test)]
+    pub fn amomaxu_d(rd: Reg, rs2: Reg, rs1: Reg) -> Instruction {
+        return amo(AmoOp::MAXU, rd, rs2, rs1, true);
+    }
+
+    /* Zicsr, csr is the 12 bit address (the CSR_* constants in csr.rs) */
+    pub fn csrrw(rd: Reg, csr: u16, rs1: Reg) -> Instruction {
+        return Instruction::Csrrw { rd: rd as u8, rs1: rs1 as u8, csr: check_uimm(csr as u32, 12, "csr") as u16 };
+    }
+
+    pub fn csrrs(rd: Reg, csr: u16, rs1: Reg) -> Instruction {
+        return Instruction::Csrrs { rd: rd as u8, rs1: rs1 as u8, csr: check_uimm(csr as u32, 12, "csr") as u16 };
+    }
+
+    pub fn csrrc(rd: Reg, csr: u16, rs1: Reg) -> Instruction {
+        return Instruction::Csrrc { rd: rd as u8, rs1: rs1 as u8, csr: check_uimm(csr as u32, 12, "csr") as u16 };
+    }
+
+    pub fn csrrwi(rd: Reg, csr: u16, uimm: u32) -> Instruction {
+        let uimm: u8 = check_uimm(uimm, 5, "immediate") as u8;
+        return Instruction::Csrrwi { rd: rd as u8, uimm: uimm, csr: check_uimm(csr as u32, 12, "csr") as u16 };
+    }
+
+    pub fn csrrsi(rd: Reg, csr: u16, uimm: u32) -> Instruction {
+        let uimm: u8 = check_uimm(uimm, 5, "immediate") as u8;
+        return Instruction::Csrrsi { rd: rd as u8, uimm: uimm, csr: check_uimm(csr as u32, 12, "csr") as u16 };
+    }
+
+    pub fn csrrci(rd: Reg, csr: u16, uimm: u32) -> Instruction {
+        let uimm: u8 = check_uimm(uimm, 5, "immediate") as u8;
+        return Instruction::Csrrci { rd: rd as u8, uimm: uimm, csr: check_uimm(csr as u32, 12, "csr") as u16 };
+    }
+
+    pub fn ecall() -> Instruction {
+        return Instruction::Ecall;
+    }
+
+    pub fn ebreak() -> Instruction {
+        return Instruction::Ebreak;
+    }
+
+    pub fn mret() -> Instruction {
+        return Instruction::Mret;
+    }
+
+    pub fn sret() -> Instruction {
+        return Instruction::Sret;
+    }
+
+    pub fn wfi() -> Instruction {
+        return Instruction::Wfi;
+    }
+
+    pub fn sfence_vma(rs1: Reg, rs2: Reg) -> Instruction {
+        return Instruction::SfenceVma { rs1: rs1 as u8, rs2: rs2 as u8 };
+    }
+
+    /* pred/succ are 4 bit iorw sets, i = 8 down to w = 1 */
+    pub fn fence(pred: u8, succ: u8) -> Instruction {
+        let pred: u8 = check_uimm(pred as u32, 4, "fence predecessor set") as u8;
+        let succ: u8 = check_uimm(succ as u32, 4, "fence successor set") as u8;
+        return Instruction::Fence { fm: 0, pred: pred, succ: succ };
+    }
+
+    /* fence rw,rw with fm = TSO */
+    pub fn fence_tso() -> Instruction {
+        return Instruction::Fence { fm: 0x8, pred: 0x3, succ: 0x3 };
+    }
+
+    pub fn fence_i() -> Instruction {
+        return Instruction::FenceI;
+    }
+}
+
+/* the F registers by ABI name */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FReg {
+    Ft0 = 0, Ft1, Ft2, Ft3, Ft4, Ft5, Ft6, Ft7,
+    Fs0, Fs1, Fa0, Fa1, Fa2, Fa3, Fa4, Fa5,
+    Fa6, Fa7, Fs2, Fs3, Fs4, Fs5, Fs6, Fs7,
+    Fs8, Fs9, Fs10, Fs11, Ft8, Ft9, Ft10, Ft11,
+}
+
+const FREGS: [FReg; 32] = [
+    FReg::Ft0, FReg::Ft1, FReg::Ft2, FReg::Ft3, FReg::Ft4, FReg::Ft5, FReg::Ft6, FReg::Ft7,
+    FReg::Fs0, FReg::Fs1, FReg::Fa0, FReg::Fa1, FReg::Fa2, FReg::Fa3, FReg::Fa4, FReg::Fa5,
+    FReg::Fa6, FReg::Fa7, FReg::Fs2, FReg::Fs3, FReg::Fs4, FReg::Fs5, FReg::Fs6, FReg::Fs7,
+    FReg::Fs8, FReg::Fs9, FReg::Fs10, FReg::Fs11, FReg::Ft8, FReg::Ft9, FReg::Ft10, FReg::Ft11,
+];
+
+impl FReg {
+    /* f0..f31 by number, only the low 5 bits count */
+    pub fn f(num: u8) -> FReg {
+        return FREGS[(num & 0x1F) as usize];
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VReg {
+    V0 = 0, V1, V2, V3, V4, V5, V6, V7,
+    V8, V9, V10, V11, V12, V13, V14, V15,
+    V16, V17, V18, V19, V20, V21, V22, V23,
+    V24, V25, V26, V27, V28, V29, V30, V31,
+}
+
+#[cfg(test)]
+const VREGS: [VReg; 32] = [
+    VReg::V0, VReg::V1, VReg::V2, VReg::V3, VReg::V4, VReg::V5, VReg::V6, VReg::V7,
+    VReg::V8, VReg::V9, VReg::V10, VReg::V11, VReg::V12, VReg::V13, VReg::V14, VReg::V15,
+    VReg::V16, VReg::V17, VReg::V18, VReg::V19, VReg::V20, VReg::V21, VReg::V22, VReg::V23,
+    VReg::V24, VReg::V25, VReg::V26, VReg::V27, VReg::V28, VReg::V29, VReg::V30, VReg::V31,
+];
+
+#[cfg(test)]
+impl VReg {
+    /* v0..v31 by number, only the low 5 bits count */
+    pub fn v(num: u8) -> VReg {
+        return VREGS[(num & 0x1F) as usize];
+    }
+}
+
+/* rm of the F/D instructions that round, frm unless .rm() picks one */
+#[cfg(test)]
+const RM_DYN: u32 = (FLOAT_RM_DYN as u32) << 12;
+
+/* vm of the V instructions that can be masked, unmasked unless .masked() */
+#[cfg(test)]
+const VM: u32 = 1 << 25;
+
+/* register fields at their R-Type place, the F/D and V constructors start from the MATCH_ bits */
+fn fields(rd: u8, rs1: u8, rs2: u8) -> u32 {
+    return ((rs2 as u32) << 20) | ((rs1 as u32) << 15) | ((rd as u32) << 7);
+}
+
+/* F/D and V keep the raw word, build them the way decode does */
+fn float(word: u32) -> Instruction {
+    return Instruction::Float(FloatInst::new(word));
+}
+
+#[cfg(test)]
+fn vector(word: u32) -> Instruction {
+    return Instruction::Vector(VectorInst::new(word));
+}
+
+/* built for RV64, .xlen() switches */
+#[cfg(test)]
+fn bitmanip(op: BitmanipOp, rd: Reg, rs1: Reg, rs2: Reg, shamt: u32) -> Instruction {
+    return Instruction::Bitmanip(BitmanipInst { op: op, rd: rd as u8, rs1: rs1 as u8, rs2: rs2 as u8, shamt: shamt as u8, xlen: Xlen::RV64 });
+}
+
+/* F/D loads and stores, what C.FLW/C.FSW/C.FLD/C.FSD and the sp forms expand to */
+impl Instruction {
+
+    pub fn flw(rd: FReg, rs1: Reg, imm: i32) -> Instruction {
+        return float(MATCH_FLW | fields(rd as u8, rs1 as u8, 0) | place_i(check_imm(imm, 12, 1, "offset")));
+    }
+
+    pub fn fsw(rs2: FReg, rs1: Reg, imm: i32) -> Instruction {
+        return float(MATCH_FSW | fields(0, rs1 as u8, rs2 as u8) | place_s(check_imm(imm, 12, 1, "offset")));
+    }
+
+    pub fn fld(rd: FReg, rs1: Reg, imm: i32) -> Instruction {
+        return float(MATCH_FLD | fields(rd as u8, rs1 as u8, 0) | place_i(check_imm(imm, 12, 1, "offset")));
+    }
+
+    pub fn fsd(rs2: FReg, rs1: Reg, imm: i32) -> Instruction {
+        return float(MATCH_FSD | fields(0, rs1 as u8, rs2 as u8) | place_s(check_imm(imm, 12, 1, "offset")));
+    }
+}
+
+/* the rest of F/D, Zb* and V, only the tests build them so far */
+#[cfg(test)]
+impl Instruction {
+
+    /* static rounding mode instead of frm, panics on an F/D instruction that doesn't round */
+    pub fn rm(self, rm: RoundingMode) -> Instruction {
+        let inst: Instruction = match self {
+            Instruction::Float(_) => float((self.encode() & !(0x7 << 12)) | ((rm as u32) << 12)),
+            _ => panic!("rm only applies to floating point, not {:?}", self),
+        };
+        assert!(mnemonic(&inst) == mnemonic(&self), "{:?} has no rounding mode", self);
+        return inst;
+    }
+
+    /* masked by v0 (v0.t), panics on a V instruction without a vm bit */
+    pub fn masked(self) -> Instruction {
+        let inst: Instruction = match self {
+            Instruction::Vector(_) => vector(self.encode() & !VM),
+            _ => panic!("masked only applies to vector instructions, not {:?}", self),
+        };
+        assert!(mnemonic(&inst) == mnemonic(&self), "{:?} can't be masked", self);
+        return inst;
+    }
+
+    /*
+     * name: xlen
+     * desc: the XLEN a bit manipulation instruction is encoded for
+     *
+     * NOTE: the constructors build for RV64, ZEXT.H and REV8 encode differently on
+     *       RV32 and the RV64 only ones have no RV32 encoding (encode() panics)
+     */
+    pub fn xlen(self, xlen: Xlen) -> Instruction {
+        return match self {
+            Instruction::Bitmanip(bm) => {
+                assert!(xlen == Xlen::RV64 || bm.shamt < 32, "shift amount {} doesn't fit RV32", bm.shamt);
+                Instruction::Bitmanip(BitmanipInst { xlen: xlen, ..bm })
+            }
+            _ => panic!("xlen only applies to bit manipulation, not {:?}", self),
+        };
+    }
+
+    /* F and D, the fcvt/fmv/fclass/compare forms take an X register on their integer side */
+
+    pub fn fmadd_s(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FMADD_S | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fmsub_s(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FMSUB_S | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fnmsub_s(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FNMSUB_S | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fnmadd_s(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FNMADD_S | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fadd_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FADD_S | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fsub_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSUB_S | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fmul_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FMUL_S | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fdiv_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FDIV_S | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fsgnj_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSGNJ_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fsgnjn_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSGNJN_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fsgnjx_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSGNJX_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fmin_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FMIN_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fmax_s(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FMAX_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fsqrt_s(rd: FReg, rs1: FReg) -> Instruction {
+        return float(MATCH_FSQRT_S | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fle_s(rd: Reg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FLE_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn flt_s(rd: Reg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FLT_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn feq_s(rd: Reg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FEQ_S | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fcvt_w_s(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_W_S | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_wu_s(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_WU_S | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fmv_x_w(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FMV_X_W | fields(rd as u8, rs1 as u8, 0));
+    }
+
+    pub fn fclass_s(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCLASS_S | fields(rd as u8, rs1 as u8, 0));
+    }
+
+    pub fn fcvt_s_w(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_S_W | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_s_wu(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_S_WU | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fmv_w_x(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FMV_W_X | fields(rd as u8, rs1 as u8, 0));
+    }
+
+    pub fn fcvt_l_s(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_L_S | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_lu_s(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_LU_S | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_s_l(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_S_L | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_s_lu(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_S_LU | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fmadd_d(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FMADD_D | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fmsub_d(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FMSUB_D | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fnmsub_d(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FNMSUB_D | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fnmadd_d(rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg) -> Instruction {
+        return float(MATCH_FNMADD_D | fields(rd as u8, rs1 as u8, rs2 as u8) | ((rs3 as u32) << 27) | RM_DYN);
+    }
+
+    pub fn fadd_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FADD_D | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fsub_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSUB_D | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fmul_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FMUL_D | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fdiv_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FDIV_D | fields(rd as u8, rs1 as u8, rs2 as u8) | RM_DYN);
+    }
+
+    pub fn fsgnj_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSGNJ_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fsgnjn_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSGNJN_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fsgnjx_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FSGNJX_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fmin_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FMIN_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fmax_d(rd: FReg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FMAX_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fsqrt_d(rd: FReg, rs1: FReg) -> Instruction {
+        return float(MATCH_FSQRT_D | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_s_d(rd: FReg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_S_D | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_d_s(rd: FReg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_D_S | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fle_d(rd: Reg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FLE_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn flt_d(rd: Reg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FLT_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn feq_d(rd: Reg, rs1: FReg, rs2: FReg) -> Instruction {
+        return float(MATCH_FEQ_D | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn fcvt_w_d(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_W_D | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_wu_d(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_WU_D | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fclass_d(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCLASS_D | fields(rd as u8, rs1 as u8, 0));
+    }
+
+    pub fn fcvt_d_w(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_D_W | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_d_wu(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_D_WU | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_l_d(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_L_D | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_lu_d(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FCVT_LU_D | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fmv_x_d(rd: Reg, rs1: FReg) -> Instruction {
+        return float(MATCH_FMV_X_D | fields(rd as u8, rs1 as u8, 0));
+    }
+
+    pub fn fcvt_d_l(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_D_L | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fcvt_d_lu(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FCVT_D_LU | fields(rd as u8, rs1 as u8, 0) | RM_DYN);
+    }
+
+    pub fn fmv_d_x(rd: FReg, rs1: Reg) -> Instruction {
+        return float(MATCH_FMV_D_X | fields(rd as u8, rs1 as u8, 0));
+    }
+
+
+    /* Zba, Zbb, Zbc and Zbs, immediate shifts and bit indexes take 0..63 (roriw 0..31) */
+
+    pub fn sh1add(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SH1ADD, rd, rs1, rs2, 0);
+    }
+
+    pub fn sh2add(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SH2ADD, rd, rs1, rs2, 0);
+    }
+
+    pub fn sh3add(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SH3ADD, rd, rs1, rs2, 0);
+    }
+
+    pub fn add_uw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ADD_UW, rd, rs1, rs2, 0);
+    }
+
+    pub fn sh1add_uw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SH1ADD_UW, rd, rs1, rs2, 0);
+    }
+
+    pub fn sh2add_uw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SH2ADD_UW, rd, rs1, rs2, 0);
+    }
+
+    pub fn sh3add_uw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SH3ADD_UW, rd, rs1, rs2, 0);
+    }
+
+    pub fn slli_uw(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::SLLI_UW, rd, rs1, Reg::Zero, check_uimm(shamt, 6, "shift amount"));
+    }
+
+    pub fn andn(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ANDN, rd, rs1, rs2, 0);
+    }
+
+    pub fn orn(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ORN, rd, rs1, rs2, 0);
+    }
+
+    pub fn xnor(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::XNOR, rd, rs1, rs2, 0);
+    }
+
+    pub fn clz(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CLZ, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn ctz(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CTZ, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn cpop(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CPOP, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn max(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::MAX, rd, rs1, rs2, 0);
+    }
+
+    pub fn maxu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::MAXU, rd, rs1, rs2, 0);
+    }
+
+    pub fn min(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::MIN, rd, rs1, rs2, 0);
+    }
+
+    pub fn minu(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::MINU, rd, rs1, rs2, 0);
+    }
+
+    pub fn sext_b(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SEXT_B, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn sext_h(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::SEXT_H, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn rol(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ROL, rd, rs1, rs2, 0);
+    }
+
+    pub fn ror(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ROR, rd, rs1, rs2, 0);
+    }
+
+    pub fn orc_b(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ORC_B, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn rori(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::RORI, rd, rs1, Reg::Zero, check_uimm(shamt, 6, "shift amount"));
+    }
+
+    pub fn rev8(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::REV8, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn zext_h(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ZEXT_H, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn clzw(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CLZW, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn ctzw(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CTZW, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn cpopw(rd: Reg, rs1: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CPOPW, rd, rs1, Reg::Zero, 0);
+    }
+
+    pub fn rolw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::ROLW, rd, rs1, rs2, 0);
+    }
+
+    pub fn rorw(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::RORW, rd, rs1, rs2, 0);
+    }
+
+    pub fn roriw(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::RORIW, rd, rs1, Reg::Zero, check_uimm(shamt, 5, "shift amount"));
+    }
+
+    pub fn clmul(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CLMUL, rd, rs1, rs2, 0);
+    }
+
+    pub fn clmulr(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CLMULR, rd, rs1, rs2, 0);
+    }
+
+    pub fn clmulh(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::CLMULH, rd, rs1, rs2, 0);
+    }
+
+    pub fn bclr(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::BCLR, rd, rs1, rs2, 0);
+    }
+
+    pub fn bext(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::BEXT, rd, rs1, rs2, 0);
+    }
+
+    pub fn binv(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::BINV, rd, rs1, rs2, 0);
+    }
+
+    pub fn bset(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return bitmanip(BitmanipOp::BSET, rd, rs1, rs2, 0);
+    }
+
+    pub fn bclri(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::BCLRI, rd, rs1, Reg::Zero, check_uimm(shamt, 6, "shift amount"));
+    }
+
+    pub fn bexti(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::BEXTI, rd, rs1, Reg::Zero, check_uimm(shamt, 6, "shift amount"));
+    }
+
+    pub fn binvi(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::BINVI, rd, rs1, Reg::Zero, check_uimm(shamt, 6, "shift amount"));
+    }
+
+    pub fn bseti(rd: Reg, rs1: Reg, shamt: u32) -> Instruction {
+        return bitmanip(BitmanipOp::BSETI, rd, rs1, Reg::Zero, check_uimm(shamt, 6, "shift amount"));
+    }
+
+
+    /*
+     * V, operands in assembler order (vadd.vx vd,vs2,rs1, vmacc.vx vd,rs1,vs2),
+     * the .vi immediates are -16..15 except the shifts which take 0..31,
+     * vtype is the raw immediate of vsetvli/vsetivli
+     */
+
+    pub fn vsetvli(rd: Reg, rs1: Reg, vtype: u32) -> Instruction {
+        return vector(MATCH_VSETVLI | fields(rd as u8, rs1 as u8, 0) | (check_uimm(vtype, 11, "vtype") << 20));
+    }
+
+    pub fn vsetivli(rd: Reg, avl: u32, vtype: u32) -> Instruction {
+        return vector(MATCH_VSETIVLI | fields(rd as u8, check_uimm(avl, 5, "avl") as u8, 0) | (check_uimm(vtype, 10, "vtype") << 20));
+    }
+
+    pub fn vsetvl(rd: Reg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VSETVL | fields(rd as u8, rs1 as u8, rs2 as u8));
+    }
+
+    pub fn vle8_v(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VLE8_V | fields(vd as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vse8_v(vs3: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSE8_V | fields(vs3 as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vlse8_v(vd: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VLSE8_V | fields(vd as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vsse8_v(vs3: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VSSE8_V | fields(vs3 as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vle16_v(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VLE16_V | fields(vd as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vse16_v(vs3: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSE16_V | fields(vs3 as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vlse16_v(vd: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VLSE16_V | fields(vd as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vsse16_v(vs3: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VSSE16_V | fields(vs3 as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vle32_v(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VLE32_V | fields(vd as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vse32_v(vs3: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSE32_V | fields(vs3 as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vlse32_v(vd: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VLSE32_V | fields(vd as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vsse32_v(vs3: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VSSE32_V | fields(vs3 as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vle64_v(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VLE64_V | fields(vd as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vse64_v(vs3: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSE64_V | fields(vs3 as u8, rs1 as u8, 0) | VM);
+    }
+
+    pub fn vlse64_v(vd: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VLSE64_V | fields(vd as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vsse64_v(vs3: VReg, rs1: Reg, rs2: Reg) -> Instruction {
+        return vector(MATCH_VSSE64_V | fields(vs3 as u8, rs1 as u8, rs2 as u8) | VM);
+    }
+
+    pub fn vlm_v(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VLM_V | fields(vd as u8, rs1 as u8, 0));
+    }
+
+    pub fn vsm_v(vs3: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSM_V | fields(vs3 as u8, rs1 as u8, 0));
+    }
+
+    pub fn vadd_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VADD_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vadd_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VADD_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vadd_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VADD_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsub_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSUB_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsub_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSUB_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vrsub_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VRSUB_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vrsub_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VRSUB_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vminu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMINU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vminu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMINU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmin_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMIN_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmin_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMIN_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmaxu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMAXU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmaxu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMAXU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmax_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMAX_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmax_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMAX_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vand_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VAND_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vand_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VAND_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vand_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VAND_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vor_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VOR_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vor_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VOR_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vor_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VOR_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vxor_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VXOR_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vxor_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VXOR_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vxor_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VXOR_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vadc_vvm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VADC_VVM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vadc_vxm(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VADC_VXM | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vadc_vim(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VADC_VIM | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8));
+    }
+
+    pub fn vmadc_vvm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMADC_VVM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmadc_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMADC_VV | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmadc_vxm(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMADC_VXM | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmadc_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMADC_VX | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmadc_vim(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMADC_VIM | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8));
+    }
+
+    pub fn vmadc_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMADC_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8));
+    }
+
+    pub fn vsbc_vvm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSBC_VVM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vsbc_vxm(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSBC_VXM | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmsbc_vvm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSBC_VVM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmsbc_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSBC_VV | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmsbc_vxm(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSBC_VXM | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmsbc_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSBC_VX | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmerge_vvm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMERGE_VVM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmv_v_v(vd: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMV_V_V | fields(vd as u8, vs1 as u8, 0));
+    }
+
+    pub fn vmerge_vxm(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMERGE_VXM | fields(vd as u8, rs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmv_v_x(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMV_V_X | fields(vd as u8, rs1 as u8, 0));
+    }
+
+    pub fn vmerge_vim(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMERGE_VIM | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8));
+    }
+
+    pub fn vmv_v_i(vd: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMV_V_I | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, 0));
+    }
+
+    pub fn vmseq_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSEQ_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmseq_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSEQ_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmseq_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMSEQ_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsne_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSNE_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsne_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSNE_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsne_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMSNE_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsltu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSLTU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsltu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSLTU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmslt_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSLT_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmslt_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSLT_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsleu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSLEU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsleu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSLEU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsleu_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMSLEU_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsle_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMSLE_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsle_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSLE_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsle_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMSLE_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsgtu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSGTU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsgtu_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMSGTU_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsgt_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMSGT_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmsgt_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VMSGT_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsaddu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSADDU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsaddu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSADDU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsaddu_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VSADDU_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsadd_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSADD_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsadd_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSADD_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsadd_vi(vd: VReg, vs2: VReg, imm: i32) -> Instruction {
+        return vector(MATCH_VSADD_VI | fields(vd as u8, (check_imm(imm, 5, 1, "immediate") & 0x1F) as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vssubu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSSUBU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vssubu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSSUBU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vssub_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSSUB_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vssub_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSSUB_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsll_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSLL_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsll_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSLL_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsll_vi(vd: VReg, vs2: VReg, uimm: u32) -> Instruction {
+        return vector(MATCH_VSLL_VI | fields(vd as u8, check_uimm(uimm, 5, "immediate") as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsrl_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSRL_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsrl_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSRL_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsrl_vi(vd: VReg, vs2: VReg, uimm: u32) -> Instruction {
+        return vector(MATCH_VSRL_VI | fields(vd as u8, check_uimm(uimm, 5, "immediate") as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsra_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VSRA_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsra_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VSRA_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vsra_vi(vd: VReg, vs2: VReg, uimm: u32) -> Instruction {
+        return vector(MATCH_VSRA_VI | fields(vd as u8, check_uimm(uimm, 5, "immediate") as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnsrl_wv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VNSRL_WV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnsrl_wx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VNSRL_WX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnsrl_wi(vd: VReg, vs2: VReg, uimm: u32) -> Instruction {
+        return vector(MATCH_VNSRL_WI | fields(vd as u8, check_uimm(uimm, 5, "immediate") as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnsra_wv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VNSRA_WV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnsra_wx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VNSRA_WX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnsra_wi(vd: VReg, vs2: VReg, uimm: u32) -> Instruction {
+        return vector(MATCH_VNSRA_WI | fields(vd as u8, check_uimm(uimm, 5, "immediate") as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwredsumu_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWREDSUMU_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwredsum_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWREDSUM_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredsum_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDSUM_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredand_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDAND_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredor_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDOR_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredxor_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDXOR_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredminu_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDMINU_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredmin_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDMIN_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredmaxu_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDMAXU_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vredmax_vs(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREDMAX_VS | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmandn_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMANDN_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmand_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMAND_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmor_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMOR_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmxor_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMXOR_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmorn_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMORN_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmnand_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMNAND_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmnor_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMNOR_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vmxnor_mm(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMXNOR_MM | fields(vd as u8, vs1 as u8, vs2 as u8));
+    }
+
+    pub fn vdivu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VDIVU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vdivu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VDIVU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vdiv_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VDIV_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vdiv_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VDIV_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vremu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREMU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vremu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VREMU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vrem_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VREM_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vrem_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VREM_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmulhu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMULHU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmulhu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMULHU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmul_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMUL_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmul_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMUL_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmulhsu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMULHSU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmulhsu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMULHSU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmulh_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VMULH_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmulh_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMULH_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmadd_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMADD_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmadd_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMADD_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnmsub_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VNMSUB_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnmsub_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VNMSUB_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmacc_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMACC_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmacc_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMACC_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnmsac_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VNMSAC_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vnmsac_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VNMSAC_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwaddu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWADDU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwaddu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWADDU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwadd_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWADD_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwadd_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWADD_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsubu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWSUBU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsubu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWSUBU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsub_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWSUB_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsub_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWSUB_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwaddu_wv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWADDU_WV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwaddu_wx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWADDU_WX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwadd_wv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWADD_WV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwadd_wx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWADD_WX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsubu_wv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWSUBU_WV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsubu_wx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWSUBU_WX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsub_wv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWSUB_WV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwsub_wx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWSUB_WX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmulu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWMULU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmulu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWMULU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmulsu_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWMULSU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmulsu_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWMULSU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmul_vv(vd: VReg, vs2: VReg, vs1: VReg) -> Instruction {
+        return vector(MATCH_VWMUL_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmul_vx(vd: VReg, vs2: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VWMUL_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmaccu_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACCU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmaccu_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACCU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmacc_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACC_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmacc_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACC_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmaccus_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACCUS_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmaccsu_vv(vd: VReg, vs1: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACCSU_VV | fields(vd as u8, vs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vwmaccsu_vx(vd: VReg, rs1: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VWMACCSU_VX | fields(vd as u8, rs1 as u8, vs2 as u8) | VM);
+    }
+
+    pub fn vmv_x_s(rd: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMV_X_S | fields(rd as u8, 0, vs2 as u8));
+    }
+
+    pub fn vcpop_m(rd: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VCPOP_M | fields(rd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vfirst_m(rd: Reg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VFIRST_M | fields(rd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vmv_s_x(vd: VReg, rs1: Reg) -> Instruction {
+        return vector(MATCH_VMV_S_X | fields(vd as u8, rs1 as u8, 0));
+    }
+
+    pub fn vzext_vf8(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VZEXT_VF8 | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vsext_vf8(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VSEXT_VF8 | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vzext_vf4(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VZEXT_VF4 | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vsext_vf4(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VSEXT_VF4 | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vzext_vf2(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VZEXT_VF2 | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vsext_vf2(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VSEXT_VF2 | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vmsbf_m(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMSBF_M | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vmsof_m(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMSOF_M | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vmsif_m(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VMSIF_M | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn viota_m(vd: VReg, vs2: VReg) -> Instruction {
+        return vector(MATCH_VIOTA_M | fields(vd as u8, 0, vs2 as u8) | VM);
+    }
+
+    pub fn vid_v(vd: VReg) -> Instruction {
+        return vector(MATCH_VID_V | fields(vd as u8, 0, 0) | VM);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csr::*;
+
+    /* xorshift64, the tests have to be reproducible and there are no dependencies for this */
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            return self.0;
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            return self.next() % n;
+        }
+
+        fn reg(&mut self) -> Reg {
+            return Reg::x(self.next() as u8);
+        }
+
+        fn freg(&mut self) -> FReg {
+            return FReg::f(self.next() as u8);
+        }
+
+        fn vreg(&mut self) -> VReg {
+            return VReg::v(self.next() as u8);
+        }
+
+        /* signed immediate with bits bits, a multiple of scale */
+        fn imm(&mut self, bits: u32, scale: i32) -> i32 {
+            let val: i32 = ((self.next() as u32) << (32 - bits)) as i32 >> (32 - bits);
+            return val - val % scale;
+        }
+    }
+
+    /* words the assembler produces for the same instructions */
+    #[test]
+    fn known_encodings() {
+        assert_eq!(Inst::addi(Reg::A0, Reg::Zero, 5).encode(), 0x00500513);
+        assert_eq!(Inst::addi(Reg::A0, Reg::Zero, -5).encode(), 0xffb00513);
+        assert_eq!(Inst::lui(Reg::Sp, 8).encode(), 0x00008137);
+        assert_eq!(Inst::add(Reg::A2, Reg::A0, Reg::A1).encode(), 0x00b50633);
+        assert_eq!(Inst::srli(Reg::T5, Reg::T3, 28).encode(), 0x01ce5f13);
+        assert_eq!(Inst::sb(Reg::T5, Reg::T6, 0).encode(), 0x01ef8023);
+        assert_eq!(Inst::blt(Reg::T5, Reg::T6, 8).encode(), 0x01ff4463);
+        assert_eq!(Inst::jalr(Reg::Zero, Reg::Ra, 0).encode(), 0x00008067);
+        assert_eq!(Inst::csrrs(Reg::A1, CSR_FFLAGS, Reg::Zero).encode(), 0x001025f3);
+        assert_eq!(Inst::csrrc(Reg::Zero, CSR_MSTATUS, Reg::A2).encode(), 0x30063073);
+        assert_eq!(Inst::csrrw(Reg::Zero, CSR_CYCLE, Reg::Zero).encode(), 0xc0001073);
+        assert_eq!(Inst::ecall().encode(), 0x00000073);
+        assert_eq!(Inst::mret().encode(), 0x30200073);
+        assert_eq!(Inst::fence(0xF, 0xF).encode(), 0x0ff0000f);
+        assert_eq!(Inst::fence_tso().encode(), 0x8330000f);
+        assert_eq!(Inst::amoadd_w(Reg::A0, Reg::A2, Reg::A1).aq().rl().encode(), 0x06c5a52f);
+        assert_eq!(Inst::fadd_s(FReg::Fa0, FReg::Fa1, FReg::Fa2).encode(), 0x00c5f553);
+        assert_eq!(Inst::fld(FReg::Fa0, Reg::Sp, 8).encode(), 0x00813507);
+        assert_eq!(Inst::fsd(FReg::Fs0, Reg::Sp, -16).encode(), 0xfe813827);
+        assert_eq!(Inst::fcvt_w_d(Reg::A0, FReg::Fa1).rm(RoundingMode::RTZ).encode(), 0xc2059553);
+        assert_eq!(Inst::fmadd_d(FReg::Fa0, FReg::Fa1, FReg::Fa2, FReg::Fa3).encode(), 0x6ac5f543);
+        assert_eq!(Inst::sh1add(Reg::A0, Reg::A1, Reg::A2).encode(), 0x20c5a533);
+        assert_eq!(Inst::rori(Reg::A0, Reg::A1, 3).encode(), 0x6035d513);
+        assert_eq!(Inst::rev8(Reg::A0, Reg::A1).encode(), 0x6b85d513);
+        assert_eq!(Inst::rev8(Reg::A0, Reg::A1).xlen(Xlen::RV32).encode(), 0x6985d513);
+        assert_eq!(Inst::vadd_vv(VReg::V1, VReg::V2, VReg::V3).encode(), 0x022180d7);
+        assert_eq!(Inst::vadd_vi(VReg::V1, VReg::V2, -1).masked().encode(), 0x002fb0d7);
+        assert_eq!(Inst::vsetvli(Reg::A0, Reg::A1, 0xD0).encode(), 0x0d05f557);
+        assert_eq!(Inst::vle32_v(VReg::V4, Reg::A0).encode(), 0x02056207);
+        assert_eq!(Inst::vmacc_vx(VReg::V1, Reg::A0, VReg::V2).encode(), 0xb62560d7);
+    }
+
+    /* every constructor with random operands, RV64 so the RV64 only ones decode too */
+    fn random_inst(rng: &mut Rng) -> Inst {
+        let (rd, rs1, rs2): (Reg, Reg, Reg) = (rng.reg(), rng.reg(), rng.reg());
+        let imm: i32 = rng.imm(12, 1);
+        let boff: i32 = rng.imm(13, 2);
+        let shamt: u32 = rng.below(64) as u32;
+        let shamtw: u32 = rng.below(32) as u32;
+        let csr: u16 = rng.below(0x1000) as u16;
+        let uimm: u32 = rng.below(32) as u32;
+
+        let inst: Inst = match rng.below(108) {
+            0 => Inst::lui(rd, rng.below(1 << 20) as u32),
+            1 => Inst::auipc(rd, rng.below(1 << 20) as u32),
+            2 => Inst::jal(rd, rng.imm(21, 2)),
+            3 => Inst::jalr(rd, rs1, imm),
+            4 => Inst::beq(rs1, rs2, boff),
+            5 => Inst::bne(rs1, rs2, boff),
+            6 => Inst::blt(rs1, rs2, boff),
+            7 => Inst::bge(rs1, rs2, boff),
+            8 => Inst::bltu(rs1, rs2, boff),
+            9 => Inst::bgeu(rs1, rs2, boff),
+            10 => Inst::lb(rd, rs1, imm),
+            11 => Inst::lh(rd, rs1, imm),
+            12 => Inst::lw(rd, rs1, imm),
+            13 => Inst::ld(rd, rs1, imm),
+            14 => Inst::lbu(rd, rs1, imm),
+            15 => Inst::lhu(rd, rs1, imm),
+            16 => Inst::lwu(rd, rs1, imm),
+            17 => Inst::sb(rs2, rs1, imm),
+            18 => Inst::sh(rs2, rs1, imm),
+            19 => Inst::sw(rs2, rs1, imm),
+            20 => Inst::sd(rs2, rs1, imm),
+            21 => Inst::addi(rd, rs1, imm),
+            22 => Inst::slti(rd, rs1, imm),
+            23 => Inst::sltiu(rd, rs1, imm),
+            24 => Inst::xori(rd, rs1, imm),
+            25 => Inst::ori(rd, rs1, imm),
+            26 => Inst::andi(rd, rs1, imm),
+            27 => Inst::slli(rd, rs1, shamt),
+            28 => Inst::srli(rd, rs1, shamt),
+            29 => Inst::srai(rd, rs1, shamt),
+            30 => Inst::add(rd, rs1, rs2),
+            31 => Inst::sub(rd, rs1, rs2),
+            32 => Inst::sll(rd, rs1, rs2),
+            33 => Inst::slt(rd, rs1, rs2),
+            34 => Inst::sltu(rd, rs1, rs2),
+            35 => Inst::xor(rd, rs1, rs2),
+            36 => Inst::srl(rd, rs1, rs2),
+            37 => Inst::sra(rd, rs1, rs2),
+            38 => Inst::or(rd, rs1, rs2),
+            39 => Inst::and(rd, rs1, rs2),
+            40 => Inst::addiw(rd, rs1, imm),
+            41 => Inst::slliw(rd, rs1, shamtw),
+            42 => Inst::srliw(rd, rs1, shamtw),
+            43 => Inst::sraiw(rd, rs1, shamtw),
+            44 => Inst::addw(rd, rs1, rs2),
+            45 => Inst::subw(rd, rs1, rs2),
+            46 => Inst::sllw(rd, rs1, rs2),
+            47 => Inst::srlw(rd, rs1, rs2),
+            48 => Inst::sraw(rd, rs1, rs2),
+            49 => Inst::mul(rd, rs1, rs2),
+            50 => Inst::mulh(rd, rs1, rs2),
+            51 => Inst::mulhsu(rd, rs1, rs2),
+            52 => Inst::mulhu(rd, rs1, rs2),
+            53 => Inst::div(rd, rs1, rs2),
+            54 => Inst::divu(rd, rs1, rs2),
+            55 => Inst::rem(rd, rs1, rs2),
+            56 => Inst::remu(rd, rs1, rs2),
+            57 => Inst::mulw(rd, rs1, rs2),
+            58 => Inst::divw(rd, rs1, rs2),
+            59 => Inst::divuw(rd, rs1, rs2),
+            60 => Inst::remw(rd, rs1, rs2),
+            61 => Inst::remuw(rd, rs1, rs2),
+            62 => Inst::lr_w(rd, rs1),
+            63 => Inst::lr_d(rd, rs1),
+            64 => Inst::sc_w(rd, rs2, rs1),
+            65 => Inst::sc_d(rd, rs2, rs1),
+            66 => Inst::amoswap_w(rd, rs2, rs1),
+            67 => Inst::amoadd_w(rd, rs2, rs1),
+            68 => Inst::amoxor_w(rd, rs2, rs1),
+            69 => Inst::amoand_w(rd, rs2, rs1),
+            70 => Inst::amoor_w(rd, rs2, rs1),
+            71 => Inst::amomin_w(rd, rs2, rs1),
+            72 => Inst::amomax_w(rd, rs2, rs1),
+            73 => Inst::amominu_w(rd, rs2, rs1),
+            74 => Inst::amomaxu_w(rd, rs2, rs1),
+            75 => Inst::amoswap_d(rd, rs2, rs1),
+            76 => Inst::amoadd_d(rd, rs2, rs1),
+            77 => Inst::amoxor_d(rd, rs2, rs1),
+            78 => Inst::amoand_d(rd, rs2, rs1),
+            79 => Inst::amoor_d(rd, rs2, rs1),
+            80 => Inst::amomin_d(rd, rs2, rs1),
+            81 => Inst::amomax_d(rd, rs2, rs1),
+            82 => Inst::amominu_d(rd, rs2, rs1),
+            83 => Inst::amomaxu_d(rd, rs2, rs1),
+            84 => Inst::csrrw(rd, csr, rs1),
+            85 => Inst::csrrs(rd, csr, rs1),
+            86 => Inst::csrrc(rd, csr, rs1),
+            87 => Inst::csrrwi(rd, csr, uimm),
+            88 => Inst::csrrsi(rd, csr, uimm),
+            89 => Inst::csrrci(rd, csr, uimm),
+            90 => Inst::ecall(),
+            91 => Inst::ebreak(),
+            92 => Inst::mret(),
+            93 => Inst::sret(),
+            94 => Inst::wfi(),
+            95 => Inst::sfence_vma(rs1, rs2),
+            96 => Inst::fence(rng.below(16) as u8, rng.below(16) as u8),
+            97 => Inst::fence_tso(),
+            98 => Inst::fence_i(),
+            /* the ordering bits on a few atomics */
+            99 => Inst::lr_w(rd, rs1).aq(),
+            100 => Inst::sc_d(rd, rs2, rs1).rl(),
+            101 => Inst::amoadd_w(rd, rs2, rs1).aq().rl(),
+            102 => Inst::amomaxu_d(rd, rs2, rs1).aq(),
+            103 => Inst::amoswap_w(rd, rs2, rs1).rl(),
+            104 => Inst::addi(rd, rs1, 2047),
+            105 => Inst::addi(rd, rs1, -2048),
+            106 => Inst::jal(rd, (1 << 20) - 2),
+            _ => Inst::beq(rs1, rs2, -4096),
+        };
+        return inst;
+    }
+
+    /* the F/D constructors, plus a few static rounding modes */
+    fn random_float(rng: &mut Rng) -> Inst {
+        let (rd, rs1): (Reg, Reg) = (rng.reg(), rng.reg());
+        let (frd, frs1, frs2, frs3): (FReg, FReg, FReg, FReg) = (rng.freg(), rng.freg(), rng.freg(), rng.freg());
+        let imm: i32 = rng.imm(12, 1);
+
+        let inst: Inst = match rng.below(65) {
+            0 => Inst::flw(frd, rs1, imm),
+            1 => Inst::fsw(frs2, rs1, imm),
+            2 => Inst::fmadd_s(frd, frs1, frs2, frs3),
+            3 => Inst::fmsub_s(frd, frs1, frs2, frs3),
+            4 => Inst::fnmsub_s(frd, frs1, frs2, frs3),
+            5 => Inst::fnmadd_s(frd, frs1, frs2, frs3),
+            6 => Inst::fadd_s(frd, frs1, frs2),
+            7 => Inst::fsub_s(frd, frs1, frs2),
+            8 => Inst::fmul_s(frd, frs1, frs2),
+            9 => Inst::fdiv_s(frd, frs1, frs2),
+            10 => Inst::fsgnj_s(frd, frs1, frs2),
+            11 => Inst::fsgnjn_s(frd, frs1, frs2),
+            12 => Inst::fsgnjx_s(frd, frs1, frs2),
+            13 => Inst::fmin_s(frd, frs1, frs2),
+            14 => Inst::fmax_s(frd, frs1, frs2),
+            15 => Inst::fsqrt_s(frd, frs1),
+            16 => Inst::fle_s(rd, frs1, frs2),
+            17 => Inst::flt_s(rd, frs1, frs2),
+            18 => Inst::feq_s(rd, frs1, frs2),
+            19 => Inst::fcvt_w_s(rd, frs1),
+            20 => Inst::fcvt_wu_s(rd, frs1),
+            21 => Inst::fmv_x_w(rd, frs1),
+            22 => Inst::fclass_s(rd, frs1),
+            23 => Inst::fcvt_s_w(frd, rs1),
+            24 => Inst::fcvt_s_wu(frd, rs1),
+            25 => Inst::fmv_w_x(frd, rs1),
+            26 => Inst::fcvt_l_s(rd, frs1),
+            27 => Inst::fcvt_lu_s(rd, frs1),
+            28 => Inst::fcvt_s_l(frd, rs1),
+            29 => Inst::fcvt_s_lu(frd, rs1),
+            30 => Inst::fld(frd, rs1, imm),
+            31 => Inst::fsd(frs2, rs1, imm),
+            32 => Inst::fmadd_d(frd, frs1, frs2, frs3),
+            33 => Inst::fmsub_d(frd, frs1, frs2, frs3),
+            34 => Inst::fnmsub_d(frd, frs1, frs2, frs3),
+            35 => Inst::fnmadd_d(frd, frs1, frs2, frs3),
+            36 => Inst::fadd_d(frd, frs1, frs2),
+            37 => Inst::fsub_d(frd, frs1, frs2),
+            38 => Inst::fmul_d(frd, frs1, frs2),
+            39 => Inst::fdiv_d(frd, frs1, frs2),
+            40 => Inst::fsgnj_d(frd, frs1, frs2),
+            41 => Inst::fsgnjn_d(frd, frs1, frs2),
+            42 => Inst::fsgnjx_d(frd, frs1, frs2),
+            43 => Inst::fmin_d(frd, frs1, frs2),
+            44 => Inst::fmax_d(frd, frs1, frs2),
+            45 => Inst::fsqrt_d(frd, frs1),
+            46 => Inst::fcvt_s_d(frd, frs1),
+            47 => Inst::fcvt_d_s(frd, frs1),
+            48 => Inst::fle_d(rd, frs1, frs2),
+            49 => Inst::flt_d(rd, frs1, frs2),
+            50 => Inst::feq_d(rd, frs1, frs2),
+            51 => Inst::fcvt_w_d(rd, frs1),
+            52 => Inst::fcvt_wu_d(rd, frs1),
+            53 => Inst::fclass_d(rd, frs1),
+            54 => Inst::fcvt_d_w(frd, rs1),
+            55 => Inst::fcvt_d_wu(frd, rs1),
+            56 => Inst::fcvt_l_d(rd, frs1),
+            57 => Inst::fcvt_lu_d(rd, frs1),
+            58 => Inst::fmv_x_d(rd, frs1),
+            59 => Inst::fcvt_d_l(frd, rs1),
+            60 => Inst::fcvt_d_lu(frd, rs1),
+            61 => Inst::fmv_d_x(frd, rs1),
+            62 => Inst::fadd_s(frd, frs1, frs2).rm(RoundingMode::RTZ),
+            63 => Inst::fcvt_w_d(rd, frs1).rm(RoundingMode::RMM),
+            _ => Inst::fmadd_d(frd, frs1, frs2, frs3).rm(RoundingMode::RNE),
+        };
+        return inst;
+    }
+
+    fn random_bitmanip(rng: &mut Rng) -> Inst {
+        let (rd, rs1, rs2): (Reg, Reg, Reg) = (rng.reg(), rng.reg(), rng.reg());
+        let shamt: u32 = rng.below(64) as u32;
+        let shamtw: u32 = rng.below(32) as u32;
+
+        let inst: Inst = match rng.below(43) {
+            0 => Inst::sh1add(rd, rs1, rs2),
+            1 => Inst::sh2add(rd, rs1, rs2),
+            2 => Inst::sh3add(rd, rs1, rs2),
+            3 => Inst::add_uw(rd, rs1, rs2),
+            4 => Inst::sh1add_uw(rd, rs1, rs2),
+            5 => Inst::sh2add_uw(rd, rs1, rs2),
+            6 => Inst::sh3add_uw(rd, rs1, rs2),
+            7 => Inst::slli_uw(rd, rs1, shamt),
+            8 => Inst::andn(rd, rs1, rs2),
+            9 => Inst::orn(rd, rs1, rs2),
+            10 => Inst::xnor(rd, rs1, rs2),
+            11 => Inst::clz(rd, rs1),
+            12 => Inst::ctz(rd, rs1),
+            13 => Inst::cpop(rd, rs1),
+            14 => Inst::max(rd, rs1, rs2),
+            15 => Inst::maxu(rd, rs1, rs2),
+            16 => Inst::min(rd, rs1, rs2),
+            17 => Inst::minu(rd, rs1, rs2),
+            18 => Inst::sext_b(rd, rs1),
+            19 => Inst::sext_h(rd, rs1),
+            20 => Inst::rol(rd, rs1, rs2),
+            21 => Inst::ror(rd, rs1, rs2),
+            22 => Inst::orc_b(rd, rs1),
+            23 => Inst::rori(rd, rs1, shamt),
+            24 => Inst::rev8(rd, rs1),
+            25 => Inst::zext_h(rd, rs1),
+            26 => Inst::clzw(rd, rs1),
+            27 => Inst::ctzw(rd, rs1),
+            28 => Inst::cpopw(rd, rs1),
+            29 => Inst::rolw(rd, rs1, rs2),
+            30 => Inst::rorw(rd, rs1, rs2),
+            31 => Inst::roriw(rd, rs1, shamtw),
+            32 => Inst::clmul(rd, rs1, rs2),
+            33 => Inst::clmulr(rd, rs1, rs2),
+            34 => Inst::clmulh(rd, rs1, rs2),
+            35 => Inst::bclr(rd, rs1, rs2),
+            36 => Inst::bext(rd, rs1, rs2),
+            37 => Inst::binv(rd, rs1, rs2),
+            38 => Inst::bset(rd, rs1, rs2),
+            39 => Inst::bclri(rd, rs1, shamt),
+            40 => Inst::bexti(rd, rs1, shamt),
+            41 => Inst::binvi(rd, rs1, shamt),
+            _ => Inst::bseti(rd, rs1, shamt),
+        };
+        return inst;
+    }
+
+    fn masked(inst: Inst, mask: bool) -> Inst {
+        return if mask { inst.masked() } else { inst };
+    }
+
+    /* the V constructors, the ones with a vm bit masked half the time */
+    fn random_vector(rng: &mut Rng) -> Inst {
+        let (rd, rs1, rs2): (Reg, Reg, Reg) = (rng.reg(), rng.reg(), rng.reg());
+        let (vd, vs1, vs2): (VReg, VReg, VReg) = (rng.vreg(), rng.vreg(), rng.vreg());
+        let simm5: i32 = rng.imm(5, 1);
+        let uimm: u32 = rng.below(32) as u32;
+        let vtype11: u32 = rng.below(1 << 11) as u32;
+        let vtype10: u32 = rng.below(1 << 10) as u32;
+        let mask: bool = rng.below(2) == 0;
+
+        let inst: Inst = match rng.below(197) {
+            0 => Inst::vsetvli(rd, rs1, vtype11),
+            1 => Inst::vsetivli(rd, uimm, vtype10),
+            2 => Inst::vsetvl(rd, rs1, rs2),
+            3 => masked(Inst::vle8_v(vd, rs1), mask),
+            4 => masked(Inst::vse8_v(vd, rs1), mask),
+            5 => masked(Inst::vlse8_v(vd, rs1, rs2), mask),
+            6 => masked(Inst::vsse8_v(vd, rs1, rs2), mask),
+            7 => masked(Inst::vle16_v(vd, rs1), mask),
+            8 => masked(Inst::vse16_v(vd, rs1), mask),
+            9 => masked(Inst::vlse16_v(vd, rs1, rs2), mask),
+            10 => masked(Inst::vsse16_v(vd, rs1, rs2), mask),
+            11 => masked(Inst::vle32_v(vd, rs1), mask),
+            12 => masked(Inst::vse32_v(vd, rs1), mask),
+            13 => masked(Inst::vlse32_v(vd, rs1, rs2), mask),
+            14 => masked(Inst::vsse32_v(vd, rs1, rs2), mask),
+            15 => masked(Inst::vle64_v(vd, rs1), mask),
+            16 => masked(Inst::vse64_v(vd, rs1), mask),
+            17 => masked(Inst::vlse64_v(vd, rs1, rs2), mask),
+            18 => masked(Inst::vsse64_v(vd, rs1, rs2), mask),
+            19 => Inst::vlm_v(vd, rs1),
+            20 => Inst::vsm_v(vd, rs1),
+            21 => masked(Inst::vadd_vv(vd, vs2, vs1), mask),
+            22 => masked(Inst::vadd_vx(vd, vs2, rs1), mask),
+            23 => masked(Inst::vadd_vi(vd, vs2, simm5), mask),
+            24 => masked(Inst::vsub_vv(vd, vs2, vs1), mask),
+            25 => masked(Inst::vsub_vx(vd, vs2, rs1), mask),
+            26 => masked(Inst::vrsub_vx(vd, vs2, rs1), mask),
+            27 => masked(Inst::vrsub_vi(vd, vs2, simm5), mask),
+            28 => masked(Inst::vminu_vv(vd, vs2, vs1), mask),
+            29 => masked(Inst::vminu_vx(vd, vs2, rs1), mask),
+            30 => masked(Inst::vmin_vv(vd, vs2, vs1), mask),
+            31 => masked(Inst::vmin_vx(vd, vs2, rs1), mask),
+            32 => masked(Inst::vmaxu_vv(vd, vs2, vs1), mask),
+            33 => masked(Inst::vmaxu_vx(vd, vs2, rs1), mask),
+            34 => masked(Inst::vmax_vv(vd, vs2, vs1), mask),
+            35 => masked(Inst::vmax_vx(vd, vs2, rs1), mask),
+            36 => masked(Inst::vand_vv(vd, vs2, vs1), mask),
+            37 => masked(Inst::vand_vx(vd, vs2, rs1), mask),
+            38 => masked(Inst::vand_vi(vd, vs2, simm5), mask),
+            39 => masked(Inst::vor_vv(vd, vs2, vs1), mask),
+            40 => masked(Inst::vor_vx(vd, vs2, rs1), mask),
+            41 => masked(Inst::vor_vi(vd, vs2, simm5), mask),
+            42 => masked(Inst::vxor_vv(vd, vs2, vs1), mask),
+            43 => masked(Inst::vxor_vx(vd, vs2, rs1), mask),
+            44 => masked(Inst::vxor_vi(vd, vs2, simm5), mask),
+            45 => Inst::vadc_vvm(vd, vs2, vs1),
+            46 => Inst::vadc_vxm(vd, vs2, rs1),
+            47 => Inst::vadc_vim(vd, vs2, simm5),
+            48 => Inst::vmadc_vvm(vd, vs2, vs1),
+            49 => Inst::vmadc_vv(vd, vs2, vs1),
+            50 => Inst::vmadc_vxm(vd, vs2, rs1),
+            51 => Inst::vmadc_vx(vd, vs2, rs1),
+            52 => Inst::vmadc_vim(vd, vs2, simm5),
+            53 => Inst::vmadc_vi(vd, vs2, simm5),
+            54 => Inst::vsbc_vvm(vd, vs2, vs1),
+            55 => Inst::vsbc_vxm(vd, vs2, rs1),
+            56 => Inst::vmsbc_vvm(vd, vs2, vs1),
+            57 => Inst::vmsbc_vv(vd, vs2, vs1),
+            58 => Inst::vmsbc_vxm(vd, vs2, rs1),
+            59 => Inst::vmsbc_vx(vd, vs2, rs1),
+            60 => Inst::vmerge_vvm(vd, vs2, vs1),
+            61 => Inst::vmv_v_v(vd, vs1),
+            62 => Inst::vmerge_vxm(vd, vs2, rs1),
+            63 => Inst::vmv_v_x(vd, rs1),
+            64 => Inst::vmerge_vim(vd, vs2, simm5),
+            65 => Inst::vmv_v_i(vd, simm5),
+            66 => masked(Inst::vmseq_vv(vd, vs2, vs1), mask),
+            67 => masked(Inst::vmseq_vx(vd, vs2, rs1), mask),
+            68 => masked(Inst::vmseq_vi(vd, vs2, simm5), mask),
+            69 => masked(Inst::vmsne_vv(vd, vs2, vs1), mask),
+            70 => masked(Inst::vmsne_vx(vd, vs2, rs1), mask),
+            71 => masked(Inst::vmsne_vi(vd, vs2, simm5), mask),
+            72 => masked(Inst::vmsltu_vv(vd, vs2, vs1), mask),
+            73 => masked(Inst::vmsltu_vx(vd, vs2, rs1), mask),
+            74 => masked(Inst::vmslt_vv(vd, vs2, vs1), mask),
+            75 => masked(Inst::vmslt_vx(vd, vs2, rs1), mask),
+            76 => masked(Inst::vmsleu_vv(vd, vs2, vs1), mask),
+            77 => masked(Inst::vmsleu_vx(vd, vs2, rs1), mask),
+            78 => masked(Inst::vmsleu_vi(vd, vs2, simm5), mask),
+            79 => masked(Inst::vmsle_vv(vd, vs2, vs1), mask),
+            80 => masked(Inst::vmsle_vx(vd, vs2, rs1), mask),
+            81 => masked(Inst::vmsle_vi(vd, vs2, simm5), mask),
+            82 => masked(Inst::vmsgtu_vx(vd, vs2, rs1), mask),
+            83 => masked(Inst::vmsgtu_vi(vd, vs2, simm5), mask),
+            84 => masked(Inst::vmsgt_vx(vd, vs2, rs1), mask),
+            85 => masked(Inst::vmsgt_vi(vd, vs2, simm5), mask),
+            86 => masked(Inst::vsaddu_vv(vd, vs2, vs1), mask),
+            87 => masked(Inst::vsaddu_vx(vd, vs2, rs1), mask),
+            88 => masked(Inst::vsaddu_vi(vd, vs2, simm5), mask),
+            89 => masked(Inst::vsadd_vv(vd, vs2, vs1), mask),
+            90 => masked(Inst::vsadd_vx(vd, vs2, rs1), mask),
+            91 => masked(Inst::vsadd_vi(vd, vs2, simm5), mask),
+            92 => masked(Inst::vssubu_vv(vd, vs2, vs1), mask),
+            93 => masked(Inst::vssubu_vx(vd, vs2, rs1), mask),
+            94 => masked(Inst::vssub_vv(vd, vs2, vs1), mask),
+            95 => masked(Inst::vssub_vx(vd, vs2, rs1), mask),
+            96 => masked(Inst::vsll_vv(vd, vs2, vs1), mask),
+            97 => masked(Inst::vsll_vx(vd, vs2, rs1), mask),
+            98 => masked(Inst::vsll_vi(vd, vs2, uimm), mask),
+            99 => masked(Inst::vsrl_vv(vd, vs2, vs1), mask),
+            100 => masked(Inst::vsrl_vx(vd, vs2, rs1), mask),
+            101 => masked(Inst::vsrl_vi(vd, vs2, uimm), mask),
+            102 => masked(Inst::vsra_vv(vd, vs2, vs1), mask),
+            103 => masked(Inst::vsra_vx(vd, vs2, rs1), mask),
+            104 => masked(Inst::vsra_vi(vd, vs2, uimm), mask),
+            105 => masked(Inst::vnsrl_wv(vd, vs2, vs1), mask),
+            106 => masked(Inst::vnsrl_wx(vd, vs2, rs1), mask),
+            107 => masked(Inst::vnsrl_wi(vd, vs2, uimm), mask),
+            108 => masked(Inst::vnsra_wv(vd, vs2, vs1), mask),
+            109 => masked(Inst::vnsra_wx(vd, vs2, rs1), mask),
+            110 => masked(Inst::vnsra_wi(vd, vs2, uimm), mask),
+            111 => masked(Inst::vwredsumu_vs(vd, vs2, vs1), mask),
+            112 => masked(Inst::vwredsum_vs(vd, vs2, vs1), mask),
+            113 => masked(Inst::vredsum_vs(vd, vs2, vs1), mask),
+            114 => masked(Inst::vredand_vs(vd, vs2, vs1), mask),
+            115 => masked(Inst::vredor_vs(vd, vs2, vs1), mask),
+            116 => masked(Inst::vredxor_vs(vd, vs2, vs1), mask),
+            117 => masked(Inst::vredminu_vs(vd, vs2, vs1), mask),
+            118 => masked(Inst::vredmin_vs(vd, vs2, vs1), mask),
+            119 => masked(Inst::vredmaxu_vs(vd, vs2, vs1), mask),
+            120 => masked(Inst::vredmax_vs(vd, vs2, vs1), mask),
+            121 => Inst::vmandn_mm(vd, vs2, vs1),
+            122 => Inst::vmand_mm(vd, vs2, vs1),
+            123 => Inst::vmor_mm(vd, vs2, vs1),
+            124 => Inst::vmxor_mm(vd, vs2, vs1),
+            125 => Inst::vmorn_mm(vd, vs2, vs1),
+            126 => Inst::vmnand_mm(vd, vs2, vs1),
+            127 => Inst::vmnor_mm(vd, vs2, vs1),
+            128 => Inst::vmxnor_mm(vd, vs2, vs1),
+            129 => masked(Inst::vdivu_vv(vd, vs2, vs1), mask),
+            130 => masked(Inst::vdivu_vx(vd, vs2, rs1), mask),
+            131 => masked(Inst::vdiv_vv(vd, vs2, vs1), mask),
+            132 => masked(Inst::vdiv_vx(vd, vs2, rs1), mask),
+            133 => masked(Inst::vremu_vv(vd, vs2, vs1), mask),
+            134 => masked(Inst::vremu_vx(vd, vs2, rs1), mask),
+            135 => masked(Inst::vrem_vv(vd, vs2, vs1), mask),
+            136 => masked(Inst::vrem_vx(vd, vs2, rs1), mask),
+            137 => masked(Inst::vmulhu_vv(vd, vs2, vs1), mask),
+            138 => masked(Inst::vmulhu_vx(vd, vs2, rs1), mask),
+            139 => masked(Inst::vmul_vv(vd, vs2, vs1), mask),
+            140 => masked(Inst::vmul_vx(vd, vs2, rs1), mask),
+            141 => masked(Inst::vmulhsu_vv(vd, vs2, vs1), mask),
+            142 => masked(Inst::vmulhsu_vx(vd, vs2, rs1), mask),
+            143 => masked(Inst::vmulh_vv(vd, vs2, vs1), mask),
+            144 => masked(Inst::vmulh_vx(vd, vs2, rs1), mask),
+            145 => masked(Inst::vmadd_vv(vd, vs1, vs2), mask),
+            146 => masked(Inst::vmadd_vx(vd, rs1, vs2), mask),
+            147 => masked(Inst::vnmsub_vv(vd, vs1, vs2), mask),
+            148 => masked(Inst::vnmsub_vx(vd, rs1, vs2), mask),
+            149 => masked(Inst::vmacc_vv(vd, vs1, vs2), mask),
+            150 => masked(Inst::vmacc_vx(vd, rs1, vs2), mask),
+            151 => masked(Inst::vnmsac_vv(vd, vs1, vs2), mask),
+            152 => masked(Inst::vnmsac_vx(vd, rs1, vs2), mask),
+            153 => masked(Inst::vwaddu_vv(vd, vs2, vs1), mask),
+            154 => masked(Inst::vwaddu_vx(vd, vs2, rs1), mask),
+            155 => masked(Inst::vwadd_vv(vd, vs2, vs1), mask),
+            156 => masked(Inst::vwadd_vx(vd, vs2, rs1), mask),
+            157 => masked(Inst::vwsubu_vv(vd, vs2, vs1), mask),
+            158 => masked(Inst::vwsubu_vx(vd, vs2, rs1), mask),
+            159 => masked(Inst::vwsub_vv(vd, vs2, vs1), mask),
+            160 => masked(Inst::vwsub_vx(vd, vs2, rs1), mask),
+            161 => masked(Inst::vwaddu_wv(vd, vs2, vs1), mask),
+            162 => masked(Inst::vwaddu_wx(vd, vs2, rs1), mask),
+            163 => masked(Inst::vwadd_wv(vd, vs2, vs1), mask),
+            164 => masked(Inst::vwadd_wx(vd, vs2, rs1), mask),
+            165 => masked(Inst::vwsubu_wv(vd, vs2, vs1), mask),
+            166 => masked(Inst::vwsubu_wx(vd, vs2, rs1), mask),
+            167 => masked(Inst::vwsub_wv(vd, vs2, vs1), mask),
+            168 => masked(Inst::vwsub_wx(vd, vs2, rs1), mask),
+            169 => masked(Inst::vwmulu_vv(vd, vs2, vs1), mask),
+            170 => masked(Inst::vwmulu_vx(vd, vs2, rs1), mask),
+            171 => masked(Inst::vwmulsu_vv(vd, vs2, vs1), mask),
+            172 => masked(Inst::vwmulsu_vx(vd, vs2, rs1), mask),
+            173 => masked(Inst::vwmul_vv(vd, vs2, vs1), mask),
+            174 => masked(Inst::vwmul_vx(vd, vs2, rs1), mask),
+            175 => masked(Inst::vwmaccu_vv(vd, vs1, vs2), mask),
+            176 => masked(Inst::vwmaccu_vx(vd, rs1, vs2), mask),
+            177 => masked(Inst::vwmacc_vv(vd, vs1, vs2), mask),
+            178 => masked(Inst::vwmacc_vx(vd, rs1, vs2), mask),
+            179 => masked(Inst::vwmaccus_vx(vd, rs1, vs2), mask),
+            180 => masked(Inst::vwmaccsu_vv(vd, vs1, vs2), mask),
+            181 => masked(Inst::vwmaccsu_vx(vd, rs1, vs2), mask),
+            182 => Inst::vmv_x_s(rd, vs2),
+            183 => masked(Inst::vcpop_m(rd, vs2), mask),
+            184 => masked(Inst::vfirst_m(rd, vs2), mask),
+            185 => Inst::vmv_s_x(vd, rs1),
+            186 => masked(Inst::vzext_vf8(vd, vs2), mask),
+            187 => masked(Inst::vsext_vf8(vd, vs2), mask),
+            188 => masked(Inst::vzext_vf4(vd, vs2), mask),
+            189 => masked(Inst::vsext_vf4(vd, vs2), mask),
+            190 => masked(Inst::vzext_vf2(vd, vs2), mask),
+            191 => masked(Inst::vsext_vf2(vd, vs2), mask),
+            192 => masked(Inst::vmsbf_m(vd, vs2), mask),
+            193 => masked(Inst::vmsof_m(vd, vs2), mask),
+            194 => masked(Inst::vmsif_m(vd, vs2), mask),
+            195 => masked(Inst::viota_m(vd, vs2), mask),
+            _ => masked(Inst::vid_v(vd), mask),
+        };
+        return inst;
+    }
+
+    /* decode(encode(x)) == x for everything the constructors build */
+    #[test]
+    fn constructors_round_trip() {
+        let mut rng: Rng = Rng(0x2545_F491_4F6C_DD1D);
+        for _ in 0..100_000 {
+            let inst: Inst = match rng.below(4) {
+                0 => random_float(&mut rng),
+                1 => random_bitmanip(&mut rng),
+                2 => random_vector(&mut rng),
+                _ => random_inst(&mut rng),
+            };
+            let word: u32 = inst.encode();
+            assert_eq!(Instruction::decode(word, Xlen::RV64), Ok(inst), "{:08x}", word);
+        }
+    }
+
+    /*
+     * any word that decodes encodes back to itself, over the opcodes that decode
+     * to something with random bits everywhere else, that covers the words the
+     * constructors never produce (the reserved rm values, RV32 Zb* encodings)
+     */
+    #[test]
+    fn decoded_words_round_trip() {
+        const OPCODES: [u32; 17] = [
+            0x03, 0x07, 0x0F, 0x13, 0x17, 0x1B, 0x23, 0x27, 0x2F, 0x33, 0x37, 0x3B,
+            0x43, 0x53, 0x57, 0x63, 0x73,
+        ];
+        let mut rng: Rng = Rng(0x9E37_79B9_7F4A_7C15);
+        let mut decoded: u32 = 0;
+
+        for _ in 0..400_000 {
+            let opcode: u32 = OPCODES[rng.below(OPCODES.len() as u64) as usize];
+            let word: u32 = ((rng.next() as u32) & !0x7F) | opcode;
+
+            for xlen in [Xlen::RV32, Xlen::RV64] {
+                let inst: Inst = match Instruction::decode(word, xlen) {
+                    Ok(inst) => inst,
+                    Err(_) => continue,
+                };
+                decoded += 1;
+                assert!(mnemonic(&inst).is_some(), "{:08x} has no name", word);
+                match inst {
+                    /* the reserved fields aren't kept */
+                    Instruction::Fence { .. } | Instruction::FenceI => {
+                        assert_eq!(Instruction::decode(inst.encode(), xlen), Ok(inst), "{:08x}", word);
+                    }
+                    _ => assert_eq!(inst.encode(), word, "{:08x} {:?}", word, inst),
+                }
+            }
+        }
+        /* make sure the test isn't passing on nothing */
+        assert!(decoded > 100_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_range_immediate() {
+        Inst::addi(Reg::A0, Reg::A0, 2048);
+    }
+
+    #[test]
+    #[should_panic]
+    fn odd_branch_offset() {
+        Inst::beq(Reg::A0, Reg::A1, 3);
+    }
+
+    /* the Bitmanip encode() panics with a message rather than making up a word */
+    #[test]
+    #[should_panic(expected = "has no encoding for its XLEN")]
+    fn rv64_only_on_rv32() {
+        Inst::add_uw(Reg::A0, Reg::A1, Reg::A2).xlen(Xlen::RV32).encode();
+    }
+
+    #[test]
+    #[should_panic(expected = "has no rounding mode")]
+    fn rounding_mode_without_rm() {
+        Inst::fsgnj_s(FReg::Fa0, FReg::Fa1, FReg::Fa2).rm(RoundingMode::RUP);
+    }
+}
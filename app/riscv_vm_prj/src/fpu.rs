@@ -0,0 +1,532 @@
+/*
+ * name: fpu.rs
+ * desc: F and D extensions, floating point register file and instruction execution
+ *
+ * Note: the arithmetic itself lives in softfloat.rs, this file decodes the fields,
+ *       picks the rounding mode, moves values between the register files and
+ *       memory and accrues the exception flags into fcsr
+ *
+ *       single precision values are NaN-boxed in the 64 bit registers, reading a
+ *       single that isn't properly boxed gives the canonical NaN (spec section 12.2)
+ *
+ */
+
+use crate::memory::*;
+use crate::csr::*;
+use crate::trap::*;
+use crate::xlen::*;
+use crate::mmu::*;
+use crate::softfloat;
+use crate::softfloat::{FpFormat, RoundingMode, F32, F64};
+use crate::disasm::*;
+
+const NAN_BOX: u64 = 0xFFFF_FFFF_0000_0000;
+
+#[derive(Debug)]
+pub struct FpRegFile {
+    f: [u64; 32],
+}
+
+impl FpRegFile {
+    /* constructor: all registers start at +0 */
+    pub fn new() -> FpRegFile {
+        return FpRegFile {
+            f: [0; 32],
+        };
+    }
+
+    /* value in the given format, singles are unboxed */
+    pub fn read(&self, reg: u8, fmt: FpFormat) -> u64 {
+        let val: u64 = self.f[reg as usize];
+        if fmt == F64 {
+            return val;
+        }
+        if (val & NAN_BOX) != NAN_BOX {
+            return F32.canonical_nan();
+        }
+        return val & 0xFFFF_FFFF;
+    }
+
+    /* all 64 bits as stored, used by FSW/FMV.X.W which don't check the boxing */
+    pub fn read_raw(&self, reg: u8) -> u64 {
+        return self.f[reg as usize];
+    }
+
+    /* singles get NaN-boxed */
+    pub fn write(&mut self, reg: u8, fmt: FpFormat, val: u64) {
+        self.f[reg as usize] = if fmt == F64 { val } else { NAN_BOX | (val & 0xFFFF_FFFF) };
+    }
+}
+
+/* FP instructions, R-Type layout with func7 split into func5/fmt, rs3 for the fused ops */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatInst {
+    rs3: u8,        /* 5 bits, fused multiply-add only */
+    func5: u8,      /* 5 bits */
+    fmt: u8,        /* 2 bits */
+    rs2: u8,        /* 5 bits */
+    rs1: u8,        /* 5 bits */
+    rm: u8,         /* 3 bits, rounding mode or func3 */
+    rd: u8,         /* 5 bits */
+    opcode: u8,     /* 7 bits */
+    imm: i32,       /* 12 bits sign extended, loads (I-Type) and stores (S-Type) */
+    ilen: u8,       /* instruction length in bytes, 2 when expanded from RVC */
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub enum FloatOpcodes {
+    LOAD_FP  = 0x07,
+    STORE_FP = 0x27,
+    FMADD    = 0x43,
+    FMSUB    = 0x47,
+    FNMSUB   = 0x4B,
+    FNMADD   = 0x4F,
+    OP_FP    = 0x53,
+}
+
+/* width field (func3) of FP loads/stores */
+pub enum FloatWidthSel {
+    W = 0x2,
+    D = 0x3,
+}
+
+/* fmt field, H and Q aren't implemented */
+pub enum FloatFmtSel {
+    S = 0x0,
+    D = 0x1,
+}
+
+/* OP-FP func5 */
+#[allow(clippy::upper_case_acronyms)]
+pub enum FloatFunc5Sel {
+    FADD      = 0x00,
+    FSUB      = 0x01,
+    FMUL      = 0x02,
+    FDIV      = 0x03,
+    FSGNJ     = 0x04, /* rm: 0 = J, 1 = JN, 2 = JX */
+    FMINMAX   = 0x05, /* rm: 0 = MIN, 1 = MAX */
+    FCVT_FF   = 0x08, /* between S and D, rs2 holds the source format */
+    FSQRT     = 0x0B,
+    FCMP      = 0x14, /* rm: 0 = FLE, 1 = FLT, 2 = FEQ */
+    FCVT_I_F  = 0x18, /* float to int, rs2: 0 = W, 1 = WU, 2 = L, 3 = LU */
+    FCVT_F_I  = 0x1A, /* int to float, rs2 as above */
+    FMV_X_F   = 0x1C, /* rm: 0 = FMV.X.W/D, 1 = FCLASS */
+    FMV_F_X   = 0x1E,
+}
+
+/* rm value selecting frm */
+pub const FLOAT_RM_DYN: u8 = 0x7;
+
+impl FloatInst {
+
+    pub fn new(inst: u32) -> FloatInst {
+        let rs3: u8 = ((inst >> 27) & 0x1F) as u8;
+        let func5: u8 = ((inst >> 27) & 0x1F) as u8;
+        let fmt: u8 = ((inst >> 25) & 0x3) as u8;
+        let rs2: u8 = ((inst >> 20) & 0x1F) as u8;
+        let rs1: u8 = ((inst >> 15) & 0x1F) as u8;
+        let rm: u8 = ((inst >> 12) & 0x7) as u8;
+        let rd: u8 = ((inst >> 7) & 0x1F) as u8;
+        let opcode: u8 = (inst & 0x7F) as u8;
+
+        /* stores split the immediate around rs2 like STypeInst */
+        let imm: i32 = if opcode == FloatOpcodes::STORE_FP as u8 {
+            (((inst & 0xFE00_0000) as i32) >> 20) | (((inst >> 7) & 0x1F) as i32)
+        }
+        else {
+            (inst as i32) >> 20
+        };
+
+        return FloatInst {
+            rs3: rs3,
+            func5: func5,
+            fmt: fmt,
+            rs2: rs2,
+            rs1: rs1,
+            rm: rm,
+            rd: rd,
+            opcode: opcode,
+            imm: imm,
+            ilen: 4,
+        };
+    }
+
+    /* back to the 32 bit encoding, the raw fields cover every bit (a store's immediate sits in rd/func5/fmt/rs2) */
+    pub fn encode(&self) -> u32 {
+        return ((self.func5 as u32) << 27)
+             | ((self.fmt as u32) << 25)
+             | ((self.rs2 as u32) << 20)
+             | ((self.rs1 as u32) << 15)
+             | ((self.rm as u32) << 12)
+             | ((self.rd as u32) << 7)
+             | (self.opcode as u32);
+    }
+
+    /* length of the encoding the instruction was fetched as, 2 for RVC */
+    pub fn with_len(mut self, ilen: u8) -> Self {
+        self.ilen = ilen;
+        return self;
+    }
+
+    /* fmt field to a format, None for the unimplemented H and Q */
+    fn format(&self) -> Option<FpFormat> {
+        return match self.fmt {
+            fmt if fmt == FloatFmtSel::S as u8 => Some(F32),
+            fmt if fmt == FloatFmtSel::D as u8 => Some(F64),
+            _ => None,
+        };
+    }
+
+    /* does it need D, loads/stores go by their width, FCVT.S.D has the source format in rs2 */
+    pub fn is_double(&self) -> bool {
+        if self.opcode == FloatOpcodes::LOAD_FP as u8 || self.opcode == FloatOpcodes::STORE_FP as u8 {
+            return self.rm == FloatWidthSel::D as u8;
+        }
+        if self.opcode == FloatOpcodes::OP_FP as u8 && self.func5 == FloatFunc5Sel::FCVT_FF as u8 && self.rs2 == FloatFmtSel::D as u8 {
+            return true;
+        }
+        return self.fmt == FloatFmtSel::D as u8;
+    }
+
+    /* static rounding mode or frm, reserved values are illegal */
+    fn rounding_mode(&self, csrs: &CsrFile) -> Option<RoundingMode> {
+        let rm: u8 = if self.rm == FLOAT_RM_DYN { csrs.get_frm() } else { self.rm };
+        return RoundingMode::from_bits(rm);
+    }
+
+    fn load_execute(&mut self, regs: &mut RegFile, fregs: &mut FpRegFile, mem: &mut Memory, mmu: &mut Mmu) -> Result<(),Exception> {
+        let addr: u64 = regs.xlen().trunc(regs.read(self.rs1).wrapping_add(self.imm as i64 as u64));
+        let fault: Exception = Exception::LoadAccessFault(addr);
+
+        match self.rm {
+            rm if rm == FloatWidthSel::W as u8 => {
+                check_load_align(addr, 4)?;
+                let paddr: u64 = mmu.translate(mem, addr, 4, AccessType::Load)?;
+                let data: u32 = mem.read_32bit(paddr).map_err(|_| fault)?;
+                fregs.write(self.rd, F32, data as u64);
+            }
+            rm if rm == FloatWidthSel::D as u8 => {
+                check_load_align(addr, 8)?;
+                let paddr: u64 = mmu.translate(mem, addr, 8, AccessType::Load)?;
+                let data: u64 = mem.read_64bit(paddr).map_err(|_| fault)?;
+                fregs.write(self.rd, F64, data);
+            }
+            _ => {
+                return Err(Exception::IllegalInstruction(0));
+            }
+        }
+        return Ok(());
+    }
+
+    fn store_execute(&mut self, regs: &mut RegFile, fregs: &mut FpRegFile, mem: &mut Memory, mmu: &mut Mmu) -> Result<(),Exception> {
+        let addr: u64 = regs.xlen().trunc(regs.read(self.rs1).wrapping_add(self.imm as i64 as u64));
+        let fault: Exception = Exception::StoreAccessFault(addr);
+        let data: u64 = fregs.read_raw(self.rs2);
+
+        match self.rm {
+            rm if rm == FloatWidthSel::W as u8 => {
+                check_store_align(addr, 4)?;
+                let paddr: u64 = mmu.translate(mem, addr, 4, AccessType::Store)?;
+                mem.write_32bit(paddr, data as u32).map_err(|_| fault)?;
+            }
+            rm if rm == FloatWidthSel::D as u8 => {
+                check_store_align(addr, 8)?;
+                let paddr: u64 = mmu.translate(mem, addr, 8, AccessType::Store)?;
+                mem.write_64bit(paddr, data).map_err(|_| fault)?;
+            }
+            _ => {
+                return Err(Exception::IllegalInstruction(0));
+            }
+        }
+        return Ok(());
+    }
+
+    /* FMADD/FMSUB/FNMSUB/FNMADD, the opcode picks which signs get flipped */
+    fn fused_execute(&mut self, fregs: &mut FpRegFile, csrs: &mut CsrFile) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let fmt: FpFormat = self.format().ok_or(illegal)?;
+        let rm: RoundingMode = self.rounding_mode(csrs).ok_or(illegal)?;
+
+        let (neg_prod, neg_add): (bool, bool) = match self.opcode {
+            op if op == FloatOpcodes::FMADD as u8 => (false, false),
+            op if op == FloatOpcodes::FMSUB as u8 => (false, true),
+            op if op == FloatOpcodes::FNMSUB as u8 => (true, false),
+            _ => (true, true), /* FNMADD */
+        };
+
+        let a: u64 = fregs.read(self.rs1, fmt);
+        let b: u64 = fregs.read(self.rs2, fmt);
+        let c: u64 = fregs.read(self.rs3, fmt);
+        let mut flags: u8 = 0;
+        let res: u64 = softfloat::fma(fmt, a, b, c, neg_prod, neg_add, rm, &mut flags);
+
+        fregs.write(self.rd, fmt, res);
+        csrs.accrue_fflags(flags);
+        return Ok(());
+    }
+
+    /* everything under the OP-FP opcode */
+    fn op_execute(&mut self, regs: &mut RegFile, fregs: &mut FpRegFile, csrs: &mut CsrFile) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let fmt: FpFormat = self.format().ok_or(illegal)?;
+        let rv64: bool = regs.xlen() == Xlen::RV64;
+        let func5: u8 = self.func5;
+        let a: u64 = fregs.read(self.rs1, fmt);
+        let b: u64 = fregs.read(self.rs2, fmt);
+        let mut flags: u8 = 0;
+
+        match func5 {
+            /* arithmetic */
+            func5 if func5 == FloatFunc5Sel::FADD as u8 || func5 == FloatFunc5Sel::FSUB as u8
+                  || func5 == FloatFunc5Sel::FMUL as u8 || func5 == FloatFunc5Sel::FDIV as u8 => {
+                let rm: RoundingMode = self.rounding_mode(csrs).ok_or(illegal)?;
+                let res: u64 = match func5 {
+                    func5 if func5 == FloatFunc5Sel::FADD as u8 => softfloat::add(fmt, a, b, rm, &mut flags),
+                    func5 if func5 == FloatFunc5Sel::FSUB as u8 => softfloat::sub(fmt, a, b, rm, &mut flags),
+                    func5 if func5 == FloatFunc5Sel::FMUL as u8 => softfloat::mul(fmt, a, b, rm, &mut flags),
+                    _ => softfloat::div(fmt, a, b, rm, &mut flags),
+                };
+                fregs.write(self.rd, fmt, res);
+            }
+            func5 if func5 == FloatFunc5Sel::FSQRT as u8 => {
+                if self.rs2 != 0 {
+                    return Err(illegal);
+                }
+                let rm: RoundingMode = self.rounding_mode(csrs).ok_or(illegal)?;
+                fregs.write(self.rd, fmt, softfloat::sqrt(fmt, a, rm, &mut flags));
+            }
+            /* sign injection, pure bit manipulation that never raises flags */
+            func5 if func5 == FloatFunc5Sel::FSGNJ as u8 => {
+                let sign_bit: u64 = if fmt == F64 { 1 << 63 } else { 1 << 31 };
+                let sign: u64 = match self.rm {
+                    0 => b & sign_bit,
+                    1 => !b & sign_bit,
+                    2 => (a ^ b) & sign_bit,
+                    _ => return Err(illegal),
+                };
+                fregs.write(self.rd, fmt, (a & !sign_bit) | sign);
+            }
+            func5 if func5 == FloatFunc5Sel::FMINMAX as u8 => {
+                if self.rm > 1 {
+                    return Err(illegal);
+                }
+                fregs.write(self.rd, fmt, softfloat::min_max(fmt, a, b, self.rm == 1, &mut flags));
+            }
+            /* FCVT.S.D and FCVT.D.S, rs2 is the source format */
+            func5 if func5 == FloatFunc5Sel::FCVT_FF as u8 => {
+                let src: FpFormat = match self.rs2 {
+                    rs2 if rs2 == FloatFmtSel::S as u8 => F32,
+                    rs2 if rs2 == FloatFmtSel::D as u8 => F64,
+                    _ => return Err(illegal),
+                };
+                if src == fmt {
+                    return Err(illegal);
+                }
+                let rm: RoundingMode = self.rounding_mode(csrs).ok_or(illegal)?;
+                let val: u64 = fregs.read(self.rs1, src);
+                fregs.write(self.rd, fmt, softfloat::convert(src, fmt, val, rm, &mut flags));
+            }
+            /* compares write 0/1 to an integer register */
+            func5 if func5 == FloatFunc5Sel::FCMP as u8 => {
+                let res: bool = match self.rm {
+                    0 => softfloat::le(fmt, a, b, &mut flags),
+                    1 => softfloat::lt(fmt, a, b, &mut flags),
+                    2 => softfloat::eq(fmt, a, b, &mut flags),
+                    _ => return Err(illegal),
+                };
+                regs.write(self.rd, res as u64);
+            }
+            /* float to integer, L/LU only exist on RV64 */
+            func5 if func5 == FloatFunc5Sel::FCVT_I_F as u8 => {
+                if self.rs2 > 3 || (self.rs2 > 1 && !rv64) {
+                    return Err(illegal);
+                }
+                let rm: RoundingMode = self.rounding_mode(csrs).ok_or(illegal)?;
+                let signed: bool = (self.rs2 & 0x1) == 0;
+                let bits: u32 = if self.rs2 < 2 { 32 } else { 64 };
+                regs.write(self.rd, softfloat::to_int(fmt, a, signed, bits, rm, &mut flags));
+            }
+            /* integer to float */
+            func5 if func5 == FloatFunc5Sel::FCVT_F_I as u8 => {
+                if self.rs2 > 3 || (self.rs2 > 1 && !rv64) {
+                    return Err(illegal);
+                }
+                let rm: RoundingMode = self.rounding_mode(csrs).ok_or(illegal)?;
+                let signed: bool = (self.rs2 & 0x1) == 0;
+                let bits: u32 = if self.rs2 < 2 { 32 } else { 64 };
+                let src: u64 = regs.read(self.rs1);
+                fregs.write(self.rd, fmt, softfloat::from_int(fmt, src, signed, bits, rm, &mut flags));
+            }
+            /* FMV.X.W/FMV.X.D move the raw bits, FMV.X.W sign extends on RV64 */
+            func5 if func5 == FloatFunc5Sel::FMV_X_F as u8 && self.rm == 0 => {
+                if self.rs2 != 0 || (fmt == F64 && !rv64) {
+                    return Err(illegal);
+                }
+                let raw: u64 = fregs.read_raw(self.rs1);
+                regs.write(self.rd, if fmt == F64 { raw } else { sext32(raw) });
+            }
+            func5 if func5 == FloatFunc5Sel::FMV_X_F as u8 && self.rm == 1 => {
+                if self.rs2 != 0 {
+                    return Err(illegal);
+                }
+                regs.write(self.rd, softfloat::classify(fmt, a));
+            }
+            func5 if func5 == FloatFunc5Sel::FMV_F_X as u8 => {
+                if self.rs2 != 0 || self.rm != 0 || (fmt == F64 && !rv64) {
+                    return Err(illegal);
+                }
+                fregs.write(self.rd, fmt, regs.read(self.rs1));
+            }
+            _ => {
+                return Err(illegal);
+            }
+        }
+
+        csrs.accrue_fflags(flags);
+        return Ok(());
+    }
+
+    /*
+     * NOTE: every FP instruction is illegal while mstatus.FS is Off, anything that
+     *       may have changed an f register or fflags marks the state dirty
+     */
+    pub fn execute(&mut self, regs: &mut RegFile, fregs: &mut FpRegFile, pc: &mut u64, mem: &mut Memory, mmu: &mut Mmu, csrs: &mut CsrFile) -> Result<(),Exception> {
+        if !csrs.fp_enabled() {
+            return Err(Exception::IllegalInstruction(0));
+        }
+
+        let opcode: u8 = self.opcode;
+        match opcode {
+            opcode if opcode == FloatOpcodes::LOAD_FP as u8 => {
+                self.load_execute(regs, fregs, mem, mmu)?;
+                csrs.set_fp_dirty();
+            }
+            opcode if opcode == FloatOpcodes::STORE_FP as u8 => {
+                self.store_execute(regs, fregs, mem, mmu)?;
+            }
+            opcode if opcode == FloatOpcodes::FMADD as u8 || opcode == FloatOpcodes::FMSUB as u8
+                   || opcode == FloatOpcodes::FNMSUB as u8 || opcode == FloatOpcodes::FNMADD as u8 => {
+                self.fused_execute(fregs, csrs)?;
+                csrs.set_fp_dirty();
+            }
+            opcode if opcode == FloatOpcodes::OP_FP as u8 => {
+                self.op_execute(regs, fregs, csrs)?;
+                csrs.set_fp_dirty();
+            }
+            _ => {
+                return Err(Exception::IllegalInstruction(0));
+            }
+        }
+
+        *pc = regs.xlen().trunc(*pc + self.ilen as u64);
+        return Ok(());
+    }
+
+    /*
+     * name: disassemble
+     * desc: objdump text of the instruction, name is the mnemonic of its table entry
+     *
+     * NOTE: the rounding mode is only printed when it isn't dyn, same as objdump,
+     *       and not at all for the ops that can't round, None for a reserved one
+     *       (the func3 of the ops without a rounding mode is never one)
+     */
+    pub fn disassemble(&self, name: &str) -> Option<String> {
+        let rm: String = match self.rm {
+            FLOAT_RM_DYN => String::new(),
+            rm => format!(",{}", format!("{:?}", RoundingMode::from_bits(rm)?).to_lowercase()),
+        };
+        let (rd, rs1, rs2, rs3): (&str, &str, &str, &str) = (freg(self.rd), freg(self.rs1), freg(self.rs2), freg(self.rs3));
+
+        let text: String = match self.opcode {
+            op if op == FloatOpcodes::LOAD_FP as u8 => format!("{}\t{},{}({})", name, rd, self.imm, xreg(self.rs1)),
+            op if op == FloatOpcodes::STORE_FP as u8 => format!("{}\t{},{}({})", name, rs2, self.imm, xreg(self.rs1)),
+            op if op != FloatOpcodes::OP_FP as u8 => format!("{}\t{},{},{},{}{}", name, rd, rs1, rs2, rs3, rm),
+
+            _ => match self.func5 {
+                f if f <= FloatFunc5Sel::FDIV as u8 => format!("{}\t{},{},{}{}", name, rd, rs1, rs2, rm),
+                f if f == FloatFunc5Sel::FSQRT as u8 => format!("{}\t{},{}{}", name, rd, rs1, rm),
+                f if f == FloatFunc5Sel::FSGNJ as u8 || f == FloatFunc5Sel::FMINMAX as u8 => {
+                    format!("{}\t{},{},{}", name, rd, rs1, rs2)
+                }
+                /* widening to double is exact, objdump leaves the rounding mode out */
+                f if f == FloatFunc5Sel::FCVT_FF as u8 => {
+                    format!("{}\t{},{}{}", name, rd, rs1, if self.fmt == FloatFmtSel::D as u8 { "" } else { &rm })
+                }
+                f if f == FloatFunc5Sel::FCMP as u8 => format!("{}\t{},{},{}", name, xreg(self.rd), rs1, rs2),
+                f if f == FloatFunc5Sel::FCVT_I_F as u8 => format!("{}\t{},{}{}", name, xreg(self.rd), rs1, rm),
+                /* so is every 32 bit integer to double */
+                f if f == FloatFunc5Sel::FCVT_F_I as u8 => {
+                    let exact: bool = self.fmt == FloatFmtSel::D as u8 && self.rs2 <= 1;
+                    format!("{}\t{},{}{}", name, rd, xreg(self.rs1), if exact { "" } else { &rm })
+                }
+                f if f == FloatFunc5Sel::FMV_X_F as u8 => format!("{}\t{},{}", name, xreg(self.rd), rs1),
+                _ => format!("{}\t{},{}", name, rd, xreg(self.rs1)), /* FMV_F_X */
+            },
+        };
+        return Some(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::*;
+    use crate::isa::*;
+    use crate::encoder::*;
+
+    #[test]
+    fn singles_are_nan_boxed() {
+        let mut fregs: FpRegFile = FpRegFile::new();
+        fregs.write(1, F32, 0x3F80_0000);
+        assert_eq!(fregs.read_raw(1), 0xFFFF_FFFF_3F80_0000);
+        assert_eq!(fregs.read(1, F32), 0x3F80_0000);
+
+        /* a double, or a single with a broken box, reads back as the canonical NaN */
+        fregs.write(2, F64, 0x3FF0_0000_0000_0000);
+        assert_eq!(fregs.read(2, F32), F32.canonical_nan());
+        fregs.write(3, F64, 0xFFFF_FFFE_3F80_0000);
+        assert_eq!(fregs.read(3, F32), F32.canonical_nan());
+        assert_eq!(fregs.read(3, F64), 0xFFFF_FFFE_3F80_0000);
+    }
+
+    /* fflags accrues across instructions, FMV.X.D sees the box, FCVT saturates */
+    #[test]
+    fn fflags_boxing_and_saturation() {
+        let word = |inst: Inst| format!(".word {:#x}", inst.encode());
+        let src: String = format!("
+                li t0, 0x3f800000
+                {}                   # fmv.w.x ft0, t0 = 1.0
+                {}                   # fmv.w.x ft1, zero = 0.0
+                {}                   # fdiv.s ft2, ft0, ft1 = inf, DZ
+                li t1, 0x40400000
+                {}                   # fmv.w.x ft3, t1 = 3.0
+                {}                   # fdiv.s ft4, ft0, ft3 = 1/3, NX
+                csrr a0, fflags
+                {}                   # fcvt.w.s a1, ft2, NV
+                csrr a2, fflags
+                {}                   # fmv.d.x ft5, t0, not boxed
+                {}                   # fadd.s ft6, ft5, ft0
+                {}                   # fmv.x.w a3, ft6
+                {}                   # fmv.x.d a4, ft0
+                j .
+            ",
+            word(Inst::fmv_w_x(FReg::Ft0, Reg::T0)),
+            word(Inst::fmv_w_x(FReg::Ft1, Reg::Zero)),
+            word(Inst::fdiv_s(FReg::Ft2, FReg::Ft0, FReg::Ft1)),
+            word(Inst::fmv_w_x(FReg::Ft3, Reg::T1)),
+            word(Inst::fdiv_s(FReg::Ft4, FReg::Ft0, FReg::Ft3)),
+            word(Inst::fcvt_w_s(Reg::A1, FReg::Ft2)),
+            word(Inst::fmv_d_x(FReg::Ft5, Reg::T0)),
+            word(Inst::fadd_s(FReg::Ft6, FReg::Ft5, FReg::Ft0)),
+            word(Inst::fmv_x_w(Reg::A3, FReg::Ft6)),
+            word(Inst::fmv_x_d(Reg::A4, FReg::Ft0)),
+        );
+        let machine: Machine = run_source(&src, &IsaConfig::new(Xlen::RV64), 1);
+        let reg = |r: Reg| machine.get_harts()[0].get_reg(r as u8);
+        assert_eq!(reg(Reg::A0), (softfloat::FLAG_DZ | softfloat::FLAG_NX) as u64);
+        assert_eq!(reg(Reg::A1), 0x7FFF_FFFF);
+        assert_eq!(reg(Reg::A2), (softfloat::FLAG_NV | softfloat::FLAG_DZ | softfloat::FLAG_NX) as u64);
+        assert_eq!(reg(Reg::A3), 0x7FC0_0000);
+        assert_eq!(reg(Reg::A4), 0xFFFF_FFFF_3F80_0000);
+    }
+}
@@ -0,0 +1,90 @@
+/*
+ * name: hpm.rs
+ * desc: hardware performance monitor (Zihpm), mhpmcounter3..31 and the
+ *       mhpmevent3..31 selectors that pick what each of them counts
+ *
+ * Note: the events are the ones the emulator actually sees, they are counted per
+ *       retired instruction (a vector load counts once, an AMO is a load and a store),
+ *       traps count both exceptions and interrupts
+ *
+ *       TLB misses only happen while translation is on, there is no cache model
+ *       so there is no cache miss event
+ *
+ *       mhpmevent is WARL, selecting an event that doesn't exist stores 0 (none),
+ *       the counters are 64 bits wide on both RV32 and RV64
+ *
+ */
+
+use crate::xlen::*;
+
+/* mhpmcounter3 is the first one, 0..2 are cycle, time and instret */
+pub const HPM_FIRST: usize = 3;
+pub const HPM_COUNTERS: usize = 29;
+
+/* mhpmevent values */
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum HpmEvent {
+    NONE         = 0,
+    LOAD         = 1, /* loads, LR and AMOs, integer, FP and vector */
+    STORE        = 2, /* stores, SC and AMOs, integer, FP and vector */
+    BRANCH_TAKEN = 3, /* conditional branches that were taken */
+    TRAP         = 4, /* exceptions and interrupts */
+    TLB_MISS     = 5, /* page table walks */
+}
+
+const HPM_EVENT_MAX: u64 = HpmEvent::TLB_MISS as u64;
+
+#[derive(Debug)]
+pub struct Hpm {
+    counter: [u64; HPM_COUNTERS],
+    event: [u64; HPM_COUNTERS],
+}
+
+impl Hpm {
+    /* constructor: every counter at zero and counting nothing */
+    pub fn new() -> Hpm {
+        return Hpm {
+            counter: [0; HPM_COUNTERS],
+            event: [0; HPM_COUNTERS],
+        };
+    }
+
+    /* i is the counter number, 3..31 */
+    pub fn read_counter(&self, i: usize) -> u64 {
+        return self.counter[i - HPM_FIRST];
+    }
+
+    /* the low XLEN bits, the upper half of an RV32 counter is written through write_counter_high */
+    pub fn write_counter(&mut self, i: usize, val: u64, xlen: Xlen) {
+        let counter: &mut u64 = &mut self.counter[i - HPM_FIRST];
+        *counter = (*counter & !xlen.trunc(u64::MAX)) | xlen.trunc(val);
+    }
+
+    pub fn write_counter_high(&mut self, i: usize, val: u64) {
+        let counter: &mut u64 = &mut self.counter[i - HPM_FIRST];
+        *counter = (*counter & 0xFFFF_FFFF) | (val << 32);
+    }
+
+    pub fn read_event(&self, i: usize) -> u64 {
+        return self.event[i - HPM_FIRST];
+    }
+
+    pub fn write_event(&mut self, i: usize, val: u64) {
+        self.event[i - HPM_FIRST] = if val <= HPM_EVENT_MAX { val } else { HpmEvent::NONE as u64 };
+    }
+
+    /*
+     * name: record
+     * desc: add n occurrences of event to every counter selecting it
+     *
+     * NOTE: inhibit is mcountinhibit, bit i stops counter i
+     */
+    pub fn record(&mut self, event: HpmEvent, n: u64, inhibit: u64) {
+        for j in 0..HPM_COUNTERS {
+            if self.event[j] == event as u64 && ((inhibit >> (j + HPM_FIRST)) & 1) == 0 {
+                self.counter[j] = self.counter[j].wrapping_add(n);
+            }
+        }
+    }
+}
@@ -7,543 +7,687 @@
 /* desc of each instruction here https://mark.theis.site/riscv/ */
 /* alternative desc of each instruction here: https://msyksphinz-self.github.io/riscv-isadoc/html/rvi.html */
 
-/* INSTRUCTIONS NOT IMPLEMENTED
-   mostly needed for implementing an OS-we will assume pure baremetal
-   FENCE
-   FENCE.I
-   ECALL
-   EBREAK
-   CSR*
-*/
-
-/* program counter note */
-// I made he PC too big in cpu.rs and am too lazy to fix it
-// should be 32 bits, is 128 bits instead
+/* BASE: RV32I or RV64I, picked when the Cpu is built */
+/* EXTENSIONS: M (multiply/divide), A (atomics), F/D (floating point, fpu.rs), C (compressed), Zicsr,
+               Zba/Zbb/Zbc/Zbs (bit manipulation, bitmanip.rs), V integer subset (vector.rs),
+               decode() knows all of them, the cpu turns away the ones its ISA string leaves out (isa.rs) */
+
+/* fence note */
+// every hart reaches memory through the same Mutex<Memory> (machine.rs), fetch and
+// execute each take the lock for the whole access, so a store is visible to every
+// other hart's loads and fetches once it returns and the lock orders them, FENCE has
+// nothing left to order, there is no decoded instruction cache either, every fetch
+// reads memory again, so FENCE.I has no stale instruction state to throw away
+
+/* translation note */
+// loads, stores and AMOs compute a virtual address and hand it to the Mmu (mmu.rs),
+// alignment is checked first since misaligned exceptions take priority over page faults,
+// access faults still report the virtual address
+
+/* compressed note */
+// RVC instructions are expanded to their 32 bit form before they get here (rvc.rs),
+// ilen tracks the real length for pc increments and link addresses
+
+/* decode note */
+// Instruction::decode() picks every field out of the encoding once and checks the
+// reserved bits, execute() then only works on typed operands, the same Instruction
+// is what a disassembler or a trace would look at
+//
+// no encoding is written out here, build.rs generates decode_table()/encode_table()
+// and the disassembler's mnemonic() from the riscv-opcodes style tables in opcodes/
+// (every bit accounted for, no two entries matching the same word), opcodes/variants
+// says which Instruction variant each entry becomes, an instruction is added with a
+// table entry and its variant
+//
+// F/D and V keep the raw word (FloatInst/VectorInst pick their own fields apart),
+// the table only decides which words are instructions, Zb* gets its fields filled in
+
+/* exception note */
+// execute() returns Err(Exception) without touching any state when the instruction
+// faults, decode() reports IllegalInstruction with the raw bits, execute() and the
+// extension decoders use a tval of 0 since they don't keep them, the cpu fills it in
+
+/* XLEN note */
+// the same execute functions run RV32I and RV64I, registers and the pc are u64 and
+// RegFile truncates every write to XLEN, the RV64 only instructions (LD/SD/LWU, the
+// *W ops) are illegal on an RV32 machine
 
 use crate::memory::*;
-use crate::logging::*;
-use std::process;
-
-#[derive(Debug)]
-pub enum InstType {
-    RType,
-    IType,
-    SType,
-    BType,
-    UType,
-    JType,
-    Invalid,
+use crate::csr::*;
+use crate::trap::*;
+use crate::xlen::*;
+use crate::bitmanip::*;
+use crate::mmu::*;
+use crate::vector::*;
+use crate::fpu::*;
+
+/* atomic operations that read, modify and write memory, LR/SC have variants of their own */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum AmoOp {
+    SWAP,
+    ADD,
+    XOR,
+    AND,
+    OR,
+    MIN,
+    MAX,
+    MINU,
+    MAXU,
 }
 
-pub fn opcode_to_InstType(inst: u32) -> InstType {
-    use InstType::*; 
-
-    let mut ret: InstType = Invalid;
-    let opcode = (inst & 0x7F as u32) as u8;
-
-    return match opcode {
+/*
+ * one decoded instruction, built once by Instruction::decode and run by execute
+ *
+ * registers are numbers 0..31, immediates are already reassembled and sign
+ * extended (LUI/AUIPC keep the low 12 bits as zero), shamt and uimm are unsigned
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /* upper immediates and jumps */
+    Lui { rd: u8, imm: i32 },
+    Auipc { rd: u8, imm: i32 },
+    Jal { rd: u8, imm: i32 },
+    Jalr { rd: u8, rs1: u8, imm: i32 },
+
+    /* branches */
+    Beq { rs1: u8, rs2: u8, imm: i32 },
+    Bne { rs1: u8, rs2: u8, imm: i32 },
+    Blt { rs1: u8, rs2: u8, imm: i32 },
+    Bge { rs1: u8, rs2: u8, imm: i32 },
+    Bltu { rs1: u8, rs2: u8, imm: i32 },
+    Bgeu { rs1: u8, rs2: u8, imm: i32 },
+
+    /* loads and stores, Ld/Lwu/Sd are RV64 only */
+    Lb { rd: u8, rs1: u8, imm: i32 },
+    Lh { rd: u8, rs1: u8, imm: i32 },
+    Lw { rd: u8, rs1: u8, imm: i32 },
+    Ld { rd: u8, rs1: u8, imm: i32 },
+    Lbu { rd: u8, rs1: u8, imm: i32 },
+    Lhu { rd: u8, rs1: u8, imm: i32 },
+    Lwu { rd: u8, rs1: u8, imm: i32 },
+    Sb { rs1: u8, rs2: u8, imm: i32 },
+    Sh { rs1: u8, rs2: u8, imm: i32 },
+    Sw { rs1: u8, rs2: u8, imm: i32 },
+    Sd { rs1: u8, rs2: u8, imm: i32 },
+
+    /* register-immediate ALU */
+    Addi { rd: u8, rs1: u8, imm: i32 },
+    Slti { rd: u8, rs1: u8, imm: i32 },
+    Sltiu { rd: u8, rs1: u8, imm: i32 },
+    Xori { rd: u8, rs1: u8, imm: i32 },
+    Ori { rd: u8, rs1: u8, imm: i32 },
+    Andi { rd: u8, rs1: u8, imm: i32 },
+    Slli { rd: u8, rs1: u8, shamt: u8 },
+    Srli { rd: u8, rs1: u8, shamt: u8 },
+    Srai { rd: u8, rs1: u8, shamt: u8 },
+
+    /* register-register ALU */
+    Add { rd: u8, rs1: u8, rs2: u8 },
+    Sub { rd: u8, rs1: u8, rs2: u8 },
+    Sll { rd: u8, rs1: u8, rs2: u8 },
+    Slt { rd: u8, rs1: u8, rs2: u8 },
+    Sltu { rd: u8, rs1: u8, rs2: u8 },
+    Xor { rd: u8, rs1: u8, rs2: u8 },
+    Srl { rd: u8, rs1: u8, rs2: u8 },
+    Sra { rd: u8, rs1: u8, rs2: u8 },
+    Or { rd: u8, rs1: u8, rs2: u8 },
+    And { rd: u8, rs1: u8, rs2: u8 },
+
+    /* RV64 only 32 bit word ops */
+    Addiw { rd: u8, rs1: u8, imm: i32 },
+    Slliw { rd: u8, rs1: u8, shamt: u8 },
+    Srliw { rd: u8, rs1: u8, shamt: u8 },
+    Sraiw { rd: u8, rs1: u8, shamt: u8 },
+    Addw { rd: u8, rs1: u8, rs2: u8 },
+    Subw { rd: u8, rs1: u8, rs2: u8 },
+    Sllw { rd: u8, rs1: u8, rs2: u8 },
+    Srlw { rd: u8, rs1: u8, rs2: u8 },
+    Sraw { rd: u8, rs1: u8, rs2: u8 },
+
+    /* M extension */
+    Mul { rd: u8, rs1: u8, rs2: u8 },
+    Mulh { rd: u8, rs1: u8, rs2: u8 },
+    Mulhsu { rd: u8, rs1: u8, rs2: u8 },
+    Mulhu { rd: u8, rs1: u8, rs2: u8 },
+    Div { rd: u8, rs1: u8, rs2: u8 },
+    Divu { rd: u8, rs1: u8, rs2: u8 },
+    Rem { rd: u8, rs1: u8, rs2: u8 },
+    Remu { rd: u8, rs1: u8, rs2: u8 },
+    Mulw { rd: u8, rs1: u8, rs2: u8 },
+    Divw { rd: u8, rs1: u8, rs2: u8 },
+    Divuw { rd: u8, rs1: u8, rs2: u8 },
+    Remw { rd: u8, rs1: u8, rs2: u8 },
+    Remuw { rd: u8, rs1: u8, rs2: u8 },
+
+    /* A extension, wide is the RV64 only double word form */
+    Lr { rd: u8, rs1: u8, wide: bool, aq: bool, rl: bool },
+    Sc { rd: u8, rs1: u8, rs2: u8, wide: bool, aq: bool, rl: bool },
+    Amo { op: AmoOp, rd: u8, rs1: u8, rs2: u8, wide: bool, aq: bool, rl: bool },
+
+    /* Zicsr, the *i forms take a 5 bit zero extended immediate instead of rs1 */
+    Csrrw { rd: u8, rs1: u8, csr: u16 },
+    Csrrs { rd: u8, rs1: u8, csr: u16 },
+    Csrrc { rd: u8, rs1: u8, csr: u16 },
+    Csrrwi { rd: u8, uimm: u8, csr: u16 },
+    Csrrsi { rd: u8, uimm: u8, csr: u16 },
+    Csrrci { rd: u8, uimm: u8, csr: u16 },
+
+    /* privileged and memory ordering */
+    Ecall,
+    Ebreak,
+    Mret,
+    Sret,
+    Wfi,
+    SfenceVma { rs1: u8, rs2: u8 },
+    Fence { fm: u8, pred: u8, succ: u8 },
+    FenceI,
+
+    /* extensions with state or operands of their own, the cpu runs them */
+    Float(FloatInst),
+    Bitmanip(BitmanipInst),
+    Vector(VectorInst),
+}
 
-        /* R-Types */
-        0x33 => RType,
+/* I-Type, arithmetic shift sign extends the 12 bit immediate */
+fn imm_i(inst: u32) -> i32 {
+    return (inst as i32) >> 20;
+}
 
-        /* all IType opcodes */
-        0x03 => IType, /* load instructions */
-        0x13 => IType, /* ALU instructions */
-        0x67 => IType, /* JALR - Jump and Link Reg */
-        //0x73 => IType, /* Special Instructions/Ignored */
+/* S-Type, imm[11:5] holds the sign bit */
+fn imm_s(inst: u32) -> i32 {
+    return (((inst as i32) >> 20) & !0x1F) | ((inst >> 7) & 0x1F) as i32;
+}
 
-        /* S-Types */
-        0x23 => SType, /* store instructions */
+/* B-Type, branch offsets are always even */
+fn imm_b(inst: u32) -> i32 {
+    let imm: u32 = (((inst >> 31) & 0x1) << 12)
+                 | (((inst >> 7) & 0x1) << 11)
+                 | (((inst >> 25) & 0x3F) << 5)
+                 | (((inst >> 8) & 0xF) << 1);
+    return ((imm << 19) as i32) >> 19; /* sign extend from bit 12 */
+}
 
-        /* B-Types */
-        0x63 => BType, /* branches */
+/* U-Type, upper 20 bits in place, low 12 bits are zero */
+fn imm_u(inst: u32) -> i32 {
+    return (inst & 0xFFFF_F000) as i32;
+}
 
-        /* U-Types */
-        0x17 => UType, /* AUIPC */
-        0x37 => UType, /* LUI */
+/* J-Type, jump offsets are always even */
+fn imm_j(inst: u32) -> i32 {
+    let imm: u32 = (((inst >> 31) & 0x1) << 20)
+                 | (((inst >> 12) & 0xFF) << 12)
+                 | (((inst >> 20) & 0x1) << 11)
+                 | (((inst >> 21) & 0x3FF) << 1);
+    return ((imm << 11) as i32) >> 11; /* sign extend from bit 20 */
+}
 
-        /* J-Types */
-        0x6F => JType, /* JAL */
+/* the inverses, immediate bits at their place in the encoding */
+pub(crate) fn place_i(imm: i32) -> u32 {
+    return ((imm as u32) & 0xFFF) << 20;
+}
 
-        /* just defer this to a higher level */
-        _ => Invalid,
-    }
+pub(crate) fn place_s(imm: i32) -> u32 {
+    return (((imm as u32) & 0xFE0) << 20) | (((imm as u32) & 0x1F) << 7);
+}
 
+fn place_b(imm: i32) -> u32 {
+    let imm: u32 = imm as u32;
+    return (((imm >> 12) & 0x1) << 31)
+         | (((imm >> 5) & 0x3F) << 25)
+         | (((imm >> 1) & 0xF) << 8)
+         | (((imm >> 11) & 0x1) << 7);
 }
 
-/* decode 32 bit instruction into fields */
-#[derive(Debug)]
-pub struct RTypeInst {
-    func7 : u8, /* 7 bits */
-    rs2: u8,    /* 5 bits */
-    rs1: u8,    /* 5 bits */
-    func3: u8,  /* 3 bits */ 
-    rd: u8,     /* 5 bits */
-    opcode: u8, /* 7 bits */
+fn place_u(imm: i32) -> u32 {
+    return (imm as u32) & 0xFFFF_F000;
 }
 
-/* based on the fun3 field */
-/* reminder: only one R-Type opcode, easy to handle with one enum */
-pub enum RTypeALUFuncSel {
-    ADD_SUB = 0x0, /* both add and sub use this func sel */
-    SSL     = 0x1,
-    SLT     = 0x2,
-    SLTU    = 0x3,
-    XOR     = 0x4,
-    SRL_SRA = 0x5, /* logical shift left/right */
-    OR      = 0x6,
-    AND     = 0x7,
+fn place_j(imm: i32) -> u32 {
+    let imm: u32 = imm as u32;
+    return (((imm >> 20) & 0x1) << 31)
+         | (((imm >> 1) & 0x3FF) << 21)
+         | (((imm >> 11) & 0x1) << 20)
+         | (((imm >> 12) & 0xFF) << 12);
 }
 
-impl RTypeInst {
-
-    pub fn new( inst: u32) -> RTypeInst {
-        let mut func7: u8;
-        let mut rs2 : u8;
-        let mut rs1 : u8;
-        let mut func3 : u8; 
-        let mut rd : u8;
-        let mut opcode : u8;
-        
-        func7  = ( (inst >> 25) & 0x7F as u32) as u8;
-        rs2    = (( inst >> 20) & 0x1F as u32) as u8;
-        rs1    = (( inst >> 15) & 0x1F as u32) as u8;
-        func3  = (( inst >> 12) & 0x7  as u32) as u8; 
-        rd     = (( inst >> 7 ) & 0x1F as u32) as u8; 
-        opcode = (inst & 0x7F as u32) as u8;
-
-        return RTypeInst {
-            func7: func7, /* 7 bits */
-            rs2: rs2,    /* 5 bits */
-            rs1: rs1,    /* 5 bits */
-            func3: func3,  /* 3 bits */ 
-            rd: rd,     /* 5 bits */
-            opcode: opcode, /* 7 bits */
-        };
+/* decode_table(), encode_table(), mnemonic() and the encoding constants, generated from the tables in opcodes/ by build.rs */
+include!(concat!(env!("OUT_DIR"), "/opcodes.rs"));
+
+impl Instruction {
+
+    /*
+     * name: decode
+     * desc: pick apart a 32 bit encoding, RVC has to be expanded first (rvc.rs)
+     *
+     * NOTE: anything that doesn't name a real instruction for this XLEN is an illegal
+     *       instruction here, including reserved func fields and the RV64 only
+     *       encodings on RV32, so execute only ever sees valid instructions
+     */
+    pub fn decode(inst: u32, xlen: Xlen) -> Result<Instruction,Exception> {
+        return decode_table(inst, xlen).ok_or(Exception::IllegalInstruction(inst as u64));
     }
 
-    pub fn execute(&mut self,regs: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-        /* casting hell */
-        let rs1: u32 = regs[self.rs1 as usize];
-        let rs2: u32 = regs[self.rs2 as usize];
-        let rd: usize = self.rd as usize;
-        let func3: u32 = self.func7.into();
-
-        match func3 {
-            func3 if func3 == RTypeALUFuncSel::ADD_SUB as u32 => {
-                /* subtract */
-                if self.func7 != 0 {
-                    regs[rd] = (rs1 as i32 - rs2 as i32) as u32;
-                }
-                /* add */
-                else {
-                    regs[rd] = (rs1 as i32 + rs2 as i32) as u32;
-                }
+    /*
+     * name: execute
+     * desc: run a base, M, A, Zicsr or privileged instruction and move the pc
+     *
+     * NOTE: ilen is the length the instruction was fetched as, 2 for RVC, it sets
+     *       the next pc and the link address of JAL/JALR
+     *
+     *       Float/Bitmanip/Vector need state this doesn't have and are illegal here,
+     *       the cpu hands them to their own execute
+     */
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute(&self, ilen: u8, regs: &mut RegFile, pc: &mut u64, mem: &mut Memory, mmu: &mut Mmu, csrs: &mut CsrFile, hart: usize) -> Result<(),Exception> {
+        use Instruction::*;
+
+        let xlen: Xlen = regs.xlen();
+        let next_pc: u64 = xlen.trunc(*pc + ilen as u64);
+
+        match *self {
+            /* load upper immediate, sign extended on RV64 */
+            Lui { rd, imm } => regs.write(rd, imm as i64 as u64),
+            /* add upper immediate to pc */
+            Auipc { rd, imm } => regs.write(rd, pc.wrapping_add(imm as i64 as u64)),
+
+            Jal { rd, imm } => {
+                let target: u64 = xlen.trunc(pc.wrapping_add(imm as i64 as u64));
+                check_fetch_align(target, csrs.ialign())?;
+                regs.write(rd, next_pc);
+                *pc = target;
+                return Ok(());
             }
-            /* shift left logical */
-            func3 if func3 == RTypeALUFuncSel::SSL as u32 => {
-                regs[rd] = rs1 << rs2;
+            Jalr { rd, rs1, imm } => {
+                /* read rs1 before writing rd, they may be the same register */
+                let target: u64 = xlen.trunc(regs.read(rs1).wrapping_add(imm as i64 as u64)) & !1;
+                check_fetch_align(target, csrs.ialign())?;
+                regs.write(rd, next_pc);
+                *pc = target;
+                return Ok(());
             }
-            /* set less than */
-            func3 if func3 == RTypeALUFuncSel::SLT as u32 => {
-                regs[rd] = ( (rs1 as i32) < (rs2 as i32) ) as u32;
-            }
-            /* set less than unsigned */
-            func3 if func3 == RTypeALUFuncSel::SLTU as u32 => {
-                regs[rd] = ((rs1 as u32) < (rs2 as u32)) as u32;
-            }
-            /* */
-            func3 if func3 == RTypeALUFuncSel::XOR as u32 => {
-                regs[rd] = rs1 ^ rs2;
-            }
-            /* shift left or right arithmatic */
-            func3 if func3 == RTypeALUFuncSel::SRL_SRA as u32 => {
-                /* SRA */
-                if self.func7 != 0{
-                    regs[rd] = ((rs1 as i32) >> (rs2 as i32)) as u32;
-                }
-                /* SLA */
-                else{
-                    regs[rd] = ((rs1 as i32) << (rs2 as i32)) as u32;
+
+            Beq { rs1, rs2, imm } | Bne { rs1, rs2, imm } | Blt { rs1, rs2, imm } |
+            Bge { rs1, rs2, imm } | Bltu { rs1, rs2, imm } | Bgeu { rs1, rs2, imm } => {
+                let (a, b): (u64, u64) = (regs.read(rs1), regs.read(rs2));
+                let (sa, sb): (i64, i64) = (regs.read_signed(rs1), regs.read_signed(rs2));
+                let taken: bool = match *self {
+                    Beq { .. } => a == b,
+                    Bne { .. } => a != b,
+                    Blt { .. } => sa < sb,
+                    Bge { .. } => sa >= sb,
+                    Bltu { .. } => a < b,
+                    _ => a >= b,
+                };
+
+                /* only a taken branch can raise a misaligned target */
+                if taken {
+                    let target: u64 = xlen.trunc(pc.wrapping_add(imm as i64 as u64));
+                    check_fetch_align(target, csrs.ialign())?;
+                    *pc = target;
+                    return Ok(());
                 }
             }
-            /*  */
-            func3 if func3 == RTypeALUFuncSel::OR as u32 => {
-                regs[rd] = rs1 | rs2;
-            } 
-            /*  */
-            func3 if func3 == RTypeALUFuncSel::AND as u32 => {
-                regs[rd] = rs1 & rs2;
-            }
 
-            _ => {
-                print_log("Error: RTypeInst execute invalid sel func3: {self.func3} opcode: {self.opcode}".to_string());
+            Lb { rd, rs1, imm } | Lh { rd, rs1, imm } | Lw { rd, rs1, imm } | Ld { rd, rs1, imm } |
+            Lbu { rd, rs1, imm } | Lhu { rd, rs1, imm } | Lwu { rd, rs1, imm } => {
+                let addr: u64 = xlen.trunc(regs.read(rs1).wrapping_add(imm as i64 as u64));
+                let data: u64 = self.load(addr, mem, mmu)?;
+                regs.write(rd, data);
             }
 
-        }
+            Sb { rs1, rs2, imm } | Sh { rs1, rs2, imm } | Sw { rs1, rs2, imm } | Sd { rs1, rs2, imm } => {
+                let addr: u64 = xlen.trunc(regs.read(rs1).wrapping_add(imm as i64 as u64));
+                self.store(addr, regs.read(rs2), mem, mmu)?;
+            }
 
-    }
-}
+            Addi { rd, rs1, imm } => regs.write(rd, regs.read(rs1).wrapping_add(imm as i64 as u64)),
+            Slti { rd, rs1, imm } => regs.write(rd, (regs.read_signed(rs1) < imm as i64) as u64),
+            /* immediate is sign extended then compared unsigned */
+            Sltiu { rd, rs1, imm } => regs.write(rd, (regs.read(rs1) < xlen.trunc(imm as i64 as u64)) as u64),
+            Xori { rd, rs1, imm } => regs.write(rd, regs.read(rs1) ^ (imm as i64 as u64)),
+            Ori { rd, rs1, imm } => regs.write(rd, regs.read(rs1) | (imm as i64 as u64)),
+            Andi { rd, rs1, imm } => regs.write(rd, regs.read(rs1) & (imm as i64 as u64)),
+            Slli { rd, rs1, shamt } => regs.write(rd, regs.read(rs1) << shamt),
+            Srli { rd, rs1, shamt } => regs.write(rd, regs.read(rs1) >> shamt),
+            Srai { rd, rs1, shamt } => regs.write(rd, (regs.read_signed(rs1) >> shamt) as u64),
+
+            Add { rd, rs1, rs2 } | Sub { rd, rs1, rs2 } | Sll { rd, rs1, rs2 } | Slt { rd, rs1, rs2 } |
+            Sltu { rd, rs1, rs2 } | Xor { rd, rs1, rs2 } | Srl { rd, rs1, rs2 } | Sra { rd, rs1, rs2 } |
+            Or { rd, rs1, rs2 } | And { rd, rs1, rs2 } => {
+                let (a, b): (u64, u64) = (regs.read(rs1), regs.read(rs2));
+                let shamt: u32 = (b as u32) & xlen.shamt_mask(); /* only the low log2(XLEN) bits of rs2 are used for shifts */
+                let res: u64 = match *self {
+                    Add { .. } => a.wrapping_add(b),
+                    Sub { .. } => a.wrapping_sub(b),
+                    Sll { .. } => a << shamt,
+                    Slt { .. } => (regs.read_signed(rs1) < regs.read_signed(rs2)) as u64,
+                    Sltu { .. } => (a < b) as u64,
+                    Xor { .. } => a ^ b,
+                    Srl { .. } => a >> shamt,
+                    Sra { .. } => (regs.read_signed(rs1) >> shamt) as u64,
+                    Or { .. } => a | b,
+                    _ => a & b,
+                };
+                regs.write(rd, res);
+            }
 
-#[derive(Debug)]
-pub struct ITypeInst {
-    imm: u16,   /* 12 bits */
-    rs1: u8,    /* 5 bits */
-    func3: u8,  /* 3 bits */
-    rd: u8,     /* 5 bits */
-    opcode: u8, /* 7 bits */
-}
+            /* the word ops work on the low 32 bits and sign extend the 32 bit result */
+            Addiw { rd, rs1, imm } => regs.write(rd, sext32((regs.read(rs1) as u32).wrapping_add(imm as u32) as u64)),
+            Slliw { rd, rs1, shamt } => regs.write(rd, sext32(((regs.read(rs1) as u32) << shamt) as u64)),
+            Srliw { rd, rs1, shamt } => regs.write(rd, sext32(((regs.read(rs1) as u32) >> shamt) as u64)),
+            Sraiw { rd, rs1, shamt } => regs.write(rd, sext32(((regs.read(rs1) as i32) >> shamt) as u32 as u64)),
+
+            Addw { rd, rs1, rs2 } | Subw { rd, rs1, rs2 } | Sllw { rd, rs1, rs2 } | Srlw { rd, rs1, rs2 } |
+            Sraw { rd, rs1, rs2 } | Mulw { rd, rs1, rs2 } | Divw { rd, rs1, rs2 } | Divuw { rd, rs1, rs2 } |
+            Remw { rd, rs1, rs2 } | Remuw { rd, rs1, rs2 } => {
+                let (a, b): (u32, u32) = (regs.read(rs1) as u32, regs.read(rs2) as u32);
+                let shamt: u32 = b & 0x1F;
+                let res: u32 = match *self {
+                    Addw { .. } => a.wrapping_add(b),
+                    Subw { .. } => a.wrapping_sub(b),
+                    Sllw { .. } => a << shamt,
+                    Srlw { .. } => a >> shamt,
+                    Sraw { .. } => ((a as i32) >> shamt) as u32,
+                    Mulw { .. } => a.wrapping_mul(b),
+                    Divw { .. } => if b == 0 { u32::MAX } else { (a as i32).wrapping_div(b as i32) as u32 },
+                    Divuw { .. } => a.checked_div(b).unwrap_or(u32::MAX),
+                    Remw { .. } => if b == 0 { a } else { (a as i32).wrapping_rem(b as i32) as u32 },
+                    _ => a.checked_rem(b).unwrap_or(a),
+                };
+                regs.write(rd, sext32(res as u64));
+            }
 
-/* supports Load,immediate ALU, and JAL*/
-pub enum ITypeOpcodes {
-    LD   = 0x03, /* load */
-    ALU  = 0x13,
-    JALR = 0x67,
-}
+            /*
+             * division never traps, divide by zero and signed overflow
+             * return the results defined in the spec (section 7.2)
+             */
+            Mul { rd, rs1, rs2 } | Mulh { rd, rs1, rs2 } | Mulhsu { rd, rs1, rs2 } | Mulhu { rd, rs1, rs2 } |
+            Div { rd, rs1, rs2 } | Divu { rd, rs1, rs2 } | Rem { rd, rs1, rs2 } | Remu { rd, rs1, rs2 } => {
+                let bits: u32 = xlen.bits();
+                let (a, b): (u64, u64) = (regs.read(rs1), regs.read(rs2));
+                let (sa, sb): (i64, i64) = (regs.read_signed(rs1), regs.read_signed(rs2));
+                let res: u64 = match *self {
+                    /* lower XLEN bits of the product, same for signed and unsigned */
+                    Mul { .. } => a.wrapping_mul(b),
+                    /* upper XLEN bits of signed x signed, signed x unsigned and unsigned x unsigned */
+                    Mulh { .. } => (((sa as i128) * (sb as i128)) >> bits) as u64,
+                    Mulhsu { .. } => (((sa as i128) * (b as i128)) >> bits) as u64,
+                    Mulhu { .. } => (((a as u128) * (b as u128)) >> bits) as u64,
+                    /* divide by zero gives -1, overflow (-2^(XLEN-1) / -1) gives -2^(XLEN-1) */
+                    Div { .. } => if b == 0 { u64::MAX } else { sa.wrapping_div(sb) as u64 },
+                    /* divide by zero gives 2^XLEN-1 */
+                    Divu { .. } => a.checked_div(b).unwrap_or(u64::MAX),
+                    /* divide by zero gives the dividend, overflow gives 0 */
+                    Rem { .. } => if b == 0 { a } else { sa.wrapping_rem(sb) as u64 },
+                    /* divide by zero gives the dividend */
+                    _ => a.checked_rem(b).unwrap_or(a),
+                };
+                regs.write(rd, res);
+            }
 
-/* ITypeLoadFuncSel */
-pub enum ITypeLoadFuncSel {
-    LB  = 0x0,
-    LH  = 0x1,
-    LW  = 0x2,
-    LBU = 0x4,
-    LHU = 0x5,
-}
+            Lr { .. } | Sc { .. } | Amo { .. } => self.amo_execute(regs, mem, mmu, hart)?,
 
-/* ITypeALUFuncSel */
-pub enum ITypeALUFuncSel {
-    ADDI  = 0x0,
-    SLTI  = 0x2,
-    STLIU = 0x3,
-    XORI  = 0x4,
-    ORI   = 0x6,
-    ANDI  = 0x7,
-}
+            Csrrw { rd, rs1, csr } | Csrrs { rd, rs1, csr } | Csrrc { rd, rs1, csr } => {
+                let src: u64 = regs.read(rs1);
+                self.csr_execute(rd, rs1, src, csr, regs, csrs)?;
+            }
+            Csrrwi { rd, uimm, csr } | Csrrsi { rd, uimm, csr } | Csrrci { rd, uimm, csr } => {
+                self.csr_execute(rd, uimm, uimm as u64, csr, regs, csrs)?;
+            }
 
-/* ITypeJALFuncSel */
-// unneeded 
+            Ecall | Ebreak | Mret | Sret | Wfi | SfenceVma { .. } => {
+                return self.priv_execute(next_pc, pc, csrs);
+            }
 
-impl ITypeInst {
+            Fence { .. } => {
+                /* the memory lock already orders every access, see the fence note */
+            }
+            FenceI => {
+                /* every fetch goes to memory so later fetches already see earlier stores */
+            }
 
-    pub fn new(inst: u32) -> ITypeInst {
+            Float(_) | Bitmanip(_) | Vector(_) => {
+                return Err(Exception::IllegalInstruction(0));
+            }
+        }
 
-        let mut imm: u16;
-        let mut rs1: u8;
-        let mut func3: u8;
-        let mut rd: u8;
-        let mut opcode: u8;
+        *pc = next_pc;
+        return Ok(());
+    }
 
-        imm   = (( inst >> 20) & 0xFFF as u32) as u16;
-        rs1   = (( inst >> 15) & 0x1F  as u32) as u8;
-        func3 = (( inst >> 12) & 07    as u32) as u8;
-        rd    = (( inst >> 7 ) & 0x1F  as u32) as u8;
-        opcode = (inst & 0x7F as u32) as u8;
+    /* NOTE: all addresses are byte addressed, loads sign extend unless they end in U */
+    fn load(&self, addr: u64, mem: &mut Memory, mmu: &mut Mmu) -> Result<u64,Exception> {
+        use Instruction::*;
 
-        return ITypeInst {
-            imm: imm,
-            rs1: rs1,
-            func3: func3,
-            rd: rd,
-            opcode: opcode,
+        let size: u64 = match *self {
+            Lb { .. } | Lbu { .. } => 1,
+            Lh { .. } | Lhu { .. } => 2,
+            Lw { .. } | Lwu { .. } => 4,
+            _ => 8,
         };
-
+        let fault: Exception = Exception::LoadAccessFault(addr);
+
+        check_load_align(addr, size)?;
+        let paddr: u64 = mmu.translate(mem, addr, size, AccessType::Load)?;
+        let data: u64 = match *self {
+            Lb { .. } => mem.read_8bit(paddr).map_err(|_| fault)? as i8 as i64 as u64,
+            Lh { .. } => mem.read_16bit(paddr).map_err(|_| fault)? as i16 as i64 as u64,
+            Lw { .. } => sext32(mem.read_32bit(paddr).map_err(|_| fault)? as u64),
+            Lbu { .. } => mem.read_8bit(paddr).map_err(|_| fault)? as u64,
+            Lhu { .. } => mem.read_16bit(paddr).map_err(|_| fault)? as u64,
+            Lwu { .. } => mem.read_32bit(paddr).map_err(|_| fault)? as u64,
+            _ => mem.read_64bit(paddr).map_err(|_| fault)?,
+        };
+        return Ok(data);
     }
 
-    fn load_execute(&mut self,regs: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-        /* NOTE: all addresses are byte addressed */
-        let func3: u32 = self.func3 as u32;
-        let rs1: usize = self.rs1 as usize; 
-        let rd: usize = self.rd as usize;
-        let imm: i32 = self.imm as i32;
+    fn store(&self, addr: u64, data: u64, mem: &mut Memory, mmu: &mut Mmu) -> Result<(),Exception> {
+        use Instruction::*;
 
-        match func3 {
+        let size: u64 = match *self {
+            Sb { .. } => 1,
+            Sh { .. } => 2,
+            Sw { .. } => 4,
+            _ => 8,
+        };
+        let fault: Exception = Exception::StoreAccessFault(addr);
+
+        check_store_align(addr, size)?;
+        let paddr: u64 = mmu.translate(mem, addr, size, AccessType::Store)?;
+        match size {
+            1 => mem.write_8bit(paddr, data as u8),
+            2 => mem.write_16bit(paddr, data as u16),
+            4 => mem.write_32bit(paddr, data as u32),
+            _ => mem.write_64bit(paddr, data),
+        }.map_err(|_| fault)?;
+        return Ok(());
+    }
 
-            /* load byte */
-            func3 if func3 == ITypeLoadFuncSel::LB as u32 => {
-                regs[rd] = (mem.read_8bit(regs[rs1] as u64 + imm as u64) as i32) as u32; /* sign extend then recaste to u32 */
+    /*
+     * name: amo_execute
+     * desc: LR, SC and the read-modify-write AMOs
+     *
+     * NOTE: aq/rl are decoded but need no extra work, every hart performs its
+     *       accesses in program order and each instruction holds the memory lock
+     *       for all of them, so all atomics are already sequentially consistent
+     *
+     *       word AMOs on RV64 work on sign extended values, this keeps both the
+     *       signed and unsigned min/max orderings of the 32 bit operands
+     *
+     *       reservations are kept per hart on the physical address
+     */
+    fn amo_execute(&self, regs: &mut RegFile, mem: &mut Memory, mmu: &mut Mmu, hart: usize) -> Result<(),Exception> {
+        /* memory access of the instruction width, words are sign extended like LW */
+        let read = |mem: &mut Memory, addr: u64, wide: bool| -> Result<u64,()> {
+            if wide {
+                return mem.read_64bit(addr);
             }
-
-            /* load half word (16 bits) */
-            func3 if func3 == ITypeLoadFuncSel::LH as u32 => {
-                regs[rd] = ((mem.read_32bit(regs[rs1] as u64 + imm as u64) as u16) as i32) as u32; /* truncate to 16 bits */ 
+            return Ok(sext32(mem.read_32bit(addr)? as u64));
+        };
+        let write = |mem: &mut Memory, addr: u64, data: u64, wide: bool| -> Result<(),()> {
+            if wide {
+                return mem.write_64bit(addr, data);
             }
-
-            /* load word */
-            func3 if func3 == ITypeLoadFuncSel::LW as u32 => {
-                regs[rd] = mem.read_32bit(regs[rs1] as u64 + imm as u64);
+            return mem.write_32bit(addr, data as u32);
+        };
+        let width = |wide: bool| -> u64 { return if wide { 8 } else { 4 }; };
+        let operand = |regs: &RegFile, rs2: u8, wide: bool| -> u64 { return if wide { regs.read(rs2) } else { sext32(regs.read(rs2)) }; };
+
+        match *self {
+            /* load reserved */
+            Instruction::Lr { rd, rs1, wide, .. } => {
+                let addr: u64 = regs.read(rs1);
+                let size: u64 = width(wide);
+                check_load_align(addr, size)?;
+                let paddr: u64 = mmu.translate(mem, addr, size, AccessType::Load)?;
+                let data: u64 = read(mem, paddr, wide).map_err(|_| Exception::LoadAccessFault(addr))?;
+                regs.write(rd, data);
+                mem.reserve(hart, paddr);
             }
-
-            /* load byte 0-extend */
-            func3 if func3 == ITypeLoadFuncSel::LBU as u32 => {
-                regs[rd] = (mem.read_8bit(regs[rs1] as u64 + imm as u64) as u32); /* DO NOT SIGN EXTEND */
-            } 
-            /* load half-word 0-extend */
-            func3 if func3 == ITypeLoadFuncSel::LHU as u32 => {
-                regs[rd] = ((mem.read_32bit(regs[rs1] as u64 + imm as u64) as u16) as u32); /* truncate to 16 bits then caste back to 32 bits */ 
+            /* store conditional, rd = 0 on success */
+            Instruction::Sc { rd, rs1, rs2, wide, .. } => {
+                let addr: u64 = regs.read(rs1);
+                let size: u64 = width(wide);
+                let data: u64 = operand(regs, rs2, wide);
+                check_store_align(addr, size)?;
+                let paddr: u64 = mmu.translate(mem, addr, size, AccessType::Store)?;
+                mem.check_access(paddr, size).map_err(|_| Exception::StoreAccessFault(addr))?;
+                if mem.check_reservation(hart, paddr) {
+                    write(mem, paddr, data, wide).map_err(|_| Exception::StoreAccessFault(addr))?;
+                    regs.write(rd, 0);
+                }
+                else {
+                    regs.write(rd, 1);
+                }
+            }
+            /* read-modify-write, rd gets the original memory value */
+            Instruction::Amo { op, rd, rs1, rs2, wide, .. } => {
+                let addr: u64 = regs.read(rs1);
+                let size: u64 = width(wide);
+                let src: u64 = operand(regs, rs2, wide);
+                /* AMOs report store/AMO faults even for the read half */
+                let fault: Exception = Exception::StoreAccessFault(addr);
+                check_store_align(addr, size)?;
+                let paddr: u64 = mmu.translate(mem, addr, size, AccessType::Store)?;
+                let old: u64 = read(mem, paddr, wide).map_err(|_| fault)?;
+                let new: u64 = match op {
+                    AmoOp::SWAP => src,
+                    AmoOp::ADD => old.wrapping_add(src),
+                    AmoOp::XOR => old ^ src,
+                    AmoOp::AND => old & src,
+                    AmoOp::OR => old | src,
+                    AmoOp::MIN => (old as i64).min(src as i64) as u64,
+                    AmoOp::MAX => (old as i64).max(src as i64) as u64,
+                    AmoOp::MINU => old.min(src),
+                    AmoOp::MAXU => old.max(src),
+                };
+                write(mem, paddr, new, wide).map_err(|_| fault)?;
+                regs.write(rd, old);
             }
             _ => {
-
+                return Err(Exception::IllegalInstruction(0));
             }
         }
+        return Ok(());
     }
 
-    fn alu_execute(&mut self,regs: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-
-        let func3: u32 = self.func3 as u32;
-        let rs1:   usize = self.rs1 as usize; 
-        let rd:    usize = self.rd as usize;
-        let imm:   i32 = self.imm as i32;
-
-        match func3 {
-            func3 if func3 == ITypeALUFuncSel::ADDI as u32 => {
-                regs[rd] = (regs[rs1] as i32 + imm as i32) as u32; 
-            }   
-            func3 if func3 == ITypeALUFuncSel::SLTI as u32 => {
-                regs[rd] = ((regs[rs1] as i32) < imm as i32) as bool as u32; 
-            }
-            func3 if func3 == ITypeALUFuncSel::STLIU as u32 => {
-                regs[rd] = ((regs[rs1] as u32) < imm as u32) as bool as u32; 
+    /*
+     * NOTE: an unimplemented CSR or a write to a read-only CSR is an illegal
+     *       instruction, nothing has been modified in that case
+     *
+     *       CSRRW/CSRRWI with rd=x0 do not read the CSR, CSRRS/CSRRC with
+     *       rs1=x0 (or uimm=0) do not write it, src_field is rs1 or uimm
+     */
+    fn csr_execute(&self, rd: u8, src_field: u8, src: u64, csr: u16, regs: &mut RegFile, csrs: &mut CsrFile) -> Result<(),Exception> {
+        use Instruction::*;
+
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let is_write: bool;
+        let old: u64;
+        let new: u64;
+
+        match *self {
+            Csrrw { .. } | Csrrwi { .. } => {
+                is_write = true;
+                old = if rd != 0 { csrs.read(csr).map_err(|_| illegal)? } else { 0 };
+                new = src;
             }
-            func3 if func3 == ITypeALUFuncSel::XORI as u32 => {
-                regs[rd] = regs[rs1] as u32 ^ imm as u32; 
-            }
-            func3 if func3 == ITypeALUFuncSel::ORI as u32 => {
-                regs[rd] = regs[rs1] as u32 | imm as u32; 
-            }
-            func3 if func3 == ITypeALUFuncSel::ANDI as u32 => {
-                regs[rd] = regs[rs1] as u32 & imm as u32; 
+            Csrrs { .. } | Csrrsi { .. } => {
+                is_write = src_field != 0;
+                old = csrs.read(csr).map_err(|_| illegal)?;
+                new = old | src;
             }
             _ => {
-
+                is_write = src_field != 0;
+                old = csrs.read(csr).map_err(|_| illegal)?;
+                new = old & !src;
             }
         }
-    }
 
-    pub fn jalr_execute(&mut self,regs: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-        let func3: u32 = self.func3 as u32;
-        let rs1: usize = self.rs1 as usize; 
-        let rd: usize = self.rd as usize;
-        let imm: u32 = self.imm as u32;
-      
-        /* will handle here */
-        regs[rd as usize] = (*pc + 4) as u32;
-        *pc = ( (regs[rs1] as i128 + imm as i128) &  !(1 as i128) ) as u128;     
+        if is_write {
+            csrs.write(csr, new).map_err(|_| illegal)?;
+        }
+        regs.write(rd, old);
+        return Ok(());
     }
 
-    pub fn execute(&mut self,regs: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-        
-        let opcode: u32 = self.opcode as u32;
+    /*
+     * NOTE: SRET, WFI and SFENCE.VMA are illegal in U-mode, mstatus.TSR/TW/TVM
+     *       make them illegal in S-mode too
+     */
+    fn priv_execute(&self, next_pc: u64, pc: &mut u64, csrs: &mut CsrFile) -> Result<(),Exception> {
+        let privilege: PrivMode = csrs.get_privilege();
+        let mstatus: u64 = csrs.get_mstatus();
+        let trapped = |bit: MstatusBm| -> bool {
+            return privilege == PrivMode::U || (privilege == PrivMode::S && (mstatus & bit as u64) != 0);
+        };
 
-        /* opcode matching */
-        match opcode {
-            opcode if opcode == ITypeOpcodes::LD as u32 => {
-                self.load_execute(regs,pc,mem);
+        match *self {
+            /* environment call, mepc points at the ecall itself */
+            Instruction::Ecall => {
+                return match privilege {
+                    PrivMode::U => Err(Exception::EnvironmentCallFromUMode),
+                    PrivMode::S => Err(Exception::EnvironmentCallFromSMode),
+                    PrivMode::M => Err(Exception::EnvironmentCallFromMMode),
+                };
+            }
+            /* breakpoint, mtval gets the address of the ebreak */
+            Instruction::Ebreak => {
+                return Err(Exception::Breakpoint(*pc));
+            }
+            /* return from trap, only M-mode can use it */
+            Instruction::Mret => {
+                if privilege != PrivMode::M {
+                    return Err(Exception::IllegalInstruction(0));
+                }
+                *pc = csrs.mret();
+            }
+            /* return from a trap taken in S-mode */
+            Instruction::Sret => {
+                if trapped(MstatusBm::TSR_bm) {
+                    return Err(Exception::IllegalInstruction(0));
+                }
+                *pc = csrs.sret();
             }
-            opcode if opcode == ITypeOpcodes::ALU as u32 => {
-                self.alu_execute(regs,pc,mem);
+            /* wait for interrupt, the cpu does the waiting */
+            Instruction::Wfi => {
+                if trapped(MstatusBm::TW_bm) {
+                    return Err(Exception::IllegalInstruction(0));
+                }
+                *pc = next_pc;
             }
-            opcode if opcode == ITypeOpcodes::JALR as u32 => {
-                self.jalr_execute(regs,pc,mem);
+            /* address and ASID are picked up by the cpu, nothing to do here */
+            Instruction::SfenceVma { .. } => {
+                if trapped(MstatusBm::TVM_bm) {
+                    return Err(Exception::IllegalInstruction(0));
+                }
+                *pc = next_pc;
             }
             _ => {
-                print_log("ITypeInst execute() : Inavalid opcode {opcode}".to_string());
+                return Err(Exception::IllegalInstruction(0));
             }
-
         }
+        return Ok(());
     }
-
 }
-
-/* store type instruction */
-#[derive(Debug)]
-pub struct STypeInst {
-    imm11_5: u8,     /* 7 bits */
-    rs2: u8,         /* 5 bits */
-    rs1: u8,         /* 5 bits */
-    func3: u8,       /* 3 bits */
-    imm4_0: u8,      /* 5 bits */
-    opcode: u8,      /* 7 bits */
-}
-
-/* only has one associated opcode */
-pub enum STypeStoreFuncSel {
-    SB = 0x0,
-    SH = 0x1,
-    SW = 0x2,
-}  
-
-impl STypeInst {
-
-    pub fn new(inst: u32) -> STypeInst {
-
-        let mut imm11_5: u8;     /* 7 bits */
-        let mut rs2: u8;         /* 5 bits */
-        let mut rs1: u8;         /* 5 bits */
-        let mut func3: u8;       /* 3 bits */
-        let mut imm4_0: u8;      /* 5 bits */
-        let mut opcode: u8;      /* 7 bits */
-
-        imm11_5 = (( inst >> 25) & 0x7F as u32) as u8;
-        rs2     = (( inst >> 20) & 0x1F as u32) as u8;
-        rs1     = (( inst >> 15) & 0x1F as u32) as u8;
-        func3   = (( inst >> 12) & 0x7  as u32) as u8;
-        imm4_0  = (( inst >> 7 ) & 0x1F as u32) as u8;
-        opcode = (inst & 0x7F as u32) as u8;
-
-        return STypeInst {
-            imm11_5: imm11_5,
-            rs2: rs2,
-            rs1: rs1,
-            func3: func3,
-            imm4_0: imm4_0,
-            opcode: opcode,
-        };
-
-    }
-
-    pub fn execute(&mut self,arr: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-
-    }
-
-}
-
-/* branch instruction */
-#[derive(Debug)]
-pub struct BTypeInst {
-    imm12: u8,      /* 1 bit */
-    imm10_5: u8,    /* 6 bits */
-    rs2: u8,        /* 5 bits */
-    rs1: u8,        /* 5 bits */
-    func3: u8,      /* 3 bits */
-    imm4_1: u8,     /* 4 bits */
-    imm11: u8,      /* 1 bit */
-    opcode: u8,     /* 7 bits */
-}
-
-pub enum BTypeBranchFuncSel {
-    BEQ = 0x0,
-    BNE = 0x1,
-    BLT = 0x4,
-    BGE = 0x5,
-    BLTU = 0x6,
-    BGEU = 0x7,
-}
-
-impl BTypeInst {
-
-    pub fn new(inst: u32) -> BTypeInst {
-        let mut imm12: u8;      /* 1 bit */
-        let mut imm10_5: u8;    /* 6 bits */
-        let mut rs2: u8;        /* 5 bits */
-        let mut rs1: u8;        /* 5 bits */
-        let mut func3: u8;      /* 3 bits */
-        let mut imm4_1: u8;     /* 4 bits */
-        let mut imm11: u8;      /* 1 bit */
-        let mut opcode: u8;     /* 7 bits */
-
-        imm12 = (( inst >> 31) & 0x01 as u32) as u8;
-        imm10_5 = (( inst >> 25) & 0x3F as u32) as u8;
-        rs2 =  (( inst >> 20) & 0x1F as u32) as u8;
-        rs1 = (( inst >> 15) & 0x1F as u32) as u8;
-        func3 =  (( inst >> 12) & 0x07 as u32) as u8;
-        imm4_1 = (( inst >> 8) & 0x0F as u32) as u8;
-        imm11 =  (( inst >> 7) & 0x01 as u32) as u8;
-        opcode = (inst & 0x7F as u32) as u8;
-
-        return BTypeInst {
-            imm12: imm12,
-            imm10_5: imm10_5,
-            rs2: rs2,
-            rs1: rs1,
-            func3: func3,
-            imm4_1: imm4_1,
-            imm11: imm11,
-            opcode: opcode,
-        };
-    }
-
-    pub fn execute(&mut self,arr: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-
-    }
-}
-
-#[derive(Debug)]
-pub struct UTypeInst {
-    imm: u32,       /* 20 bits */
-    rd: u8,         /* 5 bits  */ 
-    opcode: u8,     /* 7 bits  */
-}
-
-pub enum UTypeOpcodes {
-    AUIPC = 0x17,
-    LUI   = 0x37, 
-}
-
-impl UTypeInst {
-
-    pub fn new(inst: u32) -> UTypeInst {
-        let mut imm: u32;       /* 20 bits */
-        let mut rd: u8;        /* 5 bits  */ 
-        let mut opcode: u8;     /* 7 bits  */
-
-        imm    = (( inst >> 12) & 0xFFFFF as u32) as u32;
-        rd     = (( inst >> 7) & 0x1F as u32) as u8;
-        opcode = (inst & 0x7F as u32) as u8;
-
-        return UTypeInst {
-            imm: imm,
-            rd: rd,
-            opcode: opcode,
-        };
-    }
-
-    pub fn execute(&mut self,arr: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-
-    }
-
-}
-
-#[derive(Debug)]
-pub struct JTypeInst {
-    imm20: u8,       /* 1 bit */
-    imm10_1: u16,    /* 10 bits */
-    imm11: u8,       /* 1 bit */ 
-    imm19_12: u8,    /* 8 bits */
-    rd: u8,          /* 5 bits */
-    opcode: u8,      /* 7 bits */
-}
-
-impl JTypeInst {
-
-    pub fn new(inst: u32) -> JTypeInst {
-        let mut imm20: u8;
-        let mut imm10_1: u16;
-        let mut imm11: u8;
-        let mut imm19_12: u8;
-        let mut rd: u8;
-        let mut opcode: u8;
-
-        imm20    = ((inst >> 31) & 0x01 as u32) as u8;
-        imm10_1  = ((inst >> 21) & 0x3FF as u32) as u16;
-        imm11    = ((inst >> 22) & 0x01 as u32) as u8;
-        imm19_12 = ((inst >> 12) & 0x01 as u32) as u8;
-        rd       = ((inst >> 7) & 0x1F as u32) as u8;
-        opcode   = (inst & 0x7F as u32) as u8;
-
-        return JTypeInst {
-            imm20: imm20,
-            imm10_1: imm10_1,
-            imm11: imm11,
-            imm19_12: imm19_12,
-            rd: rd,
-            opcode: opcode,
-        };
-    }
-
-    pub fn execute(&mut self,arr: &mut [u32], pc: &mut u128, mem: &mut Memory) {
-
-    }
-
-}
-
-
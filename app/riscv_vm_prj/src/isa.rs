@@ -0,0 +1,348 @@
+/*
+ * name: isa.rs
+ * desc: which extensions a hart implements, picked from an ISA string like
+ *       "rv32imac_zicsr_zifencei_zba_zbb" when the machine is built
+ *
+ * Note: an instruction from an extension that is switched off decodes as an illegal
+ *       instruction the same as on hardware that doesn't have it, misa and the
+ *       instruction alignment (IALIGN is 32 bits without C) follow the same choice
+ *
+ *       the string follows the naming rules of the unprivileged spec (chapter 27):
+ *       the base, then single letter extensions, then the multi letter ones each
+ *       after an '_', version numbers ("m2p0") are accepted and ignored
+ *
+ */
+
+use crate::xlen::*;
+use crate::csr::*;
+use crate::idecoder::*;
+use crate::bitmanip::*;
+
+/* the optional extensions, used to index IsaConfig */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsaExt {
+    M = 0,    /* multiply/divide */
+    A,        /* atomics */
+    F,        /* single precision floating point */
+    D,        /* double precision floating point */
+    C,        /* compressed instructions */
+    Zicsr,    /* CSR instructions */
+    Zifencei, /* FENCE.I */
+    Zba,
+    Zbb,
+    Zbc,
+    Zbs,
+    Zve64x,   /* the integer vector subset (vector.rs), there is no full V */
+}
+
+const ISA_EXT_COUNT: usize = 12;
+
+/* ISA string names in canonical order, single letters first */
+const ISA_EXT_NAMES: [(IsaExt, &str); ISA_EXT_COUNT] = [
+    (IsaExt::M, "m"), (IsaExt::A, "a"), (IsaExt::F, "f"), (IsaExt::D, "d"), (IsaExt::C, "c"),
+    (IsaExt::Zicsr, "zicsr"), (IsaExt::Zifencei, "zifencei"),
+    (IsaExt::Zba, "zba"), (IsaExt::Zbb, "zbb"), (IsaExt::Zbc, "zbc"), (IsaExt::Zbs, "zbs"),
+    (IsaExt::Zve64x, "zve64x"),
+];
+
+/* G is shorthand for IMAFD_Zicsr_Zifencei, B for Zba_Zbb_Zbs */
+const ISA_G: [IsaExt; 6] = [IsaExt::M, IsaExt::A, IsaExt::F, IsaExt::D, IsaExt::Zicsr, IsaExt::Zifencei];
+const ISA_B: [IsaExt; 3] = [IsaExt::Zba, IsaExt::Zbb, IsaExt::Zbs];
+
+impl IsaExt {
+    pub fn from_bitmanip(ext: BitmanipExt) -> IsaExt {
+        return match ext {
+            BitmanipExt::Zba => IsaExt::Zba,
+            BitmanipExt::Zbb => IsaExt::Zbb,
+            BitmanipExt::Zbc => IsaExt::Zbc,
+            BitmanipExt::Zbs => IsaExt::Zbs,
+        };
+    }
+
+    /* extensions that can't be there without another one, D needs F and the FP/vector state needs Zicsr */
+    fn requires(&self) -> Option<IsaExt> {
+        return match self {
+            IsaExt::D => Some(IsaExt::F),
+            IsaExt::F | IsaExt::Zve64x => Some(IsaExt::Zicsr),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsaConfig {
+    xlen: Xlen,
+    enabled: [bool; ISA_EXT_COUNT],
+}
+
+impl IsaConfig {
+    /* constructor: everything this machine implements, what a hart gets without an ISA string */
+    pub fn new(xlen: Xlen) -> IsaConfig {
+        return IsaConfig {
+            xlen: xlen,
+            enabled: [true; ISA_EXT_COUNT],
+        };
+    }
+
+    /*
+     * name: parse
+     * desc: ISA string to a configuration, case doesn't matter
+     *
+     * NOTE: anything this machine doesn't implement is an error rather than being
+     *       dropped, a program built for it would hit illegal instructions later on
+     *
+     *       extensions another one depends on are switched on with it (rv32id is rv32ifd_zicsr)
+     */
+    pub fn parse(isa: &str) -> Result<IsaConfig,String> {
+        let lower: String = isa.to_ascii_lowercase();
+        let (xlen, rest): (Xlen, &str) = if let Some(rest) = lower.strip_prefix("rv32") {
+            (Xlen::RV32, rest)
+        } else if let Some(rest) = lower.strip_prefix("rv64") {
+            (Xlen::RV64, rest)
+        } else {
+            return Err(format!("{} doesn't start with rv32 or rv64", isa));
+        };
+
+        let mut config: IsaConfig = IsaConfig {
+            xlen: xlen,
+            enabled: [false; ISA_EXT_COUNT],
+        };
+
+        match rest.chars().next() {
+            Some('i') | Some('g') => {}
+            Some('e') => return Err(String::from("the RV32E/RV64E base (16 registers) isn't supported")),
+            _ => return Err(format!("{} doesn't name a base ISA (i, e or g)", isa)),
+        }
+
+        for (idx, part) in rest.split('_').enumerate() {
+            let first: char = part.chars().next().ok_or(format!("empty extension name in {}", isa))?;
+            if idx != 0 && matches!(first, 'z' | 's' | 'h' | 'x') {
+                let name: &str = strip_version(part);
+                let ext: IsaExt = ISA_EXT_NAMES.iter().find(|(_, n)| n.len() > 1 && *n == name).map(|(ext, _)| *ext)
+                    .ok_or(format!("extension {} isn't supported", name))?;
+                config.set_enabled(ext, true);
+            }
+            else {
+                config.single_letters(part)?;
+            }
+        }
+
+        for (ext, _) in ISA_EXT_NAMES.iter().rev() {
+            if let Some(needed) = ext.requires() {
+                if config.is_enabled(*ext) {
+                    config.set_enabled(needed, true);
+                }
+            }
+        }
+        return Ok(config);
+    }
+
+    /* a run of single letter extensions, each may carry a version (2p0) */
+    fn single_letters(&mut self, part: &str) -> Result<(),String> {
+        let chars: Vec<char> = part.chars().collect();
+        let mut i: usize = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                'i' => {}
+                'g' => ISA_G.iter().for_each(|ext| self.set_enabled(*ext, true)),
+                'b' => ISA_B.iter().for_each(|ext| self.set_enabled(*ext, true)),
+                'v' => return Err(String::from("the V extension isn't supported, only its integer subset zve64x is")),
+                letter => {
+                    let ext: IsaExt = ISA_EXT_NAMES.iter().find(|(_, n)| n.len() == 1 && n.starts_with(letter)).map(|(ext, _)| *ext)
+                        .ok_or(format!("extension {} isn't supported", letter))?;
+                    self.set_enabled(ext, true);
+                }
+            }
+            i += 1;
+
+            /* major version, then an optional p and minor version */
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i + 1 < chars.len() && chars[i] == 'p' && chars[i - 1].is_ascii_digit() && chars[i + 1].is_ascii_digit() {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn xlen(&self) -> Xlen {
+        return self.xlen;
+    }
+
+    pub fn is_enabled(&self, ext: IsaExt) -> bool {
+        return self.enabled[ext as usize];
+    }
+
+    pub fn set_enabled(&mut self, ext: IsaExt, enabled: bool) {
+        self.enabled[ext as usize] = enabled;
+    }
+
+    /* canonical ISA string, rv32imac_zicsr_zifencei */
+    pub fn name(&self) -> String {
+        let mut name: String = format!("rv{}i", self.xlen.bits());
+        for (ext, ext_name) in ISA_EXT_NAMES.iter() {
+            if self.is_enabled(*ext) {
+                if ext_name.len() > 1 {
+                    name.push('_');
+                }
+                name.push_str(ext_name);
+            }
+        }
+        return name;
+    }
+
+    /*
+     * name: misa
+     * desc: reset value of misa, MXL plus the single letter extensions
+     *
+     * NOTE: S and U are always there since the privilege modes don't depend on the
+     *       ISA string, Zve64x has no bit of its own (V means the full extension)
+     */
+    pub fn misa(&self) -> u64 {
+        let mut misa: u64 = misa_mxl(self.xlen) | misa_ext('I') | misa_ext('S') | misa_ext('U');
+        for (ext, letter) in [(IsaExt::M, 'M'), (IsaExt::A, 'A'), (IsaExt::F, 'F'), (IsaExt::D, 'D'), (IsaExt::C, 'C')] {
+            if self.is_enabled(ext) {
+                misa |= misa_ext(letter);
+            }
+        }
+        return misa;
+    }
+
+    /* is the (already expanded) instruction part of an enabled extension, the base ISA always is */
+    pub fn allows(&self, inst: &Instruction) -> bool {
+        use Instruction::*;
+
+        let ext: IsaExt = match inst {
+            Mul { .. } | Mulh { .. } | Mulhsu { .. } | Mulhu { .. } | Div { .. } | Divu { .. } | Rem { .. } | Remu { .. } |
+            Mulw { .. } | Divw { .. } | Divuw { .. } | Remw { .. } | Remuw { .. } => IsaExt::M,
+
+            Lr { .. } | Sc { .. } | Amo { .. } => IsaExt::A,
+
+            Csrrw { .. } | Csrrs { .. } | Csrrc { .. } | Csrrwi { .. } | Csrrsi { .. } | Csrrci { .. } => IsaExt::Zicsr,
+
+            FenceI => IsaExt::Zifencei,
+
+            /* the double precision forms need F as well, D can't be enabled without it */
+            Float(fp) => if fp.is_double() { IsaExt::D } else { IsaExt::F },
+
+            Vector(_) => IsaExt::Zve64x,
+
+            Bitmanip(bm) => IsaExt::from_bitmanip(bm.extension()),
+
+            _ => return true,
+        };
+        return self.is_enabled(ext);
+    }
+}
+
+/* zicsr2p0 -> zicsr */
+fn strip_version(name: &str) -> &str {
+    let major: &str = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if major.len() == name.len() {
+        return name;
+    }
+    return match major.strip_suffix('p') {
+        Some(minor) if minor.ends_with(|c: char| c.is_ascii_digit()) => minor.trim_end_matches(|c: char| c.is_ascii_digit()),
+        _ => major,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::*;
+    use crate::machine::*;
+    use crate::memory::*;
+
+    fn parse(isa: &str) -> IsaConfig {
+        return IsaConfig::parse(isa).unwrap_or_else(|e| panic!("{}: {}", isa, e));
+    }
+
+    fn enabled(isa: &IsaConfig) -> Vec<IsaExt> {
+        return ISA_EXT_NAMES.iter().map(|(ext, _)| *ext).filter(|ext| isa.is_enabled(*ext)).collect();
+    }
+
+    #[test]
+    fn versions_and_case() {
+        assert_eq!(parse("rv32i2p1_m2p0_zicsr2p0"), parse("rv32im_zicsr"));
+        assert_eq!(parse("rv32i2m2a_zifencei2"), parse("rv32ima_zifencei"));
+        assert_eq!(parse("RV64IMAC_Zicsr"), parse("rv64imac_zicsr"));
+        assert_eq!(parse("rv64imac_zicsr").name(), "rv64imac_zicsr");
+        assert_eq!(parse("rv32i").xlen(), Xlen::RV32);
+        assert_eq!(parse("rv64i").xlen(), Xlen::RV64);
+    }
+
+    #[test]
+    fn shorthands() {
+        use IsaExt::*;
+
+        assert_eq!(enabled(&parse("rv64g")), vec![M, A, F, D, Zicsr, Zifencei]);
+        assert_eq!(enabled(&parse("rv32ib")), vec![Zba, Zbb, Zbs]);
+        assert_eq!(parse("rv64gc").name(), "rv64imafdc_zicsr_zifencei");
+        assert_eq!(parse("rv64gcb_zbc_zve64x"), IsaConfig::new(Xlen::RV64));
+    }
+
+    /* what an extension can't do without is switched on with it */
+    #[test]
+    fn dependencies() {
+        use IsaExt::*;
+
+        assert_eq!(enabled(&parse("rv32id")), vec![F, D, Zicsr]);
+        assert_eq!(enabled(&parse("rv32if")), vec![F, Zicsr]);
+        assert_eq!(enabled(&parse("rv64i_zve64x")), vec![Zicsr, Zve64x]);
+        assert_eq!(enabled(&parse("rv32ic")), vec![C]);
+    }
+
+    #[test]
+    fn errors() {
+        for (isa, msg) in [
+            ("rv128i", "doesn't start with rv32 or rv64"),
+            ("rv32", "doesn't name a base ISA"),
+            ("rv32m", "doesn't name a base ISA"),
+            ("rv32e", "RV32E/RV64E"),
+            ("rv64gcv", "zve64x"),
+            ("rv32iq", "extension q isn't supported"),
+            ("rv32i_zfh", "extension zfh isn't supported"),
+            ("rv32i__zicsr", "empty extension name"),
+        ] {
+            let err: String = IsaConfig::parse(isa).unwrap_err();
+            assert!(err.contains(msg), "{}: {}", isa, err);
+        }
+    }
+
+    #[test]
+    fn misa_letters() {
+        let misa: u64 = parse("rv64gc_zve64x").misa();
+        assert_eq!(misa, misa_mxl(Xlen::RV64) | "IMAFDCSU".chars().map(misa_ext).fold(0, |acc, bit| acc | bit));
+        assert_eq!(parse("rv32i").misa(), misa_mxl(Xlen::RV32) | misa_ext('I') | misa_ext('S') | misa_ext('U'));
+    }
+
+    /* csrr without Zicsr and no handler halts on the instruction instead of restarting at 0 forever */
+    #[test]
+    fn unhandled_illegal_instruction_halts() {
+        let mut mem: Memory = Memory::new();
+        assemble_into(&mut mem, "nop\ncsrr a0, mstatus\nj .").unwrap_or_else(|e| panic!("line {}: {}", e.line, e.msg));
+
+        let mut machine: Machine = Machine::new(mem, &parse("rv32i"), 1);
+        machine.run();
+        assert_eq!(machine.get_harts()[0].get_pc(), 4);
+        assert_eq!(machine.get_harts()[0].get_inst_count(), 1);
+    }
+
+    /* same for any other exception, a load from outside memory doesn't restart the program */
+    #[test]
+    fn unhandled_exception_halts() {
+        let mut mem: Memory = Memory::new();
+        assemble_into(&mut mem, "lui a0, 0x10\nlw a1, 0(a0)\nj .").unwrap_or_else(|e| panic!("line {}: {}", e.line, e.msg));
+
+        let mut machine: Machine = Machine::new(mem, &parse("rv32i"), 1);
+        machine.run();
+        assert_eq!(machine.get_harts()[0].get_pc(), 4);
+        assert_eq!(machine.get_harts()[0].get_inst_count(), 1);
+    }
+}
@@ -1,4 +0,0 @@
-
-pub fn print_log( msg: String){
-    println!("{}",msg);
-}
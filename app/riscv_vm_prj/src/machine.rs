@@ -0,0 +1,175 @@
+/*
+ * name: machine.rs
+ * desc: a system of one or more harts sharing a single memory and its peripherals
+ *
+ * Note: by default the harts take turns on the calling thread, each one runs a
+ *       quantum of steps before handing over to the next in mhartid order, so a
+ *       run is exactly reproducible, in real thread mode every hart gets an OS
+ *       thread of its own and they interleave however the host schedules them
+ *
+ *       every hart starts at address 0, software tells them apart by mhartid, they
+ *       interrupt each other through the CLINT (clint.rs) msip registers
+ *
+ *       the machine halts once every one of its harts has
+ *
+ */
+
+use crate::memory::*;
+use crate::cpu::*;
+use crate::isa::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/* steps a hart runs before the next one gets its turn */
+pub const DEFAULT_QUANTUM: u64 = 100;
+
+#[derive(Debug)]
+pub struct Machine {
+    harts: Vec<Cpu>,
+    mem: SharedMemory,
+    quantum: u64,
+}
+
+impl Machine {
+    /* constructor: num_harts harts (at least one) with mhartid 0..num_harts, all sharing mem */
+    pub fn new(mem: Memory, isa: &IsaConfig, num_harts: usize) -> Machine {
+        let mem: SharedMemory = Arc::new(Mutex::new(mem));
+        let mut harts: Vec<Cpu> = Vec::new();
+        for hartid in 0..num_harts.max(1) {
+            harts.push(Cpu::new(Arc::clone(&mem), isa, hartid));
+        }
+
+        return Machine {
+            harts: harts,
+            mem: mem,
+            quantum: DEFAULT_QUANTUM,
+        };
+    }
+
+    /* a quantum of 1 interleaves the harts instruction by instruction */
+    pub fn set_quantum(&mut self, quantum: u64) {
+        self.quantum = quantum.max(1);
+    }
+
+    pub fn get_harts(&self) -> &[Cpu] {
+        return &self.harts;
+    }
+
+    pub fn get_harts_mut(&mut self) -> &mut [Cpu] {
+        return &mut self.harts;
+    }
+
+    /*
+     * name: run
+     * desc: deterministic round robin until every hart has halted
+     *
+     * NOTE: a hart parked by WFI gives up the rest of its quantum, once every hart
+     *       still running is parked mtime skips ahead to the next timer, with none
+     *       armed the host thread sleeps instead of spinning
+     */
+    pub fn run(&mut self) {
+        loop {
+            let mut running: bool = false;
+            let mut all_waiting: bool = true;
+
+            for hart in self.harts.iter_mut() {
+                for _ in 0..self.quantum {
+                    hart.step();
+                    if hart.is_halted() || hart.is_waiting() {
+                        break;
+                    }
+                }
+
+                if !hart.is_halted() {
+                    running = true;
+                    all_waiting &= hart.is_waiting();
+                }
+            }
+
+            if !running {
+                break;
+            }
+            /* nothing but a timer or the uart can wake them, only the uart needs real time */
+            if all_waiting && !self.mem.lock().unwrap().skip_to_next_timer() {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        /* make sure everything the program printed actually made it out */
+        self.mem.lock().unwrap().flush_peripherals();
+    }
+
+    /* one OS thread per hart, returns once all of them have halted */
+    pub fn run_threaded(&mut self) {
+        thread::scope(|scope| {
+            for hart in self.harts.iter_mut() {
+                scope.spawn(move || hart.run());
+            }
+        });
+    }
+}
+
+/* tests: assemble src into a memory of its own and run it on num_harts harts until all of them halt */
+#[cfg(test)]
+pub fn run_source(src: &str, isa: &IsaConfig, num_harts: usize) -> Machine {
+    let mut mem: Memory = Memory::new();
+    crate::asm::assemble_into(&mut mem, src).unwrap_or_else(|e| panic!("line {}: {}", e.line, e.msg));
+
+    let mut machine: Machine = Machine::new(mem, isa, num_harts);
+    machine.run();
+    return machine;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xlen::*;
+    use crate::encoder::*;
+
+    /* the usual secondary hart parking, hart 0 releases hart 1 through its msip */
+    #[test]
+    fn msip_wakes_a_parked_hart() {
+        let src: &str = "
+                csrr t0, mhartid
+                bnez t0, secondary
+                li t1, 0x2000004     # msip of hart 1
+                li t2, 1
+                sw t2, 0(t1)
+                li a0, 1
+                j .
+            secondary:
+                csrsi mie, 8         # MSIE
+                wfi
+                li t1, 0x2000004
+                sw zero, 0(t1)
+                csrci mie, 8
+                li a0, 2
+                j .
+        ";
+        let machine: Machine = run_source(src, &IsaConfig::new(Xlen::RV32), 2);
+        assert_eq!(machine.get_harts()[0].get_reg(Reg::A0 as u8), 1);
+        assert_eq!(machine.get_harts()[1].get_reg(Reg::A0 as u8), 2);
+    }
+
+    /* a timer far in the future is skipped to once the hart waits for it */
+    #[test]
+    fn mtimecmp_raises_mtip() {
+        let src: &str = "
+                li t1, 0x2004000     # mtimecmp of hart 0
+                li t2, 1000000
+                sw t2, 0(t1)
+                sw zero, 4(t1)
+                li t0, 0x80          # MTIE
+                csrs mie, t0
+                wfi
+                csrc mie, t0
+                rdtime a0
+                csrr a1, mip
+                j .
+        ";
+        let machine: Machine = run_source(src, &IsaConfig::new(Xlen::RV32), 1);
+        assert!(machine.get_harts()[0].get_reg(Reg::A0 as u8) >= 1000000);
+        assert_eq!(machine.get_harts()[0].get_reg(Reg::A1 as u8) & 0x80, 0x80);
+    }
+}
@@ -1,104 +1,287 @@
+/* the code base follows C style naming for register/flag enums and keeps explicit returns */
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(clippy::needless_return)]
+#![allow(clippy::redundant_field_names)]
+
+use clap::{Parser, Subcommand};
+
 mod memory;
 use memory::*;
 
 mod vuart;
-use vuart:: *;
+
+mod clint;
 
 mod cpu;
-use cpu::*;
+
+mod machine;
+use machine::*;
 
 mod idecoder;
-use idecoder::*;
 
-mod logging;
-use logging::*;
+mod csr;
 
-const RX_ADDR: u64   = 0x7000000;
-const TX_ADDR: u64   = 0x7000001;
-const FLAG_ADDR: u64 = 0x7000002;
+mod trap;
 
-fn dump_test() {
-    let mut memory: Memory = Memory::new();
-    match memory.load_from_text("print_array.c.hex"){
-        Ok(_) => println!("Mission Accomplished"),
-        Err(_) => println!("Done goofed"),
-    }
-    memory.debug_print_mem_dump();
+mod rvc;
 
-    let mut memory: Memory = Memory::new();
-    match memory.load_from_bin("print_array.c.bin"){
-        Ok(_) => println!("Mission Accomplished"),
-        Err(_) => println!("Done goofed"),
-    }
-    memory.debug_print_mem_dump();   
-}
+mod xlen;
+use xlen::*;
 
-fn read_write_test() {
-    let mut memory: Memory = Memory::new();
-    match memory.load_from_text("print_array.c.hex"){
-        Ok(_) => println!("Mission Accomplished"),
-        Err(_) => println!("Done goofed"),
-    }
+mod fpu;
 
-    println!("Memory Size = {}",memory.get_size());
+mod softfloat;
 
-    let mut tval32 : u32 = memory.read_32bit(0);
-    println!("read_32bit : val={:08x}",tval32);
-    let mut tval8 : u8;
-    for i in 0..4{
-        tval8 = memory.read_8bit(i);
-        println!("read_8bit: i={} val={:02x}",i,tval8);
-    }
+mod bitmanip;
 
-    memory.write_32bit(0, 0x25252525);
-    tval32 = memory.read_32bit(0);
-    println!("read_32bit : val={:08x}",tval32); 
+mod isa;
+use isa::*;
 
-    for i in 0..4{
-        memory.write_8bit(i,0x37);
-    }
+mod mmu;
+mod pmp;
+mod hpm;
 
-    for i in 0..4{
-        tval8 = memory.read_8bit(i);
-        println!("read_8bit: i={} val={:02x}",i,tval8);
-    }    
+mod vector;
+use vector::*;
 
-    memory.write_8bit(2,0x20);
-    memory.write_8bit(3,0x40);
+mod disasm;
+use disasm::*;
 
-    tval32 = memory.read_32bit(0);
-    println!("read_32bit : val={:08x}",tval32); 
+mod elf;
+use elf::*;
 
-    memory.make_big_endian();
+mod asm;
+use asm::*;
 
-    tval32 = memory.read_32bit(0);
-    println!("read_32bit : val={:08x}",tval32); 
+mod encoder;
+
+/* how many harts to build and how to schedule them */
+struct SmpConfig {
+    harts: usize,
+    quantum: u64,
+    threaded: bool,
 }
 
-fn uart_test() {
+/* assembly source, reports the first error the assembler runs into */
+fn load_from_asm(memory: &mut Memory, filename: &str) -> Result<(),()> {
+    let src: String = std::fs::read_to_string(filename).map_err(|_| ())?;
+    return assemble_into(memory, &src).map_err(|e| println!("{}:{}: {}",filename,e.line,e.msg));
+}
 
+/* load a .hex text file, a raw .bin or a .s assembly file and run it until every hart halts */
+fn run_program(filename: &str, isa: &IsaConfig, vlen: usize, smp: &SmpConfig) {
     let mut memory: Memory = Memory::new();
-    match memory.load_from_text("print_array.c.hex"){
-        Ok(_) => println!("Mission Accomplished"),
+    let res = if filename.ends_with(".bin") {
+        memory.load_from_bin(filename)
+    } else if filename.ends_with(".s") || filename.ends_with(".S") {
+        load_from_asm(&mut memory, filename)
+    } else {
+        memory.load_from_text(filename)
+    };
+    if res.is_err() {
+        println!("Error: could not load {}",filename);
+        return;
+    }
+
+    let mut machine: Machine = Machine::new(memory, isa, smp.harts);
+    machine.set_quantum(smp.quantum);
+    for cpu in machine.get_harts_mut() {
+        cpu.set_vlen(vlen);
+    }
+    if smp.threaded {
+        machine.run_threaded();
+    }
+    else {
+        machine.run();
+    }
+
+    /* single hart output stays the way it always was */
+    let harts: &[cpu::Cpu] = machine.get_harts();
+    for (hartid, cpu) in harts.iter().enumerate() {
+        let prefix: String = if harts.len() > 1 { format!("hart {}: ",hartid) } else { String::new() };
+        println!("{}Halted at pc={:08x} after {} instructions",prefix,cpu.get_pc(),cpu.get_inst_count());
+
+        /* only interesting once something ran with translation on */
+        let tlb: mmu::TlbStats = cpu.get_tlb_stats();
+        if tlb.hits + tlb.misses != 0 {
+            println!("{}TLB: {} hits, {} misses, {} flushes",prefix,tlb.hits,tlb.misses,tlb.flushes);
+        }
+    }
+}
+
+/*
+ * name: disasm_program
+ * desc: print the disassembly of an ELF object/executable or of a raw .hex/.bin image
+ *
+ * NOTE: an ELF file brings its own XLEN, section addresses and symbols, a raw
+ *       image is disassembled from address 0 like it gets loaded
+ *
+ *       with an ISA string anything the core doesn't implement is printed as
+ *       .2byte/.4byte, which makes it easy to spot in firmware built for a smaller core
+ */
+fn disasm_program(filename: &str, xlen: Xlen, isa: Option<&IsaConfig>, aliases: bool) {
+    let bytes: Vec<u8> = match std::fs::read(filename) {
+        Ok(b) => b,
         Err(_) => {
-            println!("Done goofed"); 
+            println!("Error: could not load {}",filename);
             return;
         }
+    };
+
+    if ElfImage::is_elf(&bytes) {
+        let image: ElfImage = match ElfImage::parse(&bytes) {
+            Ok(image) => image,
+            Err(_) => {
+                println!("Error: {} is not a little endian RISC-V ELF file",filename);
+                return;
+            }
+        };
+        let mut symbols: SymbolTable = SymbolTable::new();
+        for sym in image.symbols.iter() {
+            symbols.add(sym.addr, &sym.name);
+        }
+        if let Some(isa) = isa {
+            if isa.xlen() != image.xlen {
+                println!("Error: {} is an RV{} file, {} is not",filename,image.xlen.bits(),isa.name());
+                return;
+            }
+        }
+        let mut disasm: Disassembler = Disassembler::new(image.xlen, aliases, symbols);
+        if let Some(isa) = isa {
+            disasm = disasm.with_isa(isa);
+        }
+        for section in image.sections.iter() {
+            println!();
+            println!("Disassembly of section {}:",section.name);
+            println!();
+            for line in disasm.disassemble_region(&section.data, section.addr) {
+                println!("{}",line);
+            }
+        }
+        return;
     }
-    println!("Memory Size = {}",memory.get_size());
 
+    /* same formats load_from_text/load_from_bin take, read here so no uart gets started */
+    let image: Vec<u8> = if filename.ends_with(".bin") {
+        bytes
+    } else {
+        let mut image: Vec<u8> = Vec::new();
+        for line in String::from_utf8_lossy(&bytes).lines() {
+            match u32::from_str_radix(line, 16) {
+                Ok(word) => image.extend_from_slice(&word.to_le_bytes()),
+                Err(_) => {
+                    println!("Error: Encountered non-hex number in {}",filename);
+                    return;
+                }
+            }
+        }
+        image
+    };
+    let mut disasm: Disassembler = Disassembler::new(xlen, aliases, SymbolTable::new());
+    if let Some(isa) = isa {
+        disasm = disasm.with_isa(isa);
+    }
+    for line in disasm.disassemble_region(&image, 0) {
+        println!("{}",line);
+    }
+}
 
-    loop {
-        while( (memory.read_8bit(FLAG_ADDR) ) == 0 ){}
-        let data: u8 = memory.read_8bit(RX_ADDR);
-        memory.write_8bit(TX_ADDR,data);
-        memory.write_8bit(TX_ADDR,'\n' as u8);
-    }  
+#[derive(Subcommand, Debug)]
+enum Command {
+    /* print the instructions in a .hex/.bin image or an ELF file instead of running them */
+    Disasm {
+        file: String,
 
+        /* raw images only, ELF files say what they are */
+        #[clap(long)]
+        rv64: bool,
+
+        /* ISA string of the target core, e.g. rv32ic, other instructions print as data */
+        #[clap(long, conflicts_with = "rv64")]
+        isa: Option<String>,
+
+        /* base instructions only, no pseudo instructions (objdump -M no-aliases) */
+        #[clap(long)]
+        no_aliases: bool,
+    },
 }
-    // dump_test();
-    // read_write_test();
+
+#[derive(Parser, Debug)]
+#[clap(about = "RISC-V virtual machine", args_conflicts_with_subcommands = true)]
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /* program image, .hex (one 32 bit word per line), .bin or .s (RV32 assembly) */
+    #[clap(default_value = "print_array.c.hex")]
+    program: String,
+
+    /* run as an RV64I hart instead of RV32I */
+    #[clap(long)]
+    rv64: bool,
+
+    /* ISA string like rv32imac_zicsr_zifencei_zba_zbb, everything is implemented without one */
+    #[clap(long, conflicts_with = "rv64", help = "ISA string like rv32imac_zicsr_zifencei_zba_zbb (default: everything \
+        implemented), instructions from other extensions are illegal and without a trap handler the hart reports \
+        the first one and halts")]
+    isa: Option<String>,
+
+    /* bits per vector register, a power of two from 64 to 65536 */
+    #[clap(long, default_value_t = VLEN_DEFAULT)]
+    vlen: usize,
+
+    /* number of harts sharing the memory, mhartid 0..harts */
+    #[clap(long, default_value_t = 1)]
+    harts: usize,
+
+    /* instructions each hart runs before the next one gets a turn */
+    #[clap(long, default_value_t = DEFAULT_QUANTUM)]
+    quantum: u64,
+
+    /* give every hart its own host thread instead of the deterministic round robin */
+    #[clap(long)]
+    threads: bool,
+}
+
 fn main() {
+    let args = Args::parse();
 
-    uart_test();
+    if let Some(Command::Disasm { file, rv64, isa, no_aliases }) = &args.command {
+        let isa: Option<IsaConfig> = match isa.as_deref().map(IsaConfig::parse).transpose() {
+            Ok(isa) => isa,
+            Err(e) => {
+                println!("Error: {}",e);
+                return;
+            }
+        };
+        let xlen: Xlen = match &isa {
+            Some(isa) => isa.xlen(),
+            None if *rv64 => Xlen::RV64,
+            None => Xlen::RV32,
+        };
+        disasm_program(file, xlen, isa.as_ref(), !*no_aliases);
+        return;
+    }
+
+    let isa: IsaConfig = match &args.isa {
+        Some(name) => match IsaConfig::parse(name) {
+            Ok(isa) => isa,
+            Err(e) => {
+                println!("Error: {}",e);
+                return;
+            }
+        },
+        None => IsaConfig::new(if args.rv64 { Xlen::RV64 } else { Xlen::RV32 }),
+    };
+    if !is_valid_vlen(args.vlen) {
+        println!("Error: --vlen must be a power of two between {} and {}",VLEN_MIN,VLEN_MAX);
+        return;
+    }
+    let smp: SmpConfig = SmpConfig {
+        harts: args.harts,
+        quantum: args.quantum,
+        threaded: args.threads,
+    };
+    run_program(&args.program, &isa, args.vlen, &smp);
 }
@@ -13,19 +13,26 @@ use std::{
     io::{Read, BufRead, BufReader},
 };
 use std::fs;
+use std::sync::{Arc, Mutex};
 use crate::vuart::*;
+use crate::clint::*;
 
 #[derive(Debug)]
 pub struct Memory {
     filename: String,
     mem: Vec<u8>, /* byte vector */
-    size: u64,    /* size of memory, grabbed from vector */
     is_little_endian: bool, /* default = true */
     uart: Uart,
+    clint: Clint, /* msip/mtimecmp/mtime of every hart */
+    reservations: Vec<Option<u64>>, /* per hart granule reserved by LR.W/LR.D, cleared by SC or any write to it */
 }
 
+/* one memory image (and its peripherals) shared by every hart of the machine */
+pub type SharedMemory = Arc<Mutex<Memory>>;
+
 /* set to some giant address */
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 enum PeripheralMap {
     UART_FIFO_RX = 0x7000000,    /* read only  */
     UART_FIFO_TX,                /* write only */
@@ -33,6 +40,9 @@ enum PeripheralMap {
     INVALID,                     /* failure    */
 }
 
+/* LR/SC reservations cover an aligned doubleword so LR.D and LR.W share one mechanism */
+const RESERVATION_GRANULE_MASK: u64 = 0x7;
+
 impl PeripheralMap {
     pub const BASE: PeripheralMap  = PeripheralMap::UART_FIFO_RX; /* base address, same as first peripheral */
 }
@@ -44,26 +54,19 @@ impl Memory {
         return Memory {
             filename: String::new(),
             mem: Vec::new(),
-            size: 0,
             is_little_endian: true,
             uart: Uart::new(),
+            clint: Clint::new(),
+            reservations: Vec::new(),
         };
     }
 
-    pub fn get_size(&mut self) -> u64 {
-        return self.size;
-    }
-
-    pub fn make_little_endian(&mut self) {
-        self.is_little_endian = true;
-    }
-
-    pub fn make_big_endian(&mut self){
-        self.is_little_endian = false;
-    }
-    
     /* check if provided addr is a peripheral mapping */
     pub fn check_peripheral(&mut self, addr: u64 ) -> bool {
+        if Clint::contains(addr) {
+            return true;
+        }
+
         if addr >= PeripheralMap::INVALID as u64{
             return false;
         }
@@ -80,6 +83,7 @@ impl Memory {
             addr if addr == PeripheralMap::UART_FIFO_RX as u64 => self.uart.cpu_read_rx_fifo(),
             addr if addr == PeripheralMap::UART_FIFO_TX as u64 => 0,
             addr if addr == PeripheralMap::UART_FLAGS as u64 => self.uart.cpu_get_flags(),
+            addr if Clint::contains(addr) => self.clint.read(addr - CLINT_BASE),
             _ => return 0,
         }
     }
@@ -89,10 +93,62 @@ impl Memory {
             addr if addr == PeripheralMap::UART_FIFO_RX as u64 => return,
             addr if addr == PeripheralMap::UART_FIFO_TX as u64 => self.uart.cpu_write_tx_fifo(data), 
             addr if addr == PeripheralMap::UART_FLAGS as u64 => return,
+            addr if Clint::contains(addr) => self.clint.write(addr - CLINT_BASE, data),
             _ => return,
         }
     }
 
+    /* external interrupt line, the uart raises it when received data is waiting */
+    pub fn external_interrupt_pending(&mut self) -> bool {
+        return self.uart.cpu_rx_pending();
+    }
+
+    /* software and timer interrupt lines of a hart, from its msip and mtimecmp */
+    pub fn software_interrupt_pending(&mut self, hart: usize) -> bool {
+        return self.clint.software_interrupt_pending(hart);
+    }
+
+    pub fn timer_interrupt_pending(&mut self, hart: usize) -> bool {
+        return self.clint.timer_interrupt_pending(hart);
+    }
+
+    /* mtime goes up once for every step a hart takes */
+    pub fn tick_timer(&mut self) {
+        self.clint.tick();
+    }
+
+    pub fn get_mtime(&mut self) -> u64 {
+        return self.clint.get_mtime();
+    }
+
+    /* every hart is idle, jump mtime ahead to the next timer rather than waiting for it */
+    pub fn skip_to_next_timer(&mut self) -> bool {
+        return self.clint.skip_to_next_timer();
+    }
+
+    /* block until peripherals have pushed out any pending output */
+    pub fn flush_peripherals(&mut self) {
+        self.uart.cpu_flush_tx_fifo();
+    }
+
+    /* LR: register a reservation for the hart on the doubleword granule containing addr */
+    pub fn reserve(&mut self, hart: usize, addr: u64) {
+        if hart >= self.reservations.len() {
+            self.reservations.resize(hart + 1, None);
+        }
+        self.reservations[hart] = Some(addr & !RESERVATION_GRANULE_MASK);
+    }
+
+    /* SC: returns true if the hart's reservation on addr is still valid, always clears it */
+    pub fn check_reservation(&mut self, hart: usize, addr: u64) -> bool {
+        let mut valid: bool = false;
+        if let Some(reservation) = self.reservations.get_mut(hart) {
+            valid = *reservation == Some(addr & !RESERVATION_GRANULE_MASK);
+            *reservation = None;
+        }
+        return valid;
+    }
+
     /*
      * name: conv32to8 
      * desc: converts a 32 bit number to a vector of 8 bits
@@ -118,13 +174,13 @@ impl Memory {
     pub fn conv8to32(&mut self, x: Vec<u8>) -> u32 {
         let mut res: u32 = 0;
 
-        for i in 0..4 {
+        for (i, byte) in x.iter().enumerate().take(4) {
             if self.is_little_endian {
-                let temp: u32 = x[i] as u32;
+                let temp: u32 = *byte as u32;
                 res |= temp << (i*8);
             }
             else {
-                let temp: u32 = (x[i] as u32) << 24;
+                let temp: u32 = (*byte as u32) << 24;
                 res |= temp >> (i*8);         
             }
         }
@@ -170,8 +226,6 @@ impl Memory {
             }
         }
 
-        //if this panics, I'll cry
-        self.size = self.mem.len().try_into().unwrap();
         println!("successfully loaded {}",self.filename);
         return Ok(());
     }
@@ -192,13 +246,10 @@ impl Memory {
             Err(_) => return Err(()),
         };
 
-        let metadata = fs::metadata(&infile).expect("Unable to read metadata");
+        let metadata = fs::metadata(infile).expect("Unable to read metadata");
         self.mem = vec![0;metadata.len() as usize];
         match file.read_exact(&mut self.mem){
-            Ok(_) => {
-                self.size = self.mem.len().try_into().unwrap();
-                return Ok(());
-            }
+            Ok(_) => return Ok(()),
             Err(_) => {
                 println!("Error Reading Binary File");
                 return Err(());
@@ -206,63 +257,161 @@ impl Memory {
         }
     }
     
+    /* an image that was built in memory (see asm.rs), same as loading it from a .bin */
+    pub fn load_from_bytes(&mut self, bytes: &[u8]) {
+        self.mem = bytes.to_vec();
+    }
+
+    /*
+     * name: check_access
+     * desc: every byte in [addr, addr+size) has to be backed by ram or a peripheral,
+     *       checked up front so a failing access never partially completes
+     */
+    pub fn check_access(&mut self, addr: u64, size: u64) -> Result<(),()> {
+        for i in 0..size {
+            let byte_addr: u64 = addr.wrapping_add(i);
+            if byte_addr >= self.mem.len() as u64 && !self.check_peripheral(byte_addr) {
+                return Err(());
+            }
+        }
+        return Ok(());
+    }
+
     /* accept address pointing to 8 bit value */
-    pub fn read_8bit(&mut self, addr: u64) -> u8 {
+    pub fn read_8bit(&mut self, addr: u64) -> Result<u8,()> {
         if self.check_peripheral(addr){
-            return self.peripheral_read(addr);
+            return Ok(self.peripheral_read(addr));
         }
 
-        let addr: usize = addr.try_into().unwrap();
-        return self.mem[addr];
+        self.check_access(addr, 1)?;
+        return Ok(self.mem[addr as usize]);
+    }
+
+    /* accept address pointer to 8 bit value */
+    pub fn read_16bit(&mut self, addr: u64) -> Result<u16,()> {
+        self.check_access(addr, 2)?;
+        let lo: u16 = self.read_8bit(addr)? as u16;
+        let hi: u16 = self.read_8bit(addr + 1)? as u16;
+
+        if self.is_little_endian {
+            return Ok(lo | (hi << 8));
+        }
+        return Ok((lo << 8) | hi);
     }
 
     /* accept address pointer to 8 bit value */
-    pub fn read_32bit(&mut self, addr: u64) -> u32 {
-        // also convert to 8 bit address
-        let addr: usize = addr as usize;
+    pub fn read_32bit(&mut self, addr: u64) -> Result<u32,()> {
+        self.check_access(addr, 4)?;
         let mut slice: Vec<u8> = Vec::new();
 
         for i in 0..4{
-            slice.push(self.read_8bit((addr+i) as u64));
+            slice.push(self.read_8bit(addr + i)?);
         }
 
         let res: u32 = self.conv8to32(slice);
-        return res; 
+        return Ok(res);
+    }
+
+    /* accept address pointer to 8 bit value, built from two words */
+    pub fn read_64bit(&mut self, addr: u64) -> Result<u64,()> {
+        self.check_access(addr, 8)?;
+        let lo: u64 = self.read_32bit(addr)? as u64;
+        let hi: u64 = self.read_32bit(addr + 4)? as u64;
+
+        if self.is_little_endian {
+            return Ok(lo | (hi << 32));
+        }
+        return Ok((lo << 32) | hi);
     }
 
     /* accept address pointing to 8 bit value */
-    pub fn write_8bit(&mut self, addr: u64, data: u8) {
+    pub fn write_8bit(&mut self, addr: u64, data: u8) -> Result<(),()> {
+        self.check_access(addr, 1)?;
+
+        /* any write into a reserved granule, by any hart, breaks that LR/SC pair */
+        for reservation in self.reservations.iter_mut() {
+            if *reservation == Some(addr & !RESERVATION_GRANULE_MASK) {
+                *reservation = None;
+            }
+        }
+
         if self.check_peripheral(addr){
-            return self.peripheral_write(addr,data);
+            self.peripheral_write(addr,data);
+            return Ok(());
         }
-        let addr: usize = addr.try_into().unwrap();
-        self.mem[addr] = data;
+        self.mem[addr as usize] = data;
+        return Ok(());
     }
     
     /* accept address pointer to 8 bit value */
-    pub fn write_32bit(&mut self, addr: u64, data: u32) {
-        /* convert to 8 bit address */
-        let mut addr: usize = addr as usize;
+    pub fn write_16bit(&mut self, addr: u64, data: u16) -> Result<(),()> {
+        self.check_access(addr, 2)?;
+        let lo: u8 = (data & 0xFF) as u8;
+        let hi: u8 = (data >> 8) as u8;
+
+        if self.is_little_endian {
+            self.write_8bit(addr, lo)?;
+            self.write_8bit(addr + 1, hi)?;
+        }
+        else {
+            self.write_8bit(addr, hi)?;
+            self.write_8bit(addr + 1, lo)?;
+        }
+        return Ok(());
+    }
+
+    /* accept address pointer to 8 bit value */
+    pub fn write_32bit(&mut self, addr: u64, data: u32) -> Result<(),()> {
+        self.check_access(addr, 4)?;
         let bytes:Vec<u8> = self.conv32to8(data);
-        for byte in bytes {
-            self.write_8bit(addr as u64,byte);
-            addr+=1;
+        for (i, byte) in bytes.into_iter().enumerate() {
+            self.write_8bit(addr + i as u64,byte)?;
         }
+        return Ok(());
     }
 
-    // /* dump memory contents to file */
-    // pub fn debug_mem_dump(&mut self, outfile: &String) {
-    //     println!("Dumping to {}",outfile);
-    //     for addr in &self.mem {
-    //         println!("{:02x}",addr);
-    //     }
-    // }
-
-    /* print current memory to outfile for debug purpose */
-    pub fn debug_print_mem_dump(&mut self) {
-        let num_words: u64 = self.get_size()/4;
-        for i in 0..num_words{
-            println!("{:08x}",self.read_32bit(i));
+    /* accept address pointer to 8 bit value, split into two words */
+    pub fn write_64bit(&mut self, addr: u64, data: u64) -> Result<(),()> {
+        self.check_access(addr, 8)?;
+        let lo: u32 = data as u32;
+        let hi: u32 = (data >> 32) as u32;
+
+        if self.is_little_endian {
+            self.write_32bit(addr, lo)?;
+            self.write_32bit(addr + 4, hi)?;
+        }
+        else {
+            self.write_32bit(addr, hi)?;
+            self.write_32bit(addr + 4, lo)?;
         }
+        return Ok(());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* a store by another hart anywhere in the doubleword granule breaks the reservation */
+    #[test]
+    fn reservation_lost_to_another_hart() {
+        let mut mem: Memory = Memory::new();
+        mem.load_from_bytes(&[0; 32]);
+
+        mem.reserve(0, 0x10);
+        mem.write_32bit(0x8, 1).unwrap();
+        assert!(mem.check_reservation(0, 0x10));
+        /* SC clears it whatever the outcome */
+        assert!(!mem.check_reservation(0, 0x10));
+
+        mem.reserve(0, 0x10);
+        mem.reserve(1, 0x18);
+        mem.write_8bit(0x17, 1).unwrap();
+        assert!(!mem.check_reservation(0, 0x10));
+        assert!(mem.check_reservation(1, 0x18));
+
+        /* the same granule, not the same word */
+        mem.reserve(1, 0x8);
+        assert!(mem.check_reservation(1, 0xC));
+    }
+}
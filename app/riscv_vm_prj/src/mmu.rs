@@ -0,0 +1,477 @@
+/*
+ * name: mmu.rs
+ * desc: Sv32 virtual address translation with a software TLB
+ *
+ * Note: translation applies to S-mode (and lower) accesses while satp.MODE is Sv32,
+ *       M-mode and Bare mode use virtual = physical and never touch the TLB, RV64
+ *       harts only implement Bare (there is no Sv39)
+ *       with mstatus.MPRV set M-mode loads and stores are handled as if made in MPP
+ *
+ *       every physical address that comes out of here, and every PTE the walker
+ *       touches, has passed the PMP check, a PMP failure is an access fault
+ *
+ *       the walker sets the A and D bits in memory itself rather than raising a
+ *       page fault for software to do it (Svadu behaviour)
+ *
+ *       the TLB caches translations per 4KiB page, a 4MiB megapage fills one entry
+ *       for every 4KiB page of it that gets touched, it is only ever flushed by
+ *       SFENCE.VMA the same as real hardware, writing satp does not flush it
+ *
+ */
+
+use crate::memory::*;
+use crate::csr::*;
+use crate::trap::*;
+use crate::pmp::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessType {
+    Fetch,
+    Load,
+    Store, /* stores and AMOs */
+}
+
+/* PTE bit masks */
+pub enum PteBm {
+    V_bm = 0x01, /* valid */
+    R_bm = 0x02, /* readable */
+    W_bm = 0x04, /* writable */
+    X_bm = 0x08, /* executable */
+    U_bm = 0x10, /* accessible in U-mode */
+    G_bm = 0x20, /* global mapping, present in every address space */
+    A_bm = 0x40, /* accessed */
+    D_bm = 0x80, /* dirty */
+}
+
+/* satp fields, RV32 layout */
+pub const SATP32_MODE_SV32: u64 = 0x8000_0000;
+pub const SATP32_ASID_SHIFT: u64 = 22;
+pub const SATP32_ASID_MASK: u64  = 0x1FF;
+pub const SATP32_PPN_MASK: u64   = 0x003F_FFFF;
+
+/* Sv32 geometry */
+const PAGE_SHIFT: u64 = 12;
+const PAGE_OFFSET_MASK: u64 = (1 << PAGE_SHIFT) - 1;
+const SV32_LEVELS: u32 = 2;
+const SV32_VPN_BITS: u64 = 10;
+const SV32_VPN_MASK: u64 = (1 << SV32_VPN_BITS) - 1;
+const SV32_PTE_SIZE: u64 = 4;
+const SV32_PTE_PPN_SHIFT: u64 = 10;
+
+/* direct mapped, indexed by the low VPN bits */
+const TLB_ENTRIES: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct TlbEntry {
+    valid: bool,
+    vpn: u64,   /* full virtual page number of the 4KiB page */
+    ppn: u64,   /* physical page number the 4KiB page maps to */
+    asid: u64,
+    level: u32, /* level of the leaf PTE, 1 for megapages */
+    pte: u64,   /* leaf PTE as last written back, for permission checks */
+}
+
+impl TlbEntry {
+    pub fn new() -> TlbEntry {
+        return TlbEntry {
+            valid: false,
+            vpn: 0,
+            ppn: 0,
+            asid: 0,
+            level: 0,
+            pte: 0,
+        };
+    }
+
+    /* the entry maps part of the page (or megapage) containing vpn */
+    fn maps(&self, vpn: u64) -> bool {
+        let shift: u64 = SV32_VPN_BITS * self.level as u64;
+        return (self.vpn >> shift) == (vpn >> shift);
+    }
+
+    fn is_global(&self) -> bool {
+        return (self.pte & PteBm::G_bm as u64) != 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlbStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub flushes: u64, /* SFENCE.VMA executed */
+}
+
+#[derive(Debug)]
+pub struct Mmu {
+    tlb: [TlbEntry; TLB_ENTRIES],
+    stats: TlbStats,
+
+    /* translation state of the hart, refreshed by the cpu before every instruction */
+    satp: u64,
+    sv32: bool, /* satp.MODE is Sv32, never on RV64 */
+    privilege: PrivMode,      /* instruction fetches */
+    data_privilege: PrivMode, /* loads and stores, differs from privilege under MPRV */
+    sum: bool, /* S-mode may access U pages */
+    mxr: bool, /* loads from execute-only pages succeed */
+    pmp: Pmp,
+}
+
+impl Mmu {
+    /* constructor: empty TLB, translation off */
+    pub fn new() -> Mmu {
+        return Mmu {
+            tlb: [TlbEntry::new(); TLB_ENTRIES],
+            stats: TlbStats { hits: 0, misses: 0, flushes: 0 },
+            satp: 0,
+            sv32: false,
+            privilege: PrivMode::M,
+            data_privilege: PrivMode::M,
+            sum: false,
+            mxr: false,
+            pmp: Pmp::new(),
+        };
+    }
+
+    pub fn get_stats(&self) -> TlbStats {
+        return self.stats;
+    }
+
+    /* pick up satp, the privilege levels, mstatus.SUM/MXR and the PMP entries */
+    pub fn set_context(&mut self, csrs: &CsrFile) {
+        let mstatus: u64 = csrs.get_mstatus();
+        self.satp = csrs.get_satp();
+        self.sv32 = csrs.sv32_enabled();
+        self.privilege = csrs.get_privilege();
+        self.data_privilege = csrs.get_data_privilege();
+        self.sum = (mstatus & MstatusBm::SUM_bm as u64) != 0;
+        self.mxr = (mstatus & MstatusBm::MXR_bm as u64) != 0;
+        self.pmp = *csrs.get_pmp();
+    }
+
+    fn access_privilege(&self, access: AccessType) -> PrivMode {
+        if access == AccessType::Fetch {
+            return self.privilege;
+        }
+        return self.data_privilege;
+    }
+
+    fn translation_enabled(&self, access: AccessType) -> bool {
+        return self.access_privilege(access) != PrivMode::M && self.sv32;
+    }
+
+    fn asid(&self) -> u64 {
+        return (self.satp >> SATP32_ASID_SHIFT) & SATP32_ASID_MASK;
+    }
+
+    /*
+     * name: translate
+     * desc: virtual to physical address for an access of size bytes of the given type
+     *
+     * NOTE: faults carry the virtual address, a walk that can't read or update a
+     *       PTE is an access fault of the original access type
+     */
+    pub fn translate(&mut self, mem: &mut Memory, vaddr: u64, size: u64, access: AccessType) -> Result<u64,Exception> {
+        let paddr: u64 = self.translate_page(mem, vaddr, access)?;
+        if !self.pmp.check(paddr, size, self.access_privilege(access), access) {
+            return Err(access_fault(vaddr, access));
+        }
+        return Ok(paddr);
+    }
+
+    fn translate_page(&mut self, mem: &mut Memory, vaddr: u64, access: AccessType) -> Result<u64,Exception> {
+        if !self.translation_enabled(access) {
+            return Ok(vaddr);
+        }
+
+        let vpn: u64 = vaddr >> PAGE_SHIFT;
+        let idx: usize = (vpn as usize) % TLB_ENTRIES;
+        let entry: TlbEntry = self.tlb[idx];
+        let asid_match: bool = entry.is_global() || entry.asid == self.asid();
+
+        /* a store to a clean page has to go back to the PTE to set D */
+        let needs_dirty: bool = access == AccessType::Store && (entry.pte & PteBm::D_bm as u64) == 0;
+        if entry.valid && entry.vpn == vpn && asid_match && !needs_dirty {
+            self.stats.hits += 1;
+            if !self.permitted(entry.pte, access) {
+                return Err(page_fault(vaddr, access));
+            }
+            return Ok((entry.ppn << PAGE_SHIFT) | (vaddr & PAGE_OFFSET_MASK));
+        }
+
+        self.stats.misses += 1;
+        let (ppn, level, pte): (u64, u32, u64) = self.walk(mem, vaddr, access)?;
+        self.tlb[idx] = TlbEntry {
+            valid: true,
+            vpn: vpn,
+            ppn: ppn,
+            asid: self.asid(),
+            level: level,
+            pte: pte,
+        };
+        return Ok((ppn << PAGE_SHIFT) | (vaddr & PAGE_OFFSET_MASK));
+    }
+
+    /*
+     * name: walk
+     * desc: two level Sv32 table walk (privileged spec section 4.3.2), returns the
+     *       physical page number of the 4KiB page, the leaf level and the leaf PTE
+     */
+    fn walk(&mut self, mem: &mut Memory, vaddr: u64, access: AccessType) -> Result<(u64, u32, u64),Exception> {
+        let access_fault: Exception = access_fault(vaddr, access);
+        let vpn: u64 = vaddr >> PAGE_SHIFT;
+
+        let mut table: u64 = (self.satp & SATP32_PPN_MASK) << PAGE_SHIFT;
+        let mut level: u32 = SV32_LEVELS - 1;
+        let mut global: bool = false;
+
+        loop {
+            let vpn_i: u64 = (vpn >> (SV32_VPN_BITS * level as u64)) & SV32_VPN_MASK;
+            let pte_addr: u64 = table + vpn_i * SV32_PTE_SIZE;
+            /* the walker's own accesses are checked as S-mode */
+            if !self.pmp.check(pte_addr, SV32_PTE_SIZE, PrivMode::S, AccessType::Load) {
+                return Err(access_fault);
+            }
+            let pte: u64 = mem.read_32bit(pte_addr).map_err(|_| access_fault)? as u64;
+
+            let valid: bool = (pte & PteBm::V_bm as u64) != 0;
+            let r: bool = (pte & PteBm::R_bm as u64) != 0;
+            let w: bool = (pte & PteBm::W_bm as u64) != 0;
+            let x: bool = (pte & PteBm::X_bm as u64) != 0;
+            if !valid || (!r && w) {
+                return Err(page_fault(vaddr, access));
+            }
+
+            let ppn: u64 = pte >> SV32_PTE_PPN_SHIFT;
+            global |= (pte & PteBm::G_bm as u64) != 0;
+
+            /* pointer to the next level */
+            if !r && !x {
+                if level == 0 {
+                    return Err(page_fault(vaddr, access));
+                }
+                level -= 1;
+                table = ppn << PAGE_SHIFT;
+                continue;
+            }
+
+            /* leaf */
+            if !self.permitted(pte, access) {
+                return Err(page_fault(vaddr, access));
+            }
+            /* megapages must be aligned to their size */
+            let low_mask: u64 = (1 << (SV32_VPN_BITS * level as u64)) - 1;
+            if (ppn & low_mask) != 0 {
+                return Err(page_fault(vaddr, access));
+            }
+
+            let mut new_pte: u64 = pte | PteBm::A_bm as u64;
+            if access == AccessType::Store {
+                new_pte |= PteBm::D_bm as u64;
+            }
+            if new_pte != pte {
+                if !self.pmp.check(pte_addr, SV32_PTE_SIZE, PrivMode::S, AccessType::Store) {
+                    return Err(access_fault);
+                }
+                mem.write_32bit(pte_addr, new_pte as u32).map_err(|_| access_fault)?;
+            }
+
+            if global {
+                new_pte |= PteBm::G_bm as u64;
+            }
+            return Ok((ppn | (vpn & low_mask), level, new_pte));
+        }
+    }
+
+    /* leaf PTE permission check against the privilege level of the access */
+    fn permitted(&self, pte: u64, access: AccessType) -> bool {
+        let r: bool = (pte & PteBm::R_bm as u64) != 0;
+        let w: bool = (pte & PteBm::W_bm as u64) != 0;
+        let x: bool = (pte & PteBm::X_bm as u64) != 0;
+        let user: bool = (pte & PteBm::U_bm as u64) != 0;
+
+        match self.access_privilege(access) {
+            PrivMode::U if !user => return false,
+            /* S-mode never executes user pages, data accesses need SUM */
+            PrivMode::S if user && (access == AccessType::Fetch || !self.sum) => return false,
+            _ => {}
+        }
+
+        return match access {
+            AccessType::Fetch => x,
+            AccessType::Load => r || (self.mxr && x),
+            AccessType::Store => w,
+        };
+    }
+
+    /*
+     * name: flush
+     * desc: SFENCE.VMA, vaddr/asid of None mean every address/address space
+     *
+     * NOTE: flushing a single address space keeps the global mappings
+     */
+    pub fn flush(&mut self, vaddr: Option<u64>, asid: Option<u64>) {
+        self.stats.flushes += 1;
+        for entry in self.tlb.iter_mut() {
+            let addr_match: bool = vaddr.is_none_or(|va| entry.maps(va >> PAGE_SHIFT));
+            let asid_match: bool = asid.is_none_or(|id| !entry.is_global() && entry.asid == (id & SATP32_ASID_MASK));
+            if addr_match && asid_match {
+                entry.valid = false;
+            }
+        }
+    }
+}
+
+fn page_fault(vaddr: u64, access: AccessType) -> Exception {
+    return match access {
+        AccessType::Fetch => Exception::InstructionPageFault(vaddr),
+        AccessType::Load => Exception::LoadPageFault(vaddr),
+        AccessType::Store => Exception::StorePageFault(vaddr),
+    };
+}
+
+fn access_fault(vaddr: u64, access: AccessType) -> Exception {
+    return match access {
+        AccessType::Fetch => Exception::InstructionAccessFault(vaddr),
+        AccessType::Load => Exception::LoadAccessFault(vaddr),
+        AccessType::Store => Exception::StoreAccessFault(vaddr),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::*;
+    use crate::isa::*;
+    use crate::xlen::*;
+    use crate::encoder::*;
+
+    /* the Sv32 MODE bit is just PPN bit 31 of a Bare satp on RV64 */
+    #[test]
+    fn rv64_satp_is_bare() {
+        let src: &str = "
+                li a0, 1
+                slli a0, a0, 31
+                csrw satp, a0
+                la t0, smode
+                csrw mepc, t0
+                li t1, 0x1800
+                csrc mstatus, t1
+                li t1, 0x800     # MPP = S
+                csrs mstatus, t1
+                mret
+            smode:
+                li a1, 1
+                csrr a2, satp
+                j .
+        ";
+        let machine: Machine = run_source(src, &IsaConfig::new(Xlen::RV64), 1);
+        assert_eq!(machine.get_harts()[0].get_reg(Reg::A1 as u8), 1);
+        assert_eq!(machine.get_harts()[0].get_reg(Reg::A2 as u8), 1 << 31);
+    }
+
+    /*
+     * M-mode builds the tables and drops to S-mode at the body, VA 0..4MiB is an
+     * identity megapage with A and D already set, the three 4KiB pages at VA 4MiB
+     * all map data: a clean RW page, a U page and an execute-only page
+     *
+     * the handler steps over faulting instructions, a7 counts them and a6 collects
+     * their mcause a byte at a time, s1 points at the leaf table
+     */
+    const SV32_SETUP: &str = "
+                la t0, trap
+                csrw mtvec, t0
+                la s0, root
+                la s1, leaves
+                li t0, 0xcf          # identity megapage, V R W X A D
+                sw t0, 0(s0)
+                srli t0, s1, 12
+                slli t0, t0, 10
+                ori t0, t0, 1        # pointer to the leaf table
+                sw t0, 4(s0)
+                la t1, data
+                srli t1, t1, 12
+                slli t1, t1, 10
+                ori t0, t1, 0x07     # V R W
+                sw t0, 0(s1)
+                ori t0, t1, 0xd7     # V R W U A D
+                sw t0, 4(s1)
+                ori t0, t1, 0x49     # V X A
+                sw t0, 8(s1)
+                srli t0, s0, 12
+                li t1, 0x80000000
+                or t0, t0, t1
+                csrw satp, t0
+                la t0, smode
+                csrw mepc, t0
+                li t1, 0x1800
+                csrc mstatus, t1
+                li t1, 0x800         # MPP = S
+                csrs mstatus, t1
+                mret
+            trap:
+                addi a7, a7, 1
+                csrr t5, mcause
+                slli a6, a6, 8
+                or a6, a6, t5
+                csrr t6, mepc
+                addi t6, t6, 4
+                csrw mepc, t6
+                mret
+            smode:
+    ";
+
+    const SV32_TABLES: &str = "
+                j .
+                .balign 4096
+            root:
+                .zero 4096
+            leaves:
+                .zero 4096
+            data:
+                .word 0x12345678
+                .zero 4092
+    ";
+
+    fn run_sv32(body: &str) -> Machine {
+        return run_source(&format!("{}{}{}", SV32_SETUP, body, SV32_TABLES), &IsaConfig::new(Xlen::RV32), 1);
+    }
+
+    /* the walker sets A on the first access and D on the first store */
+    #[test]
+    fn sv32_sets_accessed_and_dirty() {
+        let machine: Machine = run_sv32("
+                li s2, 0x400000
+                lw a0, 0(s2)
+                lw a1, 0(s1)
+                sw a0, 0(s2)
+                lw a2, 0(s1)
+        ");
+        let reg = |r: Reg| machine.get_harts()[0].get_reg(r as u8);
+        assert_eq!(reg(Reg::A0), 0x12345678);
+        assert_eq!(reg(Reg::A1) & 0xFF, 0x47);
+        assert_eq!(reg(Reg::A2) & 0xFF, 0xC7);
+        assert_eq!(reg(Reg::A7), 0);
+    }
+
+    /* U pages need SUM for S-mode loads, execute-only pages need MXR */
+    #[test]
+    fn sv32_sum_and_mxr() {
+        let machine: Machine = run_sv32("
+                li s3, 0x401000
+                lw a3, 0(s3)
+                li t0, 0x40000       # SUM
+                csrs sstatus, t0
+                lw a3, 0(s3)
+                li s4, 0x402000
+                lw a4, 0(s4)
+                li t0, 0x80000       # MXR
+                csrs sstatus, t0
+                lw a4, 0(s4)
+        ");
+        let reg = |r: Reg| machine.get_harts()[0].get_reg(r as u8);
+        assert_eq!(reg(Reg::A3), 0x12345678);
+        assert_eq!(reg(Reg::A4), 0x12345678);
+        /* one load page fault each before the bits get set */
+        assert_eq!(reg(Reg::A7), 2);
+        assert_eq!(reg(Reg::A6), 0x0D0D);
+    }
+}
@@ -0,0 +1,295 @@
+/*
+ * name: pmp.rs
+ * desc: physical memory protection, 16 entries with TOR, NA4 and NAPOT matching
+ *
+ * Note: the checks run on physical addresses, after translation, for instruction
+ *       fetches, loads, stores/AMOs and the PTE accesses of the page table walker
+ *
+ *       an access has to lie entirely inside the lowest numbered entry it touches,
+ *       one that only partially overlaps that entry fails
+ *
+ *       while every entry is OFF the PMP counts as unconfigured and S/U-mode
+ *       accesses are let through (like QEMU), once an entry is in use an S/U access
+ *       matching none of them fails, M-mode only ever fails on locked entries
+ *
+ *       the grain is 4 bytes (G = 0)
+ *
+ */
+
+use crate::csr::*;
+use crate::mmu::*;
+use crate::xlen::*;
+
+pub const PMP_ENTRIES: usize = 16;
+
+/* pmpNcfg bit masks */
+pub enum PmpCfgBm {
+    R_bm = 0x01,
+    W_bm = 0x02,
+    X_bm = 0x04,
+    A_bm = 0x18, /* address matching mode */
+    L_bm = 0x80, /* locked until reset, also enforced on M-mode */
+}
+
+pub const PMP_CFG_A_SHIFT: u8 = 3;
+
+/* pmpNcfg.A */
+#[allow(clippy::upper_case_acronyms)]
+pub enum PmpMatch {
+    OFF   = 0,
+    TOR   = 1, /* top of range, the previous pmpaddr is the bottom */
+    NA4   = 2, /* naturally aligned four byte region */
+    NAPOT = 3, /* naturally aligned power of two region, 8 bytes or more */
+}
+
+/* pmpaddr holds address bits [33:2] on RV32 and [55:2] on RV64 */
+fn pmpaddr_mask(xlen: Xlen) -> u64 {
+    return match xlen {
+        Xlen::RV32 => 0xFFFF_FFFF,
+        Xlen::RV64 => (1 << 54) - 1,
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pmp {
+    cfg: [u8; PMP_ENTRIES],
+    addr: [u64; PMP_ENTRIES],
+}
+
+impl Pmp {
+    /* constructor: every entry OFF and unlocked */
+    pub fn new() -> Pmp {
+        return Pmp {
+            cfg: [0; PMP_ENTRIES],
+            addr: [0; PMP_ENTRIES],
+        };
+    }
+
+    fn is_locked(&self, i: usize) -> bool {
+        return (self.cfg[i] & PmpCfgBm::L_bm as u8) != 0;
+    }
+
+    fn mode(&self, i: usize) -> u8 {
+        return (self.cfg[i] & PmpCfgBm::A_bm as u8) >> PMP_CFG_A_SHIFT;
+    }
+
+    /* pmpcfg registers pack XLEN/8 entries each, RV64 only has the even numbered ones */
+    pub fn read_cfg(&self, reg: usize, xlen: Xlen) -> u64 {
+        let per_reg: usize = (xlen.bits() / 8) as usize;
+        let mut val: u64 = 0;
+        for j in 0..per_reg {
+            val |= (self.cfg[reg * 4 + j] as u64) << (8 * j);
+        }
+        return val;
+    }
+
+    /*
+     * NOTE: locked entries keep their configuration, the reserved bits read as zero
+     *       and the reserved R=0 W=1 combination is stored with W cleared
+     */
+    pub fn write_cfg(&mut self, reg: usize, val: u64, xlen: Xlen) {
+        let per_reg: usize = (xlen.bits() / 8) as usize;
+        for j in 0..per_reg {
+            let i: usize = reg * 4 + j;
+            if self.is_locked(i) {
+                continue;
+            }
+            let mut cfg: u8 = (val >> (8 * j)) as u8;
+            cfg &= PmpCfgBm::R_bm as u8 | PmpCfgBm::W_bm as u8 | PmpCfgBm::X_bm as u8
+                 | PmpCfgBm::A_bm as u8 | PmpCfgBm::L_bm as u8;
+            if (cfg & PmpCfgBm::R_bm as u8) == 0 {
+                cfg &= !(PmpCfgBm::W_bm as u8);
+            }
+            self.cfg[i] = cfg;
+        }
+    }
+
+    pub fn read_addr(&self, i: usize) -> u64 {
+        return self.addr[i];
+    }
+
+    /* a locked entry also locks the pmpaddr below it when it uses it as its TOR bottom */
+    pub fn write_addr(&mut self, i: usize, val: u64, xlen: Xlen) {
+        if self.is_locked(i) {
+            return;
+        }
+        if i + 1 < PMP_ENTRIES && self.is_locked(i + 1) && self.mode(i + 1) == PmpMatch::TOR as u8 {
+            return;
+        }
+        self.addr[i] = val & pmpaddr_mask(xlen);
+    }
+
+    /* byte range [lo, hi) covered by an entry that is not OFF */
+    fn range(&self, i: usize) -> (u64, u64) {
+        let addr: u64 = self.addr[i];
+        return match self.mode(i) {
+            mode if mode == PmpMatch::TOR as u8 => {
+                let lo: u64 = if i == 0 { 0 } else { self.addr[i - 1] << 2 };
+                (lo, addr << 2)
+            }
+            mode if mode == PmpMatch::NA4 as u8 => (addr << 2, (addr << 2) + 4),
+            mode if mode == PmpMatch::NAPOT as u8 => {
+                /* the trailing ones give the size: pmpaddr = base/4 | (size/8 - 1) */
+                let ones: u64 = addr ^ (addr + 1);
+                let base: u64 = (addr & !ones) << 2;
+                (base, base + ((ones + 1) << 2))
+            }
+            _ => (0, 0),
+        };
+    }
+
+    /*
+     * name: check
+     * desc: may an access of size bytes at the physical address addr go ahead
+     */
+    pub fn check(&self, addr: u64, size: u64, privilege: PrivMode, access: AccessType) -> bool {
+        let end: u64 = addr.saturating_add(size);
+        let mut configured: bool = false;
+
+        for i in 0..PMP_ENTRIES {
+            if self.mode(i) == PmpMatch::OFF as u8 {
+                continue;
+            }
+            configured = true;
+
+            let (lo, hi): (u64, u64) = self.range(i);
+            if lo >= hi || addr >= hi || end <= lo {
+                continue;
+            }
+            if addr < lo || end > hi {
+                return false;
+            }
+            if privilege == PrivMode::M && !self.is_locked(i) {
+                return true;
+            }
+            let cfg: u8 = self.cfg[i];
+            return match access {
+                AccessType::Fetch => (cfg & PmpCfgBm::X_bm as u8) != 0,
+                AccessType::Load => (cfg & PmpCfgBm::R_bm as u8) != 0,
+                AccessType::Store => (cfg & PmpCfgBm::W_bm as u8) != 0,
+            };
+        }
+        return privilege == PrivMode::M || !configured;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::*;
+    use crate::isa::*;
+    use crate::encoder::*;
+
+    const R: u64 = PmpCfgBm::R_bm as u64;
+    const W: u64 = PmpCfgBm::W_bm as u64;
+    const L: u64 = PmpCfgBm::L_bm as u64;
+    const TOR: u64 = (PmpMatch::TOR as u64) << PMP_CFG_A_SHIFT;
+    const NAPOT: u64 = (PmpMatch::NAPOT as u64) << PMP_CFG_A_SHIFT;
+
+    fn s_load(pmp: &Pmp, addr: u64, size: u64) -> bool {
+        return pmp.check(addr, size, PrivMode::S, AccessType::Load);
+    }
+
+    fn s_store(pmp: &Pmp, addr: u64, size: u64) -> bool {
+        return pmp.check(addr, size, PrivMode::S, AccessType::Store);
+    }
+
+    /* [0, 0x100) read only, [0x100, 0x200) read/write, nothing above */
+    #[test]
+    fn tor() {
+        let mut pmp: Pmp = Pmp::new();
+        pmp.write_addr(0, 0x100 >> 2, Xlen::RV32);
+        pmp.write_addr(1, 0x200 >> 2, Xlen::RV32);
+        pmp.write_cfg(0, ((TOR | R | W) << 8) | TOR | R, Xlen::RV32);
+
+        assert!(s_load(&pmp, 0x80, 4));
+        assert!(!s_store(&pmp, 0x80, 4));
+        assert!(s_store(&pmp, 0x1FC, 4));
+        /* an access has to fit the first entry it touches */
+        assert!(!s_load(&pmp, 0xFE, 4));
+        /* configured, so S-mode misses fail and M-mode misses pass */
+        assert!(!s_load(&pmp, 0x200, 4));
+        assert!(pmp.check(0x200, 4, PrivMode::M, AccessType::Store));
+        assert!(pmp.check(0x80, 4, PrivMode::M, AccessType::Store));
+    }
+
+    /* pmpaddr = base/4 | (size/8 - 1) */
+    #[test]
+    fn napot() {
+        let mut pmp: Pmp = Pmp::new();
+        pmp.write_addr(0, (0x1000 >> 2) | 0x1FF, Xlen::RV32);
+        pmp.write_addr(1, 0x3000 >> 2, Xlen::RV32);
+        pmp.write_cfg(0, ((NAPOT | R | W) << 8) | NAPOT | R, Xlen::RV32);
+
+        assert!(s_load(&pmp, 0x1000, 4));
+        assert!(s_load(&pmp, 0x1FFC, 4));
+        assert!(!s_store(&pmp, 0x1000, 4));
+        assert!(!s_load(&pmp, 0x2000, 4));
+        assert!(!s_load(&pmp, 0xFFC, 8));
+        /* no trailing ones is the smallest NAPOT, 8 bytes */
+        assert!(s_store(&pmp, 0x3000, 8));
+        assert!(!s_store(&pmp, 0x3008, 4));
+        assert!(!s_store(&pmp, 0x3004, 8));
+    }
+
+    /* locked entries bind M-mode and can't be rewritten, a locked TOR entry also locks its bottom */
+    #[test]
+    fn locked() {
+        let mut pmp: Pmp = Pmp::new();
+        pmp.write_addr(0, 0x100 >> 2, Xlen::RV32);
+        pmp.write_addr(1, 0x200 >> 2, Xlen::RV32);
+        pmp.write_cfg(0, ((L | TOR | R) << 8) | TOR | R | W, Xlen::RV32);
+
+        assert!(pmp.check(0x80, 4, PrivMode::M, AccessType::Store));
+        assert!(pmp.check(0x180, 4, PrivMode::M, AccessType::Load));
+        assert!(!pmp.check(0x180, 4, PrivMode::M, AccessType::Store));
+
+        pmp.write_cfg(0, 0, Xlen::RV32);
+        pmp.write_addr(0, 0, Xlen::RV32);
+        pmp.write_addr(1, 0x400 >> 2, Xlen::RV32);
+        assert_eq!(pmp.read_cfg(0, Xlen::RV32), (L | TOR | R) << 8);
+        assert_eq!(pmp.read_addr(0), 0x100 >> 2);
+        assert_eq!(pmp.read_addr(1), 0x200 >> 2);
+        assert!(!pmp.check(0x180, 4, PrivMode::M, AccessType::Store));
+    }
+
+    /* the same through the CSRs, a locked read only NAPOT entry turns M-mode stores into access faults */
+    #[test]
+    fn locked_entry_faults_m_mode() {
+        let src: &str = "
+                la t0, mtrap
+                csrw mtvec, t0
+                la s0, data
+                srli t0, s0, 2
+                csrw pmpaddr0, t0    # 8 bytes
+                li t0, 0x99          # L, NAPOT, R
+                csrw pmpcfg0, t0
+                lw a0, 0(s0)
+                sw zero, 0(s0)
+                csrw pmpcfg0, zero
+                csrr a1, pmpcfg0
+                csrw pmpaddr0, zero
+                csrr a2, pmpaddr0
+                lw a3, 0(s0)
+                j .
+            mtrap:
+                addi a7, a7, 1
+                csrr a5, mcause
+                csrr t6, mepc
+                addi t6, t6, 4
+                csrw mepc, t6
+                mret
+                .balign 8
+            data:
+                .word 0x12345678, 0
+        ";
+        let machine: Machine = run_source(src, &IsaConfig::new(Xlen::RV32), 1);
+        let reg = |r: Reg| machine.get_harts()[0].get_reg(r as u8);
+        assert_eq!(reg(Reg::A0), 0x12345678);
+        assert_eq!(reg(Reg::A1), 0x99);
+        assert_ne!(reg(Reg::A2), 0);
+        assert_eq!(reg(Reg::A3), 0x12345678);
+        assert_eq!(reg(Reg::A7), 1);
+        assert_eq!(reg(Reg::A5), 7);
+    }
+}
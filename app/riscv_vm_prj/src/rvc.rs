@@ -0,0 +1,294 @@
+/*
+ * name: rvc.rs
+ * desc: RV32C/RV64C compressed instruction expansion
+ *
+ * Note: every 16 bit instruction has an equivalent 32 bit encoding (spec chapter 16),
+ *       the fetch unit expands it and hands the result to the normal decoders,
+ *       which only need to know the instruction was 2 bytes long, the expansion
+ *       is built with the encoder.rs constructors like everything else
+ *
+ *       a few encodings mean different things on RV64 (C.JAL is C.ADDIW, the
+ *       single precision float loads/stores are C.LD/C.SD)
+ *
+ */
+
+use crate::xlen::*;
+use crate::encoder::*;
+
+/* instructions whose low two bits aren't 0b11 are 16 bits long */
+pub fn is_compressed(low_half: u16) -> bool {
+    return (low_half & 0x3) != 0x3;
+}
+
+/* pick bit `from` of inst and place it at bit `to` */
+fn bit(inst: u32, from: u32, to: u32) -> u32 {
+    return ((inst >> from) & 0x1) << to;
+}
+
+/* sign extend the low `bits` bits */
+fn sext(val: u32, bits: u32) -> i32 {
+    let shift: u32 = 32 - bits;
+    return ((val << shift) as i32) >> shift;
+}
+
+/* 3 bit register fields (rd', rs1', rs2') address x8-x15 */
+fn creg(field: u32) -> u8 {
+    return ((field & 0x7) + 8) as u8;
+}
+
+/*
+ * name: expand_compressed
+ * desc: turn a 16 bit instruction into the 32 bit instruction it stands for
+ *
+ * NOTE: returns Err(()) for reserved/illegal encodings, including the all zero word
+ */
+pub fn expand_compressed(inst: u16, xlen: Xlen) -> Result<u32,()> {
+    let inst: u32 = inst as u32;
+    let rv64: bool = xlen == Xlen::RV64;
+    let quadrant: u32 = inst & 0x3;
+    let func3: u32 = (inst >> 13) & 0x7;
+
+    /* full 5 bit register fields */
+    let rd: u8 = ((inst >> 7) & 0x1F) as u8;   /* also rs1 */
+    let rs2: u8 = ((inst >> 2) & 0x1F) as u8;
+
+    /* compressed 3 bit register fields */
+    let rd_p: u8 = creg(inst >> 2);    /* rd'/rs2' */
+    let rs1_p: u8 = creg(inst >> 7);   /* rs1'/rd' */
+
+    /* offsets shared by several formats */
+    let lw_off: i32 = (bit(inst, 6, 2) | (((inst >> 10) & 0x7) << 3) | bit(inst, 5, 6)) as i32;
+    let ld_off: i32 = ((((inst >> 10) & 0x7) << 3) | (((inst >> 5) & 0x3) << 6)) as i32;
+    let imm6: i32 = sext(((inst >> 2) & 0x1F) | bit(inst, 12, 5), 6);
+
+    match (quadrant, func3) {
+        /* ---- quadrant 0 ---- */
+
+        /* C.ADDI4SPN -> addi rd', x2, nzuimm */
+        (0, 0) => {
+            let nzuimm: u32 = bit(inst, 6, 2) | bit(inst, 5, 3) | (((inst >> 11) & 0x3) << 4) | (((inst >> 7) & 0xF) << 6);
+            if nzuimm == 0 {
+                return Err(());
+            }
+            return Ok(Inst::addi(Reg::x(rd_p), Reg::Sp, nzuimm as i32).encode());
+        }
+        /* C.FLD -> fld rd', offset(rs1') */
+        (0, 1) => return Ok(Inst::fld(FReg::f(rd_p), Reg::x(rs1_p), ld_off).encode()),
+        /* C.LW -> lw rd', offset(rs1') */
+        (0, 2) => return Ok(Inst::lw(Reg::x(rd_p), Reg::x(rs1_p), lw_off).encode()),
+        /* C.LD -> ld rd', offset(rs1') */
+        (0, 3) if rv64 => return Ok(Inst::ld(Reg::x(rd_p), Reg::x(rs1_p), ld_off).encode()),
+        /* C.FLW -> flw rd', offset(rs1') */
+        (0, 3) => return Ok(Inst::flw(FReg::f(rd_p), Reg::x(rs1_p), lw_off).encode()),
+        /* C.FSD -> fsd rs2', offset(rs1') */
+        (0, 5) => return Ok(Inst::fsd(FReg::f(rd_p), Reg::x(rs1_p), ld_off).encode()),
+        /* C.SW -> sw rs2', offset(rs1') */
+        (0, 6) => return Ok(Inst::sw(Reg::x(rd_p), Reg::x(rs1_p), lw_off).encode()),
+        /* C.SD -> sd rs2', offset(rs1') */
+        (0, 7) if rv64 => return Ok(Inst::sd(Reg::x(rd_p), Reg::x(rs1_p), ld_off).encode()),
+        /* C.FSW -> fsw rs2', offset(rs1') */
+        (0, 7) => return Ok(Inst::fsw(FReg::f(rd_p), Reg::x(rs1_p), lw_off).encode()),
+
+        /* ---- quadrant 1 ---- */
+
+        /* C.NOP/C.ADDI -> addi rd, rd, imm */
+        (1, 0) => return Ok(Inst::addi(Reg::x(rd), Reg::x(rd), imm6).encode()),
+        /* C.ADDIW -> addiw rd, rd, imm, rd = x0 is reserved */
+        (1, 1) if rv64 => {
+            if rd == 0 {
+                return Err(());
+            }
+            return Ok(Inst::addiw(Reg::x(rd), Reg::x(rd), imm6).encode());
+        }
+        /* C.JAL -> jal x1, offset */
+        (1, 1) => return Ok(Inst::jal(Reg::Ra, cj_offset(inst)).encode()),
+        /* C.LI -> addi rd, x0, imm */
+        (1, 2) => return Ok(Inst::addi(Reg::x(rd), Reg::Zero, imm6).encode()),
+        (1, 3) => {
+            /* C.ADDI16SP -> addi x2, x2, nzimm */
+            if rd == 2 {
+                let nzimm: i32 = sext(bit(inst, 6, 4) | bit(inst, 2, 5) | bit(inst, 5, 6) | (((inst >> 3) & 0x3) << 7) | bit(inst, 12, 9), 10);
+                if nzimm == 0 {
+                    return Err(());
+                }
+                return Ok(Inst::addi(Reg::Sp, Reg::Sp, nzimm).encode());
+            }
+            /* C.LUI -> lui rd, nzimm */
+            if imm6 == 0 {
+                return Err(());
+            }
+            return Ok(Inst::lui(Reg::x(rd), (imm6 as u32) & 0xFFFFF).encode());
+        }
+        (1, 4) => {
+            let shamt: u32 = ((inst >> 2) & 0x1F) | bit(inst, 12, 5);
+            match (inst >> 10) & 0x3 {
+                /* C.SRLI/C.SRAI, shamt[5] must be zero on RV32 */
+                0 | 1 => {
+                    if shamt >= 32 && !rv64 {
+                        return Err(());
+                    }
+                    let (rd, rs1): (Reg, Reg) = (Reg::x(rs1_p), Reg::x(rs1_p));
+                    return Ok(if ((inst >> 10) & 0x3) == 1 { Inst::srai(rd, rs1, shamt) } else { Inst::srli(rd, rs1, shamt) }.encode());
+                }
+                /* C.ANDI */
+                2 => return Ok(Inst::andi(Reg::x(rs1_p), Reg::x(rs1_p), imm6).encode()),
+                /* C.SUBW/C.ADDW, the other two bit 12 encodings are reserved */
+                _ if bit(inst, 12, 0) != 0 => {
+                    if !rv64 || ((inst >> 5) & 0x3) >= 2 {
+                        return Err(());
+                    }
+                    let op: fn(Reg, Reg, Reg) -> Inst = if ((inst >> 5) & 0x3) == 0 { Inst::subw } else { Inst::addw };
+                    return Ok(op(Reg::x(rs1_p), Reg::x(rs1_p), Reg::x(rd_p)).encode());
+                }
+                /* C.SUB/C.XOR/C.OR/C.AND */
+                _ => {
+                    let op: fn(Reg, Reg, Reg) -> Inst = match (inst >> 5) & 0x3 {
+                        0 => Inst::sub,
+                        1 => Inst::xor,
+                        2 => Inst::or,
+                        _ => Inst::and,
+                    };
+                    return Ok(op(Reg::x(rs1_p), Reg::x(rs1_p), Reg::x(rd_p)).encode());
+                }
+            }
+        }
+        /* C.J -> jal x0, offset */
+        (1, 5) => return Ok(Inst::jal(Reg::Zero, cj_offset(inst)).encode()),
+        /* C.BEQZ/C.BNEZ -> beq/bne rs1', x0, offset */
+        (1, 6) | (1, 7) => {
+            let offset: i32 = sext(bit(inst, 3, 1) | bit(inst, 4, 2) | bit(inst, 10, 3) | bit(inst, 11, 4)
+                                 | bit(inst, 2, 5) | bit(inst, 5, 6) | bit(inst, 6, 7) | bit(inst, 12, 8), 9);
+            let op: fn(Reg, Reg, i32) -> Inst = if func3 == 6 { Inst::beq } else { Inst::bne };
+            return Ok(op(Reg::x(rs1_p), Reg::Zero, offset).encode());
+        }
+
+        /* ---- quadrant 2 ---- */
+
+        /* C.SLLI -> slli rd, rd, shamt */
+        (2, 0) => {
+            let shamt: u32 = ((inst >> 2) & 0x1F) | bit(inst, 12, 5);
+            if shamt >= 32 && !rv64 {
+                return Err(());
+            }
+            return Ok(Inst::slli(Reg::x(rd), Reg::x(rd), shamt).encode());
+        }
+        /* C.FLDSP -> fld rd, offset(x2) */
+        (2, 1) => return Ok(Inst::fld(FReg::f(rd), Reg::Sp, ldsp_offset(inst)).encode()),
+        /* C.LWSP -> lw rd, offset(x2), rd = x0 is reserved */
+        (2, 2) => {
+            if rd == 0 {
+                return Err(());
+            }
+            return Ok(Inst::lw(Reg::x(rd), Reg::Sp, lwsp_offset(inst)).encode());
+        }
+        /* C.LDSP -> ld rd, offset(x2), rd = x0 is reserved */
+        (2, 3) if rv64 => {
+            if rd == 0 {
+                return Err(());
+            }
+            return Ok(Inst::ld(Reg::x(rd), Reg::Sp, ldsp_offset(inst)).encode());
+        }
+        /* C.FLWSP -> flw rd, offset(x2) */
+        (2, 3) => return Ok(Inst::flw(FReg::f(rd), Reg::Sp, lwsp_offset(inst)).encode()),
+        (2, 4) => {
+            let bit12: u32 = bit(inst, 12, 0);
+            match (bit12, rd, rs2) {
+                /* C.JR rs1 = x0 is reserved */
+                (0, 0, 0) => return Err(()),
+                /* C.JR -> jalr x0, 0(rs1) */
+                (0, _, 0) => return Ok(Inst::jalr(Reg::Zero, Reg::x(rd), 0).encode()),
+                /* C.MV -> add rd, x0, rs2 */
+                (0, _, _) => return Ok(Inst::add(Reg::x(rd), Reg::Zero, Reg::x(rs2)).encode()),
+                /* C.EBREAK */
+                (1, 0, 0) => return Ok(Inst::ebreak().encode()),
+                /* C.JALR -> jalr x1, 0(rs1) */
+                (1, _, 0) => return Ok(Inst::jalr(Reg::Ra, Reg::x(rd), 0).encode()),
+                /* C.ADD -> add rd, rd, rs2 */
+                _ => return Ok(Inst::add(Reg::x(rd), Reg::x(rd), Reg::x(rs2)).encode()),
+            }
+        }
+        /* C.FSDSP -> fsd rs2, offset(x2) */
+        (2, 5) => return Ok(Inst::fsd(FReg::f(rs2), Reg::Sp, sdsp_offset(inst)).encode()),
+        /* C.SWSP -> sw rs2, offset(x2) */
+        (2, 6) => return Ok(Inst::sw(Reg::x(rs2), Reg::Sp, swsp_offset(inst)).encode()),
+        /* C.SDSP -> sd rs2, offset(x2) */
+        (2, 7) if rv64 => return Ok(Inst::sd(Reg::x(rs2), Reg::Sp, sdsp_offset(inst)).encode()),
+        /* C.FSWSP -> fsw rs2, offset(x2) */
+        (2, 7) => return Ok(Inst::fsw(FReg::f(rs2), Reg::Sp, swsp_offset(inst)).encode()),
+
+        /* C.Q0 func3 = 4 is reserved */
+        _ => return Err(()),
+    }
+}
+
+/* CJ format jump offset, bits [11|4|9:8|10|6|7|3:1|5] */
+fn cj_offset(inst: u32) -> i32 {
+    return sext(bit(inst, 3, 1) | bit(inst, 4, 2) | bit(inst, 5, 3) | bit(inst, 11, 4)
+              | bit(inst, 2, 5) | bit(inst, 7, 6) | bit(inst, 6, 7) | bit(inst, 9, 8)
+              | bit(inst, 10, 9) | bit(inst, 8, 10) | bit(inst, 12, 11), 12);
+}
+
+/* C.LWSP/C.FLWSP offset, bits [5|4:2|7:6] */
+fn lwsp_offset(inst: u32) -> i32 {
+    return ((((inst >> 4) & 0x7) << 2) | bit(inst, 12, 5) | (((inst >> 2) & 0x3) << 6)) as i32;
+}
+
+/* C.SWSP/C.FSWSP offset, bits [5:2|7:6] */
+fn swsp_offset(inst: u32) -> i32 {
+    return ((((inst >> 9) & 0xF) << 2) | (((inst >> 7) & 0x3) << 6)) as i32;
+}
+
+/* C.LDSP/C.FLDSP offset, bits [5|4:3|8:6] */
+fn ldsp_offset(inst: u32) -> i32 {
+    return ((((inst >> 5) & 0x3) << 3) | bit(inst, 12, 5) | (((inst >> 2) & 0x7) << 6)) as i32;
+}
+
+/* C.SDSP/C.FSDSP offset, bits [5:3|8:6] */
+fn sdsp_offset(inst: u32) -> i32 {
+    return ((((inst >> 10) & 0x7) << 3) | (((inst >> 7) & 0x7) << 6)) as i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expansion() {
+        for (half, word) in [
+            (0x0808, 0x0101_0513), /* c.addi4spn a0, sp, 16 -> addi a0, sp, 16 */
+            (0x41C8, 0x0045_A503), /* c.lw a0, 4(a1) -> lw a0, 4(a1) */
+            (0x157D, 0xFFF5_0513), /* c.addi a0, -1 -> addi a0, a0, -1 */
+            (0x4515, 0x0050_0513), /* c.li a0, 5 -> addi a0, zero, 5 */
+            (0xA001, 0x0000_006F), /* c.j 0 -> jal zero, 0 */
+            (0x4532, 0x00C1_2503), /* c.lwsp a0, 12(sp) -> lw a0, 12(sp) */
+            (0x852E, 0x00B0_0533), /* c.mv a0, a1 -> add a0, zero, a1 */
+            (0x952E, 0x00B5_0533), /* c.add a0, a1 -> add a0, a0, a1 */
+            (0x8082, 0x0000_8067), /* c.jr ra -> jalr zero, 0(ra) */
+            (0x9002, 0x0010_0073), /* c.ebreak */
+        ] {
+            assert_eq!(expand_compressed(half, Xlen::RV32), Ok(word), "{:04x}", half);
+            assert_eq!(expand_compressed(half, Xlen::RV64), Ok(word), "{:04x}", half);
+        }
+    }
+
+    /* the encodings RV32 and RV64 read differently */
+    #[test]
+    fn xlen_dependent() {
+        assert_eq!(expand_compressed(0x2505, Xlen::RV32), Ok(Inst::jal(Reg::Ra, 0x620).encode()));
+        assert_eq!(expand_compressed(0x2505, Xlen::RV64), Ok(0x0015_051B)); /* addiw a0, a0, 1 */
+        assert_eq!(expand_compressed(0x6588, Xlen::RV32), Ok(0x0085_A507)); /* flw fa0, 8(a1) */
+        assert_eq!(expand_compressed(0x6588, Xlen::RV64), Ok(0x0085_B503)); /* ld a0, 8(a1) */
+        /* c.slli a0, 32 only exists on RV64 */
+        assert_eq!(expand_compressed(0x1502, Xlen::RV32), Err(()));
+        assert_eq!(expand_compressed(0x1502, Xlen::RV64), Ok(0x0205_1513));
+    }
+
+    #[test]
+    fn reserved() {
+        assert_eq!(expand_compressed(0x0000, Xlen::RV32), Err(())); /* all zero, c.addi4spn with nzuimm 0 */
+        assert_eq!(expand_compressed(0x4002, Xlen::RV32), Err(())); /* c.lwsp x0 */
+        assert_eq!(expand_compressed(0x8002, Xlen::RV32), Err(())); /* c.jr x0 */
+        assert_eq!(expand_compressed(0x6101, Xlen::RV32), Err(())); /* c.addi16sp 0 */
+        assert_eq!(expand_compressed(0x2001, Xlen::RV64), Err(())); /* c.addiw x0 */
+    }
+}
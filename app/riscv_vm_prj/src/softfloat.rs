@@ -0,0 +1,772 @@
+/*
+ * name: softfloat.rs
+ * desc: IEEE-754 binary32/binary64 arithmetic done in software
+ *
+ * Note: the host FPU can't be told which rounding mode to use and doesn't report
+ *       exception flags, so every operation is computed on integers here and
+ *       rounded explicitly, flags are OR'ed into the caller's fflags
+ *
+ *       values are passed around as raw bit patterns in a u64, NaN results are
+ *       always the RISC-V canonical NaN (no payload propagation)
+ *
+ */
+
+/* fflags bits */
+pub const FLAG_NX: u8 = 0x01; /* inexact */
+pub const FLAG_UF: u8 = 0x02; /* underflow */
+pub const FLAG_OF: u8 = 0x04; /* overflow */
+pub const FLAG_DZ: u8 = 0x08; /* divide by zero */
+pub const FLAG_NV: u8 = 0x10; /* invalid operation */
+
+/* rounding modes, same encoding as the rm field and frm */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum RoundingMode {
+    RNE = 0, /* to nearest, ties to even */
+    RTZ = 1, /* towards zero */
+    RDN = 2, /* down, towards -inf */
+    RUP = 3, /* up, towards +inf */
+    RMM = 4, /* to nearest, ties to max magnitude */
+}
+
+impl RoundingMode {
+    /* 5 and 6 are reserved, 7 (dynamic) has to be resolved by the caller */
+    pub fn from_bits(bits: u8) -> Option<RoundingMode> {
+        return match bits {
+            0 => Some(RoundingMode::RNE),
+            1 => Some(RoundingMode::RTZ),
+            2 => Some(RoundingMode::RDN),
+            3 => Some(RoundingMode::RUP),
+            4 => Some(RoundingMode::RMM),
+            _ => None,
+        };
+    }
+}
+
+/* FCLASS result bits */
+pub enum FClassBm {
+    NEG_INF_bm       = 0x001,
+    NEG_NORMAL_bm    = 0x002,
+    NEG_SUBNORMAL_bm = 0x004,
+    NEG_ZERO_bm      = 0x008,
+    POS_ZERO_bm      = 0x010,
+    POS_SUBNORMAL_bm = 0x020,
+    POS_NORMAL_bm    = 0x040,
+    POS_INF_bm       = 0x080,
+    SNAN_bm          = 0x100,
+    QNAN_bm          = 0x200,
+}
+
+/* floating point format, only the field widths differ between single and double */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FpFormat {
+    exp_bits: u32,
+    frac_bits: u32,
+}
+
+pub const F32: FpFormat = FpFormat { exp_bits: 8, frac_bits: 23 };
+pub const F64: FpFormat = FpFormat { exp_bits: 11, frac_bits: 52 };
+
+/* finite value (-1)^sign * sig * 2^exp, sig may have a sticky bit OR'ed into bit 0 */
+#[derive(Debug, Clone, Copy)]
+struct Unpacked {
+    sign: bool,
+    exp: i32,
+    sig: u128,
+}
+
+impl FpFormat {
+    fn bias(&self) -> i32 {
+        return (1 << (self.exp_bits - 1)) - 1;
+    }
+
+    fn exp_max(&self) -> u64 {
+        return (1 << self.exp_bits) - 1;
+    }
+
+    fn frac_mask(&self) -> u64 {
+        return (1 << self.frac_bits) - 1;
+    }
+
+    /* significand precision including the hidden bit */
+    fn precision(&self) -> u32 {
+        return self.frac_bits + 1;
+    }
+
+    fn sign(&self, a: u64) -> bool {
+        return (a >> (self.exp_bits + self.frac_bits)) & 0x1 != 0;
+    }
+
+    fn exp_field(&self, a: u64) -> u64 {
+        return (a >> self.frac_bits) & self.exp_max();
+    }
+
+    fn frac_field(&self, a: u64) -> u64 {
+        return a & self.frac_mask();
+    }
+
+    fn pack(&self, sign: bool, exp: u64, frac: u64) -> u64 {
+        return ((sign as u64) << (self.exp_bits + self.frac_bits)) | (exp << self.frac_bits) | (frac & self.frac_mask());
+    }
+
+    pub fn is_nan(&self, a: u64) -> bool {
+        return self.exp_field(a) == self.exp_max() && self.frac_field(a) != 0;
+    }
+
+    /* signaling NaNs have the top fraction bit clear */
+    pub fn is_snan(&self, a: u64) -> bool {
+        return self.is_nan(a) && (a >> (self.frac_bits - 1)) & 0x1 == 0;
+    }
+
+    pub fn is_inf(&self, a: u64) -> bool {
+        return self.exp_field(a) == self.exp_max() && self.frac_field(a) == 0;
+    }
+
+    pub fn is_zero(&self, a: u64) -> bool {
+        return self.exp_field(a) == 0 && self.frac_field(a) == 0;
+    }
+
+    pub fn canonical_nan(&self) -> u64 {
+        return self.pack(false, self.exp_max(), 1 << (self.frac_bits - 1));
+    }
+
+    fn inf(&self, sign: bool) -> u64 {
+        return self.pack(sign, self.exp_max(), 0);
+    }
+
+    fn zero(&self, sign: bool) -> u64 {
+        return self.pack(sign, 0, 0);
+    }
+
+    fn max_finite(&self, sign: bool) -> u64 {
+        return self.pack(sign, self.exp_max() - 1, self.frac_mask());
+    }
+
+    /* only valid for finite values, zero comes back with sig = 0 */
+    fn unpack(&self, a: u64) -> Unpacked {
+        let exp: u64 = self.exp_field(a);
+        let frac: u64 = self.frac_field(a);
+        let min_exp: i32 = 1 - self.bias() - self.frac_bits as i32;
+
+        if exp == 0 {
+            return Unpacked { sign: self.sign(a), exp: min_exp, sig: frac as u128 };
+        }
+        return Unpacked {
+            sign: self.sign(a),
+            exp: exp as i32 - self.bias() - self.frac_bits as i32,
+            sig: (frac | (1 << self.frac_bits)) as u128,
+        };
+    }
+
+    /*
+     * name: round_pack
+     * desc: round (-1)^sign * sig * 2^exp to this format and raise NX/UF/OF
+     *
+     * NOTE: tininess is detected after rounding as RISC-V requires, the value
+     *       is tiny if rounding it with an unbounded exponent range still gives
+     *       something below the smallest normal number
+     */
+    fn round_pack(&self, sign: bool, exp: i32, sig: u128, rm: RoundingMode, flags: &mut u8) -> u64 {
+        if sig == 0 {
+            return self.zero(sign);
+        }
+
+        let p: i32 = self.precision() as i32;
+        let msb: i32 = 127 - sig.leading_zeros() as i32;
+        let emin: i32 = 1 - self.bias() - (p - 1); /* exponent of the lsb of the smallest normal */
+
+        let e_nat: i32 = exp + msb - (p - 1);
+        let mut e: i32 = e_nat.max(emin);
+        let (mut m, inexact) = round_sig(sig, e - exp, sign, rm);
+        if m == (1 << p) {
+            m >>= 1;
+            e += 1;
+        }
+
+        if e_nat < emin && inexact {
+            let (m_unb, _) = round_sig(sig, e_nat - exp, sign, rm);
+            let e_unb: i32 = if m_unb == (1 << p) { e_nat + 1 } else { e_nat };
+            if e_unb < emin {
+                *flags |= FLAG_UF;
+            }
+        }
+        if inexact {
+            *flags |= FLAG_NX;
+        }
+
+        /* subnormal results keep a zero exponent field, a carry into the hidden bit makes them normal */
+        let biased: i64 = if m >= (1 << (p - 1)) { (e + (p - 1) + self.bias()) as i64 } else { 0 };
+        if biased >= self.exp_max() as i64 {
+            *flags |= FLAG_OF | FLAG_NX;
+            let to_inf: bool = match rm {
+                RoundingMode::RNE | RoundingMode::RMM => true,
+                RoundingMode::RTZ => false,
+                RoundingMode::RDN => sign,
+                RoundingMode::RUP => !sign,
+            };
+            return if to_inf { self.inf(sign) } else { self.max_finite(sign) };
+        }
+        return self.pack(sign, biased as u64, m as u64);
+    }
+}
+
+/*
+ * name: round_sig
+ * desc: drop the low `shift` bits of sig, rounding in the given direction
+ *       returns the rounded value and whether any set bits were dropped
+ */
+fn round_sig(sig: u128, shift: i32, sign: bool, rm: RoundingMode) -> (u128, bool) {
+    if shift <= 0 {
+        return (sig << (-shift) as u32, false);
+    }
+
+    let (kept, rem_zero, above_half, at_half): (u128, bool, bool, bool);
+    if shift > 128 {
+        kept = 0;
+        rem_zero = sig == 0;
+        above_half = false;
+        at_half = false;
+    }
+    else {
+        let shift: u32 = shift as u32;
+        let rem: u128 = if shift == 128 { sig } else { sig & ((1 << shift) - 1) };
+        let half: u128 = 1 << (shift - 1);
+        kept = if shift == 128 { 0 } else { sig >> shift };
+        rem_zero = rem == 0;
+        above_half = rem > half;
+        at_half = rem == half;
+    }
+
+    let round_up: bool = match rm {
+        RoundingMode::RNE => above_half || (at_half && (kept & 0x1) != 0),
+        RoundingMode::RTZ => false,
+        RoundingMode::RDN => !rem_zero && sign,
+        RoundingMode::RUP => !rem_zero && !sign,
+        RoundingMode::RMM => above_half || at_half,
+    };
+    return (if round_up { kept + 1 } else { kept }, !rem_zero);
+}
+
+/* shift right, OR'ing everything shifted out into bit 0 */
+fn shift_right_jam(sig: u128, shift: u32) -> u128 {
+    if shift == 0 {
+        return sig;
+    }
+    if shift >= 128 {
+        return (sig != 0) as u128;
+    }
+    let lost: bool = (sig & ((1 << shift) - 1)) != 0;
+    return (sig >> shift) | lost as u128;
+}
+
+/* integer square root, also returns whether it was exact */
+fn isqrt(val: u128) -> (u128, bool) {
+    let mut rem: u128 = val;
+    let mut res: u128 = 0;
+    let mut bit: u128 = 1 << 126;
+
+    while bit > rem {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rem >= res + bit {
+            rem -= res + bit;
+            res = (res >> 1) + bit;
+        }
+        else {
+            res >>= 1;
+        }
+        bit >>= 2;
+    }
+    return (res, rem == 0);
+}
+
+/* canonical NaN result, invalid if any of the inputs was signaling */
+fn propagate_nan(fmt: FpFormat, inputs: &[u64], flags: &mut u8) -> u64 {
+    if inputs.iter().any(|x| fmt.is_snan(*x)) {
+        *flags |= FLAG_NV;
+    }
+    return fmt.canonical_nan();
+}
+
+/* sum of two finite values, either may be zero */
+fn add_unpacked(fmt: FpFormat, a: Unpacked, b: Unpacked, rm: RoundingMode, flags: &mut u8) -> u64 {
+    /* exact zero results are +0 except when rounding down, x + x keeps its sign */
+    if a.sig == 0 && b.sig == 0 {
+        let sign: bool = if a.sign == b.sign { a.sign } else { rm == RoundingMode::RDN };
+        return fmt.zero(sign);
+    }
+    if a.sig == 0 {
+        return fmt.round_pack(b.sign, b.exp, b.sig, rm, flags);
+    }
+    if b.sig == 0 {
+        return fmt.round_pack(a.sign, a.exp, a.sig, rm, flags);
+    }
+
+    /* line both operands up with their msb at bit 125, leaving room for the carry */
+    let norm = |x: Unpacked| -> Unpacked {
+        let shift: i32 = x.sig.leading_zeros() as i32 - 2;
+        return Unpacked { sign: x.sign, exp: x.exp - shift, sig: x.sig << shift };
+    };
+    let mut big: Unpacked = norm(a);
+    let mut small: Unpacked = norm(b);
+    if small.exp > big.exp {
+        std::mem::swap(&mut big, &mut small);
+    }
+    let small_sig: u128 = shift_right_jam(small.sig, (big.exp - small.exp).min(128) as u32);
+
+    if big.sign == small.sign {
+        return fmt.round_pack(big.sign, big.exp, big.sig + small_sig, rm, flags);
+    }
+    if big.sig == small_sig {
+        return fmt.zero(rm == RoundingMode::RDN);
+    }
+    if big.sig > small_sig {
+        return fmt.round_pack(big.sign, big.exp, big.sig - small_sig, rm, flags);
+    }
+    return fmt.round_pack(small.sign, big.exp, small_sig - big.sig, rm, flags);
+}
+
+pub fn add(fmt: FpFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u8) -> u64 {
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        return propagate_nan(fmt, &[a, b], flags);
+    }
+    if fmt.is_inf(a) && fmt.is_inf(b) {
+        if fmt.sign(a) != fmt.sign(b) {
+            *flags |= FLAG_NV;
+            return fmt.canonical_nan();
+        }
+        return a;
+    }
+    if fmt.is_inf(a) {
+        return a;
+    }
+    if fmt.is_inf(b) {
+        return b;
+    }
+    return add_unpacked(fmt, fmt.unpack(a), fmt.unpack(b), rm, flags);
+}
+
+pub fn sub(fmt: FpFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u8) -> u64 {
+    /* flip the sign of b, NaNs are replaced anyway so their sign doesn't matter */
+    let neg_b: u64 = b ^ (1 << (fmt.exp_bits + fmt.frac_bits));
+    return add(fmt, a, neg_b, rm, flags);
+}
+
+pub fn mul(fmt: FpFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u8) -> u64 {
+    let sign: bool = fmt.sign(a) != fmt.sign(b);
+
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        return propagate_nan(fmt, &[a, b], flags);
+    }
+    if fmt.is_inf(a) || fmt.is_inf(b) {
+        if fmt.is_zero(a) || fmt.is_zero(b) {
+            *flags |= FLAG_NV;
+            return fmt.canonical_nan();
+        }
+        return fmt.inf(sign);
+    }
+
+    let ua: Unpacked = fmt.unpack(a);
+    let ub: Unpacked = fmt.unpack(b);
+    return fmt.round_pack(sign, ua.exp + ub.exp, ua.sig * ub.sig, rm, flags);
+}
+
+pub fn div(fmt: FpFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u8) -> u64 {
+    let sign: bool = fmt.sign(a) != fmt.sign(b);
+
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        return propagate_nan(fmt, &[a, b], flags);
+    }
+    if fmt.is_inf(a) {
+        if fmt.is_inf(b) {
+            *flags |= FLAG_NV;
+            return fmt.canonical_nan();
+        }
+        return fmt.inf(sign);
+    }
+    if fmt.is_inf(b) {
+        return fmt.zero(sign);
+    }
+    if fmt.is_zero(b) {
+        if fmt.is_zero(a) {
+            *flags |= FLAG_NV;
+            return fmt.canonical_nan();
+        }
+        *flags |= FLAG_DZ;
+        return fmt.inf(sign);
+    }
+    if fmt.is_zero(a) {
+        return fmt.zero(sign);
+    }
+
+    /* dividend msb at bit 125, divisor msb at bit 63, the quotient gets 62+ bits */
+    let ua: Unpacked = fmt.unpack(a);
+    let ub: Unpacked = fmt.unpack(b);
+    let sa: u32 = ua.sig.leading_zeros() - 2;
+    let sb: u32 = ub.sig.leading_zeros() - 64;
+    let num: u128 = ua.sig << sa;
+    let den: u128 = ub.sig << sb;
+    let mut quot: u128 = num / den;
+    if !num.is_multiple_of(den) {
+        quot |= 1;
+    }
+    let exp: i32 = (ua.exp - sa as i32) - (ub.exp - sb as i32);
+    return fmt.round_pack(sign, exp, quot, rm, flags);
+}
+
+pub fn sqrt(fmt: FpFormat, a: u64, rm: RoundingMode, flags: &mut u8) -> u64 {
+    if fmt.is_nan(a) {
+        return propagate_nan(fmt, &[a], flags);
+    }
+    /* sqrt(-0) = -0 */
+    if fmt.is_zero(a) {
+        return a;
+    }
+    if fmt.sign(a) {
+        *flags |= FLAG_NV;
+        return fmt.canonical_nan();
+    }
+    if fmt.is_inf(a) {
+        return a;
+    }
+
+    /* make the exponent even and the significand big enough for a 62 bit root */
+    let ua: Unpacked = fmt.unpack(a);
+    let mut shift: i32 = ua.sig.leading_zeros() as i32 - 3;
+    if (ua.exp - shift) & 0x1 != 0 {
+        shift += 1;
+    }
+    let (mut root, exact) = isqrt(ua.sig << shift);
+    if !exact {
+        root |= 1;
+    }
+    return fmt.round_pack(false, (ua.exp - shift) / 2, root, rm, flags);
+}
+
+/*
+ * name: fma
+ * desc: (a * b) + c with a single rounding, the product and addend signs can be
+ *       flipped to get FMSUB/FNMSUB/FNMADD
+ *
+ * NOTE: inf * 0 is invalid even when c is a quiet NaN (spec section 11.6)
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn fma(fmt: FpFormat, a: u64, b: u64, c: u64, neg_prod: bool, neg_add: bool, rm: RoundingMode, flags: &mut u8) -> u64 {
+    let inf_times_zero: bool = (fmt.is_inf(a) && fmt.is_zero(b)) || (fmt.is_zero(a) && fmt.is_inf(b));
+    if inf_times_zero {
+        *flags |= FLAG_NV;
+        return fmt.canonical_nan();
+    }
+    if fmt.is_nan(a) || fmt.is_nan(b) || fmt.is_nan(c) {
+        return propagate_nan(fmt, &[a, b, c], flags);
+    }
+
+    let prod_sign: bool = (fmt.sign(a) != fmt.sign(b)) != neg_prod;
+    let add_sign: bool = fmt.sign(c) != neg_add;
+
+    if fmt.is_inf(a) || fmt.is_inf(b) {
+        if fmt.is_inf(c) && add_sign != prod_sign {
+            *flags |= FLAG_NV;
+            return fmt.canonical_nan();
+        }
+        return fmt.inf(prod_sign);
+    }
+    if fmt.is_inf(c) {
+        return fmt.inf(add_sign);
+    }
+
+    let ua: Unpacked = fmt.unpack(a);
+    let ub: Unpacked = fmt.unpack(b);
+    let mut uc: Unpacked = fmt.unpack(c);
+    uc.sign = add_sign;
+    let prod: Unpacked = Unpacked { sign: prod_sign, exp: ua.exp + ub.exp, sig: ua.sig * ub.sig };
+    return add_unpacked(fmt, prod, uc, rm, flags);
+}
+
+/*
+ * name: convert
+ * desc: change precision, widening is always exact
+ */
+pub fn convert(from: FpFormat, to: FpFormat, a: u64, rm: RoundingMode, flags: &mut u8) -> u64 {
+    if from.is_nan(a) {
+        if from.is_snan(a) {
+            *flags |= FLAG_NV;
+        }
+        return to.canonical_nan();
+    }
+    if from.is_inf(a) {
+        return to.inf(from.sign(a));
+    }
+    let ua: Unpacked = from.unpack(a);
+    return to.round_pack(ua.sign, ua.exp, ua.sig, rm, flags);
+}
+
+/*
+ * name: to_int
+ * desc: float to a 32 or 64 bit integer, 32 bit results come back sign extended
+ *
+ * NOTE: out of range values and NaN are invalid and saturate, NaN gives the
+ *       largest positive value (spec table 11.4)
+ */
+pub fn to_int(fmt: FpFormat, a: u64, signed: bool, bits: u32, rm: RoundingMode, flags: &mut u8) -> u64 {
+    let (max, min): (i128, i128) = if signed {
+        ((1 << (bits - 1)) - 1, -(1 << (bits - 1)))
+    } else {
+        ((1 << bits) - 1, 0)
+    };
+    let result = |val: i128| -> u64 {
+        let val: u64 = val as u64;
+        return if bits == 32 { val as u32 as i32 as i64 as u64 } else { val };
+    };
+
+    if fmt.is_nan(a) {
+        *flags |= FLAG_NV;
+        return result(max);
+    }
+    if fmt.is_inf(a) {
+        *flags |= FLAG_NV;
+        return result(if fmt.sign(a) { min } else { max });
+    }
+
+    let ua: Unpacked = fmt.unpack(a);
+    let (mag, inexact): (u128, bool) = if ua.exp >= 0 {
+        /* already an integer, anything this big is out of range for 64 bits anyway */
+        if ua.exp > 64 { (u128::MAX, false) } else { (ua.sig << ua.exp, false) }
+    } else {
+        round_sig(ua.sig, -ua.exp, ua.sign, rm)
+    };
+
+    let val: i128 = if mag > (1 << 65) {
+        if ua.sign { i128::MIN } else { i128::MAX }
+    } else if ua.sign {
+        -(mag as i128)
+    } else {
+        mag as i128
+    };
+
+    if val > max || val < min {
+        *flags |= FLAG_NV;
+        return result(if val > max { max } else { min });
+    }
+    if inexact {
+        *flags |= FLAG_NX;
+    }
+    return result(val);
+}
+
+/* 32 or 64 bit integer to float, the integer is taken from the low `bits` bits of val */
+pub fn from_int(fmt: FpFormat, val: u64, signed: bool, bits: u32, rm: RoundingMode, flags: &mut u8) -> u64 {
+    let val: u64 = if bits == 32 { val & 0xFFFF_FFFF } else { val };
+    let negative: bool = signed && (val >> (bits - 1)) & 0x1 != 0;
+    let mag: u64 = if !negative {
+        val
+    } else if bits == 32 {
+        (val as u32 as i32).unsigned_abs() as u64
+    } else {
+        (val as i64).unsigned_abs()
+    };
+    return fmt.round_pack(negative, 0, mag as u128, rm, flags);
+}
+
+/* a < b ignoring NaNs, -0 and +0 are equal */
+fn less(fmt: FpFormat, a: u64, b: u64) -> bool {
+    let (sa, sb): (bool, bool) = (fmt.sign(a), fmt.sign(b));
+    let mag_mask: u64 = (1 << (fmt.exp_bits + fmt.frac_bits)) - 1;
+    let (ma, mb): (u64, u64) = (a & mag_mask, b & mag_mask);
+
+    if ma == 0 && mb == 0 {
+        return false;
+    }
+    if sa != sb {
+        return sa;
+    }
+    return if sa { ma > mb } else { ma < mb };
+}
+
+/* FEQ is a quiet compare, only signaling NaNs are invalid */
+pub fn eq(fmt: FpFormat, a: u64, b: u64, flags: &mut u8) -> bool {
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        if fmt.is_snan(a) || fmt.is_snan(b) {
+            *flags |= FLAG_NV;
+        }
+        return false;
+    }
+    return a == b || (fmt.is_zero(a) && fmt.is_zero(b));
+}
+
+/* FLT/FLE are signaling compares, any NaN is invalid */
+pub fn lt(fmt: FpFormat, a: u64, b: u64, flags: &mut u8) -> bool {
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        *flags |= FLAG_NV;
+        return false;
+    }
+    return less(fmt, a, b);
+}
+
+pub fn le(fmt: FpFormat, a: u64, b: u64, flags: &mut u8) -> bool {
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        *flags |= FLAG_NV;
+        return false;
+    }
+    return !less(fmt, b, a);
+}
+
+/*
+ * name: min_max
+ * desc: IEEE 754-2019 minimumNumber/maximumNumber, a single NaN input is ignored
+ *       and -0 is treated as smaller than +0
+ */
+pub fn min_max(fmt: FpFormat, a: u64, b: u64, is_max: bool, flags: &mut u8) -> u64 {
+    if fmt.is_snan(a) || fmt.is_snan(b) {
+        *flags |= FLAG_NV;
+    }
+    if fmt.is_nan(a) && fmt.is_nan(b) {
+        return fmt.canonical_nan();
+    }
+    if fmt.is_nan(a) {
+        return b;
+    }
+    if fmt.is_nan(b) {
+        return a;
+    }
+
+    let a_less: bool = if fmt.is_zero(a) && fmt.is_zero(b) { fmt.sign(a) } else { less(fmt, a, b) };
+    return if a_less != is_max { a } else { b };
+}
+
+pub fn classify(fmt: FpFormat, a: u64) -> u64 {
+    let sign: bool = fmt.sign(a);
+    let exp: u64 = fmt.exp_field(a);
+
+    let class: FClassBm = if fmt.is_nan(a) {
+        if fmt.is_snan(a) { FClassBm::SNAN_bm } else { FClassBm::QNAN_bm }
+    } else if fmt.is_inf(a) {
+        if sign { FClassBm::NEG_INF_bm } else { FClassBm::POS_INF_bm }
+    } else if fmt.is_zero(a) {
+        if sign { FClassBm::NEG_ZERO_bm } else { FClassBm::POS_ZERO_bm }
+    } else if exp == 0 {
+        if sign { FClassBm::NEG_SUBNORMAL_bm } else { FClassBm::POS_SUBNORMAL_bm }
+    } else if sign {
+        FClassBm::NEG_NORMAL_bm
+    } else {
+        FClassBm::POS_NORMAL_bm
+    };
+    return class as u64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [RoundingMode; 5] = [RoundingMode::RNE, RoundingMode::RTZ, RoundingMode::RDN, RoundingMode::RUP, RoundingMode::RMM];
+
+    /* result and the flags it raised, starting from none */
+    fn run(op: impl Fn(RoundingMode, &mut u8) -> u64, rm: RoundingMode) -> (u64, u8) {
+        let mut flags: u8 = 0;
+        let res: u64 = op(rm, &mut flags);
+        return (res, flags);
+    }
+
+    /* 1.0 plus half an ulp, and 1.0 + ulp plus half an ulp, are ties */
+    #[test]
+    fn ties() {
+        for (a, rne, rmm) in [(0x3F80_0000, 0x3F80_0000, 0x3F80_0001), (0x3F80_0001, 0x3F80_0002, 0x3F80_0002)] {
+            let tie = |rm: RoundingMode, flags: &mut u8| add(F32, a, 0x3380_0000, rm, flags);
+            assert_eq!(run(tie, RoundingMode::RNE), (rne, FLAG_NX), "{:08x}", a);
+            assert_eq!(run(tie, RoundingMode::RMM), (rmm, FLAG_NX), "{:08x}", a);
+            assert_eq!(run(tie, RoundingMode::RTZ), (a, FLAG_NX), "{:08x}", a);
+        }
+
+        /* a quarter ulp only moves the directed modes, away from zero for RUP on positives and RDN on negatives */
+        for (rm, pos, neg) in [
+            (RoundingMode::RNE, 0x3F80_0000, 0xBF80_0000),
+            (RoundingMode::RTZ, 0x3F80_0000, 0xBF80_0000),
+            (RoundingMode::RDN, 0x3F80_0000, 0xBF80_0001),
+            (RoundingMode::RUP, 0x3F80_0001, 0xBF80_0000),
+            (RoundingMode::RMM, 0x3F80_0000, 0xBF80_0000),
+        ] {
+            assert_eq!(run(|rm, flags| add(F32, 0x3F80_0000, 0x3300_0000, rm, flags), rm), (pos, FLAG_NX), "{:?}", rm);
+            assert_eq!(run(|rm, flags| add(F32, 0xBF80_0000, 0xB300_0000, rm, flags), rm), (neg, FLAG_NX), "{:?}", rm);
+        }
+
+        /* exact results raise nothing whatever the mode */
+        for rm in MODES {
+            assert_eq!(run(|rm, flags| add(F64, 0x3FF0_0000_0000_0000, 0x3FF0_0000_0000_0000, rm, flags), rm), (0x4000_0000_0000_0000, 0));
+        }
+    }
+
+    /* FLT_MAX * 2, the modes that round towards zero stop at the largest finite value */
+    #[test]
+    fn overflow() {
+        for (rm, pos, neg) in [
+            (RoundingMode::RNE, 0x7F80_0000, 0xFF80_0000),
+            (RoundingMode::RTZ, 0x7F7F_FFFF, 0xFF7F_FFFF),
+            (RoundingMode::RDN, 0x7F7F_FFFF, 0xFF80_0000),
+            (RoundingMode::RUP, 0x7F80_0000, 0xFF7F_FFFF),
+            (RoundingMode::RMM, 0x7F80_0000, 0xFF80_0000),
+        ] {
+            assert_eq!(run(|rm, flags| mul(F32, 0x7F7F_FFFF, 0x4000_0000, rm, flags), rm), (pos, FLAG_OF | FLAG_NX), "{:?}", rm);
+            assert_eq!(run(|rm, flags| mul(F32, 0xFF7F_FFFF, 0x4000_0000, rm, flags), rm), (neg, FLAG_OF | FLAG_NX), "{:?}", rm);
+        }
+    }
+
+    /*
+     * (1 - 2^-25) * 2^-126 is below the smallest normal, but rounding it to 24 bits
+     * with an unbounded exponent gives exactly 2^-126, so it isn't tiny after rounding
+     * and only raises NX, (1 - 2^-24) * 2^-126 stays tiny and raises UF too
+     */
+    #[test]
+    fn tininess_after_rounding() {
+        let not_tiny: u64 = 0x380F_FFFF_F000_0000;
+        let tiny: u64 = 0x380F_FFFF_E000_0000;
+        assert_eq!(run(|rm, flags| convert(F64, F32, not_tiny, rm, flags), RoundingMode::RNE), (0x0080_0000, FLAG_NX));
+        assert_eq!(run(|rm, flags| convert(F64, F32, not_tiny, rm, flags), RoundingMode::RTZ), (0x007F_FFFF, FLAG_UF | FLAG_NX));
+        assert_eq!(run(|rm, flags| convert(F64, F32, tiny, rm, flags), RoundingMode::RNE), (0x0080_0000, FLAG_UF | FLAG_NX));
+
+        /* an exact subnormal isn't an underflow */
+        assert_eq!(run(|rm, flags| mul(F32, 0x0080_0000, 0x3F00_0000, rm, flags), RoundingMode::RNE), (0x0040_0000, 0));
+    }
+
+    /* flags only ever get OR'ed in, that is how fflags accrues across instructions */
+    #[test]
+    fn flags_accumulate() {
+        let mut flags: u8 = 0;
+        assert_eq!(div(F32, 0x3F80_0000, 0, RoundingMode::RNE, &mut flags), 0x7F80_0000);
+        assert_eq!(flags, FLAG_DZ);
+        assert_eq!(div(F32, 0x3F80_0000, 0x4040_0000, RoundingMode::RNE, &mut flags), 0x3EAA_AAAB);
+        assert_eq!(flags, FLAG_DZ | FLAG_NX);
+        assert!(!lt(F32, F32.canonical_nan(), 0, &mut flags));
+        assert_eq!(flags, FLAG_DZ | FLAG_NX | FLAG_NV);
+
+        /* a signaling NaN input is invalid and gives the canonical NaN, a quiet one only propagates */
+        let mut flags: u8 = 0;
+        assert_eq!(add(F32, 0x7F80_0001, 0x3F80_0000, RoundingMode::RNE, &mut flags), F32.canonical_nan());
+        assert_eq!(flags, FLAG_NV);
+        let mut flags: u8 = 0;
+        assert_eq!(add(F64, 0x7FF8_0000_0000_0001, 0, RoundingMode::RNE, &mut flags), F64.canonical_nan());
+        assert_eq!(flags, 0);
+    }
+
+    /* out of range and NaN saturate with NV (spec table 11.4), 32 bit results are sign extended */
+    #[test]
+    fn to_int_saturates() {
+        let cvt = |a: u64, signed: bool, rm: RoundingMode| run(|rm, flags| to_int(F32, a, signed, 32, rm, flags), rm);
+        let rtz: RoundingMode = RoundingMode::RTZ;
+
+        assert_eq!(cvt(F32.canonical_nan(), true, rtz), (0x7FFF_FFFF, FLAG_NV));
+        assert_eq!(cvt(F32.canonical_nan(), false, rtz), (0xFFFF_FFFF_FFFF_FFFF, FLAG_NV));
+        assert_eq!(cvt(0xFF80_0000, true, rtz), (0xFFFF_FFFF_8000_0000, FLAG_NV));
+        assert_eq!(cvt(0x7F80_0000, true, rtz), (0x7FFF_FFFF, FLAG_NV));
+        assert_eq!(cvt(0x4F32_D05E, true, rtz), (0x7FFF_FFFF, FLAG_NV));
+        assert_eq!(cvt(0x4F32_D05E, false, rtz), (0xFFFF_FFFF_B2D0_5E00, 0));
+        assert_eq!(cvt(0xBFC0_0000, false, rtz), (0, FLAG_NV));
+        /* -0.5 truncates to -0, which fits an unsigned */
+        assert_eq!(cvt(0xBF00_0000, false, rtz), (0, FLAG_NX));
+        assert_eq!(cvt(0x4020_0000, true, RoundingMode::RNE), (2, FLAG_NX));
+        assert_eq!(cvt(0x4020_0000, true, RoundingMode::RMM), (3, FLAG_NX));
+
+        assert_eq!(run(|rm, flags| to_int(F64, 0x43E0_0000_0000_0000, true, 64, rm, flags), rtz), (i64::MAX as u64, FLAG_NV));
+        assert_eq!(run(|rm, flags| to_int(F64, 0xC3E0_0000_0000_0000, true, 64, rm, flags), rtz), (i64::MIN as u64, 0));
+    }
+}
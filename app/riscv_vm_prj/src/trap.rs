@@ -0,0 +1,124 @@
+/*
+ * name: trap.rs
+ * desc: synchronous exceptions raised while executing an instruction
+ *
+ * Note: instructions that raise an exception must not change any architectural
+ *       state, the cpu then records the cause in the CSRs and jumps to mtvec
+ *       (or stvec when the trap is delegated to S-mode)
+ *
+ */
+
+/* mcause values, see privileged spec table 3.6 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    InstructionAddressMisaligned(u64), /* target address */
+    InstructionAccessFault(u64),       /* fetch address */
+    IllegalInstruction(u64),           /* the instruction bits */
+    Breakpoint(u64),                   /* address of the ebreak */
+    LoadAddressMisaligned(u64),        /* load address */
+    LoadAccessFault(u64),              /* load address */
+    StoreAddressMisaligned(u64),       /* store/AMO address */
+    StoreAccessFault(u64),             /* store/AMO address */
+    EnvironmentCallFromUMode,
+    EnvironmentCallFromSMode,
+    EnvironmentCallFromMMode,
+    InstructionPageFault(u64),         /* virtual fetch address */
+    LoadPageFault(u64),                /* virtual load address */
+    StorePageFault(u64),               /* virtual store/AMO address */
+}
+
+/* interrupt causes, mcause/scause have the interrupt bit set on top of these */
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Interrupt {
+    SSI = 1,  /* supervisor software */
+    MSI = 3,  /* machine software */
+    STI = 5,  /* supervisor timer */
+    MTI = 7,  /* machine timer */
+    SEI = 9,  /* supervisor external */
+    MEI = 11, /* machine external */
+}
+
+impl Exception {
+    pub fn cause(&self) -> u32 {
+        return match self {
+            Exception::InstructionAddressMisaligned(_) => 0,
+            Exception::InstructionAccessFault(_) => 1,
+            Exception::IllegalInstruction(_) => 2,
+            Exception::Breakpoint(_) => 3,
+            Exception::LoadAddressMisaligned(_) => 4,
+            Exception::LoadAccessFault(_) => 5,
+            Exception::StoreAddressMisaligned(_) => 6,
+            Exception::StoreAccessFault(_) => 7,
+            Exception::EnvironmentCallFromUMode => 8,
+            Exception::EnvironmentCallFromSMode => 9,
+            Exception::EnvironmentCallFromMMode => 11,
+            Exception::InstructionPageFault(_) => 12,
+            Exception::LoadPageFault(_) => 13,
+            Exception::StorePageFault(_) => 15,
+        };
+    }
+
+    /* for messages, the spec's names */
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Exception::InstructionAddressMisaligned(_) => "instruction address misaligned",
+            Exception::InstructionAccessFault(_) => "instruction access fault",
+            Exception::IllegalInstruction(_) => "illegal instruction",
+            Exception::Breakpoint(_) => "breakpoint",
+            Exception::LoadAddressMisaligned(_) => "load address misaligned",
+            Exception::LoadAccessFault(_) => "load access fault",
+            Exception::StoreAddressMisaligned(_) => "store/AMO address misaligned",
+            Exception::StoreAccessFault(_) => "store/AMO access fault",
+            Exception::EnvironmentCallFromUMode => "environment call from U-mode",
+            Exception::EnvironmentCallFromSMode => "environment call from S-mode",
+            Exception::EnvironmentCallFromMMode => "environment call from M-mode",
+            Exception::InstructionPageFault(_) => "instruction page fault",
+            Exception::LoadPageFault(_) => "load page fault",
+            Exception::StorePageFault(_) => "store/AMO page fault",
+        };
+    }
+
+    /* value written to mtval */
+    pub fn tval(&self) -> u64 {
+        return match self {
+            Exception::InstructionAddressMisaligned(val)
+            | Exception::InstructionAccessFault(val)
+            | Exception::IllegalInstruction(val)
+            | Exception::Breakpoint(val)
+            | Exception::LoadAddressMisaligned(val)
+            | Exception::LoadAccessFault(val)
+            | Exception::StoreAddressMisaligned(val)
+            | Exception::StoreAccessFault(val)
+            | Exception::InstructionPageFault(val)
+            | Exception::LoadPageFault(val)
+            | Exception::StorePageFault(val) => *val,
+            Exception::EnvironmentCallFromUMode
+            | Exception::EnvironmentCallFromSMode
+            | Exception::EnvironmentCallFromMMode => 0,
+        };
+    }
+}
+
+/* alignment checks, loads/stores/AMOs must be naturally aligned */
+pub fn check_load_align(addr: u64, size: u64) -> Result<(), Exception> {
+    if (addr & (size - 1)) != 0 {
+        return Err(Exception::LoadAddressMisaligned(addr));
+    }
+    return Ok(());
+}
+
+pub fn check_store_align(addr: u64, size: u64) -> Result<(), Exception> {
+    if (addr & (size - 1)) != 0 {
+        return Err(Exception::StoreAddressMisaligned(addr));
+    }
+    return Ok(());
+}
+
+/* jump and branch targets must be IALIGN aligned, half words with RVC and words without */
+pub fn check_fetch_align(target: u64, ialign: u64) -> Result<(), Exception> {
+    if (target & (ialign - 1)) != 0 {
+        return Err(Exception::InstructionAddressMisaligned(target));
+    }
+    return Ok(());
+}
@@ -0,0 +1,1546 @@
+/*
+ * name: vector.rs
+ * desc: vector extension (RVV 1.0), integer subset: the vector register file,
+ *       vsetvl/vsetvli/vsetivli, unit-stride and strided loads/stores, integer
+ *       arithmetic, mask and reduction instructions
+ *
+ * Note: ELEN is 64, SEW can be 8 to 64 with LMUL 1/8 to 8 and VLEN is picked when
+ *       the machine is built (a power of two between 64 and 65536 bits)
+ *
+ *       floating point, fixed point averaging/clipping/scaling, permutations,
+ *       segment, indexed and whole register accesses are not implemented and
+ *       decode as illegal instructions, misa.V stays clear since this is only
+ *       the integer (Zve64x shaped) part of V
+ *
+ *       tail and masked-off elements are always left undisturbed, which is what
+ *       both the agnostic and the undisturbed policies allow
+ *
+ *       an instruction starts at vstart and always runs to completion, leaving
+ *       vstart at zero, loads and stores translate and check every element before
+ *       touching anything so a trap leaves all state alone and the handler simply
+ *       restarts the whole instruction
+ *
+ *       results are collected before any of them is written back, so a destination
+ *       overlapping a source never reads an element it already overwrote
+ *
+ */
+
+use crate::memory::*;
+use crate::csr::*;
+use crate::trap::*;
+use crate::xlen::*;
+use crate::mmu::*;
+use crate::disasm::*;
+
+pub const VLEN_DEFAULT: usize = 128;
+pub const VLEN_MIN: usize = 64;
+pub const VLEN_MAX: usize = 65536;
+
+/* widest element supported */
+const ELEN: u32 = 64;
+
+/* VLEN has to be a power of two no smaller than ELEN */
+pub fn is_valid_vlen(vlen: usize) -> bool {
+    return vlen.is_power_of_two() && (VLEN_MIN..=VLEN_MAX).contains(&vlen);
+}
+
+#[derive(Debug)]
+pub struct VRegFile {
+    vlenb: usize, /* bytes per register */
+    v: Vec<u8>,   /* v0..v31 back to back, elements are little endian */
+}
+
+impl VRegFile {
+    /* constructor: all registers start at zero */
+    pub fn new(vlen: usize) -> VRegFile {
+        return VRegFile {
+            vlenb: vlen / 8,
+            v: vec![0; 32 * vlen / 8],
+        };
+    }
+
+    pub fn vlen(&self) -> u64 {
+        return (self.vlenb * 8) as u64;
+    }
+
+    /* element idx of a register group starting at reg, groups are consecutive registers */
+    pub fn read(&self, reg: u8, idx: u64, sew: u32) -> u64 {
+        let bytes: usize = (sew / 8) as usize;
+        let base: usize = reg as usize * self.vlenb + idx as usize * bytes;
+        let mut val: u64 = 0;
+        for i in 0..bytes {
+            val |= (self.v[base + i] as u64) << (8 * i);
+        }
+        return val;
+    }
+
+    pub fn write(&mut self, reg: u8, idx: u64, sew: u32, val: u64) {
+        let bytes: usize = (sew / 8) as usize;
+        let base: usize = reg as usize * self.vlenb + idx as usize * bytes;
+        for i in 0..bytes {
+            self.v[base + i] = (val >> (8 * i)) as u8;
+        }
+    }
+
+    /* masks are packed one bit per element */
+    pub fn mask_bit(&self, reg: u8, idx: u64) -> bool {
+        let byte: u8 = self.v[reg as usize * self.vlenb + (idx / 8) as usize];
+        return ((byte >> (idx % 8)) & 1) != 0;
+    }
+
+    pub fn set_mask_bit(&mut self, reg: u8, idx: u64, bit: bool) {
+        let pos: usize = reg as usize * self.vlenb + (idx / 8) as usize;
+        if bit {
+            self.v[pos] |= 1 << (idx % 8);
+        }
+        else {
+            self.v[pos] &= !(1 << (idx % 8));
+        }
+    }
+}
+
+/* vtype fields */
+pub const VTYPE_VLMUL_MASK: u64 = 0x7;
+pub const VTYPE_VSEW_SHIFT: u64 = 3;
+pub const VTYPE_VSEW_MASK: u64  = 0x7;
+pub const VTYPE_FIELDS_MASK: u64 = 0xFF; /* vlmul, vsew, vta and vma, the rest is reserved */
+
+/* vill, the top bit of vtype */
+pub fn vtype_vill(xlen: Xlen) -> u64 {
+    return 1 << (xlen.bits() - 1);
+}
+
+/* a legal vtype setting */
+#[derive(Debug, Clone, Copy)]
+pub struct VType {
+    sew: u32,       /* element width in bits */
+    lmul_log2: i32, /* -3 (1/8) to 3 (8) */
+}
+
+impl VType {
+    /* None for reserved encodings and settings this hart can't run, those set vill */
+    pub fn decode(vtype: u64, xlen: Xlen) -> Option<VType> {
+        if (xlen.trunc(vtype) & !VTYPE_FIELDS_MASK) != 0 {
+            return None;
+        }
+
+        let vlmul: u64 = vtype & VTYPE_VLMUL_MASK;
+        let vsew: u64 = (vtype >> VTYPE_VSEW_SHIFT) & VTYPE_VSEW_MASK;
+        if vlmul == 4 || vsew > 3 {
+            return None;
+        }
+
+        let lmul_log2: i32 = if vlmul < 4 { vlmul as i32 } else { vlmul as i32 - 8 };
+        let sew: u32 = 8 << vsew;
+        /* a fractional LMUL has to leave room for at least one element of SEW */
+        if lmul_log2 < 0 && sew > (ELEN >> -lmul_log2) {
+            return None;
+        }
+        return Some(VType { sew: sew, lmul_log2: lmul_log2 });
+    }
+
+    /* elements a register group holds */
+    pub fn vlmax(&self, vlen: u64) -> u64 {
+        let per_reg: u64 = vlen / self.sew as u64;
+        if self.lmul_log2 >= 0 {
+            return per_reg << self.lmul_log2;
+        }
+        return per_reg >> -self.lmul_log2;
+    }
+}
+
+/* a register group of 2^emul_log2 registers has to start at a multiple of its size */
+fn group_ok(reg: u8, emul_log2: i32) -> bool {
+    if !(-3..=3).contains(&emul_log2) {
+        return false;
+    }
+    if emul_log2 <= 0 {
+        return true;
+    }
+    return (reg as u32).is_multiple_of(1 << emul_log2);
+}
+
+/* element helpers, values travel as u64 holding SEW bits */
+fn trunc(val: u64, sew: u32) -> u64 {
+    if sew == 64 {
+        return val;
+    }
+    return val & ((1 << sew) - 1);
+}
+
+fn sext(val: u64, sew: u32) -> i64 {
+    return ((val << (64 - sew)) as i64) >> (64 - sew);
+}
+
+fn smax(sew: u32) -> i128 {
+    return (1i128 << (sew - 1)) - 1;
+}
+
+fn smin(sew: u32) -> i128 {
+    return -(1i128 << (sew - 1));
+}
+
+fn umax(sew: u32) -> u128 {
+    return (1u128 << sew) - 1;
+}
+
+/* the other source: vs1 element by element, or one scalar (x register or immediate) for all of them */
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Vector(u8),
+    Scalar(u64),
+}
+
+impl Operand {
+    fn value(&self, vregs: &VRegFile, idx: u64, sew: u32) -> u64 {
+        return match self {
+            Operand::Vector(reg) => vregs.read(*reg, idx, sew),
+            Operand::Scalar(val) => trunc(*val, sew),
+        };
+    }
+
+    fn group_ok(&self, emul_log2: i32) -> bool {
+        return match self {
+            Operand::Vector(reg) => group_ok(*reg, emul_log2),
+            Operand::Scalar(_) => true,
+        };
+    }
+}
+
+/* the vtype/vl state an instruction runs with */
+#[derive(Debug, Clone, Copy)]
+struct VecCfg {
+    sew: u32,
+    lmul_log2: i32,
+    vl: u64,
+    vstart: u64,
+}
+
+/* OP-V vector instructions plus the vector forms of LOAD-FP/STORE-FP */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VectorInst {
+    func6: u8,  /* 6 bits, arithmetic */
+    nf: u8,     /* 3 bits, loads/stores, segment count - 1 */
+    mew: u8,    /* 1 bit, loads/stores, reserved */
+    mop: u8,    /* 2 bits, loads/stores, addressing mode */
+    vm: u8,     /* 1 bit, 0 = masked by v0 */
+    vs2: u8,    /* 5 bits, also rs2 and lumop/sumop */
+    vs1: u8,    /* 5 bits, also rs1 and the 5 bit immediate */
+    func3: u8,  /* 3 bits, operand kinds or load/store width */
+    vd: u8,     /* 5 bits, also rd and vs3 of stores */
+    opcode: u8, /* 7 bits */
+    zimm: u16,  /* 12 bits, vsetvli/vsetivli vtype immediate and the form bits above it */
+}
+
+pub enum VectorOpcodes {
+    LOAD_FP  = 0x07,
+    STORE_FP = 0x27,
+    OP_V     = 0x57,
+}
+
+/* OP-V func3, operand kinds, the FP kinds (OPFVV = 0x1, OPFVF = 0x5) aren't implemented */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorFunc3Sel {
+    OPIVV = 0x0,
+    OPMVV = 0x2,
+    OPIVI = 0x3,
+    OPIVX = 0x4,
+    OPMVX = 0x6,
+    OPCFG = 0x7, /* vsetvli, vsetivli, vsetvl */
+}
+
+/* width field of vector loads/stores, the remaining values are the scalar FP ones */
+pub enum VectorWidthSel {
+    E8  = 0x0,
+    E16 = 0x5,
+    E32 = 0x6,
+    E64 = 0x7,
+}
+
+/* mop field, the indexed ones (0x1, 0x3) aren't implemented */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorMopSel {
+    UNIT_STRIDE = 0x0,
+    STRIDED     = 0x2,
+}
+
+/* lumop/sumop of unit stride accesses */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorUmopSel {
+    UNIT = 0x00,
+    MASK = 0x0B, /* vlm.v/vsm.v */
+}
+
+/* OPIVV/OPIVX/OPIVI func6 */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorOpiSel {
+    VADD      = 0x00,
+    VSUB      = 0x02,
+    VRSUB     = 0x03,
+    VMINU     = 0x04,
+    VMIN      = 0x05,
+    VMAXU     = 0x06,
+    VMAX      = 0x07,
+    VAND      = 0x09,
+    VOR       = 0x0A,
+    VXOR      = 0x0B,
+    VADC      = 0x10,
+    VMADC     = 0x11,
+    VSBC      = 0x12,
+    VMSBC     = 0x13,
+    VMERGE    = 0x17, /* vmv.v.* when unmasked */
+    VMSEQ     = 0x18,
+    VMSNE     = 0x19,
+    VMSLTU    = 0x1A,
+    VMSLT     = 0x1B,
+    VMSLEU    = 0x1C,
+    VMSLE     = 0x1D,
+    VMSGTU    = 0x1E,
+    VMSGT     = 0x1F,
+    VSADDU    = 0x20,
+    VSADD     = 0x21,
+    VSSUBU    = 0x22,
+    VSSUB     = 0x23,
+    VSLL      = 0x25,
+    VSRL      = 0x28,
+    VSRA      = 0x29,
+    VNSRL     = 0x2C,
+    VNSRA     = 0x2D,
+    VWREDSUMU = 0x30,
+    VWREDSUM  = 0x31,
+}
+
+/* OPMVV/OPMVX func6 */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorOpmSel {
+    VREDSUM   = 0x00,
+    VREDAND   = 0x01,
+    VREDOR    = 0x02,
+    VREDXOR   = 0x03,
+    VREDMINU  = 0x04,
+    VREDMIN   = 0x05,
+    VREDMAXU  = 0x06,
+    VREDMAX   = 0x07,
+    VWXUNARY0 = 0x10, /* vmv.x.s, vcpop.m, vfirst.m (OPMVV) and vmv.s.x (OPMVX) */
+    VXUNARY0  = 0x12, /* vzext/vsext */
+    VMUNARY0  = 0x14, /* vmsbf, vmsof, vmsif, viota, vid */
+    VMANDN    = 0x18,
+    VMAND     = 0x19,
+    VMOR      = 0x1A,
+    VMXOR     = 0x1B,
+    VMORN     = 0x1C,
+    VMNAND    = 0x1D,
+    VMNOR     = 0x1E,
+    VMXNOR    = 0x1F,
+    VDIVU     = 0x20,
+    VDIV      = 0x21,
+    VREMU     = 0x22,
+    VMULHU    = 0x24,
+    VMUL      = 0x25,
+    VMULHSU   = 0x26,
+    VMULH     = 0x27,
+    VMADD     = 0x29,
+    VNMSUB    = 0x2B,
+    VMACC     = 0x2D,
+    VNMSAC    = 0x2F,
+    VWADDU    = 0x30,
+    VWADD     = 0x31,
+    VWSUBU    = 0x32,
+    VWSUB     = 0x33,
+    VWADDU_W  = 0x34,
+    VWADD_W   = 0x35,
+    VWSUBU_W  = 0x36,
+    VWSUB_W   = 0x37,
+    VWMULU    = 0x38,
+    VWMULSU   = 0x3A,
+    VWMUL     = 0x3B,
+    VWMACCU   = 0x3C,
+    VWMACC    = 0x3D,
+    VWMACCUS  = 0x3E, /* OPMVX only */
+}
+
+/* vs1 field of VWXUNARY0 */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorWxunarySel {
+    VMV_X_S  = 0x00,
+    VCPOP    = 0x10,
+    VFIRST   = 0x11,
+}
+
+/* vs1 field of VMUNARY0 */
+#[allow(clippy::upper_case_acronyms)]
+pub enum VectorMunarySel {
+    VMSBF = 0x01,
+    VMSOF = 0x02,
+    VMSIF = 0x03,
+    VIOTA = 0x10,
+    VID   = 0x11,
+}
+
+/* vtype as vsetvli spells it (e32,m1,ta,mu), reserved settings in hex */
+fn vtype_text(vtype: u64) -> String {
+    let vlmul: u64 = vtype & VTYPE_VLMUL_MASK;
+    let vsew: u64 = (vtype >> VTYPE_VSEW_SHIFT) & VTYPE_VSEW_MASK;
+    if (vtype & !VTYPE_FIELDS_MASK) != 0 || vlmul == 4 || vsew > 3 {
+        return format!("0x{:x}", vtype);
+    }
+
+    let lmul: &str = ["m1", "m2", "m4", "m8", "", "mf8", "mf4", "mf2"][vlmul as usize];
+    let ta: &str = if (vtype & 0x40) != 0 { "ta" } else { "tu" };
+    let ma: &str = if (vtype & 0x80) != 0 { "ma" } else { "mu" };
+    return format!("e{},{},{},{}", 8 << vsew, lmul, ta, ma);
+}
+
+/* element wise SEW op of the OPI group, returns the result and whether it saturated */
+fn opi_arith(func6: u8, a: u64, b: u64, sew: u32) -> (u64, bool) {
+    let shamt: u32 = (b as u32) & (sew - 1);
+    let sa: i128 = sext(a, sew) as i128;
+    let sb: i128 = sext(b, sew) as i128;
+
+    let res: u64 = match func6 {
+        f if f == VectorOpiSel::VADD as u8 => a.wrapping_add(b),
+        f if f == VectorOpiSel::VSUB as u8 => a.wrapping_sub(b),
+        f if f == VectorOpiSel::VRSUB as u8 => b.wrapping_sub(a),
+        f if f == VectorOpiSel::VMINU as u8 => a.min(b),
+        f if f == VectorOpiSel::VMIN as u8 => sa.min(sb) as u64,
+        f if f == VectorOpiSel::VMAXU as u8 => a.max(b),
+        f if f == VectorOpiSel::VMAX as u8 => sa.max(sb) as u64,
+        f if f == VectorOpiSel::VAND as u8 => a & b,
+        f if f == VectorOpiSel::VOR as u8 => a | b,
+        f if f == VectorOpiSel::VXOR as u8 => a ^ b,
+        f if f == VectorOpiSel::VSLL as u8 => a << shamt,
+        f if f == VectorOpiSel::VSRL as u8 => a >> shamt,
+        f if f == VectorOpiSel::VSRA as u8 => (sext(a, sew) >> shamt) as u64,
+
+        /* saturating forms clamp to the SEW range and report it in vxsat */
+        f if f == VectorOpiSel::VSADDU as u8 => {
+            let sum: u128 = a as u128 + b as u128;
+            return if sum > umax(sew) { (trunc(u64::MAX, sew), true) } else { (sum as u64, false) };
+        }
+        f if f == VectorOpiSel::VSSUBU as u8 => {
+            return if b > a { (0, true) } else { (a - b, false) };
+        }
+        f if f == VectorOpiSel::VSADD as u8 || f == VectorOpiSel::VSSUB as u8 => {
+            let res: i128 = if f == VectorOpiSel::VSADD as u8 { sa + sb } else { sa - sb };
+            let clamped: i128 = res.clamp(smin(sew), smax(sew));
+            return (trunc(clamped as u64, sew), clamped != res);
+        }
+        _ => 0,
+    };
+    return (trunc(res, sew), false);
+}
+
+/* compares of the OPI group */
+fn opi_compare(func6: u8, a: u64, b: u64, sew: u32) -> bool {
+    let sa: i64 = sext(a, sew);
+    let sb: i64 = sext(b, sew);
+    return match func6 {
+        f if f == VectorOpiSel::VMSEQ as u8 => a == b,
+        f if f == VectorOpiSel::VMSNE as u8 => a != b,
+        f if f == VectorOpiSel::VMSLTU as u8 => a < b,
+        f if f == VectorOpiSel::VMSLT as u8 => sa < sb,
+        f if f == VectorOpiSel::VMSLEU as u8 => a <= b,
+        f if f == VectorOpiSel::VMSLE as u8 => sa <= sb,
+        f if f == VectorOpiSel::VMSGTU as u8 => a > b,
+        _ => sa > sb, /* VMSGT */
+    };
+}
+
+/* multiply and divide of the OPM group, a = vs2, b = vs1/rs1 */
+fn opm_arith(func6: u8, a: u64, b: u64, sew: u32) -> u64 {
+    let sa: i64 = sext(a, sew);
+    let sb: i64 = sext(b, sew);
+    let res: u64 = match func6 {
+        f if f == VectorOpmSel::VMUL as u8 => a.wrapping_mul(b),
+        f if f == VectorOpmSel::VMULH as u8 => ((sa as i128 * sb as i128) >> sew) as u64,
+        f if f == VectorOpmSel::VMULHU as u8 => ((a as u128 * b as u128) >> sew) as u64,
+        f if f == VectorOpmSel::VMULHSU as u8 => ((sa as i128 * b as i128) >> sew) as u64,
+
+        /* same results as the scalar M extension for division by zero and overflow */
+        f if f == VectorOpmSel::VDIVU as u8 => a.checked_div(b).unwrap_or(u64::MAX),
+        f if f == VectorOpmSel::VREMU as u8 => if b == 0 { a } else { a % b },
+        f if f == VectorOpmSel::VDIV as u8 => {
+            if sb == 0 { u64::MAX } else if sa as i128 == smin(sew) && sb == -1 { a } else { (sa / sb) as u64 }
+        }
+        _ => {
+            /* VREM */
+            if sb == 0 { a } else if sa as i128 == smin(sew) && sb == -1 { 0 } else { (sa % sb) as u64 }
+        }
+    };
+    return trunc(res, sew);
+}
+
+/* single width multiply-add, d is the old destination element */
+fn opm_macc(func6: u8, d: u64, a: u64, b: u64, sew: u32) -> u64 {
+    let res: u64 = match func6 {
+        f if f == VectorOpmSel::VMACC as u8 => b.wrapping_mul(a).wrapping_add(d),
+        f if f == VectorOpmSel::VNMSAC as u8 => d.wrapping_sub(b.wrapping_mul(a)),
+        f if f == VectorOpmSel::VMADD as u8 => b.wrapping_mul(d).wrapping_add(a),
+        _ => a.wrapping_sub(b.wrapping_mul(d)), /* VNMSUB */
+    };
+    return trunc(res, sew);
+}
+
+/* reduction step, acc and the element are SEW bits */
+fn reduce(func6: u8, acc: u64, elem: u64, sew: u32) -> u64 {
+    let res: u64 = match func6 {
+        f if f == VectorOpmSel::VREDSUM as u8 => acc.wrapping_add(elem),
+        f if f == VectorOpmSel::VREDAND as u8 => acc & elem,
+        f if f == VectorOpmSel::VREDOR as u8 => acc | elem,
+        f if f == VectorOpmSel::VREDXOR as u8 => acc ^ elem,
+        f if f == VectorOpmSel::VREDMINU as u8 => acc.min(elem),
+        f if f == VectorOpmSel::VREDMIN as u8 => sext(acc, sew).min(sext(elem, sew)) as u64,
+        f if f == VectorOpmSel::VREDMAXU as u8 => acc.max(elem),
+        _ => sext(acc, sew).max(sext(elem, sew)) as u64, /* VREDMAX */
+    };
+    return trunc(res, sew);
+}
+
+/* widening op, a = vs2 (2*SEW for the .w forms), b = vs1/rs1, d = old 2*SEW destination */
+fn opm_widen(func6: u8, d: u64, a: u64, b: u64, sew: u32) -> u64 {
+    let wide: u32 = sew * 2;
+    let za: u64 = trunc(a, sew);
+    let zb: u64 = trunc(b, sew);
+    let sa: u64 = sext(a, sew) as u64;
+    let sb: u64 = sext(b, sew) as u64;
+
+    let res: u64 = match func6 {
+        f if f == VectorOpmSel::VWADDU as u8 => za.wrapping_add(zb),
+        f if f == VectorOpmSel::VWADD as u8 => sa.wrapping_add(sb),
+        f if f == VectorOpmSel::VWSUBU as u8 => za.wrapping_sub(zb),
+        f if f == VectorOpmSel::VWSUB as u8 => sa.wrapping_sub(sb),
+        f if f == VectorOpmSel::VWADDU_W as u8 => a.wrapping_add(zb),
+        f if f == VectorOpmSel::VWADD_W as u8 => a.wrapping_add(sb),
+        f if f == VectorOpmSel::VWSUBU_W as u8 => a.wrapping_sub(zb),
+        f if f == VectorOpmSel::VWSUB_W as u8 => a.wrapping_sub(sb),
+        f if f == VectorOpmSel::VWMULU as u8 => za.wrapping_mul(zb),
+        f if f == VectorOpmSel::VWMULSU as u8 => sa.wrapping_mul(zb),
+        f if f == VectorOpmSel::VWMUL as u8 => sa.wrapping_mul(sb),
+        f if f == VectorOpmSel::VWMACCU as u8 => zb.wrapping_mul(za).wrapping_add(d),
+        f if f == VectorOpmSel::VWMACC as u8 => sb.wrapping_mul(sa).wrapping_add(d),
+        f if f == VectorOpmSel::VWMACCUS as u8 => zb.wrapping_mul(sa).wrapping_add(d),
+        _ => sb.wrapping_mul(za).wrapping_add(d), /* VWMACCSU */
+    };
+    return trunc(res, wide);
+}
+
+fn commit(vregs: &mut VRegFile, vd: u8, sew: u32, results: Vec<(u64, u64)>) {
+    for (idx, val) in results {
+        vregs.write(vd, idx, sew, val);
+    }
+}
+
+fn commit_mask(vregs: &mut VRegFile, vd: u8, results: Vec<(u64, bool)>) {
+    for (idx, bit) in results {
+        vregs.set_mask_bit(vd, idx, bit);
+    }
+}
+
+impl VectorInst {
+
+    pub fn new(inst: u32) -> VectorInst {
+        let func6: u8 = ((inst >> 26) & 0x3F) as u8;
+        let nf: u8 = ((inst >> 29) & 0x7) as u8;
+        let mew: u8 = ((inst >> 28) & 0x1) as u8;
+        let mop: u8 = ((inst >> 26) & 0x3) as u8;
+        let vm: u8 = ((inst >> 25) & 0x1) as u8;
+        let vs2: u8 = ((inst >> 20) & 0x1F) as u8;
+        let vs1: u8 = ((inst >> 15) & 0x1F) as u8;
+        let func3: u8 = ((inst >> 12) & 0x7) as u8;
+        let vd: u8 = ((inst >> 7) & 0x1F) as u8;
+        let opcode: u8 = (inst & 0x7F) as u8;
+        let zimm: u16 = ((inst >> 20) & 0xFFF) as u16;
+
+        return VectorInst {
+            func6: func6,
+            nf: nf,
+            mew: mew,
+            mop: mop,
+            vm: vm,
+            vs2: vs2,
+            vs1: vs1,
+            func3: func3,
+            vd: vd,
+            opcode: opcode,
+            zimm: zimm,
+        };
+    }
+
+    /* back to the 32 bit encoding, nf/mew/mop and zimm overlap func6/vm/vs2 */
+    pub fn encode(&self) -> u32 {
+        return ((self.func6 as u32) << 26)
+             | ((self.vm as u32) << 25)
+             | ((self.vs2 as u32) << 20)
+             | ((self.vs1 as u32) << 15)
+             | ((self.func3 as u32) << 12)
+             | ((self.vd as u32) << 7)
+             | (self.opcode as u32);
+    }
+
+    /* unmasked, or the element's bit in v0 is set */
+    fn active(&self, vregs: &VRegFile, idx: u64) -> bool {
+        return self.vm == 1 || vregs.mask_bit(0, idx);
+    }
+
+    /* a masked instruction can't write v0 unless the result is a mask */
+    fn overlaps_mask(&self) -> bool {
+        return self.vm == 0 && self.vd == 0;
+    }
+
+    /*
+     * name: vset_execute
+     * desc: vsetvli, vsetivli and vsetvl, vl = min(AVL, VLMAX)
+     *
+     * NOTE: rs1 = x0 asks for VLMAX when rd isn't x0 and keeps the current vl
+     *       otherwise, an unsupported vtype sets vill and vl = 0
+     */
+    fn vset_execute(&self, regs: &mut RegFile, vregs: &VRegFile, csrs: &mut CsrFile) -> Result<(),Exception> {
+        let xlen: Xlen = regs.xlen();
+        let vtype: u64;
+        let avl: u64;
+
+        if (self.zimm >> 11) == 0 {
+            /* vsetvli */
+            vtype = (self.zimm & 0x7FF) as u64;
+            avl = self.avl(regs, csrs);
+        }
+        else if (self.zimm >> 10) == 0x3 {
+            /* vsetivli, the AVL is an immediate in the rs1 field */
+            vtype = (self.zimm & 0x3FF) as u64;
+            avl = self.vs1 as u64;
+        }
+        else if self.func6 == 0x20 && self.vm == 0 {
+            /* vsetvl */
+            vtype = regs.read(self.vs2);
+            avl = self.avl(regs, csrs);
+        }
+        else {
+            return Err(Exception::IllegalInstruction(0));
+        }
+
+        match VType::decode(vtype, xlen) {
+            Some(vt) => {
+                let vl: u64 = avl.min(vt.vlmax(vregs.vlen()));
+                csrs.set_vector_config(vl, vtype);
+                regs.write(self.vd, vl);
+            }
+            None => {
+                csrs.set_vector_config(0, vtype_vill(xlen));
+                regs.write(self.vd, 0);
+            }
+        }
+        return Ok(());
+    }
+
+    fn avl(&self, regs: &RegFile, csrs: &CsrFile) -> u64 {
+        if self.vs1 != 0 {
+            return regs.read(self.vs1);
+        }
+        if self.vd != 0 {
+            return u64::MAX;
+        }
+        return csrs.get_vl();
+    }
+
+    /*
+     * name: mem_execute
+     * desc: unit-stride (vle/vse, vlm/vsm) and strided (vlse/vsse) loads and stores
+     *
+     * NOTE: EEW comes from the width field, EMUL = EEW/SEW * LMUL, masked off
+     *       elements are never accessed so they can't fault
+     */
+    fn mem_execute(&self, regs: &RegFile, vregs: &mut VRegFile, mem: &mut Memory, mmu: &mut Mmu, cfg: &VecCfg) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let is_store: bool = self.opcode == VectorOpcodes::STORE_FP as u8;
+        let access: AccessType = if is_store { AccessType::Store } else { AccessType::Load };
+
+        let eew: u32 = match self.func3 {
+            w if w == VectorWidthSel::E8 as u8 => 8,
+            w if w == VectorWidthSel::E16 as u8 => 16,
+            w if w == VectorWidthSel::E32 as u8 => 32,
+            w if w == VectorWidthSel::E64 as u8 => 64,
+            _ => return Err(illegal),
+        };
+
+        /* segments and the extended width bit aren't supported */
+        if self.nf != 0 || self.mew != 0 {
+            return Err(illegal);
+        }
+
+        let base: u64 = regs.read(self.vs1);
+        let bytes: u64 = (eew / 8) as u64;
+        let evl: u64;
+        let stride: u64;
+        let emul_log2: i32;
+
+        match self.mop {
+            mop if mop == VectorMopSel::UNIT_STRIDE as u8 && self.vs2 == VectorUmopSel::UNIT as u8 => {
+                evl = cfg.vl;
+                stride = bytes;
+                emul_log2 = eew.trailing_zeros() as i32 - cfg.sew.trailing_zeros() as i32 + cfg.lmul_log2;
+            }
+            /* vlm.v/vsm.v move ceil(vl/8) bytes of a mask register */
+            mop if mop == VectorMopSel::UNIT_STRIDE as u8 && self.vs2 == VectorUmopSel::MASK as u8 => {
+                if eew != 8 || self.vm == 0 {
+                    return Err(illegal);
+                }
+                evl = cfg.vl.div_ceil(8);
+                stride = 1;
+                emul_log2 = 0;
+            }
+            mop if mop == VectorMopSel::STRIDED as u8 => {
+                evl = cfg.vl;
+                stride = regs.read_signed(self.vs2) as u64;
+                emul_log2 = eew.trailing_zeros() as i32 - cfg.sew.trailing_zeros() as i32 + cfg.lmul_log2;
+            }
+            _ => {
+                return Err(illegal);
+            }
+        }
+
+        if !group_ok(self.vd, emul_log2) || (!is_store && self.overlaps_mask()) {
+            return Err(illegal);
+        }
+
+        /* translate and check every element before anything is read or written */
+        let mut elems: Vec<(u64, u64, u64)> = Vec::new(); /* index, virtual and physical address */
+        for i in cfg.vstart..evl {
+            if !self.active(vregs, i) {
+                continue;
+            }
+            let addr: u64 = regs.xlen().trunc(base.wrapping_add(i.wrapping_mul(stride)));
+            if is_store {
+                check_store_align(addr, bytes)?;
+            }
+            else {
+                check_load_align(addr, bytes)?;
+            }
+            let paddr: u64 = mmu.translate(mem, addr, bytes, access)?;
+            mem.check_access(paddr, bytes).map_err(|_| {
+                if is_store { Exception::StoreAccessFault(addr) } else { Exception::LoadAccessFault(addr) }
+            })?;
+            elems.push((i, addr, paddr));
+        }
+
+        for (i, _addr, paddr) in elems {
+            if is_store {
+                let val: u64 = vregs.read(self.vd, i, eew);
+                let res: Result<(),()> = match eew {
+                    8 => mem.write_8bit(paddr, val as u8),
+                    16 => mem.write_16bit(paddr, val as u16),
+                    32 => mem.write_32bit(paddr, val as u32),
+                    _ => mem.write_64bit(paddr, val),
+                };
+                res.map_err(|_| Exception::StoreAccessFault(paddr))?;
+            }
+            else {
+                let val: u64 = match eew {
+                    8 => mem.read_8bit(paddr).map(|v| v as u64),
+                    16 => mem.read_16bit(paddr).map(|v| v as u64),
+                    32 => mem.read_32bit(paddr).map(|v| v as u64),
+                    _ => mem.read_64bit(paddr),
+                }.map_err(|_| Exception::LoadAccessFault(paddr))?;
+                vregs.write(self.vd, i, eew, val);
+            }
+        }
+        return Ok(());
+    }
+
+    /*
+     * name: opi_execute
+     * desc: OPIVV/OPIVX/OPIVI, single width arithmetic, add with carry, merge,
+     *       compares, saturating add/sub, shifts, narrowing shifts and the
+     *       widening sum reductions
+     */
+    fn opi_execute(&self, vregs: &mut VRegFile, csrs: &mut CsrFile, cfg: &VecCfg, op1: Operand) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let func6: u8 = self.func6;
+        let sew: u32 = cfg.sew;
+        let lmul: i32 = cfg.lmul_log2;
+        let is_vv: bool = self.func3 == VectorFunc3Sel::OPIVV as u8;
+        let is_vi: bool = self.func3 == VectorFunc3Sel::OPIVI as u8;
+        let is = |sel: VectorOpiSel| -> bool { return func6 == sel as u8; };
+
+        /* forms that don't exist for every operand kind */
+        if (is_vv && (is(VectorOpiSel::VRSUB) || is(VectorOpiSel::VMSGTU) || is(VectorOpiSel::VMSGT)))
+            || (is_vi && (is(VectorOpiSel::VSUB) || is(VectorOpiSel::VMINU) || is(VectorOpiSel::VMIN)
+                          || is(VectorOpiSel::VMAXU) || is(VectorOpiSel::VMAX) || is(VectorOpiSel::VSBC)
+                          || is(VectorOpiSel::VMSBC) || is(VectorOpiSel::VMSLTU) || is(VectorOpiSel::VMSLT)
+                          || is(VectorOpiSel::VSSUBU) || is(VectorOpiSel::VSSUB)))
+            || (!is_vv && (is(VectorOpiSel::VWREDSUMU) || is(VectorOpiSel::VWREDSUM))) {
+            return Err(illegal);
+        }
+
+        match func6 {
+            /* plain element wise ops */
+            f if f <= VectorOpiSel::VXOR as u8 || (f >= VectorOpiSel::VSADDU as u8 && f <= VectorOpiSel::VSRA as u8) => {
+                let known: bool = !(f == 0x01 || f == 0x08 || f == 0x24 || f == 0x26 || f == 0x27);
+                if !known || !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                let mut saturated: bool = false;
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let (res, sat): (u64, bool) = opi_arith(f, vregs.read(self.vs2, i, sew), op1.value(vregs, i, sew), sew);
+                    saturated |= sat;
+                    results.push((i, res));
+                }
+                commit(vregs, self.vd, sew, results);
+                if saturated {
+                    csrs.set_vxsat();
+                }
+            }
+
+            /* vd = vs2 +/- op1 +/- v0 carry/borrow, only the masked encoding exists */
+            f if f == VectorOpiSel::VADC as u8 || f == VectorOpiSel::VSBC as u8 => {
+                if self.vm != 0 || self.vd == 0 || !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    let a: u64 = vregs.read(self.vs2, i, sew);
+                    let b: u64 = op1.value(vregs, i, sew);
+                    let c: u64 = vregs.mask_bit(0, i) as u64;
+                    let res: u64 = if f == VectorOpiSel::VADC as u8 { a.wrapping_add(b).wrapping_add(c) } else { a.wrapping_sub(b).wrapping_sub(c) };
+                    results.push((i, trunc(res, sew)));
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+
+            /* carry/borrow out as a mask, with a carry/borrow in from v0 when masked */
+            f if f == VectorOpiSel::VMADC as u8 || f == VectorOpiSel::VMSBC as u8 => {
+                if !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, bool)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    let a: u128 = vregs.read(self.vs2, i, sew) as u128;
+                    let b: u128 = op1.value(vregs, i, sew) as u128;
+                    let c: u128 = if self.vm == 0 { vregs.mask_bit(0, i) as u128 } else { 0 };
+                    let out: bool = if f == VectorOpiSel::VMADC as u8 { a + b + c > umax(sew) } else { a < b + c };
+                    results.push((i, out));
+                }
+                commit_mask(vregs, self.vd, results);
+            }
+
+            /* masked: vd = v0 ? op1 : vs2, unmasked (vmv.v.*): vd = op1 */
+            f if f == VectorOpiSel::VMERGE as u8 => {
+                if (self.vm == 1 && self.vs2 != 0) || self.overlaps_mask()
+                   || !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    let take_op1: bool = self.vm == 1 || vregs.mask_bit(0, i);
+                    let val: u64 = if take_op1 { op1.value(vregs, i, sew) } else { vregs.read(self.vs2, i, sew) };
+                    results.push((i, val));
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+
+            f if f >= VectorOpiSel::VMSEQ as u8 && f <= VectorOpiSel::VMSGT as u8 => {
+                if !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, bool)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    results.push((i, opi_compare(f, vregs.read(self.vs2, i, sew), op1.value(vregs, i, sew), sew)));
+                }
+                commit_mask(vregs, self.vd, results);
+            }
+
+            /* vd (SEW) = vs2 (2*SEW) >> op1 */
+            f if f == VectorOpiSel::VNSRL as u8 || f == VectorOpiSel::VNSRA as u8 => {
+                let wide: u32 = sew * 2;
+                if wide > ELEN || !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul + 1) || !op1.group_ok(lmul) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let a: u64 = vregs.read(self.vs2, i, wide);
+                    let shamt: u32 = (op1.value(vregs, i, sew) as u32) & (wide - 1);
+                    let res: u64 = if f == VectorOpiSel::VNSRL as u8 { a >> shamt } else { (sext(a, wide) >> shamt) as u64 };
+                    results.push((i, trunc(res, sew)));
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+
+            /* vd[0] (2*SEW) = vs1[0] (2*SEW) + sum of the extended vs2 elements */
+            f if f == VectorOpiSel::VWREDSUMU as u8 || f == VectorOpiSel::VWREDSUM as u8 => {
+                let wide: u32 = sew * 2;
+                if wide > ELEN || cfg.vstart != 0 || !group_ok(self.vs2, lmul) {
+                    return Err(illegal);
+                }
+                if cfg.vl == 0 {
+                    return Ok(());
+                }
+                let mut acc: u64 = op1.value(vregs, 0, wide);
+                for i in 0..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let elem: u64 = vregs.read(self.vs2, i, sew);
+                    let ext: u64 = if f == VectorOpiSel::VWREDSUM as u8 { sext(elem, sew) as u64 } else { elem };
+                    acc = trunc(acc.wrapping_add(ext), wide);
+                }
+                vregs.write(self.vd, 0, wide, acc);
+            }
+
+            _ => {
+                return Err(illegal);
+            }
+        }
+        return Ok(());
+    }
+
+    /*
+     * name: opm_execute
+     * desc: OPMVV/OPMVX, reductions, scalar moves, mask ops, multiply/divide,
+     *       multiply-add, extension and the widening ops
+     */
+    fn opm_execute(&self, regs: &mut RegFile, vregs: &mut VRegFile, cfg: &VecCfg, op1: Operand) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let func6: u8 = self.func6;
+        let sew: u32 = cfg.sew;
+        let lmul: i32 = cfg.lmul_log2;
+        let is_vv: bool = self.func3 == VectorFunc3Sel::OPMVV as u8;
+        let xlen: Xlen = regs.xlen();
+
+        /* reductions, the mask ops, extension and the unary groups only have .vv forms, vwmaccus only .vx */
+        let vv_only: bool = func6 <= VectorOpmSel::VREDMAX as u8 || func6 == VectorOpmSel::VXUNARY0 as u8
+                            || func6 == VectorOpmSel::VMUNARY0 as u8
+                            || (func6 >= VectorOpmSel::VMANDN as u8 && func6 <= VectorOpmSel::VMXNOR as u8);
+        if (vv_only && !is_vv) || (func6 == VectorOpmSel::VWMACCUS as u8 && is_vv) {
+            return Err(illegal);
+        }
+
+        match func6 {
+            /* vd[0] = vs1[0] op all active vs2 elements */
+            f if f <= VectorOpmSel::VREDMAX as u8 => {
+                if cfg.vstart != 0 || !group_ok(self.vs2, lmul) {
+                    return Err(illegal);
+                }
+                if cfg.vl == 0 {
+                    return Ok(());
+                }
+                let mut acc: u64 = op1.value(vregs, 0, sew);
+                for i in 0..cfg.vl {
+                    if self.active(vregs, i) {
+                        acc = reduce(f, acc, vregs.read(self.vs2, i, sew), sew);
+                    }
+                }
+                vregs.write(self.vd, 0, sew, acc);
+            }
+
+            /* vmv.x.s, vcpop.m, vfirst.m and (OPMVX) vmv.s.x */
+            f if f == VectorOpmSel::VWXUNARY0 as u8 => {
+                if !is_vv {
+                    /* vmv.s.x vd, rs1: element 0 only, nothing to do when vl = 0 */
+                    if self.vs2 != 0 || self.vm == 0 {
+                        return Err(illegal);
+                    }
+                    if cfg.vstart < cfg.vl {
+                        vregs.write(self.vd, 0, sew, op1.value(vregs, 0, sew));
+                    }
+                    return Ok(());
+                }
+                match self.vs1 {
+                    s if s == VectorWxunarySel::VMV_X_S as u8 => {
+                        if self.vm == 0 {
+                            return Err(illegal);
+                        }
+                        regs.write(self.vd, sext(vregs.read(self.vs2, 0, sew), sew) as u64);
+                    }
+                    s if s == VectorWxunarySel::VCPOP as u8 || s == VectorWxunarySel::VFIRST as u8 => {
+                        if cfg.vstart != 0 {
+                            return Err(illegal);
+                        }
+                        let mut count: u64 = 0;
+                        let mut first: i64 = -1;
+                        for i in 0..cfg.vl {
+                            if self.active(vregs, i) && vregs.mask_bit(self.vs2, i) {
+                                if first < 0 {
+                                    first = i as i64;
+                                }
+                                count += 1;
+                            }
+                        }
+                        let res: u64 = if s == VectorWxunarySel::VCPOP as u8 { count } else { first as u64 };
+                        regs.write(self.vd, xlen.trunc(res));
+                    }
+                    _ => {
+                        return Err(illegal);
+                    }
+                }
+            }
+
+            /* vzext/vsext.vf2/vf4/vf8, vs1 = 2/3 (vf8), 4/5 (vf4), 6/7 (vf2), odd ones sign extend */
+            f if f == VectorOpmSel::VXUNARY0 as u8 => {
+                let factor_log2: i32 = match self.vs1 {
+                    2 | 3 => 3,
+                    4 | 5 => 2,
+                    6 | 7 => 1,
+                    _ => {
+                        return Err(illegal);
+                    }
+                };
+                let src: u32 = sew >> factor_log2;
+                if src < 8 || !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul - factor_log2) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let signed: bool = (self.vs1 & 1) != 0;
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let val: u64 = vregs.read(self.vs2, i, src);
+                    results.push((i, if signed { trunc(sext(val, src) as u64, sew) } else { val }));
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+
+            f if f == VectorOpmSel::VMUNARY0 as u8 => {
+                self.munary_execute(vregs, cfg)?;
+            }
+
+            /* mask logical ops on the first vl bits, unmasked only */
+            f if f >= VectorOpmSel::VMANDN as u8 && f <= VectorOpmSel::VMXNOR as u8 => {
+                if self.vm == 0 {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, bool)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    let a: bool = vregs.mask_bit(self.vs2, i);
+                    let b: bool = vregs.mask_bit(self.vs1, i);
+                    let bit: bool = match f {
+                        f if f == VectorOpmSel::VMANDN as u8 => a && !b,
+                        f if f == VectorOpmSel::VMAND as u8 => a && b,
+                        f if f == VectorOpmSel::VMOR as u8 => a || b,
+                        f if f == VectorOpmSel::VMXOR as u8 => a ^ b,
+                        f if f == VectorOpmSel::VMORN as u8 => a || !b,
+                        f if f == VectorOpmSel::VMNAND as u8 => !(a && b),
+                        f if f == VectorOpmSel::VMNOR as u8 => !(a || b),
+                        _ => !(a ^ b), /* VMXNOR */
+                    };
+                    results.push((i, bit));
+                }
+                commit_mask(vregs, self.vd, results);
+            }
+
+            /* divide and multiply */
+            f if f >= VectorOpmSel::VDIVU as u8 && f <= VectorOpmSel::VMULH as u8 => {
+                if !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    results.push((i, opm_arith(f, vregs.read(self.vs2, i, sew), op1.value(vregs, i, sew), sew)));
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+
+            /* multiply-add into vd */
+            f if f == VectorOpmSel::VMADD as u8 || f == VectorOpmSel::VNMSUB as u8
+                 || f == VectorOpmSel::VMACC as u8 || f == VectorOpmSel::VNMSAC as u8 => {
+                if !group_ok(self.vd, lmul) || !group_ok(self.vs2, lmul) || !op1.group_ok(lmul) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let d: u64 = vregs.read(self.vd, i, sew);
+                    results.push((i, opm_macc(f, d, vregs.read(self.vs2, i, sew), op1.value(vregs, i, sew), sew)));
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+
+            /* 2*SEW = SEW op SEW, the .w forms take a 2*SEW vs2 */
+            f if f >= VectorOpmSel::VWADDU as u8 && f != 0x39 => {
+                let wide: u32 = sew * 2;
+                let wide_vs2: bool = f >= VectorOpmSel::VWADDU_W as u8 && f <= VectorOpmSel::VWSUB_W as u8;
+                let vs2_emul: i32 = if wide_vs2 { lmul + 1 } else { lmul };
+                if wide > ELEN || !group_ok(self.vd, lmul + 1) || !group_ok(self.vs2, vs2_emul) || !op1.group_ok(lmul) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let a: u64 = vregs.read(self.vs2, i, if wide_vs2 { wide } else { sew });
+                    let d: u64 = vregs.read(self.vd, i, wide);
+                    results.push((i, opm_widen(f, d, a, op1.value(vregs, i, sew), sew)));
+                }
+                commit(vregs, self.vd, wide, results);
+            }
+
+            _ => {
+                return Err(illegal);
+            }
+        }
+        return Ok(());
+    }
+
+    /* vmsbf/vmsif/vmsof set bits before/including/at the first set bit of vs2, viota and vid count */
+    fn munary_execute(&self, vregs: &mut VRegFile, cfg: &VecCfg) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let sew: u32 = cfg.sew;
+
+        match self.vs1 {
+            s if s == VectorMunarySel::VMSBF as u8 || s == VectorMunarySel::VMSIF as u8 || s == VectorMunarySel::VMSOF as u8 => {
+                if cfg.vstart != 0 || self.vd == self.vs2 || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, bool)> = Vec::new();
+                let mut found: bool = false;
+                for i in 0..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    let set: bool = vregs.mask_bit(self.vs2, i);
+                    let bit: bool = match s {
+                        s if s == VectorMunarySel::VMSBF as u8 => !found && !set,
+                        s if s == VectorMunarySel::VMSIF as u8 => !found,
+                        _ => !found && set, /* VMSOF */
+                    };
+                    found |= set;
+                    results.push((i, bit));
+                }
+                commit_mask(vregs, self.vd, results);
+            }
+            s if s == VectorMunarySel::VIOTA as u8 || s == VectorMunarySel::VID as u8 => {
+                let is_iota: bool = s == VectorMunarySel::VIOTA as u8;
+                if (!is_iota && self.vs2 != 0) || (is_iota && cfg.vstart != 0) || !group_ok(self.vd, cfg.lmul_log2) || self.overlaps_mask() {
+                    return Err(illegal);
+                }
+                let mut results: Vec<(u64, u64)> = Vec::new();
+                let mut count: u64 = 0;
+                for i in cfg.vstart..cfg.vl {
+                    if !self.active(vregs, i) {
+                        continue;
+                    }
+                    results.push((i, trunc(if is_iota { count } else { i }, sew)));
+                    if is_iota && vregs.mask_bit(self.vs2, i) {
+                        count += 1;
+                    }
+                }
+                commit(vregs, self.vd, sew, results);
+            }
+            _ => {
+                return Err(illegal);
+            }
+        }
+        return Ok(());
+    }
+
+    /*
+     * name: disassemble
+     * desc: objdump text of the instruction, name is the mnemonic of its table entry
+     *
+     * NOTE: a masked instruction gets a trailing v0.t, the carry and merge forms
+     *       (.vvm/.vxm/.vim) name v0 as an operand instead
+     */
+    pub fn disassemble(&self, name: &str) -> String {
+        let (vd, vs2, vs1): (String, String, String) = (format!("v{}", self.vd), format!("v{}", self.vs2), format!("v{}", self.vs1));
+        let mask: &str = if self.vm == 0 { ",v0.t" } else { "" };
+        let (rd, rs1, rs2): (&str, &str, &str) = (xreg(self.vd), xreg(self.vs1), xreg(self.vs2));
+
+        /* loads and stores, vs1 is the base register and vs2 the stride */
+        if self.opcode != VectorOpcodes::OP_V as u8 {
+            return match self.mop {
+                mop if mop == VectorMopSel::STRIDED as u8 => format!("{}\t{},({}),{}{}", name, vd, rs1, rs2, mask),
+                _ => format!("{}\t{},({}){}", name, vd, rs1, mask),
+            };
+        }
+
+        let op: String = match self.func3 {
+            f if f == VectorFunc3Sel::OPCFG as u8 => {
+                return match name {
+                    "vsetvli" => format!("{}\t{},{},{}", name, rd, rs1, vtype_text((self.zimm & 0x7FF) as u64)),
+                    "vsetivli" => format!("{}\t{},{},{}", name, rd, self.vs1, vtype_text((self.zimm & 0x3FF) as u64)),
+                    _ => format!("{}\t{},{},{}", name, rd, rs1, rs2),
+                };
+            }
+            f if f == VectorFunc3Sel::OPIVV as u8 || f == VectorFunc3Sel::OPMVV as u8 => vs1,
+            f if f == VectorFunc3Sel::OPIVX as u8 || f == VectorFunc3Sel::OPMVX as u8 => rs1.to_string(),
+            /* the shifts take a zero extended immediate, everything else sign extends it */
+            _ if name.starts_with("vsll") || name.starts_with("vsr") || name.starts_with("vnsr") => self.vs1.to_string(),
+            _ => ((((self.vs1 as i8) << 3) >> 3) as i32).to_string(),
+        };
+
+        let func6: u8 = self.func6;
+        let opm: bool = self.func3 == VectorFunc3Sel::OPMVV as u8 || self.func3 == VectorFunc3Sel::OPMVX as u8;
+        let is = |sel: VectorOpmSel| -> bool { return opm && func6 == sel as u8; };
+        /* the multiply-adds name the multiplier before vs2 */
+        let macc: bool = is(VectorOpmSel::VMADD) || is(VectorOpmSel::VNMSUB) || is(VectorOpmSel::VMACC) || is(VectorOpmSel::VNMSAC)
+                      || (opm && func6 >= VectorOpmSel::VWMACCU as u8);
+
+        return match name {
+            "vmv.x.s" => format!("{}\t{},{}", name, rd, vs2),
+            "vcpop.m" | "vfirst.m" => format!("{}\t{},{}{}", name, rd, vs2, mask),
+            "vmv.s.x" | "vmv.v.v" | "vmv.v.x" | "vmv.v.i" => format!("{}\t{},{}", name, vd, op),
+            "vid.v" => format!("{}\t{}{}", name, vd, mask),
+            _ if is(VectorOpmSel::VXUNARY0) || is(VectorOpmSel::VMUNARY0) => format!("{}\t{},{}{}", name, vd, vs2, mask),
+            _ if name.ends_with("vm") || name.ends_with("xm") || name.ends_with("im") => format!("{}\t{},{},{},v0", name, vd, vs2, op),
+            _ if macc => format!("{}\t{},{},{}{}", name, vd, op, vs2, mask),
+            _ => format!("{}\t{},{},{}{}", name, vd, vs2, op, mask),
+        };
+    }
+
+    /*
+     * NOTE: every vector instruction is illegal while mstatus.VS is Off and all but
+     *       vset{i}vl{i} are illegal while vtype.vill is set
+     */
+    pub fn execute(&mut self, regs: &mut RegFile, vregs: &mut VRegFile, pc: &mut u64, mem: &mut Memory, mmu: &mut Mmu, csrs: &mut CsrFile) -> Result<(),Exception> {
+        let illegal: Exception = Exception::IllegalInstruction(0);
+        let xlen: Xlen = regs.xlen();
+        if !csrs.vector_enabled() {
+            return Err(illegal);
+        }
+
+        if self.opcode == VectorOpcodes::OP_V as u8 && self.func3 == VectorFunc3Sel::OPCFG as u8 {
+            self.vset_execute(regs, vregs, csrs)?;
+        }
+        else {
+            let vtype: VType = match VType::decode(csrs.get_vtype(), xlen) {
+                Some(vt) => vt,
+                None => {
+                    return Err(illegal);
+                }
+            };
+            let cfg: VecCfg = VecCfg {
+                sew: vtype.sew,
+                lmul_log2: vtype.lmul_log2,
+                vl: csrs.get_vl(),
+                vstart: csrs.get_vstart(),
+            };
+
+            /* scalar operands are sign extended from XLEN then cut down to SEW */
+            let scalar: u64 = xlen.sext(regs.read(self.vs1)) as u64;
+            let simm5: u64 = (((self.vs1 as i8) << 3) >> 3) as i64 as u64;
+
+            match self.opcode {
+                opcode if opcode == VectorOpcodes::LOAD_FP as u8 || opcode == VectorOpcodes::STORE_FP as u8 => {
+                    self.mem_execute(regs, vregs, mem, mmu, &cfg)?;
+                }
+                _ => {
+                    match self.func3 {
+                        f if f == VectorFunc3Sel::OPIVV as u8 => self.opi_execute(vregs, csrs, &cfg, Operand::Vector(self.vs1))?,
+                        f if f == VectorFunc3Sel::OPIVX as u8 => self.opi_execute(vregs, csrs, &cfg, Operand::Scalar(scalar))?,
+                        f if f == VectorFunc3Sel::OPIVI as u8 => self.opi_execute(vregs, csrs, &cfg, Operand::Scalar(simm5))?,
+                        f if f == VectorFunc3Sel::OPMVV as u8 => self.opm_execute(regs, vregs, &cfg, Operand::Vector(self.vs1))?,
+                        f if f == VectorFunc3Sel::OPMVX as u8 => self.opm_execute(regs, vregs, &cfg, Operand::Scalar(scalar))?,
+                        _ => {
+                            return Err(illegal); /* OPFVV/OPFVF */
+                        }
+                    }
+                }
+            }
+        }
+
+        csrs.clear_vstart();
+        csrs.set_vector_dirty();
+        *pc = xlen.trunc(*pc + 4);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::*;
+    use crate::isa::*;
+    use crate::xlen::*;
+    use crate::encoder::*;
+
+    /* vtype immediates */
+    const E8M1: u32 = 0x00;
+    const E8M8: u32 = 0x03;
+    const E16M1: u32 = 0x08;
+    const E16M2: u32 = 0x09;
+    const E32M1: u32 = 0x10;
+    const E64M1: u32 = 0x18;
+    const E64MF8: u32 = 0x1D; /* no room for a single element */
+    const VLMUL_RESERVED: u32 = 0x04;
+
+    /* a7 counts the illegal instructions, the handler steps over them */
+    const PROLOGUE: &str = "
+                la t0, trap
+                csrw mtvec, t0
+                j main
+            trap:
+                addi a7, a7, 1
+                csrr t6, mepc
+                addi t6, t6, 4
+                csrw mepc, t6
+                mret
+            main:
+    ";
+
+    fn word(inst: Inst) -> String {
+        return format!(".word {:#x}", inst.encode());
+    }
+
+    /* RV32 with the default VLEN of 128 */
+    fn run(body: &str) -> Machine {
+        return run_source(&format!("{}{}", PROLOGUE, body), &IsaConfig::new(Xlen::RV32), 1);
+    }
+
+    fn reg(machine: &Machine, r: Reg) -> u64 {
+        return machine.get_harts()[0].get_reg(r as u8);
+    }
+
+    #[test]
+    fn vsetvli_vl_and_vill() {
+        let machine: Machine = run(&format!("
+                li t0, 100
+                {}                   # vsetvli a0, t0, e32,m1
+                {}                   # vsetvli a1, zero, e8,m8
+                {}                   # vsetivli zero, 3, e8,m1
+                {}                   # vsetvli zero, zero, e16,m2
+                csrr a2, vl
+                {}                   # vsetivli a3, 31, e64,mf8
+                csrr a4, vtype
+                csrr a5, vl
+                {}                   # vadd.vv v1, v2, v3
+                {}                   # vsetivli a6, 4, vlmul 4
+                j .
+            ",
+            word(Inst::vsetvli(Reg::A0, Reg::T0, E32M1)),
+            word(Inst::vsetvli(Reg::A1, Reg::Zero, E8M8)),
+            word(Inst::vsetivli(Reg::Zero, 3, E8M1)),
+            word(Inst::vsetvli(Reg::Zero, Reg::Zero, E16M2)),
+            word(Inst::vsetivli(Reg::A3, 31, E64MF8)),
+            word(Inst::vadd_vv(VReg::V1, VReg::V2, VReg::V3)),
+            word(Inst::vsetivli(Reg::A6, 4, VLMUL_RESERVED)),
+        ));
+        /* AVL is clamped to VLMAX, rs1 = x0 with rd != x0 asks for VLMAX */
+        assert_eq!(reg(&machine, Reg::A0), 4);
+        assert_eq!(reg(&machine, Reg::A1), 128);
+        /* rd = rs1 = x0 keeps vl */
+        assert_eq!(reg(&machine, Reg::A2), 3);
+        /* unsupported settings set vill and vl = 0, and the next vector instruction is illegal */
+        assert_eq!(reg(&machine, Reg::A3), 0);
+        assert_eq!(reg(&machine, Reg::A4), 0x8000_0000);
+        assert_eq!(reg(&machine, Reg::A5), 0);
+        assert_eq!(reg(&machine, Reg::A6), 0);
+        assert_eq!(reg(&machine, Reg::A7), 1);
+    }
+
+    /* masked off and tail elements keep whatever the destination held */
+    #[test]
+    fn masked_and_tail_undisturbed() {
+        let machine: Machine = run(&format!("
+                {}                   # vsetivli zero, 4, e32,m1
+                {}                   # vmv.v.i v0, 5, mask 0b0101
+                {}                   # vmv.v.i v1, 7
+                {}                   # vmv.v.i v3, 10
+                {}                   # vadd.vi v3, v1, 1, v0.t
+                {}                   # vmv.v.i v4, 10
+                {}                   # vsetivli zero, 3, e32,m1
+                {}                   # vadd.vi v4, v1, 1
+                {}                   # vsetivli zero, 4, e32,m1
+                la a0, out
+                {}                   # vse32.v v3, (a0)
+                addi a0, a0, 16
+                {}                   # vse32.v v4, (a0)
+                {}                   # vadd.vi v0, v1, 1, v0.t
+                la a0, out
+                lw s2, 0(a0)
+                lw s3, 4(a0)
+                lw s4, 8(a0)
+                lw s5, 12(a0)
+                lw s6, 16(a0)
+                lw s7, 20(a0)
+                lw s8, 24(a0)
+                lw s9, 28(a0)
+                j .
+            out:
+                .zero 32
+            ",
+            word(Inst::vsetivli(Reg::Zero, 4, E32M1)),
+            word(Inst::vmv_v_i(VReg::V0, 5)),
+            word(Inst::vmv_v_i(VReg::V1, 7)),
+            word(Inst::vmv_v_i(VReg::V3, 10)),
+            word(Inst::vadd_vi(VReg::V3, VReg::V1, 1).masked()),
+            word(Inst::vmv_v_i(VReg::V4, 10)),
+            word(Inst::vsetivli(Reg::Zero, 3, E32M1)),
+            word(Inst::vadd_vi(VReg::V4, VReg::V1, 1)),
+            word(Inst::vsetivli(Reg::Zero, 4, E32M1)),
+            word(Inst::vse32_v(VReg::V3, Reg::A0)),
+            word(Inst::vse32_v(VReg::V4, Reg::A0)),
+            word(Inst::vadd_vi(VReg::V0, VReg::V1, 1).masked()),
+        ));
+        let v3: Vec<u64> = [Reg::S2, Reg::S3, Reg::S4, Reg::S5].iter().map(|r| reg(&machine, *r)).collect();
+        let v4: Vec<u64> = [Reg::S6, Reg::S7, Reg::S8, Reg::S9].iter().map(|r| reg(&machine, *r)).collect();
+        assert_eq!(v3, [8, 10, 8, 10]);
+        assert_eq!(v4, [8, 8, 8, 10]);
+        /* a masked destination can't be the mask itself */
+        assert_eq!(reg(&machine, Reg::A7), 1);
+    }
+
+    /* a 2*SEW group has to start at an even register and 2*SEW has to fit ELEN */
+    #[test]
+    fn widening_and_narrowing_groups() {
+        let machine: Machine = run(&format!("
+                {}                   # vsetivli zero, 4, e16,m1
+                li t0, -1
+                {}                   # vmv.v.x v4, t0
+                {}                   # vmv.v.i v5, 1
+                {}                   # vwaddu.vv v2, v4, v5
+                {}                   # vwaddu.vv v1, v4, v5
+                {}                   # vnsrl.wi v6, v3, 0
+                {}                   # vnsrl.wi v6, v2, 16
+                {}                   # vmv.x.s a0, v6
+                {}                   # vsetivli zero, 4, e64,m1
+                {}                   # vwaddu.vv v2, v4, v5
+                {}                   # vsetivli zero, 4, e32,m1
+                {}                   # vmv.x.s a1, v2
+                j .
+            ",
+            word(Inst::vsetivli(Reg::Zero, 4, E16M1)),
+            word(Inst::vmv_v_x(VReg::V4, Reg::T0)),
+            word(Inst::vmv_v_i(VReg::V5, 1)),
+            word(Inst::vwaddu_vv(VReg::V2, VReg::V4, VReg::V5)),
+            word(Inst::vwaddu_vv(VReg::V1, VReg::V4, VReg::V5)),
+            word(Inst::vnsrl_wi(VReg::V6, VReg::V3, 0)),
+            word(Inst::vnsrl_wi(VReg::V6, VReg::V2, 16)),
+            word(Inst::vmv_x_s(Reg::A0, VReg::V6)),
+            word(Inst::vsetivli(Reg::Zero, 4, E64M1)),
+            word(Inst::vwaddu_vv(VReg::V2, VReg::V4, VReg::V5)),
+            word(Inst::vsetivli(Reg::Zero, 4, E32M1)),
+            word(Inst::vmv_x_s(Reg::A1, VReg::V2)),
+        ));
+        assert_eq!(reg(&machine, Reg::A0), 1);
+        assert_eq!(reg(&machine, Reg::A1), 0x1_0000);
+        /* odd vd, odd vs2 group and e64 widening */
+        assert_eq!(reg(&machine, Reg::A7), 3);
+    }
+
+    #[test]
+    fn reductions() {
+        let machine: Machine = run(&format!("
+                {}                   # vsetivli zero, 4, e32,m1
+                {}                   # vid.v v1
+                {}                   # vmv.v.i v2, 10
+                {}                   # vredsum.vs v3, v1, v2
+                {}                   # vmv.x.s a0, v3
+                {}                   # vmv.v.i v0, 5
+                {}                   # vredsum.vs v3, v1, v2, v0.t
+                {}                   # vmv.x.s a1, v3
+                {}                   # vmv.v.i v7, 0
+                {}                   # vredmaxu.vs v3, v1, v7
+                {}                   # vmv.x.s a2, v3
+                {}                   # vsetivli zero, 0, e32,m1
+                {}                   # vredsum.vs v3, v1, v2
+                {}                   # vmv.x.s a3, v3
+                {}                   # vsetivli zero, 4, e8,m1
+                {}                   # vmv.v.i v1, -1
+                {}                   # vmv.v.i v2, 0
+                {}                   # vwredsumu.vs v3, v1, v2
+                {}                   # vsetivli zero, 1, e16,m1
+                {}                   # vmv.x.s a4, v3
+                j .
+            ",
+            word(Inst::vsetivli(Reg::Zero, 4, E32M1)),
+            word(Inst::vid_v(VReg::V1)),
+            word(Inst::vmv_v_i(VReg::V2, 10)),
+            word(Inst::vredsum_vs(VReg::V3, VReg::V1, VReg::V2)),
+            word(Inst::vmv_x_s(Reg::A0, VReg::V3)),
+            word(Inst::vmv_v_i(VReg::V0, 5)),
+            word(Inst::vredsum_vs(VReg::V3, VReg::V1, VReg::V2).masked()),
+            word(Inst::vmv_x_s(Reg::A1, VReg::V3)),
+            word(Inst::vmv_v_i(VReg::V7, 0)),
+            word(Inst::vredmaxu_vs(VReg::V3, VReg::V1, VReg::V7)),
+            word(Inst::vmv_x_s(Reg::A2, VReg::V3)),
+            word(Inst::vsetivli(Reg::Zero, 0, E32M1)),
+            word(Inst::vredsum_vs(VReg::V3, VReg::V1, VReg::V2)),
+            word(Inst::vmv_x_s(Reg::A3, VReg::V3)),
+            word(Inst::vsetivli(Reg::Zero, 4, E8M1)),
+            word(Inst::vmv_v_i(VReg::V1, -1)),
+            word(Inst::vmv_v_i(VReg::V2, 0)),
+            word(Inst::vwredsumu_vs(VReg::V3, VReg::V1, VReg::V2)),
+            word(Inst::vsetivli(Reg::Zero, 1, E16M1)),
+            word(Inst::vmv_x_s(Reg::A4, VReg::V3)),
+        ));
+        assert_eq!(reg(&machine, Reg::A0), 10 + 1 + 2 + 3);
+        /* only elements 0 and 2 are active */
+        assert_eq!(reg(&machine, Reg::A1), 10 + 2);
+        assert_eq!(reg(&machine, Reg::A2), 3);
+        /* vl = 0 leaves vd alone */
+        assert_eq!(reg(&machine, Reg::A3), 3);
+        /* the widening sum doesn't wrap at SEW */
+        assert_eq!(reg(&machine, Reg::A4), 4 * 0xFF);
+        assert_eq!(reg(&machine, Reg::A7), 0);
+    }
+
+    #[test]
+    fn strided_loads_and_stores() {
+        let machine: Machine = run(&format!("
+                {}                   # vsetivli zero, 4, e32,m1
+                la a0, data
+                li t0, 8
+                {}                   # vlse32.v v1, (a0), t0
+                addi a1, a0, 28
+                li t1, -4
+                {}                   # vlse32.v v2, (a1), t1
+                addi a1, a0, 4
+                {}                   # vlse32.v v3, (a1), zero
+                la a2, out
+                {}                   # vsse32.v v1, (a2), t0
+                {}                   # vmv.v.i v0, 6
+                addi a3, a2, 32
+                {}                   # vsse32.v v2, (a3), t0, v0.t
+                {}                   # vredsum.vs v4, v3, v3
+                {}                   # vmv.x.s a4, v4
+                lw s2, 0(a2)
+                lw s3, 4(a2)
+                lw s4, 8(a2)
+                lw s5, 24(a2)
+                lw s6, 28(a2)
+                lw s8, 8(a3)
+                lw s9, 16(a3)
+                lw s10, 0(a3)
+                lw s11, 24(a3)
+                j .
+            data:
+                .word 0, 1, 2, 3, 4, 5, 6, 7
+            out:
+                .word -1, -1, -1, -1, -1, -1, -1, -1
+                .word -1, -1, -1, -1, -1, -1, -1, -1
+            ",
+            word(Inst::vsetivli(Reg::Zero, 4, E32M1)),
+            word(Inst::vlse32_v(VReg::V1, Reg::A0, Reg::T0)),
+            word(Inst::vlse32_v(VReg::V2, Reg::A1, Reg::T1)),
+            word(Inst::vlse32_v(VReg::V3, Reg::A1, Reg::Zero)),
+            word(Inst::vsse32_v(VReg::V1, Reg::A2, Reg::T0)),
+            word(Inst::vmv_v_i(VReg::V0, 6)),
+            word(Inst::vsse32_v(VReg::V2, Reg::A3, Reg::T0).masked()),
+            word(Inst::vredsum_vs(VReg::V4, VReg::V3, VReg::V3)),
+            word(Inst::vmv_x_s(Reg::A4, VReg::V4)),
+        ));
+        let r = |r: Reg| reg(&machine, r) as u32;
+        /* stride 8 picks every other word and puts them back the same way, the gaps stay -1 */
+        assert_eq!([r(Reg::S2), r(Reg::S3), r(Reg::S4), r(Reg::S5), r(Reg::S6)], [0, 0xFFFF_FFFF, 2, 6, 0xFFFF_FFFF]);
+        /* v2 = 7, 6, 5, 4 read backwards, only elements 1 and 2 are stored */
+        assert_eq!([r(Reg::S8), r(Reg::S9)], [6, 5]);
+        assert_eq!([r(Reg::S10), r(Reg::S11)], [0xFFFF_FFFF, 0xFFFF_FFFF]);
+        /* stride 0 reads the same word four times, 1 + 1 + 1 + 1 + 1 */
+        assert_eq!(reg(&machine, Reg::A4), 5);
+        assert_eq!(reg(&machine, Reg::A7), 0);
+    }
+}
@@ -9,18 +9,7 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::collections::VecDeque;
 use std::io;
-
-#[derive(Debug)]
-enum UartRegs {
-    UART_FIFO_RX = 0x7000000,      /* read only  */
-    UART_FIFO_TX,                  /* write only */
-    UART_FLAGS,                    /* read only  */
-    INVALID,                          /* failure    */
-}
-
-impl UartRegs {
-    pub const BASE: UartRegs = UartRegs::UART_FIFO_RX;
-}
+use std::io::Write;
 
 /* peripheral flags bit masks */
 /* has to be packed into a 8 bit value for register semantics */
@@ -60,10 +49,8 @@ fn console_tx_thread( mut uart: Uart ) {
     loop {
         /* just wait for cpu to transmit data */
         let len: usize = uart.ext_check_tx_fifo_len();
-        if( len > 0){
-            for i in 0..len{
-                print!("{}",uart.ext_read_tx_fifo() as char);
-            }
+        if len > 0 {
+            uart.ext_print_tx_fifo();
         }
     }
 }
@@ -74,7 +61,7 @@ fn console_rx_thread( mut uart: Uart  ) {
     loop {
         let mut user_input = String::new();
         let stdin = io::stdin();
-        stdin.read_line(&mut user_input);
+        let _ = stdin.read_line(&mut user_input);
         //println!("{}",user_input);
         let char_vec: Vec<char> = user_input.chars().collect();
         for c in char_vec {
@@ -112,54 +99,71 @@ impl Uart {
         return uart;
     }
 
+    /* interrupt line, high while there is received data for the cpu */
+    pub fn cpu_rx_pending(&mut self) -> bool {
+        let uart = self.uart_arc.lock().unwrap();
+        return (uart.flags & UartFlagsBm::RX_DATA_AVAIL_bm as u8) != 0;
+    }
+
     pub fn cpu_get_flags(&mut self) -> u8 {
-        let mut uart = self.uart_arc.lock().unwrap();
-        return (*uart).flags;
+        let uart = self.uart_arc.lock().unwrap();
+        return uart.flags;
     }
 
     /* should not be used by the memory module/cpu */
     pub fn ext_check_tx_fifo_len(&mut self) -> usize {
-        let mut uart = self.uart_arc.lock().unwrap();
-        return (*uart).tx_fifo.len();
+        let uart = self.uart_arc.lock().unwrap();
+        return uart.tx_fifo.len();
     }
 
     /* should not be used by the memory module/cpu */
-    pub fn ext_read_tx_fifo(&mut self) -> u8 {
+    /* prints while holding the lock so cpu_flush_tx_fifo() knows the data is out once the fifo is empty */
+    pub fn ext_print_tx_fifo(&mut self) {
         let mut uart = self.uart_arc.lock().unwrap();
-        if (*uart).tx_fifo.is_empty(){
-            //println!("ERROR: tx_fifo empty");
-            return 0; 
+        let mut stdout = io::stdout();
+        while let Some(data) = uart.tx_fifo.pop_front() {
+            print!("{}",data as char);
         }
-        return (*uart).tx_fifo.pop_front().unwrap();
+        let _ = stdout.flush();
     }
 
     /* should not be used by the memory module */
     pub fn ext_write_rx_fifo(&mut self, data: u8){
         let mut uart = self.uart_arc.lock().unwrap();
-        if (*uart).rx_fifo.is_empty(){
+        if uart.rx_fifo.is_empty(){
             /* set this flag for the CPU to read */
-            (*uart).flags |= (UartFlagsBm::RX_DATA_AVAIL_bm as u8);
+            uart.flags |= UartFlagsBm::RX_DATA_AVAIL_bm as u8;
         }
-        (*uart).rx_fifo.push_back(data);
+        uart.rx_fifo.push_back(data);
     }
 
     pub fn cpu_write_tx_fifo(&mut self, data: u8){
         let mut uart = self.uart_arc.lock().unwrap();
-        (*uart).tx_fifo.push_back(data);
+        uart.tx_fifo.push_back(data);
+    }
+
+    /* wait for the console thread to drain everything the cpu has written */
+    pub fn cpu_flush_tx_fifo(&mut self) {
+        loop {
+            let uart = self.uart_arc.lock().unwrap();
+            if uart.tx_fifo.is_empty() {
+                return;
+            }
+        }
     }
 
     pub fn cpu_read_rx_fifo(&mut self) -> u8 {
         let mut uart = self.uart_arc.lock().unwrap();
-        if (*uart).rx_fifo.is_empty(){
+        if uart.rx_fifo.is_empty(){
             //println!("ERROR: rx_fifo empty");
             return 0; 
         }
-        let ret: u8 = (*uart).rx_fifo.pop_front().unwrap();
+        let ret: u8 = uart.rx_fifo.pop_front().unwrap();
         
         /* if that was the last available data in the fifo, clear flag for cpu */
-        if (*uart).rx_fifo.is_empty(){
+        if uart.rx_fifo.is_empty(){
             /* clear the data flag */
-            (*uart).flags &= !(UartFlagsBm::RX_DATA_AVAIL_bm as u8);
+            uart.flags &= !(UartFlagsBm::RX_DATA_AVAIL_bm as u8);
         }
 
         return ret;
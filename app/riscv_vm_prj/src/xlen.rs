@@ -0,0 +1,85 @@
+/*
+ * name: xlen.rs
+ * desc: register width of the machine and the integer register file
+ *
+ * Note: registers are always stored as u64, on RV32 the upper half is kept at zero
+ *       so unsigned compares and address calculations work the same for both widths,
+ *       anything signed goes through sext()
+ *
+ */
+
+/* base ISA, picked when the machine is constructed */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Xlen {
+    RV32 = 32,
+    RV64 = 64,
+}
+
+impl Xlen {
+    pub fn bits(&self) -> u32 {
+        return *self as u32;
+    }
+
+    /* cut a value down to XLEN bits */
+    pub fn trunc(&self, val: u64) -> u64 {
+        return match self {
+            Xlen::RV32 => val & 0xFFFF_FFFF,
+            Xlen::RV64 => val,
+        };
+    }
+
+    /* sign extend an XLEN bit value */
+    pub fn sext(&self, val: u64) -> i64 {
+        return match self {
+            Xlen::RV32 => val as u32 as i32 as i64,
+            Xlen::RV64 => val as i64,
+        };
+    }
+
+    /* shift amounts use the low log2(XLEN) bits */
+    pub fn shamt_mask(&self) -> u32 {
+        return self.bits() - 1;
+    }
+}
+
+/* sign extend the low 32 bits to 64, result of every *W instruction */
+pub fn sext32(val: u64) -> u64 {
+    return val as u32 as i32 as i64 as u64;
+}
+
+#[derive(Debug)]
+pub struct RegFile {
+    x: [u64; 32],
+    xlen: Xlen,
+}
+
+impl RegFile {
+    /* constructor: all registers start at zero */
+    pub fn new(xlen: Xlen) -> RegFile {
+        return RegFile {
+            x: [0; 32],
+            xlen: xlen,
+        };
+    }
+
+    pub fn xlen(&self) -> Xlen {
+        return self.xlen;
+    }
+
+    /* zero extended register value */
+    pub fn read(&self, reg: u8) -> u64 {
+        return self.x[reg as usize];
+    }
+
+    /* sign extended register value */
+    pub fn read_signed(&self, reg: u8) -> i64 {
+        return self.xlen.sext(self.x[reg as usize]);
+    }
+
+    /* writes are truncated to XLEN, x0 is hardwired to zero */
+    pub fn write(&mut self, reg: u8, val: u64) {
+        if reg != 0 {
+            self.x[reg as usize] = self.xlen.trunc(val);
+        }
+    }
+}